@@ -1,15 +1,49 @@
-//! Admin and config: init, min_topup, batch_charge.
+//! Admin and config: init, min_topup, cooling-off period, deposit rate limit, beta deposit
+//! caps, token rotation, batch_charge, force_charge, admin audit log.
 //!
 //! **PRs that only change admin or batch behavior should edit this file only.**
+//!
+//! # Audit log
+//!
+//! Every admin-gated mutation appends an [`AdminLogEntry`] with a monotonically
+//! increasing sequence number via [`log_admin_action`]. The log is append-only and
+//! readable with `get_admin_log`, for clients without access to historical events.
 
-use crate::charge_core::charge_one;
-use crate::types::{BatchChargeResult, Error, RecoveryEvent, RecoveryReason};
-use soroban_sdk::{Address, Env, Symbol, Vec};
+use crate::archive;
+use crate::charge_core::{charge_one, force_debit, get_total_charged};
+use crate::keeper::{
+    enqueue_retry, get_billing_stale_threshold, get_notify_lead_window, get_tracked_liabilities,
+    record_billing_run, store_billing_stale_threshold, store_notify_lead_window,
+};
+use crate::merchant::get_pause_retainer;
+use crate::queries::{get_subscription, page_bounds};
+use crate::safe_math::BPS_DENOMINATOR;
+use crate::state_machine::get_status_bucket;
+use crate::subscription::{subscription_token, SECONDS_PER_DAY};
+use crate::types::{
+    AdminLogEntry, AdminLogPage, BatchChargeResult, BatchChargeSummary, Config, ConfigImpact,
+    DepositCapExceededEvent, DepositCaps, DepositRateLimit, Error, ForceChargeEvent, PendingConfig,
+    PendingTokenRotation, RecoveryEvent, RecoveryReason, ResumableBatchResult, ResumeToken,
+    SubscriptionStatus, WithdrawalTimelock, MAX_IMPACT_SAMPLES,
+};
+use soroban_sdk::{symbol_short, Address, Env, Symbol, Vec};
 
+/// One-time setup. The admin must authorize, so whoever deploys the vault has to
+/// sign as (or on behalf of) the admin being installed, and nobody can claim an
+/// uninitialized vault for an address they do not control. A second call fails
+/// with `AlreadyInitialized`.
 pub fn do_init(env: &Env, token: Address, admin: Address, min_topup: i128) -> Result<(), Error> {
+    admin.require_auth();
+    if env.storage().instance().has(&Symbol::new(env, "admin")) {
+        return Err(Error::AlreadyInitialized);
+    }
+    let decimals = read_token_decimals(env, &token)?;
     env.storage()
         .instance()
         .set(&Symbol::new(env, "token"), &token);
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "token_decimals"), &decimals);
     env.storage()
         .instance()
         .set(&Symbol::new(env, "admin"), &admin);
@@ -23,6 +57,51 @@ pub fn do_init(env: &Env, token: Address, admin: Address, min_topup: i128) -> Re
     Ok(())
 }
 
+const KEY_ADMIN_LOG: Symbol = symbol_short!("alog");
+
+/// Appends an entry to the admin audit log and returns its sequence number.
+/// Entries live in persistent storage; only the length is kept in the instance.
+pub fn log_admin_action(env: &Env, admin: &Address, action: &str) -> u32 {
+    let len_key = Symbol::new(env, "admin_log_len");
+    let seq: u32 = env.storage().instance().get(&len_key).unwrap_or(0);
+    archive::set(
+        env,
+        &(KEY_ADMIN_LOG, seq),
+        &AdminLogEntry {
+            seq,
+            admin: admin.clone(),
+            action: Symbol::new(env, action),
+            timestamp: env.ledger().timestamp(),
+            ledger: env.ledger().sequence(),
+        },
+    );
+    env.storage().instance().set(&len_key, &(seq + 1));
+    seq
+}
+
+/// Returns a page of log entries starting at sequence number `cursor`, oldest first.
+pub fn get_admin_log(env: &Env, cursor: u32, limit: u32) -> AdminLogPage {
+    let total: u32 = env
+        .storage()
+        .instance()
+        .get(&Symbol::new(env, "admin_log_len"))
+        .unwrap_or(0);
+    let (end, next_cursor) = page_bounds(cursor, limit, total);
+    let mut items = Vec::new(env);
+    let mut seq = cursor;
+    while seq < end {
+        if let Some(entry) = archive::get(env, &(KEY_ADMIN_LOG, seq)) {
+            items.push_back(entry);
+        }
+        seq += 1;
+    }
+    AdminLogPage {
+        items,
+        next_cursor,
+        total,
+    }
+}
+
 pub fn require_admin(env: &Env) -> Result<Address, Error> {
     env.storage()
         .instance()
@@ -36,6 +115,7 @@ pub fn do_set_min_topup(env: &Env, admin: Address, min_topup: i128) -> Result<()
     if admin != stored {
         return Err(Error::Unauthorized);
     }
+    log_admin_action(env, &admin, "set_min_topup");
     env.storage()
         .instance()
         .set(&Symbol::new(env, "min_topup"), &min_topup);
@@ -51,6 +131,546 @@ pub fn get_min_topup(env: &Env) -> Result<i128, Error> {
         .ok_or(Error::NotFound)
 }
 
+/// Set the cooling-off window (seconds after creation) during which cancellation
+/// refunds everything charged so far. `0` disables the window.
+pub fn do_set_cooling_off_period(env: &Env, admin: Address, seconds: u64) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    log_admin_action(env, &admin, "set_cooling_off_period");
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "cooling_off"), &seconds);
+    env.events()
+        .publish((Symbol::new(env, "cooling_off_updated"),), seconds);
+    Ok(())
+}
+
+pub fn get_cooling_off_period(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "cooling_off"))
+        .unwrap_or(0)
+}
+
+/// Default for [`get_max_charge_elapsed`]: twenty years, far beyond any real
+/// keeper outage but short of a millisecond timestamp read as seconds.
+pub const DEFAULT_MAX_CHARGE_ELAPSED: u64 = 20 * 365 * SECONDS_PER_DAY;
+
+/// Configure the clock-jump sanity check: a charge more than `seconds` past its due
+/// time flags the subscription for review instead of charging. Zero disables it.
+pub fn do_set_max_charge_elapsed(env: &Env, admin: Address, seconds: u64) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    log_admin_action(env, &admin, "set_max_charge_elapsed");
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "max_elapsed"), &seconds);
+    env.events()
+        .publish((Symbol::new(env, "max_charge_elapsed_updated"),), seconds);
+    Ok(())
+}
+
+pub fn get_max_charge_elapsed(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "max_elapsed"))
+        .unwrap_or(DEFAULT_MAX_CHARGE_ELAPSED)
+}
+
+/// Set the fee, in basis points, withheld from the part of a refund transferred out
+/// to the subscriber's address. Refunds kept in the credit wallet are fee-free.
+pub fn do_set_external_refund_fee(env: &Env, admin: Address, fee_bps: u32) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    if fee_bps as i128 > BPS_DENOMINATOR {
+        return Err(Error::InvalidAmount);
+    }
+    log_admin_action(env, &admin, "set_external_refund_fee");
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "ext_refund_fee"), &fee_bps);
+    env.events()
+        .publish((Symbol::new(env, "external_refund_fee_set"),), fee_bps);
+    Ok(())
+}
+
+pub fn get_external_refund_fee(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "ext_refund_fee"))
+        .unwrap_or(0)
+}
+
+/// Set or clear the address that receives subscriber round-up donations.
+pub fn do_set_donation_address(
+    env: &Env,
+    admin: Address,
+    donation_address: Option<Address>,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    log_admin_action(env, &admin, "set_donation_address");
+    let key = Symbol::new(env, "donation_addr");
+    match &donation_address {
+        Some(addr) => env.storage().instance().set(&key, addr),
+        None => env.storage().instance().remove(&key),
+    }
+    env.events().publish(
+        (Symbol::new(env, "donation_address_updated"),),
+        donation_address,
+    );
+    Ok(())
+}
+
+pub fn get_donation_address(env: &Env) -> Option<Address> {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "donation_addr"))
+}
+
+/// Configure deposit rate limiting (anti dust-grief). Zero values disable a check.
+pub fn do_set_deposit_rate_limit(
+    env: &Env,
+    admin: Address,
+    limit: DepositRateLimit,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    log_admin_action(env, &admin, "set_deposit_rate_limit");
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "deposit_limit"), &limit);
+    env.events()
+        .publish((Symbol::new(env, "deposit_limit_updated"),), limit);
+    Ok(())
+}
+
+pub fn get_deposit_rate_limit(env: &Env) -> DepositRateLimit {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "deposit_limit"))
+        .unwrap_or(DepositRateLimit {
+            min_interval_seconds: 0,
+            max_per_day: 0,
+        })
+}
+
+/// Configure the beta deposit caps. Zero fields disable the respective cap, so
+/// clearing both ends the beta limits.
+pub fn do_set_deposit_caps(env: &Env, admin: Address, caps: DepositCaps) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    if caps.tvl_cap < 0 || caps.balance_cap < 0 {
+        return Err(Error::InvalidAmount);
+    }
+    log_admin_action(env, &admin, "set_deposit_caps");
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "deposit_caps"), &caps);
+    env.events()
+        .publish((Symbol::new(env, "deposit_caps_updated"),), caps);
+    Ok(())
+}
+
+pub fn get_deposit_caps(env: &Env) -> DepositCaps {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "deposit_caps"))
+        .unwrap_or(DepositCaps {
+            tvl_cap: 0,
+            balance_cap: 0,
+        })
+}
+
+/// Amount of `token` that can still be deposited under the caps: the room left
+/// under the TVL cap (tracked liabilities) and, when `balance` is a subscription's
+/// prepaid balance, under the per-subscription cap. `i128::MAX` when uncapped.
+pub fn remaining_deposit_capacity(env: &Env, token: &Address, balance: Option<i128>) -> i128 {
+    let caps = get_deposit_caps(env);
+    let mut remaining = i128::MAX;
+    if caps.tvl_cap > 0 {
+        remaining = remaining.min(caps.tvl_cap - get_tracked_liabilities(env, token));
+    }
+    if let (true, Some(balance)) = (caps.balance_cap > 0, balance) {
+        remaining = remaining.min(caps.balance_cap - balance);
+    }
+    remaining.max(0)
+}
+
+/// [`remaining_deposit_capacity`] for a deposit into `subscription_id` or, when
+/// `None`, into a credit wallet in the current settlement token.
+pub fn get_deposit_capacity(env: &Env, subscription_id: Option<u32>) -> Result<i128, Error> {
+    Ok(match subscription_id {
+        Some(id) => {
+            let sub = get_subscription(env, id)?;
+            let token = subscription_token(env, id)?;
+            remaining_deposit_capacity(env, &token, Some(sub.prepaid_balance))
+        }
+        None => remaining_deposit_capacity(env, &get_token(env)?, None),
+    })
+}
+
+/// Fails with `DepositCapExceeded` when `amount` does not fit in the remaining
+/// capacity. The `deposit_cap_exceeded` event is published first, so the rejection
+/// shows up in the failed invocation's diagnostic events.
+pub fn require_deposit_capacity(
+    env: &Env,
+    subscription_id: Option<u32>,
+    token: &Address,
+    balance: Option<i128>,
+    amount: i128,
+) -> Result<(), Error> {
+    let remaining = remaining_deposit_capacity(env, token, balance);
+    if amount > remaining {
+        env.events().publish(
+            (Symbol::new(env, "deposit_cap_exceeded"),),
+            DepositCapExceededEvent {
+                subscription_id,
+                amount,
+                remaining,
+            },
+        );
+        return Err(Error::DepositCapExceeded);
+    }
+    Ok(())
+}
+
+/// Configure the large-withdrawal time lock. A zero `threshold` disables it.
+pub fn do_set_withdrawal_timelock(
+    env: &Env,
+    admin: Address,
+    timelock: WithdrawalTimelock,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    if timelock.threshold < 0 {
+        return Err(Error::InvalidAmount);
+    }
+    log_admin_action(env, &admin, "set_withdrawal_timelock");
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "wd_timelock"), &timelock);
+    env.events()
+        .publish((Symbol::new(env, "withdrawal_timelock_updated"),), timelock);
+    Ok(())
+}
+
+pub fn get_withdrawal_timelock(env: &Env) -> WithdrawalTimelock {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "wd_timelock"))
+        .unwrap_or(WithdrawalTimelock {
+            threshold: 0,
+            delay_seconds: 0,
+        })
+}
+
+/// Delay between proposing and executing a settlement-token rotation.
+pub const TOKEN_ROTATION_DELAY: u64 = 2 * 24 * 60 * 60;
+
+pub fn get_token(env: &Env) -> Result<Address, Error> {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "token"))
+        .ok_or(Error::NotFound)
+}
+
+/// Decimals of the settlement token, read from the token contract at init.
+pub fn get_token_decimals(env: &Env) -> Result<u32, Error> {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "token_decimals"))
+        .ok_or(Error::NotFound)
+}
+
+/// Asks the token contract for its `decimals()`, rejecting values that cannot be
+/// used for fixed-point rescaling.
+fn read_token_decimals(env: &Env, token: &Address) -> Result<u32, Error> {
+    let decimals = soroban_sdk::token::Client::new(env, token).decimals();
+    if decimals > crate::fixed_point::MAX_DECIMALS {
+        return Err(Error::DecimalsMismatch);
+    }
+    Ok(decimals)
+}
+
+pub fn get_pending_token_rotation(env: &Env) -> Option<PendingTokenRotation> {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "pending_token"))
+}
+
+/// Propose switching the settlement token (e.g. a USDC contract migration).
+/// Executable after [`TOKEN_ROTATION_DELAY`]; replaces any earlier proposal.
+pub fn do_propose_token_rotation(
+    env: &Env,
+    admin: Address,
+    new_token: Address,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    log_admin_action(env, &admin, "propose_token_rotation");
+    let pending = PendingTokenRotation {
+        new_token,
+        executable_at: env
+            .ledger()
+            .timestamp()
+            .saturating_add(TOKEN_ROTATION_DELAY),
+    };
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "pending_token"), &pending);
+    env.events()
+        .publish((Symbol::new(env, "token_rotation_proposed"),), pending);
+    Ok(())
+}
+
+pub fn do_cancel_token_rotation(env: &Env, admin: Address) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    if get_pending_token_rotation(env).is_none() {
+        return Err(Error::NotFound);
+    }
+    log_admin_action(env, &admin, "cancel_token_rotation");
+    env.storage()
+        .instance()
+        .remove(&Symbol::new(env, "pending_token"));
+    env.events()
+        .publish((Symbol::new(env, "token_rotation_cancelled"),), ());
+    Ok(())
+}
+
+/// Execute a pending token rotation once its timelock has elapsed.
+///
+/// Only new subscriptions settle in the new token; existing subscriptions keep
+/// the token they were funded in, so withdrawals pay out in the original token.
+/// The new token must report the same `decimals()` as the current one, since
+/// `min_topup` and other configured amounts are denominated in those units.
+pub fn do_execute_token_rotation(env: &Env, admin: Address) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let pending = get_pending_token_rotation(env).ok_or(Error::NotFound)?;
+    if env.ledger().timestamp() < pending.executable_at {
+        return Err(Error::TimelockNotElapsed);
+    }
+    let decimals = read_token_decimals(env, &pending.new_token)?;
+    if let Ok(current) = get_token_decimals(env) {
+        if decimals != current {
+            return Err(Error::DecimalsMismatch);
+        }
+    }
+    log_admin_action(env, &admin, "execute_token_rotation");
+    let old_token = get_token(env)?;
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "token_decimals"), &decimals);
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "token"), &pending.new_token);
+    env.storage()
+        .instance()
+        .remove(&Symbol::new(env, "pending_token"));
+    env.events().publish(
+        (Symbol::new(env, "token_rotated"),),
+        (old_token, pending.new_token),
+    );
+    Ok(())
+}
+
+/// Delay between staging a bulk config update and applying it.
+pub const CONFIG_UPDATE_DELAY: u64 = 24 * 60 * 60;
+
+/// Current values of every parameter covered by [`Config`].
+pub fn get_config(env: &Env) -> Result<Config, Error> {
+    Ok(Config {
+        min_topup: get_min_topup(env)?,
+        cooling_off_seconds: get_cooling_off_period(env),
+        deposit_rate_limit: get_deposit_rate_limit(env),
+        withdrawal_timelock: get_withdrawal_timelock(env),
+        notify_lead_seconds: get_notify_lead_window(env),
+        billing_stale_seconds: get_billing_stale_threshold(env),
+    })
+}
+
+pub fn get_pending_config(env: &Env) -> Option<PendingConfig> {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "pending_config"))
+}
+
+/// Field and cross-field checks, so a staged config can never leave the contract
+/// in a state the individual setters would have rejected.
+fn validate_config(config: &Config) -> Result<(), Error> {
+    if config.min_topup < 0 || config.withdrawal_timelock.threshold < 0 {
+        return Err(Error::InvalidAmount);
+    }
+    // A withdrawal threshold without a delay would announce-and-execute instantly.
+    if config.withdrawal_timelock.threshold > 0 && config.withdrawal_timelock.delay_seconds == 0 {
+        return Err(Error::InvalidAmount);
+    }
+    // The daily cap must be reachable given the minimum spacing between deposits.
+    let limit = &config.deposit_rate_limit;
+    if limit.max_per_day > 1
+        && limit
+            .min_interval_seconds
+            .saturating_mul(limit.max_per_day as u64 - 1)
+            >= SECONDS_PER_DAY
+    {
+        return Err(Error::InvalidAmount);
+    }
+    Ok(())
+}
+
+/// Dry run of `set_config`: reports which `Active` subscriptions on one page of the
+/// active set (`limit` capped at `MAX_PAGE_LIMIT`) would newly fall short of the
+/// proposed `min_topup` or become subject to the proposed withdrawal time lock.
+/// Read-only; fails like `set_config` if the config is invalid.
+pub fn simulate_set_config(
+    env: &Env,
+    config: &Config,
+    cursor: u32,
+    limit: u32,
+) -> Result<ConfigImpact, Error> {
+    validate_config(config)?;
+    let current_min = get_min_topup(env)?;
+    let current_lock = get_withdrawal_timelock(env);
+    let timelocked =
+        |lock: &WithdrawalTimelock, balance: i128| lock.threshold > 0 && balance >= lock.threshold;
+
+    let bucket = get_status_bucket(env, &SubscriptionStatus::Active);
+    let (end, next_cursor) = page_bounds(cursor, limit, bucket.len());
+    let mut impact = ConfigImpact {
+        scanned: 0,
+        below_min_topup: 0,
+        newly_timelocked: 0,
+        sample_ids: Vec::new(env),
+        next_cursor,
+    };
+    for i in cursor..end {
+        let id = bucket.get_unchecked(i);
+        let balance = get_subscription(env, id)?.prepaid_balance;
+        impact.scanned += 1;
+        let below = balance >= current_min && balance < config.min_topup;
+        let locked =
+            !timelocked(&current_lock, balance) && timelocked(&config.withdrawal_timelock, balance);
+        if below {
+            impact.below_min_topup += 1;
+        }
+        if locked {
+            impact.newly_timelocked += 1;
+        }
+        if (below || locked) && impact.sample_ids.len() < MAX_IMPACT_SAMPLES {
+            impact.sample_ids.push_back(id);
+        }
+    }
+    Ok(impact)
+}
+
+/// Stage a bulk config update, applied atomically by `apply_config` after
+/// [`CONFIG_UPDATE_DELAY`]. Replaces any earlier staged config.
+pub fn do_set_config(env: &Env, admin: Address, config: Config) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    validate_config(&config)?;
+    log_admin_action(env, &admin, "set_config");
+    let pending = PendingConfig {
+        config,
+        executable_at: env.ledger().timestamp().saturating_add(CONFIG_UPDATE_DELAY),
+    };
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "pending_config"), &pending);
+    env.events()
+        .publish((Symbol::new(env, "config_proposed"),), pending);
+    Ok(())
+}
+
+pub fn do_cancel_config(env: &Env, admin: Address) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    if get_pending_config(env).is_none() {
+        return Err(Error::NotFound);
+    }
+    log_admin_action(env, &admin, "cancel_config");
+    env.storage()
+        .instance()
+        .remove(&Symbol::new(env, "pending_config"));
+    env.events()
+        .publish((Symbol::new(env, "config_cancelled"),), ());
+    Ok(())
+}
+
+/// Apply the staged config once its timelock has elapsed, writing every field in
+/// one invocation.
+pub fn do_apply_config(env: &Env, admin: Address) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let pending = get_pending_config(env).ok_or(Error::NotFound)?;
+    if env.ledger().timestamp() < pending.executable_at {
+        return Err(Error::TimelockNotElapsed);
+    }
+    log_admin_action(env, &admin, "apply_config");
+    let config = pending.config;
+    let storage = env.storage().instance();
+    storage.set(&Symbol::new(env, "min_topup"), &config.min_topup);
+    storage.set(
+        &Symbol::new(env, "cooling_off"),
+        &config.cooling_off_seconds,
+    );
+    storage.set(
+        &Symbol::new(env, "deposit_limit"),
+        &config.deposit_rate_limit,
+    );
+    storage.set(
+        &Symbol::new(env, "wd_timelock"),
+        &config.withdrawal_timelock,
+    );
+    store_notify_lead_window(env, config.notify_lead_seconds);
+    store_billing_stale_threshold(env, config.billing_stale_seconds);
+    storage.remove(&Symbol::new(env, "pending_config"));
+    env.events()
+        .publish((Symbol::new(env, "config_updated"),), config);
+    Ok(())
+}
+
 pub fn do_batch_charge(
     env: &Env,
     subscription_ids: &Vec<u32>,
@@ -66,16 +686,136 @@ pub fn do_batch_charge(
                 success: true,
                 error_code: 0,
             },
-            Err(e) => BatchChargeResult {
-                success: false,
-                error_code: e.clone().to_code(),
-            },
+            Err(e) => {
+                enqueue_retry(env, id, e);
+                BatchChargeResult {
+                    success: false,
+                    error_code: e.clone().to_code(),
+                }
+            }
         };
         results.push_back(res);
     }
+    record_billing_run(env);
     Ok(results)
 }
 
+/// Same as [`do_batch_charge`], returning successes and failures as separate
+/// vectors plus totals instead of one result per input index.
+pub fn do_batch_charge_v2(
+    env: &Env,
+    subscription_ids: &Vec<u32>,
+) -> Result<BatchChargeSummary, Error> {
+    let auth_admin = require_admin(env)?;
+    auth_admin.require_auth();
+
+    let mut summary = BatchChargeSummary {
+        charged: Vec::new(env),
+        failed: Vec::new(env),
+        count_charged: 0,
+        total_amount: 0,
+    };
+    for id in subscription_ids.iter() {
+        let before = get_total_charged(env, id);
+        match charge_one(env, id, None) {
+            Ok(()) => {
+                summary.charged.push_back(id);
+                summary.count_charged += 1;
+                summary.total_amount = summary
+                    .total_amount
+                    .saturating_add(get_total_charged(env, id) - before);
+            }
+            Err(e) => {
+                enqueue_retry(env, id, &e);
+                summary.failed.push_back((id, e.to_code()));
+            }
+        }
+    }
+    record_billing_run(env);
+    Ok(summary)
+}
+
+/// Maximum number of subscription ids scanned by one `batch_charge_resumable` call.
+pub const MAX_BATCH_SCAN: u32 = 200;
+
+/// Charge every `Active` subscription (and `Paused` ones with a pause retainer),
+/// scanning ids in order across many calls.
+///
+/// Starts after `resume.last_processed_id` (or at id 0) and scans at most `limit`
+/// ids (capped at [`MAX_BATCH_SCAN`]); `limit` must be non-zero. A token issued at a
+/// future ledger is rejected. The returned token lets the keeper continue
+/// in the next transaction without tracking ids off-chain.
+pub fn do_batch_charge_resumable(
+    env: &Env,
+    resume: Option<ResumeToken>,
+    limit: u32,
+) -> Result<ResumableBatchResult, Error> {
+    let auth_admin = require_admin(env)?;
+    auth_admin.require_auth();
+
+    if limit == 0 {
+        return Err(Error::InvalidAmount);
+    }
+    let seq = env.ledger().sequence();
+    let start = match resume {
+        Some(t) => {
+            if t.ledger > seq {
+                return Err(Error::InvalidAmount);
+            }
+            t.last_processed_id.saturating_add(1)
+        }
+        None => 0,
+    };
+    let next_id: u32 = env
+        .storage()
+        .instance()
+        .get(&Symbol::new(env, "next_id"))
+        .unwrap_or(0);
+    let end = start.saturating_add(limit.min(MAX_BATCH_SCAN)).min(next_id);
+
+    let mut attempted_ids = Vec::new(env);
+    let mut results = Vec::new(env);
+    let mut id = start;
+    while id < end {
+        if let Ok(sub) = get_subscription(env, id) {
+            let billable = match sub.status {
+                SubscriptionStatus::Active => true,
+                SubscriptionStatus::Paused => get_pause_retainer(env, id) > 0,
+                _ => false,
+            };
+            if billable {
+                let res = match charge_one(env, id, None) {
+                    Ok(()) => BatchChargeResult {
+                        success: true,
+                        error_code: 0,
+                    },
+                    Err(e) => {
+                        enqueue_retry(env, id, &e);
+                        BatchChargeResult {
+                            success: false,
+                            error_code: e.to_code(),
+                        }
+                    }
+                };
+                attempted_ids.push_back(id);
+                results.push_back(res);
+            }
+        }
+        id += 1;
+    }
+
+    record_billing_run(env);
+    Ok(ResumableBatchResult {
+        attempted_ids,
+        results,
+        resume_token: ResumeToken {
+            last_processed_id: end.saturating_sub(1),
+            ledger: seq,
+        },
+        has_next: end < next_id,
+    })
+}
+
 pub fn do_get_admin(env: &Env) -> Result<Address, Error> {
     env.storage()
         .instance()
@@ -95,6 +835,7 @@ pub fn do_rotate_admin(env: &Env, current_admin: Address, new_admin: Address) ->
     if current_admin != stored_admin {
         return Err(Error::Unauthorized);
     }
+    log_admin_action(env, &current_admin, "rotate_admin");
 
     env.storage()
         .instance()
@@ -130,6 +871,7 @@ pub fn do_recover_stranded_funds(
     if amount <= 0 {
         return Err(Error::InvalidRecoveryAmount);
     }
+    log_admin_action(env, &admin, "recover_stranded_funds");
 
     let recovery_event = RecoveryEvent {
         admin: admin.clone(),
@@ -149,3 +891,36 @@ pub fn do_recover_stranded_funds(
 
     Ok(())
 }
+
+/// **ADMIN ONLY**: settlement correction charge (e.g. after a dispute ruling).
+///
+/// Bypasses interval timing but is bounded by the subscription's per-interval
+/// amount and prepaid balance. Always emits a distinct `force_charge` audit event.
+pub fn do_force_charge(
+    env: &Env,
+    admin: Address,
+    subscription_id: u32,
+    amount: i128,
+    reason: Symbol,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+
+    let sub = force_debit(env, subscription_id, amount)?;
+    log_admin_action(env, &admin, "force_charge");
+    env.events().publish(
+        (Symbol::new(env, "force_charge"), subscription_id),
+        ForceChargeEvent {
+            admin,
+            subscription_id,
+            merchant: sub.merchant,
+            amount,
+            reason,
+            timestamp: env.ledger().timestamp(),
+        },
+    );
+    Ok(())
+}
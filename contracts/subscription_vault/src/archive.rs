@@ -0,0 +1,49 @@
+//! Persistent storage for append-only records.
+//!
+//! **PRs that only change record retention should edit this file only.**
+//!
+//! Instance storage is loaded in full on every invocation, so records that grow
+//! with the contract's history (such as the admin log) are kept in persistent
+//! storage instead, one entry per key. Each entry's TTL is extended whenever it is
+//! written or read; an entry nobody touches for [`ENTRY_TTL`] ledgers is archived
+//! by the network and must be restored before it can be read. Counters for these
+//! records stay in instance storage.
+
+use soroban_sdk::{Env, IntoVal, TryFromVal, Val};
+
+/// Ledgers an entry lives after its last write or read (about 30 days at 5 s per
+/// ledger).
+pub const ENTRY_TTL: u32 = 518_400;
+
+/// Remaining TTL below which an access extends the entry back to [`ENTRY_TTL`].
+const EXTEND_THRESHOLD: u32 = ENTRY_TTL - 17_280;
+
+pub fn get<K, V>(env: &Env, key: &K) -> Option<V>
+where
+    K: IntoVal<Env, Val>,
+    V: TryFromVal<Env, Val>,
+{
+    let storage = env.storage().persistent();
+    let value = storage.get(key);
+    if value.is_some() {
+        storage.extend_ttl(key, EXTEND_THRESHOLD, ENTRY_TTL);
+    }
+    value
+}
+
+pub fn set<K, V>(env: &Env, key: &K, value: &V)
+where
+    K: IntoVal<Env, Val>,
+    V: IntoVal<Env, Val>,
+{
+    let storage = env.storage().persistent();
+    storage.set(key, value);
+    storage.extend_ttl(key, EXTEND_THRESHOLD, ENTRY_TTL);
+}
+
+pub fn remove<K>(env: &Env, key: &K)
+where
+    K: IntoVal<Env, Val>,
+{
+    env.storage().persistent().remove(key);
+}
@@ -0,0 +1,94 @@
+//! Subscriber spend caps across all of their subscriptions.
+//!
+//! **PRs that only change subscriber budgets should edit this file only.**
+//!
+//! A subscriber may cap what the vault charges them per budget month (fixed
+//! [`BUDGET_PERIOD_SECONDS`] windows of ledger time), summed over every merchant.
+//! Interval, usage and out-of-schedule charges are checked before anything is
+//! debited and fail with `BudgetExceeded` if they would pass the cap. Amounts are
+//! summed as-is, so a cap assumes the subscriber's subscriptions share a token.
+
+use crate::safe_math::safe_add;
+use crate::types::{BudgetStatus, Error};
+use soroban_sdk::{symbol_short, Address, Env, Symbol};
+
+/// Length of a budget month.
+pub const BUDGET_PERIOD_SECONDS: u64 = 30 * 24 * 60 * 60;
+
+const KEY_CAP: Symbol = symbol_short!("bcap");
+const KEY_SPEND: Symbol = symbol_short!("bspend");
+
+fn current_period(env: &Env) -> u64 {
+    env.ledger().timestamp() / BUDGET_PERIOD_SECONDS
+}
+
+fn get_cap(env: &Env, subscriber: &Address) -> Option<i128> {
+    env.storage().instance().get(&(KEY_CAP, subscriber.clone()))
+}
+
+/// Amount charged to `subscriber` in the current budget month.
+fn get_spent(env: &Env, subscriber: &Address) -> i128 {
+    match env
+        .storage()
+        .instance()
+        .get::<_, (u64, i128)>(&(KEY_SPEND, subscriber.clone()))
+    {
+        Some((period, spent)) if period == current_period(env) => spent,
+        _ => 0,
+    }
+}
+
+/// Set or clear the subscriber's monthly spend cap. Auth: subscriber.
+pub fn do_set_spend_cap(env: &Env, subscriber: Address, cap: Option<i128>) -> Result<(), Error> {
+    subscriber.require_auth();
+    let key = (KEY_CAP, subscriber.clone());
+    match cap {
+        Some(c) if c < 0 => return Err(Error::InvalidAmount),
+        Some(c) => env.storage().instance().set(&key, &c),
+        None => env.storage().instance().remove(&key),
+    }
+    env.events()
+        .publish((Symbol::new(env, "spend_cap_set"), subscriber), cap);
+    Ok(())
+}
+
+pub fn get_budget_status(env: &Env, subscriber: &Address) -> BudgetStatus {
+    let period_start = current_period(env) * BUDGET_PERIOD_SECONDS;
+    BudgetStatus {
+        cap: get_cap(env, subscriber),
+        spent: get_spent(env, subscriber),
+        period_start,
+        period_end: period_start.saturating_add(BUDGET_PERIOD_SECONDS),
+    }
+}
+
+/// Fails with `BudgetExceeded` if charging `amount` would take the subscriber past
+/// their cap. Must run before the charge writes any state.
+pub fn check_budget(env: &Env, subscriber: &Address, amount: i128) -> Result<(), Error> {
+    if let Some(cap) = get_cap(env, subscriber) {
+        if safe_add(get_spent(env, subscriber), amount)? > cap {
+            return Err(Error::BudgetExceeded);
+        }
+    }
+    Ok(())
+}
+
+/// Adds a completed charge to the subscriber's current-month spend.
+pub fn record_spend(env: &Env, subscriber: &Address, amount: i128) -> Result<(), Error> {
+    let spent = safe_add(get_spent(env, subscriber), amount)?;
+    env.storage().instance().set(
+        &(KEY_SPEND, subscriber.clone()),
+        &(current_period(env), spent),
+    );
+    Ok(())
+}
+
+/// Drops the subscriber's cap and spend record (used on account closure).
+pub fn remove_budget(env: &Env, subscriber: &Address) {
+    env.storage()
+        .instance()
+        .remove(&(KEY_CAP, subscriber.clone()));
+    env.storage()
+        .instance()
+        .remove(&(KEY_SPEND, subscriber.clone()));
+}
@@ -10,15 +10,86 @@
 //! - **Optional idempotency key**: If the caller supplies an idempotency key (e.g. for retries),
 //!   we store one key per subscription. A second call with the same key returns `Ok(())` without
 //!   debiting again (idempotent success). Storage stays bounded (one key and one period per sub).
+//!
+//! # Cooling-off hold
+//!
+//! Charges made while the subscription is inside the admin's cooling-off window are
+//! held in the vault instead of being split into tax, fees and the merchant's
+//! payout, so a cancellation in the window can always refund them in full. Once the
+//! window has closed the hold is settled like a normal charge, by the next charge,
+//! a cancellation, or anyone calling `release_cooling_off_hold`.
+//!
+//! # Grace period
+//!
+//! A charge that fails for lack of funds normally moves the subscription to
+//! `InsufficientBalance`. `Priority` tier subscriptions get their merchant's grace
+//! period instead: until `due time + grace_seconds` they stay `Active` (the charge
+//! still fails), so a late top-up resumes billing without a status change.
+//!
+//! # Clock-jump guard
+//!
+//! A timestamp-basis charge more than the admin's max elapsed time past its due time
+//! (a misconfigured ledger, or milliseconds passed as seconds) is not charged: the
+//! subscription is flagged for review and every charge fails with
+//! `ChargeUnderReview` until the admin resolves it.
+//!
+//! # Pull billing
+//!
+//! Subscriptions created with `create_pull_subscription` need no prepaid balance.
+//! When an interval charge finds the balance short, the shortfall is pulled from the
+//! subscriber's wallet with `transfer_from` against the allowance the subscriber
+//! granted the vault, then charged as usual. If the allowance or the wallet cannot
+//! cover it, nothing is pulled and the charge fails like any underfunded charge.
+//! Usage charges always draw on the prepaid balance.
+//!
+//! A subscriber of a prepaid subscription may opt into the same behaviour with
+//! `set_wallet_fallback`: the wallet is then tried before a short balance leads to
+//! a grace period or `InsufficientBalance`.
 
+use crate::admin::{get_donation_address, get_max_charge_elapsed, log_admin_action, require_admin};
+use crate::budget::{check_budget, record_spend};
+use crate::claims::transfer_or_defer;
+use crate::credit::{
+    available_promo_credit, debit_wallet, get_credit_balance, route_refund, use_promo_credit,
+};
+use crate::fees::{
+    enroll_fee_waiver, protocol_fee_share, reverse_protocol_fee, reversible_protocol_fee,
+    take_protocol_fee,
+};
+use crate::keeper::track_liabilities;
+use crate::merchant::{
+    get_pause_retainer, get_payout_queue, is_test_mode, queue_payout, record_merchant_charge,
+    requeue_payout, require_merchant_scope, unqueue_payout,
+};
+use crate::platforms::{
+    apply_platform_cut, platform_cut_share, reverse_platform_cut, reversible_platform_cut,
+};
 use crate::queries::get_subscription;
-use crate::safe_math::safe_sub_balance;
-use crate::state_machine::validate_status_transition;
-use crate::types::{Error, SubscriptionChargedEvent, SubscriptionStatus};
-use soroban_sdk::{symbol_short, Env, Symbol};
+use crate::receipts::append_receipt;
+use crate::referrals::pay_commission;
+use crate::reports::{record_charge, record_refund};
+use crate::safe_math::{round_to_multiple, safe_add_balance, safe_sub_balance, Rounding};
+use crate::stake::require_merchant_not_halted;
+use crate::state_machine::apply_transition;
+use crate::subscription::{
+    get_pending_update, get_round_up, in_cooling_off, is_past_expiry, pulls_from_wallet,
+    record_term_period, remove_pending_update, subscription_token,
+};
+use crate::tax::apply_tax;
+use crate::tiers::priority_policy_for;
+use crate::types::{
+    ApiKeyScope, ChargeReview, DataKey, DonationEvent, Error, LedgerSchedule, Subscription,
+    SubscriptionChargedEvent, SubscriptionStatus,
+};
+use crate::voting::record_weight;
+use crate::webhooks::notify_charged;
+use soroban_sdk::{symbol_short, Address, Env, Symbol};
 
 const KEY_CHARGED_PERIOD: Symbol = symbol_short!("cp");
 const KEY_IDEM: Symbol = symbol_short!("idem");
+const KEY_COOLING_HOLD: Symbol = symbol_short!("coolhold");
+const KEY_PERIODS_CHARGED: Symbol = symbol_short!("pc");
+const KEY_REVIEW: Symbol = symbol_short!("review");
 
 fn charged_period_key(subscription_id: u32) -> (Symbol, u32) {
     (KEY_CHARGED_PERIOD, subscription_id)
@@ -28,14 +99,383 @@ fn idem_key(subscription_id: u32) -> (Symbol, u32) {
     (KEY_IDEM, subscription_id)
 }
 
+/// Index of the most recent billing period charged on a subscription, if any.
+pub fn get_charged_period(env: &Env, subscription_id: u32) -> Option<u64> {
+    env.storage()
+        .instance()
+        .get(&charged_period_key(subscription_id))
+}
+
+/// Number of successful interval charges on a subscription.
+pub fn get_periods_charged(env: &Env, subscription_id: u32) -> u32 {
+    env.storage()
+        .instance()
+        .get(&(KEY_PERIODS_CHARGED, subscription_id))
+        .unwrap_or(0)
+}
+
+fn increment_periods_charged(env: &Env, subscription_id: u32) {
+    let count = get_periods_charged(env, subscription_id).saturating_add(1);
+    env.storage()
+        .instance()
+        .set(&(KEY_PERIODS_CHARGED, subscription_id), &count);
+}
+
+/// Forget the last charged period. Needed when the schedule basis changes, since
+/// timestamp and ledger period indices are not comparable.
+pub fn reset_charged_period(env: &Env, subscription_id: u32) {
+    env.storage()
+        .instance()
+        .remove(&charged_period_key(subscription_id));
+}
+
+pub fn get_charge_review(env: &Env, subscription_id: u32) -> Option<ChargeReview> {
+    env.storage().instance().get(&(KEY_REVIEW, subscription_id))
+}
+
+pub fn remove_charge_review(env: &Env, subscription_id: u32) {
+    env.storage()
+        .instance()
+        .remove(&(KEY_REVIEW, subscription_id));
+}
+
+/// Clear a review flag. Auth: admin.
+///
+/// The schedule is re-anchored at the current time, so the gap is not charged and
+/// the next charge falls due one interval from now; use `force_charge` if the
+/// skipped period should be collected.
+pub fn do_resolve_charge_review(
+    env: &Env,
+    admin: Address,
+    subscription_id: u32,
+) -> Result<(), Error> {
+    admin.require_auth();
+    if admin != require_admin(env)? {
+        return Err(Error::Unauthorized);
+    }
+    if get_charge_review(env, subscription_id).is_none() {
+        return Err(Error::NotFound);
+    }
+    let mut sub = get_subscription(env, subscription_id)?;
+    log_admin_action(env, &admin, "resolve_charge_review");
+    remove_charge_review(env, subscription_id);
+    sub.last_payment_timestamp = env.ledger().timestamp();
+    env.storage().instance().set(&subscription_id, &sub);
+    env.events().publish(
+        (Symbol::new(env, "charge_review_resolved"), subscription_id),
+        sub.last_payment_timestamp,
+    );
+    Ok(())
+}
+
+/// Cumulative amount charged (interval, usage and out-of-schedule) against a subscription.
+pub fn get_total_charged(env: &Env, subscription_id: u32) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::SubTotalCharged(subscription_id))
+        .unwrap_or(0)
+}
+
+/// Charges held while the subscription is inside its cooling-off window.
+pub fn get_cooling_off_hold(env: &Env, subscription_id: u32) -> i128 {
+    env.storage()
+        .instance()
+        .get(&(KEY_COOLING_HOLD, subscription_id))
+        .unwrap_or(0)
+}
+
+fn set_cooling_off_hold(env: &Env, subscription_id: u32, amount: i128) {
+    let key = (KEY_COOLING_HOLD, subscription_id);
+    if amount == 0 {
+        env.storage().instance().remove(&key);
+    } else {
+        env.storage().instance().set(&key, &amount);
+    }
+}
+
+/// Adds `amount` to the subscription's cumulative charged total (used for
+/// refunds) and to the subscriber's monthly spend, then settles it, or holds it
+/// while the subscription is inside its cooling-off window. Returns the tax, `0`
+/// while held.
+fn record_charged(
+    env: &Env,
+    subscription_id: u32,
+    sub: &Subscription,
+    amount: i128,
+) -> Result<i128, Error> {
+    let key = DataKey::SubTotalCharged(subscription_id);
+    let total: i128 = env.storage().instance().get(&key).unwrap_or(0);
+    env.storage()
+        .instance()
+        .set(&key, &safe_add_balance(total, amount)?);
+    record_spend(env, &sub.subscriber, amount)?;
+    if in_cooling_off(env, subscription_id) {
+        let held = safe_add_balance(get_cooling_off_hold(env, subscription_id), amount)?;
+        set_cooling_off_hold(env, subscription_id, held);
+        return Ok(0);
+    }
+    release_cooling_off_hold(env, subscription_id, sub)?;
+    settle_charged(env, subscription_id, sub, amount)
+}
+
+/// Settles the charges held through the cooling-off window once it has closed.
+/// Returns the amount settled, `0` while the window is open.
+pub fn release_cooling_off_hold(
+    env: &Env,
+    subscription_id: u32,
+    sub: &Subscription,
+) -> Result<i128, Error> {
+    let held = get_cooling_off_hold(env, subscription_id);
+    if held == 0 || in_cooling_off(env, subscription_id) {
+        return Ok(0);
+    }
+    set_cooling_off_hold(env, subscription_id, 0);
+    settle_charged(env, subscription_id, sub, held)?;
+    env.events().publish(
+        (Symbol::new(env, "cooling_off_released"), subscription_id),
+        held,
+    );
+    Ok(held)
+}
+
+/// Settle the subscription's cooling-off hold once the window has closed.
+/// Permissionless. Returns the amount settled.
+pub fn do_release_cooling_off_hold(env: &Env, subscription_id: u32) -> Result<i128, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    release_cooling_off_hold(env, subscription_id, &sub)
+}
+
+/// Adds a charge of `amount` to the merchant's current settlement epoch and, net of
+/// the merchant's tax line item, the protocol fee, any platform cut and affiliate
+/// commission, to the merchant's payout queue; appends a chained receipt. Returns
+/// the tax.
+fn settle_charged(
+    env: &Env,
+    subscription_id: u32,
+    sub: &Subscription,
+    amount: i128,
+) -> Result<i128, Error> {
+    let merchant = &sub.merchant;
+    record_merchant_charge(env, merchant, amount)?;
+    enroll_fee_waiver(env, merchant);
+    let token = subscription_token(env, subscription_id)?;
+    let tax = apply_tax(env, subscription_id, merchant, &token, amount)?;
+    let protocol_fee = take_protocol_fee(env, subscription_id, merchant, &token, amount - tax)?;
+    let platform_cut = apply_platform_cut(
+        env,
+        subscription_id,
+        merchant,
+        &token,
+        amount - tax - protocol_fee,
+    )?;
+    let commission = pay_commission(
+        env,
+        subscription_id,
+        merchant,
+        &token,
+        amount - tax - protocol_fee - platform_cut,
+        subscription_age(env, subscription_id, sub),
+    )?;
+    let net = amount - tax - protocol_fee - platform_cut - commission;
+    queue_payout(env, merchant, &token, net)?;
+    record_charge(env, merchant, amount, amount - net)?;
+    record_charge_weight(env, sub, amount, net)?;
+    append_receipt(env, subscription_id, merchant, &token, amount, tax);
+    Ok(tax)
+}
+
+/// Moves the voting weight of both sides of a charge. A merchant charging itself
+/// gains no weight.
+pub fn record_charge_weight(
+    env: &Env,
+    sub: &Subscription,
+    subscriber_delta: i128,
+    merchant_delta: i128,
+) -> Result<(), Error> {
+    if sub.subscriber == sub.merchant {
+        return Ok(());
+    }
+    record_weight(env, &sub.subscriber, subscriber_delta)?;
+    record_weight(env, &sub.merchant, merchant_delta)
+}
+
+/// Part of `amount` charged to the subscription that a refund can take back: its
+/// cooling-off hold in full, then of the settled charges their protocol fee share
+/// from the collectors' unwithdrawn accruals, their platform cut share from the
+/// platforms' pending payouts, and the rest from the merchant's still pending
+/// payout. Tax, commissions, lien payments and realized payouts have left the vault
+/// or its payee and are not refundable.
+pub fn refundable_charged(
+    env: &Env,
+    subscription_id: u32,
+    merchant: &Address,
+    token: &Address,
+    amount: i128,
+) -> Result<i128, Error> {
+    let charged = get_total_charged(env, subscription_id);
+    let amount = amount.min(charged).max(0);
+    let held = get_cooling_off_hold(env, subscription_id);
+    let from_held = amount.min(held);
+    let (amount, settled) = (amount - from_held, charged - held);
+    let fee_share = protocol_fee_share(env, subscription_id, amount, settled)?;
+    let from_fees = reversible_protocol_fee(env, subscription_id, token, fee_share);
+    let cut_share = platform_cut_share(env, subscription_id, amount, settled)?;
+    let from_platforms = reversible_platform_cut(env, subscription_id, token, cut_share);
+    let from_merchant =
+        (amount - fee_share - cut_share).min(get_payout_queue(env, merchant, token).pending);
+    Ok(from_held + from_fees + from_platforms + from_merchant.max(0))
+}
+
+/// Takes back up to `amount` charged to the subscription, bounded as in
+/// [`refundable_charged`], and lowers its total charged. Returns the amount taken
+/// back, which is all the caller may refund, and the part of it that came from the
+/// merchant. The settled part is taken off the subscriber's and merchant's voting
+/// weight.
+pub fn reverse_charged(
+    env: &Env,
+    subscription_id: u32,
+    sub: &Subscription,
+    token: &Address,
+    amount: i128,
+) -> Result<(i128, i128), Error> {
+    let merchant = &sub.merchant;
+    let charged = get_total_charged(env, subscription_id);
+    let amount = amount.min(charged).max(0);
+    let held = get_cooling_off_hold(env, subscription_id);
+    let from_held = amount.min(held);
+    set_cooling_off_hold(env, subscription_id, held - from_held);
+    let (amount, settled) = (amount - from_held, charged - held);
+    let fee_share = protocol_fee_share(env, subscription_id, amount, settled)?;
+    let from_fees = reverse_protocol_fee(env, subscription_id, token, fee_share)?;
+    let cut_share = platform_cut_share(env, subscription_id, amount, settled)?;
+    let from_platforms = reverse_platform_cut(env, subscription_id, token, cut_share);
+    let from_merchant = unqueue_payout(env, merchant, token, amount - fee_share - cut_share);
+    let from_settled = from_fees + from_platforms + from_merchant;
+    let recovered = from_held + from_settled;
+    if recovered > 0 {
+        env.storage().instance().set(
+            &DataKey::SubTotalCharged(subscription_id),
+            &safe_sub_balance(charged, recovered)?,
+        );
+    }
+    record_charge_weight(env, sub, -from_settled, -from_merchant)?;
+    Ok((recovered, from_merchant))
+}
+
+/// Reverses up to `amount` of the subscription's charges and refunds what was
+/// taken back per the subscriber's refund split. With `charge_fee`, the merchant
+/// keeps the external refund fee. Returns the amount refunded, fee included.
+pub fn refund_charged(
+    env: &Env,
+    subscription_id: u32,
+    sub: &Subscription,
+    amount: i128,
+    charge_fee: bool,
+) -> Result<i128, Error> {
+    let token = subscription_token(env, subscription_id)?;
+    let (refund, from_merchant) = reverse_charged(env, subscription_id, sub, &token, amount)?;
+    if refund > 0 {
+        let fee = route_refund(env, &sub.subscriber, &token, refund, charge_fee)?;
+        requeue_payout(env, &sub.merchant, &token, fee)?;
+        record_refund(env, &sub.merchant, (from_merchant - fee).max(0))?;
+    }
+    Ok(refund)
+}
+
+/// Seconds since the subscription was created, which selects the affiliate
+/// commission tier.
+fn subscription_age(env: &Env, subscription_id: u32, sub: &Subscription) -> u64 {
+    let created_at: u64 = env
+        .storage()
+        .instance()
+        .get(&DataKey::SubCreatedAt(subscription_id))
+        .unwrap_or(sub.last_payment_timestamp);
+    env.ledger().timestamp().saturating_sub(created_at)
+}
+
+/// Round-up donation owed on an interval charge of `amount`, given the balance left
+/// after the charge. Zero when the subscriber has not opted in, no donation address
+/// is configured, the amount is already a multiple of the unit, or the remaining
+/// balance cannot cover it (the charge itself always takes priority).
+fn round_up_donation(env: &Env, subscriber: &Address, amount: i128, remaining: i128) -> i128 {
+    let unit = get_round_up(env, subscriber).unit;
+    if unit <= 0 || get_donation_address(env).is_none() {
+        return 0;
+    }
+    let donation = match round_to_multiple(amount, unit, Rounding::Ceil) {
+        Ok(rounded) => rounded - amount,
+        Err(_) => return 0,
+    };
+    if donation > remaining {
+        0
+    } else {
+        donation
+    }
+}
+
+/// Sends a round-up donation out of the vault and adds it to the subscriber's total.
+fn route_donation(
+    env: &Env,
+    subscription_id: u32,
+    subscriber: &Address,
+    amount: i128,
+) -> Result<(), Error> {
+    let donation_address = get_donation_address(env).ok_or(Error::NotFound)?;
+    let token_addr = subscription_token(env, subscription_id)?;
+    transfer_or_defer(env, &token_addr, &donation_address, amount)?;
+
+    let mut round_up = get_round_up(env, subscriber);
+    round_up.total_donated = safe_add_balance(round_up.total_donated, amount)?;
+    env.storage()
+        .instance()
+        .set(&DataKey::SubscriberRoundUp(subscriber.clone()), &round_up);
+    env.events().publish(
+        (Symbol::new(env, "donated"), subscription_id),
+        DonationEvent {
+            subscription_id,
+            subscriber: subscriber.clone(),
+            donation_address,
+            amount,
+        },
+    );
+    Ok(())
+}
+
 /// Performs a single interval-based charge with optional replay protection.
 ///
 /// # Idempotency
 ///
 /// - If `idempotency_key` is `Some(k)` and we already processed this subscription with key `k`,
 ///   returns `Ok(())` without changing state (idempotent success).
-/// - Otherwise we derive a period from `now / interval_seconds`. If this period was already
-///   charged, returns `Err(Error::Replay)`.
+/// - Otherwise we derive a period from `now / interval_seconds` (or `sequence / interval_ledgers`
+///   for ledger-sequence schedules). If this period was already charged, returns `Err(Error::Replay)`.
+///
+/// # Schedule basis
+///
+/// By default due time is timestamp-based. If the merchant switched the subscription to a
+/// [`LedgerSchedule`], due time is instead `last_payment_ledger + interval_ledgers`, so billing
+/// does not depend on validator clocks.
+///
+/// # Pause retainer
+///
+/// A `Paused` subscription with a pause retainer (proposed by the merchant, accepted
+/// by the subscriber) is billed at the normal cadence for the retainer instead of
+/// `amount`. It stays `Paused`; a retainer it
+/// cannot cover fails with `InsufficientBalance` without a status change. Retainer
+/// periods do not count toward a fixed term.
+///
+/// # Test mode
+///
+/// A subscription the merchant flagged as test mode is charged zero: the schedule
+/// advances and `charged` is emitted with a zero amount, but no balance moves and
+/// nothing is recorded toward totals, settlements or payouts.
+///
+/// # Settlement
+///
+/// The charged amount (net of tax and commission) is credited to the merchant's payout
+/// queue in the subscription's token. If the merchant's payout policy is due, the
+/// queue is transferred out in the same call; otherwise the tokens stay in the vault
+/// until the policy fires or `process_payout` is called.
 ///
 /// # Storage
 ///
@@ -45,14 +485,65 @@ pub fn charge_one(
     subscription_id: u32,
     idempotency_key: Option<soroban_sdk::BytesN<32>>,
 ) -> Result<(), Error> {
-    let mut sub = get_subscription(env, subscription_id)?;
+    charge_period(env, subscription_id, idempotency_key, false)
+}
 
-    if sub.status != SubscriptionStatus::Active {
+/// Charges the first period of a subscription right away instead of after one
+/// interval. Everything else (funding order, budget, events, settlement) matches
+/// [`charge_one`]; the schedule then runs from now.
+pub fn charge_first(env: &Env, subscription_id: u32) -> Result<(), Error> {
+    charge_period(env, subscription_id, None, true)
+}
+
+fn charge_period(
+    env: &Env,
+    subscription_id: u32,
+    idempotency_key: Option<soroban_sdk::BytesN<32>>,
+    due_now: bool,
+) -> Result<(), Error> {
+    let mut sub = get_subscription(env, subscription_id)?;
+    if is_past_expiry(env, subscription_id) {
         return Err(Error::NotActive);
     }
+    if get_charge_review(env, subscription_id).is_some() {
+        return Err(Error::ChargeUnderReview);
+    }
+
+    let retainer = match sub.status {
+        SubscriptionStatus::Active => None,
+        SubscriptionStatus::Paused if get_pause_retainer(env, subscription_id) > 0 => {
+            Some(get_pause_retainer(env, subscription_id))
+        }
+        _ => return Err(Error::NotActive),
+    };
+    // An accepted update takes effect with the next regular charge: it bills the new
+    // amount and the new interval runs from it.
+    let update = match retainer {
+        None => get_pending_update(env, subscription_id).filter(|u| u.accepted),
+        Some(_) => None,
+    };
+    // Test-mode subscriptions run the full schedule at a zero amount.
+    let test_mode = is_test_mode(env, subscription_id);
+    let charge_amount = if test_mode {
+        0
+    } else {
+        match &update {
+            Some(u) => u.amount,
+            None => retainer.unwrap_or(sub.amount),
+        }
+    };
+    require_merchant_not_halted(env, &sub.merchant)?;
 
     let now = env.ledger().timestamp();
-    let period_index = now / sub.interval_seconds;
+    let ledger_schedule: Option<LedgerSchedule> = env
+        .storage()
+        .instance()
+        .get(&DataKey::SubLedgerSchedule(subscription_id));
+    let seq = env.ledger().sequence();
+    let period_index = match &ledger_schedule {
+        Some(ls) => (seq / ls.interval_ledgers) as u64,
+        None => now / sub.interval_seconds,
+    };
 
     // Idempotent return: same idempotency key already processed for this subscription
     if let Some(ref k) = idempotency_key {
@@ -78,18 +569,78 @@ pub fn charge_one(
         }
     }
 
-    let next_allowed = sub
-        .last_payment_timestamp
-        .checked_add(sub.interval_seconds)
-        .ok_or(Error::Overflow)?;
-    if now < next_allowed {
+    let elapsed = match &ledger_schedule {
+        Some(ls) => {
+            let next_allowed = ls
+                .last_payment_ledger
+                .checked_add(ls.interval_ledgers)
+                .ok_or(Error::Overflow)?;
+            seq >= next_allowed
+        }
+        None => {
+            let next_allowed = sub
+                .last_payment_timestamp
+                .checked_add(sub.interval_seconds)
+                .ok_or(Error::Overflow)?;
+            now >= next_allowed
+        }
+    };
+    if !elapsed && !due_now {
         return Err(Error::IntervalNotElapsed);
     }
+    if ledger_schedule.is_none() {
+        check_clock_jump(env, subscription_id, &sub, now)?;
+    }
+
+    check_budget(env, &sub.subscriber, charge_amount)?;
 
-    match safe_sub_balance(sub.prepaid_balance, sub.amount) {
+    // Merchant promo credit, then the subscriber's credit wallet, are drawn down
+    // before the prepaid balance.
+    let token_addr = subscription_token(env, subscription_id)?;
+    let from_promo =
+        available_promo_credit(env, &sub.subscriber, &sub.merchant, &token_addr).min(charge_amount);
+    let from_credit =
+        get_credit_balance(env, &sub.subscriber, &token_addr).min(charge_amount - from_promo);
+    let needed = charge_amount - from_promo - from_credit;
+    if needed > sub.prepaid_balance && pulls_from_wallet(env, subscription_id) {
+        let shortfall = needed - sub.prepaid_balance;
+        if pull_from_wallet(
+            env,
+            subscription_id,
+            &sub.subscriber,
+            &token_addr,
+            shortfall,
+        ) {
+            sub.prepaid_balance = needed;
+        }
+    }
+    match safe_sub_balance(
+        sub.prepaid_balance,
+        charge_amount - from_promo - from_credit,
+    ) {
         Ok(new_balance) => {
-            sub.prepaid_balance = new_balance;
+            if from_promo > 0 {
+                use_promo_credit(env, &sub.subscriber, &sub.merchant, from_promo)?;
+            }
+            if from_credit > 0 {
+                debit_wallet(env, &sub.subscriber, &token_addr, from_credit)?;
+            }
+            let donation = round_up_donation(env, &sub.subscriber, charge_amount, new_balance);
+            sub.prepaid_balance = new_balance - donation;
             sub.last_payment_timestamp = now;
+            let mut period_index = period_index;
+            if let Some(u) = &update {
+                sub.amount = u.amount;
+                sub.interval_seconds = u.interval_seconds;
+                if ledger_schedule.is_none() {
+                    period_index = now / u.interval_seconds;
+                }
+                remove_pending_update(env, subscription_id);
+                env.events().publish(
+                    (Symbol::new(env, "update_applied"), subscription_id),
+                    u.clone(),
+                );
+            }
             env.storage().instance().set(&subscription_id, &sub);
 
             // Record charged period and optional idempotency key (bounded storage)
@@ -99,31 +650,126 @@ pub fn charge_one(
             if let Some(k) = idempotency_key {
                 env.storage().instance().set(&idem_key(subscription_id), &k);
             }
+            increment_periods_charged(env, subscription_id);
+            let tax = if test_mode {
+                0
+            } else {
+                record_charged(env, subscription_id, &sub, charge_amount)?
+            };
+            if donation > 0 {
+                route_donation(env, subscription_id, &sub.subscriber, donation)?;
+            }
+            if let Some(mut ls) = ledger_schedule {
+                ls.last_payment_ledger = seq;
+                env.storage()
+                    .instance()
+                    .set(&DataKey::SubLedgerSchedule(subscription_id), &ls);
+            }
 
             env.events().publish(
                 (symbol_short!("charged"),),
                 SubscriptionChargedEvent {
                     subscription_id,
                     merchant: sub.merchant.clone(),
-                    amount: sub.amount,
+                    amount: charge_amount,
+                    tax,
                 },
             );
+            if retainer.is_none() {
+                record_term_period(env, subscription_id, &mut sub)?;
+            }
+            notify_charged(env, &sub.merchant, subscription_id, charge_amount);
 
             Ok(())
         }
+        Err(_) if retainer.is_some() => Err(Error::InsufficientBalance),
+        Err(_) if now < grace_deadline(env, subscription_id, &sub) => {
+            Err(Error::InsufficientBalance)
+        }
         Err(_) => {
-            validate_status_transition(&sub.status, &SubscriptionStatus::InsufficientBalance)?;
-            sub.status = SubscriptionStatus::InsufficientBalance;
+            apply_transition(
+                env,
+                subscription_id,
+                &mut sub,
+                SubscriptionStatus::InsufficientBalance,
+            )?;
             env.storage().instance().set(&subscription_id, &sub);
             Err(Error::InsufficientBalance)
         }
     }
 }
 
+/// Pulls `amount` from the subscriber's wallet into the vault against its allowance.
+/// Returns whether the transfer succeeded.
+fn pull_from_wallet(
+    env: &Env,
+    subscription_id: u32,
+    subscriber: &Address,
+    token: &Address,
+    amount: i128,
+) -> bool {
+    let vault = env.current_contract_address();
+    let client = soroban_sdk::token::Client::new(env, token);
+    if !matches!(
+        client.try_transfer_from(&vault, subscriber, &vault, &amount),
+        Ok(Ok(()))
+    ) {
+        return false;
+    }
+    track_liabilities(env, token, amount);
+    env.events().publish(
+        (Symbol::new(env, "pulled"), subscription_id),
+        (subscriber.clone(), amount),
+    );
+    true
+}
+
+/// Flags the subscription for review when `now` is more than the configured max
+/// elapsed time past the charge's due time. The charge is refused either way; the
+/// flag persists when the charge runs in a batch, like the `InsufficientBalance`
+/// transition.
+fn check_clock_jump(
+    env: &Env,
+    subscription_id: u32,
+    sub: &Subscription,
+    now: u64,
+) -> Result<(), Error> {
+    let max_elapsed = get_max_charge_elapsed(env);
+    let due_at = sub
+        .last_payment_timestamp
+        .saturating_add(sub.interval_seconds);
+    if max_elapsed == 0 || now.saturating_sub(due_at) <= max_elapsed {
+        return Ok(());
+    }
+    let review = ChargeReview {
+        subscription_id,
+        due_at,
+        attempted_at: now,
+    };
+    env.storage()
+        .instance()
+        .set(&(KEY_REVIEW, subscription_id), &review);
+    env.events()
+        .publish((Symbol::new(env, "charge_review_flagged"),), review);
+    Err(Error::ChargeUnderReview)
+}
+
+/// Time until which an underfunded charge leaves the subscription `Active`: the
+/// due time plus the grace period of its tier (zero for `Standard`).
+pub fn grace_deadline(env: &Env, subscription_id: u32, sub: &Subscription) -> u64 {
+    let grace = priority_policy_for(env, subscription_id, &sub.merchant)
+        .map(|p| p.grace_seconds)
+        .unwrap_or(0);
+    sub.last_payment_timestamp
+        .saturating_add(sub.interval_seconds)
+        .saturating_add(grace)
+}
+
 /// Debit a metered `usage_amount` from a subscription's prepaid balance.
 ///
 /// Shared safety checks:
 /// * Subscription must exist (`NotFound`).
+/// * `caller` must be the merchant or a `Metering` API key (`Unauthorized`).
 /// * Subscription must be `Active` (`NotActive`).
 /// * `usage_enabled` must be `true` (`UsageNotEnabled`).
 /// * `usage_amount` must be positive (`InvalidAmount`).
@@ -132,12 +778,19 @@ pub fn charge_one(
 /// On success the prepaid balance is reduced.  If the balance reaches zero
 /// the subscription transitions to `InsufficientBalance`, blocking further
 /// charges until the subscriber tops up.
-pub fn charge_usage_one(env: &Env, subscription_id: u32, usage_amount: i128) -> Result<(), Error> {
+pub fn charge_usage_one(
+    env: &Env,
+    caller: Address,
+    subscription_id: u32,
+    usage_amount: i128,
+) -> Result<(), Error> {
     let mut sub = get_subscription(env, subscription_id)?;
+    require_merchant_scope(env, &sub.merchant, &caller, ApiKeyScope::Metering)?;
 
-    if sub.status != SubscriptionStatus::Active {
+    if sub.status != SubscriptionStatus::Active || is_past_expiry(env, subscription_id) {
         return Err(Error::NotActive);
     }
+    require_merchant_not_halted(env, &sub.merchant)?;
 
     if !sub.usage_enabled {
         return Err(Error::UsageNotEnabled);
@@ -146,23 +799,53 @@ pub fn charge_usage_one(env: &Env, subscription_id: u32, usage_amount: i128) ->
     if usage_amount <= 0 {
         return Err(Error::InvalidAmount);
     }
+    if is_test_mode(env, subscription_id) {
+        return Ok(());
+    }
 
     if sub.prepaid_balance < usage_amount {
         return Err(Error::InsufficientPrepaidBalance);
     }
+    check_budget(env, &sub.subscriber, usage_amount)?;
 
-    sub.prepaid_balance = sub
-        .prepaid_balance
-        .checked_sub(usage_amount)
-        .ok_or(Error::Overflow)?;
+    sub.prepaid_balance = safe_sub_balance(sub.prepaid_balance, usage_amount)?;
 
     // If the vault is now empty, transition to InsufficientBalance so no
     // further charges (interval or usage) can proceed until top-up.
     if sub.prepaid_balance == 0 {
-        validate_status_transition(&sub.status, &SubscriptionStatus::InsufficientBalance)?;
-        sub.status = SubscriptionStatus::InsufficientBalance;
+        apply_transition(
+            env,
+            subscription_id,
+            &mut sub,
+            SubscriptionStatus::InsufficientBalance,
+        )?;
     }
 
     env.storage().instance().set(&subscription_id, &sub);
+    record_charged(env, subscription_id, &sub, usage_amount)?;
+    notify_charged(env, &sub.merchant, subscription_id, usage_amount);
     Ok(())
 }
+
+/// Debits `amount` outside the normal schedule (no interval or replay checks).
+///
+/// Bounded by the subscription's per-interval `amount` (the ceiling the subscriber
+/// authorized) and by its prepaid balance. Status is left unchanged, and test-mode
+/// subscriptions are not debited at all. Callers are responsible for authorization
+/// and audit events.
+pub fn force_debit(env: &Env, subscription_id: u32, amount: i128) -> Result<Subscription, Error> {
+    let mut sub = get_subscription(env, subscription_id)?;
+    if amount <= 0 || amount > sub.amount {
+        return Err(Error::InvalidAmount);
+    }
+    require_merchant_not_halted(env, &sub.merchant)?;
+    if is_test_mode(env, subscription_id) {
+        return Ok(sub);
+    }
+    check_budget(env, &sub.subscriber, amount)?;
+    sub.prepaid_balance = safe_sub_balance(sub.prepaid_balance, amount)
+        .map_err(|_| Error::InsufficientPrepaidBalance)?;
+    env.storage().instance().set(&subscription_id, &sub);
+    record_charged(env, subscription_id, &sub, amount)?;
+    Ok(sub)
+}
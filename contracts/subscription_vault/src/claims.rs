@@ -0,0 +1,70 @@
+//! Claims registry: outbound transfers that could not be delivered.
+//!
+//! **PRs that only change deferred-transfer or claim behavior should edit this file only.**
+//!
+//! Outbound transfers to merchants, subscribers, and the donation address go through
+//! [`transfer_or_defer`]. If the token contract rejects the transfer (e.g. a frozen
+//! trustline on a wrapped asset), the amount is credited to the recipient's claim
+//! balance and a `transfer_deferred` event is emitted instead of reverting, so the
+//! surrounding charge, withdrawal, or payout still completes. The recipient pulls the
+//! balance later with `claim`.
+
+use crate::keeper::track_liabilities;
+use crate::safe_math::safe_add_balance;
+use crate::types::{DataKey, Error, TransferDeferredEvent};
+use soroban_sdk::{token, Address, Env, Symbol};
+
+pub fn get_claimable(env: &Env, recipient: &Address, token: &Address) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::Claim(recipient.clone(), token.clone()))
+        .unwrap_or(0)
+}
+
+/// Transfers `amount` of `token` from the vault to `to`, or records it as a claim
+/// if the transfer fails. Returns `true` if the transfer was delivered.
+pub fn transfer_or_defer(
+    env: &Env,
+    token: &Address,
+    to: &Address,
+    amount: i128,
+) -> Result<bool, Error> {
+    let client = token::Client::new(env, token);
+    if let Ok(Ok(())) = client.try_transfer(&env.current_contract_address(), to, &amount) {
+        track_liabilities(env, token, -amount);
+        return Ok(true);
+    }
+
+    let balance = safe_add_balance(get_claimable(env, to, token), amount)?;
+    env.storage()
+        .instance()
+        .set(&DataKey::Claim(to.clone(), token.clone()), &balance);
+    env.events().publish(
+        (Symbol::new(env, "transfer_deferred"), to.clone()),
+        TransferDeferredEvent {
+            recipient: to.clone(),
+            token: token.clone(),
+            amount,
+        },
+    );
+    Ok(false)
+}
+
+/// Pays out the recipient's whole claim balance in `token`. Auth: recipient.
+///
+/// Unlike [`transfer_or_defer`], a failing transfer reverts so the claim is kept.
+pub fn do_claim(env: &Env, recipient: Address, token: Address) -> Result<i128, Error> {
+    recipient.require_auth();
+    let amount = get_claimable(env, &recipient, &token);
+    if amount <= 0 {
+        return Err(Error::NotFound);
+    }
+    env.storage()
+        .instance()
+        .remove(&DataKey::Claim(recipient.clone(), token.clone()));
+    token::Client::new(env, &token).transfer(&env.current_contract_address(), &recipient, &amount);
+    track_liabilities(env, &token, -amount);
+    env.events()
+        .publish((Symbol::new(env, "claimed"), recipient), (token, amount));
+    Ok(amount)
+}
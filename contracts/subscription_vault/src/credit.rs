@@ -0,0 +1,347 @@
+//! Subscriber credit wallet: an account-level balance usable across subscriptions.
+//!
+//! **PRs that only change credit wallet behavior should edit this file only.**
+//!
+//! Credits are held per (subscriber, token). Refunds (e.g. cooling-off refunds) and
+//! promo credits land here, subscribers can top it up or withdraw from it directly,
+//! and interval charges draw from it before touching a subscription's
+//! `prepaid_balance`.
+//!
+//! # Refund routing
+//!
+//! A subscriber presets which share of their refunds (in basis points) is
+//! transferred out to their address; the rest stays in the wallet, instantly
+//! reusable and fee-free. The external share pays the admin-set external refund
+//! fee, which is left with the merchant; cooling-off refunds are exempt. While the
+//! subscriber is locked, refunds stay in the wallet.
+//!
+//! # Promo credits
+//!
+//! A merchant may move part of its accrued (queued, unpaid) balance into a
+//! [`PromoCredit`] for a specific subscriber. Promo credit only pays that merchant's
+//! charges, is used before the general wallet, and after `expires_at` can no longer
+//! be spent; the remainder is returned to the merchant's payout queue.
+
+use crate::admin::{get_external_refund_fee, get_token, require_deposit_capacity};
+use crate::claims::transfer_or_defer;
+use crate::keeper::track_liabilities;
+use crate::merchant::{get_payout_queue, requeue_payout, unqueue_payout};
+use crate::safe_math::{safe_add_balance, safe_sub_balance, split_bps, BPS_DENOMINATOR};
+use crate::subscription::is_locked;
+use crate::types::{DataKey, Error, PromoCredit, RefundRoutedEvent};
+use soroban_sdk::{symbol_short, token, Address, Env, Symbol};
+
+pub fn get_credit_balance(env: &Env, subscriber: &Address, token: &Address) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::SubscriberCredit(
+            subscriber.clone(),
+            token.clone(),
+        ))
+        .unwrap_or(0)
+}
+
+fn set_credit_balance(env: &Env, subscriber: &Address, token: &Address, balance: i128) {
+    let key = DataKey::SubscriberCredit(subscriber.clone(), token.clone());
+    if balance == 0 {
+        env.storage().instance().remove(&key);
+    } else {
+        env.storage().instance().set(&key, &balance);
+    }
+}
+
+/// Adds `amount` to the subscriber's wallet. The tokens must already be held by the vault.
+pub fn credit_wallet(
+    env: &Env,
+    subscriber: &Address,
+    token: &Address,
+    amount: i128,
+) -> Result<i128, Error> {
+    let balance = safe_add_balance(get_credit_balance(env, subscriber, token), amount)?;
+    set_credit_balance(env, subscriber, token, balance);
+    env.events().publish(
+        (Symbol::new(env, "credit_added"), subscriber.clone()),
+        (token.clone(), amount, balance),
+    );
+    Ok(balance)
+}
+
+/// Removes `amount` from the subscriber's wallet (`InsufficientBalance` if short).
+pub fn debit_wallet(
+    env: &Env,
+    subscriber: &Address,
+    token: &Address,
+    amount: i128,
+) -> Result<i128, Error> {
+    let balance = safe_sub_balance(get_credit_balance(env, subscriber, token), amount)
+        .map_err(|_| Error::InsufficientBalance)?;
+    set_credit_balance(env, subscriber, token, balance);
+    env.events().publish(
+        (Symbol::new(env, "credit_used"), subscriber.clone()),
+        (token.clone(), amount, balance),
+    );
+    Ok(balance)
+}
+
+/// Transfer `amount` of the current settlement token into the subscriber's wallet.
+/// Auth: subscriber.
+pub fn do_deposit_credit(env: &Env, subscriber: Address, amount: i128) -> Result<i128, Error> {
+    subscriber.require_auth();
+    if amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    if is_locked(env, &subscriber) {
+        return Err(Error::SubscriberLocked);
+    }
+    let token_addr = get_token(env)?;
+    require_deposit_capacity(env, None, &token_addr, None, amount)?;
+    token::Client::new(env, &token_addr).transfer(
+        &subscriber,
+        &env.current_contract_address(),
+        &amount,
+    );
+    track_liabilities(env, &token_addr, amount);
+    credit_wallet(env, &subscriber, &token_addr, amount)
+}
+
+/// Withdraw `amount` of `token` from the subscriber's wallet. Auth: subscriber.
+pub fn do_withdraw_credit(
+    env: &Env,
+    subscriber: Address,
+    token: Address,
+    amount: i128,
+) -> Result<i128, Error> {
+    subscriber.require_auth();
+    if amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    if is_locked(env, &subscriber) {
+        return Err(Error::SubscriberLocked);
+    }
+    let balance = debit_wallet(env, &subscriber, &token, amount)?;
+    transfer_or_defer(env, &token, &subscriber, amount)?;
+    Ok(balance)
+}
+
+const KEY_REFUND_SPLIT: Symbol = symbol_short!("rsplit");
+
+/// Share of the subscriber's refunds, in basis points, transferred to their address.
+pub fn get_refund_split(env: &Env, subscriber: &Address) -> u32 {
+    env.storage()
+        .instance()
+        .get(&(KEY_REFUND_SPLIT, subscriber.clone()))
+        .unwrap_or(0)
+}
+
+/// Drops the subscriber's refund preference (used when their account is closed).
+pub fn remove_refund_split(env: &Env, subscriber: &Address) {
+    env.storage()
+        .instance()
+        .remove(&(KEY_REFUND_SPLIT, subscriber.clone()));
+}
+
+/// Preset the share of future refunds sent to the subscriber's address instead of
+/// the credit wallet. `0` keeps everything in the wallet. Auth: subscriber.
+pub fn do_set_refund_split(env: &Env, subscriber: Address, external_bps: u32) -> Result<(), Error> {
+    subscriber.require_auth();
+    if external_bps as i128 > BPS_DENOMINATOR {
+        return Err(Error::InvalidAmount);
+    }
+    if external_bps == 0 {
+        remove_refund_split(env, &subscriber);
+    } else {
+        env.storage()
+            .instance()
+            .set(&(KEY_REFUND_SPLIT, subscriber.clone()), &external_bps);
+    }
+    env.events().publish(
+        (Symbol::new(env, "refund_split_set"), subscriber),
+        external_bps,
+    );
+    Ok(())
+}
+
+/// How a refund of `amount` would be split right now: `(to_wallet, to_external,
+/// fee)`, where the external fee, if `charge_fee`, is withheld from the external
+/// share.
+pub fn split_refund(
+    env: &Env,
+    subscriber: &Address,
+    amount: i128,
+    charge_fee: bool,
+) -> Result<(i128, i128, i128), Error> {
+    let external_bps = if is_locked(env, subscriber) {
+        0
+    } else {
+        get_refund_split(env, subscriber)
+    };
+    let (external, to_wallet) = split_bps(amount, external_bps)?;
+    let fee_bps = if charge_fee {
+        get_external_refund_fee(env)
+    } else {
+        0
+    };
+    let (fee, to_external) = split_bps(external, fee_bps)?;
+    Ok((to_wallet, to_external, fee))
+}
+
+/// Pays a refund of `amount` (already held by the vault) according to the
+/// subscriber's refund split, withholding the external refund fee from the external
+/// share if `charge_fee`. Returns the fee withheld, which the caller leaves with the
+/// merchant.
+pub fn route_refund(
+    env: &Env,
+    subscriber: &Address,
+    token: &Address,
+    amount: i128,
+    charge_fee: bool,
+) -> Result<i128, Error> {
+    let (to_wallet, to_external, fee) = split_refund(env, subscriber, amount, charge_fee)?;
+    if to_wallet > 0 {
+        credit_wallet(env, subscriber, token, to_wallet)?;
+    }
+    if to_external > 0 {
+        transfer_or_defer(env, token, subscriber, to_external)?;
+    }
+    env.events().publish(
+        (Symbol::new(env, "refund_routed"), subscriber.clone()),
+        RefundRoutedEvent {
+            subscriber: subscriber.clone(),
+            token: token.clone(),
+            to_wallet,
+            to_external,
+            fee,
+        },
+    );
+    Ok(fee)
+}
+
+pub fn get_promo_credit(
+    env: &Env,
+    subscriber: &Address,
+    merchant: &Address,
+) -> Option<PromoCredit> {
+    env.storage()
+        .instance()
+        .get(&DataKey::PromoCredit(subscriber.clone(), merchant.clone()))
+}
+
+/// Spendable promo credit from `merchant` in `token` right now (0 once expired).
+pub fn available_promo_credit(
+    env: &Env,
+    subscriber: &Address,
+    merchant: &Address,
+    token: &Address,
+) -> i128 {
+    match get_promo_credit(env, subscriber, merchant) {
+        Some(promo) if promo.token == *token && env.ledger().timestamp() < promo.expires_at => {
+            promo.amount
+        }
+        _ => 0,
+    }
+}
+
+/// Spends `amount` of promo credit; callers check [`available_promo_credit`] first.
+pub fn use_promo_credit(
+    env: &Env,
+    subscriber: &Address,
+    merchant: &Address,
+    amount: i128,
+) -> Result<(), Error> {
+    let key = DataKey::PromoCredit(subscriber.clone(), merchant.clone());
+    let mut promo = get_promo_credit(env, subscriber, merchant).ok_or(Error::NotFound)?;
+    promo.amount =
+        safe_sub_balance(promo.amount, amount).map_err(|_| Error::InsufficientBalance)?;
+    if promo.amount == 0 {
+        env.storage().instance().remove(&key);
+    } else {
+        env.storage().instance().set(&key, &promo);
+    }
+    env.events().publish(
+        (Symbol::new(env, "promo_used"), subscriber.clone()),
+        (merchant.clone(), amount, promo.amount),
+    );
+    Ok(())
+}
+
+/// Returns an expired promo's remainder to the merchant's payout queue.
+fn reclaim_expired(env: &Env, subscriber: &Address, merchant: &Address) -> Result<i128, Error> {
+    let promo = match get_promo_credit(env, subscriber, merchant) {
+        Some(promo) if env.ledger().timestamp() >= promo.expires_at => promo,
+        _ => return Ok(0),
+    };
+    env.storage()
+        .instance()
+        .remove(&DataKey::PromoCredit(subscriber.clone(), merchant.clone()));
+    requeue_payout(env, merchant, &promo.token, promo.amount)?;
+    env.events().publish(
+        (Symbol::new(env, "promo_expired"), subscriber.clone()),
+        (merchant.clone(), promo.amount),
+    );
+    Ok(promo.amount)
+}
+
+/// Grant `amount` of promo credit from the merchant's accrued balance in `token`.
+///
+/// Auth: merchant. Adds to an existing unexpired grant in the same token (keeping
+/// the later expiry); an expired grant is reclaimed first.
+pub fn do_grant_promo_credit(
+    env: &Env,
+    merchant: Address,
+    subscriber: Address,
+    token: Address,
+    amount: i128,
+    expires_at: u64,
+) -> Result<PromoCredit, Error> {
+    merchant.require_auth();
+    if amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    if expires_at <= env.ledger().timestamp() {
+        return Err(Error::InvalidAmount);
+    }
+    reclaim_expired(env, &subscriber, &merchant)?;
+    if get_payout_queue(env, &merchant, &token).pending < amount {
+        return Err(Error::InsufficientBalance);
+    }
+
+    let promo = match get_promo_credit(env, &subscriber, &merchant) {
+        Some(existing) if existing.token != token => return Err(Error::InvalidAmount),
+        Some(existing) => PromoCredit {
+            token: token.clone(),
+            amount: safe_add_balance(existing.amount, amount)?,
+            expires_at: existing.expires_at.max(expires_at),
+        },
+        None => PromoCredit {
+            token: token.clone(),
+            amount,
+            expires_at,
+        },
+    };
+    unqueue_payout(env, &merchant, &token, amount);
+    env.storage().instance().set(
+        &DataKey::PromoCredit(subscriber.clone(), merchant.clone()),
+        &promo,
+    );
+    env.events().publish(
+        (Symbol::new(env, "promo_granted"), subscriber),
+        (merchant, amount, promo.expires_at),
+    );
+    Ok(promo)
+}
+
+/// Return an expired promo's remainder to the merchant. Auth: merchant.
+pub fn do_reclaim_promo_credit(
+    env: &Env,
+    merchant: Address,
+    subscriber: Address,
+) -> Result<i128, Error> {
+    merchant.require_auth();
+    if get_promo_credit(env, &subscriber, &merchant).is_none() {
+        return Err(Error::NotFound);
+    }
+    match reclaim_expired(env, &subscriber, &merchant)? {
+        0 => Err(Error::TimelockNotElapsed),
+        amount => Ok(amount),
+    }
+}
@@ -0,0 +1,174 @@
+//! Contract error codes.
+//!
+//! **PRs that only add or renumber errors should edit this file only.**
+//!
+//! Codes are grouped into ranges of 100 so clients can classify an error by its
+//! code alone, and each range has room to grow:
+//!
+//! | Range | Category | Meaning |
+//! |-------|----------|---------|
+//! | 400–499 | [`ErrorCategory::AuthOrState`] | Caller not allowed, or the subscription/vault is in the wrong state. Retrying unchanged will fail again. |
+//! | 500–599 | [`ErrorCategory::Accounting`] | Amounts, balances and arithmetic. Often resolved by a top-up or a different amount. |
+//! | 600–699 | [`ErrorCategory::Config`] | Admin- or merchant-configured limits and settings. |
+//!
+//! New variants take the next free code in their range. Codes are part of the public
+//! interface (they appear in `Error(Contract, #code)` and batch results), so a
+//! variant is never moved to another code once released. The one-time move into
+//! these ranges is listed in `docs/integration_guide.md`.
+
+use soroban_sdk::contracterror;
+
+#[contracterror]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum Error {
+    // ── 4xx: authorization and state ──────────────────────────────────────────
+    InvalidStatusTransition = 400,
+    Unauthorized = 401,
+    NotFound = 404,
+    /// Subscription is not Active (e.g. Paused, Cancelled).
+    NotActive = 405,
+    /// Charge attempted before `last_payment_timestamp + interval_seconds`.
+    IntervalNotElapsed = 406,
+    /// Charge already processed for this billing period.
+    Replay = 407,
+    /// Subscriber has engaged the emergency lock; deposits and withdrawals are frozen.
+    SubscriberLocked = 408,
+    /// A guarded entrypoint was re-entered while a token call was in flight.
+    Reentrancy = 409,
+    /// Subscriber's allow-list mode is on and the merchant is not approved.
+    MerchantNotApproved = 410,
+    /// A merchant stake is required and the merchant is not registered (or is de-registering).
+    MerchantNotStaked = 411,
+    /// A signed off-chain agreement or action was submitted after its expiry.
+    AgreementExpired = 412,
+    /// The subscriber still has live subscriptions or funds held by the vault.
+    AccountNotSettled = 413,
+    /// The admin has halted charging for the subscription's merchant.
+    MerchantHalted = 414,
+    /// The terms hash does not match the merchant's current terms version.
+    TermsMismatch = 415,
+    /// The subscriptions differ in owner, merchant, price, interval or token.
+    SubscriptionsMismatch = 416,
+    /// `init` was called on a vault that already has an admin.
+    AlreadyInitialized = 417,
+    /// The subscription is flagged for manual review after an implausible clock jump.
+    ChargeUnderReview = 418,
+    /// A timelocked admin action was executed before its delay elapsed.
+    TimelockNotElapsed = 419,
+    /// Subscription label exceeds [`crate::types::MAX_LABEL_LEN`] bytes.
+    InvalidLabel = 420,
+    /// A subscription was already created with this derived subscription key.
+    SubscriptionKeyTaken = 421,
+    /// An auto top-up was executed while the prepaid balance is at or above its threshold.
+    AboveThreshold = 422,
+
+    // ── 5xx: accounting ───────────────────────────────────────────────────────
+    /// Arithmetic overflow in computation (e.g. amount * intervals).
+    Overflow = 500,
+    /// Arithmetic underflow (e.g. negative amount or balance would go negative).
+    Underflow = 501,
+    /// Charge failed due to insufficient prepaid balance.
+    InsufficientBalance = 502,
+    /// Usage-based charge amount exceeds the available prepaid balance.
+    InsufficientPrepaidBalance = 503,
+    /// The provided amount is zero or negative.
+    InvalidAmount = 504,
+    /// Recovery amount is zero or negative.
+    InvalidRecoveryAmount = 505,
+    BelowMinimumTopup = 506,
+    /// Withdrawal would leave the prepaid balance below the merchant's reserve.
+    BelowReserve = 507,
+    /// The charge would take the subscriber past their monthly spend cap.
+    BudgetExceeded = 508,
+    /// The per-interval amount is below the merchant's configured minimum.
+    BelowMerchantMinimum = 509,
+    /// The deposit would exceed a beta deposit cap (vault TVL or subscription balance).
+    DepositCapExceeded = 510,
+
+    // ── 6xx: configuration and limits ─────────────────────────────────────────
+    /// A bounded collection (e.g. merchant API keys) is already full.
+    LimitExceeded = 600,
+    /// Operation rejected by an admin-configured rate limit (e.g. deposits).
+    RateLimited = 601,
+    /// The token reports different `decimals()` than the vault's amounts are denominated in.
+    DecimalsMismatch = 602,
+    /// Usage-based charge attempted on a subscription with `usage_enabled = false`.
+    UsageNotEnabled = 603,
+}
+
+/// Range an [`Error`] code falls in; see the module docs.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ErrorCategory {
+    AuthOrState,
+    Accounting,
+    Config,
+}
+
+impl Error {
+    /// Returns the numeric code for this error (for batch result reporting).
+    pub const fn to_code(self) -> u32 {
+        self as u32
+    }
+
+    pub const fn category(self) -> ErrorCategory {
+        match self {
+            Error::InvalidStatusTransition
+            | Error::Unauthorized
+            | Error::NotFound
+            | Error::NotActive
+            | Error::IntervalNotElapsed
+            | Error::Replay
+            | Error::SubscriberLocked
+            | Error::Reentrancy
+            | Error::MerchantNotApproved
+            | Error::MerchantNotStaked
+            | Error::AgreementExpired
+            | Error::AccountNotSettled
+            | Error::MerchantHalted
+            | Error::TermsMismatch
+            | Error::SubscriptionsMismatch
+            | Error::AlreadyInitialized
+            | Error::ChargeUnderReview
+            | Error::TimelockNotElapsed
+            | Error::InvalidLabel
+            | Error::SubscriptionKeyTaken
+            | Error::AboveThreshold => ErrorCategory::AuthOrState,
+            Error::Overflow
+            | Error::Underflow
+            | Error::InsufficientBalance
+            | Error::InsufficientPrepaidBalance
+            | Error::InvalidAmount
+            | Error::InvalidRecoveryAmount
+            | Error::BelowMinimumTopup
+            | Error::BelowReserve
+            | Error::BudgetExceeded
+            | Error::BelowMerchantMinimum
+            | Error::DepositCapExceeded => ErrorCategory::Accounting,
+            Error::LimitExceeded
+            | Error::RateLimited
+            | Error::DecimalsMismatch
+            | Error::UsageNotEnabled => ErrorCategory::Config,
+        }
+    }
+}
+
+impl ErrorCategory {
+    /// Category of a numeric error code, e.g. a batch result's `error_code`, or
+    /// `None` if the code is outside 400–699 (not a vault error, or a code from
+    /// before the ranges were introduced).
+    pub const fn from_code(code: u32) -> Option<ErrorCategory> {
+        match code / 100 {
+            4 => Some(ErrorCategory::AuthOrState),
+            5 => Some(ErrorCategory::Accounting),
+            6 => Some(ErrorCategory::Config),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for ErrorCategory {
+    fn from(error: Error) -> Self {
+        error.category()
+    }
+}
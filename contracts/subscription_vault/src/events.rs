@@ -0,0 +1,26 @@
+//! Size-bounded event publishing; see the payload convention in `types.rs`.
+
+use crate::types::{DataKey, EventPayloadRef, MAX_EVENT_PAYLOAD_BYTES};
+use soroban_sdk::xdr::ToXdr;
+use soroban_sdk::{Env, IntoVal, Topics, Val};
+
+/// Publishes `payload` inline if its XDR encoding fits the bound, otherwise an
+/// [`EventPayloadRef`] pointing at `key`, where the caller has stored it.
+pub fn publish_bounded<T, P>(env: &Env, topics: T, payload: P, key: DataKey)
+where
+    T: Topics,
+    P: IntoVal<Env, Val>,
+{
+    let payload: Val = payload.into_val(env);
+    let xdr = payload.to_xdr(env);
+    if xdr.len() <= MAX_EVENT_PAYLOAD_BYTES {
+        env.events().publish(topics, payload);
+    } else {
+        let body = EventPayloadRef {
+            hash: env.crypto().sha256(&xdr).into(),
+            size: xdr.len(),
+            key,
+        };
+        env.events().publish(topics, body);
+    }
+}
@@ -0,0 +1,368 @@
+//! Fee computation helpers: admin-defined fee waiver campaigns.
+//!
+//! **PRs that only change fee handling should edit this file only.**
+//!
+//! The admin defines time-boxed [`FeeWaiverCampaign`]s (e.g. no protocol fee for
+//! new merchants for 90 days). A merchant is enrolled automatically at its first
+//! charge while a campaign it is eligible for is open; the enrollment records its
+//! own start timestamp, so each merchant gets the full `duration_seconds` from then.
+//! `fee_waiver_bps` is the share of any fee waived for a merchant right now.
+//!
+//! # Protocol fee
+//!
+//! The admin may set a [`ProtocolFeeConfig`]: `fee_bps` of every charge, net of
+//! tax, is withheld from the merchant's payout and accrued per token to the fee
+//! collector configured at the time, who withdraws it with `withdraw_fees` (also
+//! after the fee is cleared or the collector replaced). A merchant's fee waiver
+//! reduces the fee. Each charge emits a `protocol_fee` event with the gross, the
+//! fee and the waived part, so the split can be audited per charge. A refund takes
+//! the subscription's fees back pro rata from the collectors' accruals, as far as
+//! they have not been withdrawn.
+
+use crate::admin::{log_admin_action, require_admin};
+use crate::claims::transfer_or_defer;
+use crate::receipts::get_receipt_head;
+use crate::safe_math::{bps_fee, mul_div, safe_add, safe_add_balance, Rounding, BPS_DENOMINATOR};
+use crate::types::{
+    DataKey, Error, FeeWaiverCampaign, MerchantFeeWaiver, ProtocolFeeChargedEvent,
+    ProtocolFeeConfig,
+};
+use soroban_sdk::{symbol_short, Address, Env, Symbol, Vec};
+
+/// Maximum number of campaigns kept (open or closed).
+pub const MAX_FEE_CAMPAIGNS: u32 = 10;
+
+/// Highest protocol fee the admin may set (10%).
+pub const MAX_PROTOCOL_FEE_BPS: u32 = 1_000;
+
+const KEY_ACCRUED_FEES: Symbol = symbol_short!("pfees");
+const KEY_SUB_FEES: Symbol = symbol_short!("subfees");
+
+pub fn get_fee_campaigns(env: &Env) -> Vec<FeeWaiverCampaign> {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "fee_campaigns"))
+        .unwrap_or(Vec::new(env))
+}
+
+fn set_fee_campaigns(env: &Env, campaigns: &Vec<FeeWaiverCampaign>) {
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "fee_campaigns"), campaigns);
+}
+
+/// Define a fee waiver campaign open for enrollment in `[enroll_from, enroll_until)`.
+/// Admin only. Returns the campaign id.
+pub fn do_create_fee_campaign(
+    env: &Env,
+    admin: Address,
+    waiver_bps: u32,
+    duration_seconds: u64,
+    enroll_from: u64,
+    enroll_until: u64,
+    new_merchants_only: bool,
+) -> Result<u32, Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    if waiver_bps == 0
+        || waiver_bps as i128 > BPS_DENOMINATOR
+        || duration_seconds == 0
+        || enroll_until <= enroll_from
+    {
+        return Err(Error::InvalidAmount);
+    }
+    let mut campaigns = get_fee_campaigns(env);
+    if campaigns.len() >= MAX_FEE_CAMPAIGNS {
+        return Err(Error::LimitExceeded);
+    }
+    let id = campaigns.last().map(|c| c.id + 1).unwrap_or(1);
+    let campaign = FeeWaiverCampaign {
+        id,
+        waiver_bps,
+        duration_seconds,
+        enroll_from,
+        enroll_until,
+        new_merchants_only,
+    };
+    campaigns.push_back(campaign.clone());
+    set_fee_campaigns(env, &campaigns);
+    log_admin_action(env, &admin, "create_fee_campaign");
+    env.events()
+        .publish((Symbol::new(env, "fee_campaign_created"), id), campaign);
+    Ok(id)
+}
+
+/// Close a campaign to new enrollments and drop it. Existing enrollments run to
+/// their end. Admin only.
+pub fn do_end_fee_campaign(env: &Env, admin: Address, campaign_id: u32) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let mut campaigns = get_fee_campaigns(env);
+    let index = campaigns
+        .iter()
+        .position(|c| c.id == campaign_id)
+        .ok_or(Error::NotFound)?;
+    campaigns.remove(index as u32);
+    set_fee_campaigns(env, &campaigns);
+    log_admin_action(env, &admin, "end_fee_campaign");
+    env.events()
+        .publish((Symbol::new(env, "fee_campaign_ended"), campaign_id), ());
+    Ok(())
+}
+
+pub fn get_merchant_fee_waiver(env: &Env, merchant: &Address) -> Option<MerchantFeeWaiver> {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantFeeWaiver(merchant.clone()))
+}
+
+/// Enrolls `merchant` in the first open campaign it is eligible for, unless it is
+/// already in a running waiver. Called on every charge, before its receipt is
+/// appended, so a merchant without receipts counts as new.
+pub fn enroll_fee_waiver(env: &Env, merchant: &Address) {
+    let now = env.ledger().timestamp();
+    let current = get_merchant_fee_waiver(env, merchant);
+    if matches!(&current, Some(w) if now < w.expires_at) {
+        return;
+    }
+    let is_new = get_receipt_head(env, merchant).sequence == 0;
+    let campaign = get_fee_campaigns(env).iter().find(|c| {
+        now >= c.enroll_from
+            && now < c.enroll_until
+            && (is_new || !c.new_merchants_only)
+            && !matches!(&current, Some(w) if w.campaign_id == c.id)
+    });
+    if let Some(campaign) = campaign {
+        let waiver = MerchantFeeWaiver {
+            campaign_id: campaign.id,
+            waiver_bps: campaign.waiver_bps,
+            started_at: now,
+            expires_at: now.saturating_add(campaign.duration_seconds),
+        };
+        env.storage()
+            .instance()
+            .set(&DataKey::MerchantFeeWaiver(merchant.clone()), &waiver);
+        env.events().publish(
+            (Symbol::new(env, "fee_waiver_started"), merchant.clone()),
+            waiver,
+        );
+    }
+}
+
+/// Share of fees (bps) waived for `merchant` at the current time.
+pub fn fee_waiver_bps(env: &Env, merchant: &Address) -> u32 {
+    match get_merchant_fee_waiver(env, merchant) {
+        Some(w) if env.ledger().timestamp() < w.expires_at => w.waiver_bps,
+        _ => 0,
+    }
+}
+
+pub fn get_protocol_fee(env: &Env) -> Option<ProtocolFeeConfig> {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "protocol_fee"))
+}
+
+/// Set or clear (`None`) the protocol fee. Admin only.
+///
+/// `fee_bps` may not exceed [`MAX_PROTOCOL_FEE_BPS`] (`InvalidAmount`).
+pub fn do_set_protocol_fee(
+    env: &Env,
+    admin: Address,
+    config: Option<ProtocolFeeConfig>,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let key = Symbol::new(env, "protocol_fee");
+    match &config {
+        Some(config) => {
+            if config.fee_bps > MAX_PROTOCOL_FEE_BPS {
+                return Err(Error::InvalidAmount);
+            }
+            env.storage().instance().set(&key, config);
+        }
+        None => env.storage().instance().remove(&key),
+    }
+    log_admin_action(env, &admin, "set_protocol_fee");
+    env.events()
+        .publish((Symbol::new(env, "protocol_fee_set"),), config);
+    Ok(())
+}
+
+/// Protocol fees accrued to `collector` in `token` and not yet withdrawn.
+pub fn get_accrued_fees(env: &Env, collector: &Address, token: &Address) -> i128 {
+    env.storage()
+        .instance()
+        .get(&(KEY_ACCRUED_FEES, collector.clone(), token.clone()))
+        .unwrap_or(0)
+}
+
+fn set_accrued_fees(env: &Env, collector: &Address, token: &Address, amount: i128) {
+    let key = (KEY_ACCRUED_FEES, collector.clone(), token.clone());
+    if amount == 0 {
+        env.storage().instance().remove(&key);
+    } else {
+        env.storage().instance().set(&key, &amount);
+    }
+}
+
+/// Protocol fees paid by a subscription's charges and not yet reversed, per
+/// collector they accrued to.
+fn get_subscription_fees(env: &Env, subscription_id: u32) -> Vec<(Address, i128)> {
+    env.storage()
+        .instance()
+        .get(&(KEY_SUB_FEES, subscription_id))
+        .unwrap_or(Vec::new(env))
+}
+
+pub fn remove_subscription_fees(env: &Env, subscription_id: u32) {
+    env.storage()
+        .instance()
+        .remove(&(KEY_SUB_FEES, subscription_id));
+}
+
+/// Accrues the protocol fee on `amount` charged to a subscription of `merchant`
+/// to the current collector. Returns the fee.
+pub fn take_protocol_fee(
+    env: &Env,
+    subscription_id: u32,
+    merchant: &Address,
+    token: &Address,
+    amount: i128,
+) -> Result<i128, Error> {
+    let config = match get_protocol_fee(env) {
+        Some(config) => config,
+        None => return Ok(0),
+    };
+    let full = bps_fee(amount, config.fee_bps)?;
+    if full == 0 {
+        return Ok(0);
+    }
+    let waived = bps_fee(full, fee_waiver_bps(env, merchant))?;
+    let fee = full - waived;
+    if fee > 0 {
+        let collector = &config.collector;
+        let accrued = safe_add_balance(get_accrued_fees(env, collector, token), fee)?;
+        set_accrued_fees(env, collector, token, accrued);
+        let mut paid = get_subscription_fees(env, subscription_id);
+        match paid.iter().position(|(c, _)| c == *collector) {
+            Some(i) => {
+                let (_, total) = paid.get(i as u32).unwrap();
+                paid.set(i as u32, (collector.clone(), safe_add_balance(total, fee)?));
+            }
+            None => paid.push_back((collector.clone(), fee)),
+        }
+        env.storage()
+            .instance()
+            .set(&(KEY_SUB_FEES, subscription_id), &paid);
+    }
+    env.events().publish(
+        (Symbol::new(env, "protocol_fee"), subscription_id),
+        ProtocolFeeChargedEvent {
+            subscription_id,
+            merchant: merchant.clone(),
+            token: token.clone(),
+            gross: amount,
+            fee,
+            waived,
+        },
+    );
+    Ok(fee)
+}
+
+/// Protocol fee part of refunding `amount` out of the subscription's `charged`
+/// total: its fees pro rata, rounded down.
+pub fn protocol_fee_share(
+    env: &Env,
+    subscription_id: u32,
+    amount: i128,
+    charged: i128,
+) -> Result<i128, Error> {
+    if amount <= 0 || charged <= 0 {
+        return Ok(0);
+    }
+    let mut fees = 0i128;
+    for (_, fee) in get_subscription_fees(env, subscription_id).iter() {
+        fees = safe_add(fees, fee)?;
+    }
+    Ok(mul_div(fees, amount.min(charged), charged, Rounding::Floor)?.min(fees))
+}
+
+/// How much of `share` could be reversed from the collectors' accruals right now.
+pub fn reversible_protocol_fee(
+    env: &Env,
+    subscription_id: u32,
+    token: &Address,
+    share: i128,
+) -> i128 {
+    let mut left = share;
+    for (collector, fee) in get_subscription_fees(env, subscription_id).iter() {
+        left -= fee.min(get_accrued_fees(env, &collector, token)).min(left);
+    }
+    share - left
+}
+
+/// Takes back up to `share` of the subscription's protocol fees from the accruals
+/// of the collectors they were paid to, so a refunded charge does not keep its fee.
+/// Returns the amount taken back.
+pub fn reverse_protocol_fee(
+    env: &Env,
+    subscription_id: u32,
+    token: &Address,
+    share: i128,
+) -> Result<i128, Error> {
+    let paid = get_subscription_fees(env, subscription_id);
+    let mut remaining = Vec::new(env);
+    let mut left = share;
+    for (collector, fee) in paid.iter() {
+        let accrued = get_accrued_fees(env, &collector, token);
+        let taken = fee.min(accrued).min(left);
+        if taken > 0 {
+            set_accrued_fees(env, &collector, token, accrued - taken);
+            left -= taken;
+            env.events().publish(
+                (Symbol::new(env, "protocol_fee_reversed"), subscription_id),
+                (collector.clone(), token.clone(), taken),
+            );
+        }
+        if fee > taken {
+            remaining.push_back((collector, fee - taken));
+        }
+    }
+    if remaining.is_empty() {
+        remove_subscription_fees(env, subscription_id);
+    } else {
+        env.storage()
+            .instance()
+            .set(&(KEY_SUB_FEES, subscription_id), &remaining);
+    }
+    Ok(share - left)
+}
+
+/// Withdraw all protocol fees accrued to `collector` in `token`. Auth: collector.
+///
+/// Accruals belong to the collector configured when each fee was charged, so they
+/// stay withdrawable after the fee is cleared or the collector replaced.
+pub fn do_withdraw_fees(env: &Env, collector: Address, token: Address) -> Result<i128, Error> {
+    collector.require_auth();
+    let amount = get_accrued_fees(env, &collector, &token);
+    if amount == 0 {
+        return Ok(0);
+    }
+    set_accrued_fees(env, &collector, &token, 0);
+    transfer_or_defer(env, &token, &collector, amount)?;
+    env.events().publish(
+        (Symbol::new(env, "fees_withdrawn"), collector),
+        (token, amount),
+    );
+    Ok(amount)
+}
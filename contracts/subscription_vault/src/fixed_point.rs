@@ -0,0 +1,87 @@
+//! Decimals-aware fixed-point helpers for i128 token amounts.
+//!
+//! Amounts are integers in a token's smallest unit, so moving between tokens with
+//! different `decimals` (multi-token settlement, oracle prices) means rescaling by a
+//! power of ten. These helpers do that with overflow checks and an explicit
+//! [`Rounding`] mode instead of ad-hoc `checked_mul` / `/` at each call site.
+
+use crate::safe_math::{mul_div, safe_mul, validate_non_negative, Rounding};
+use crate::Error;
+
+/// Largest supported decimals value; `10^38` is the largest power of ten in an i128.
+pub const MAX_DECIMALS: u32 = 38;
+
+/// Returns `10^decimals`.
+///
+/// * `Err(Error::InvalidAmount)` - If `decimals` exceeds [`MAX_DECIMALS`]
+pub fn pow10(decimals: u32) -> Result<i128, Error> {
+    if decimals > MAX_DECIMALS {
+        return Err(Error::InvalidAmount);
+    }
+    10i128.checked_pow(decimals).ok_or(Error::Overflow)
+}
+
+/// Rescales a non-negative `amount` from `from_decimals` to `to_decimals`.
+///
+/// Scaling up is exact (or fails with `Error::Overflow`); scaling down rounds any
+/// dropped digits in the given direction.
+///
+/// # Examples
+///
+/// ```
+/// use subscription_vault::fixed_point::normalize_decimals;
+/// use subscription_vault::safe_math::Rounding;
+///
+/// // 1.5 units of a 7-decimal token expressed with 6 decimals.
+/// assert_eq!(normalize_decimals(15_000_000, 7, 6, Rounding::Floor), Ok(1_500_000));
+/// assert_eq!(normalize_decimals(1, 7, 6, Rounding::Ceil), Ok(1));
+/// ```
+pub fn normalize_decimals(
+    amount: i128,
+    from_decimals: u32,
+    to_decimals: u32,
+    rounding: Rounding,
+) -> Result<i128, Error> {
+    if from_decimals > MAX_DECIMALS || to_decimals > MAX_DECIMALS {
+        return Err(Error::InvalidAmount);
+    }
+    if to_decimals >= from_decimals {
+        safe_mul(amount, pow10(to_decimals - from_decimals)?)
+    } else {
+        mul_div(amount, 1, pow10(from_decimals - to_decimals)?, rounding)
+    }
+}
+
+/// Multiplies two fixed-point values that share `decimals`, keeping that scale.
+///
+/// `mul_fixed(2.5, 1.5, d)` is `3.75` at `d` decimals, rounded as requested.
+pub fn mul_fixed(a: i128, b: i128, decimals: u32, rounding: Rounding) -> Result<i128, Error> {
+    mul_div(a, b, pow10(decimals)?, rounding)
+}
+
+/// Converts `amount` of a base token into a quote token using an oracle `price`.
+///
+/// `price` is the quote-token value of one whole base token, expressed with
+/// `price_decimals`. The result is in the quote token's smallest unit and is
+/// rounded once, at the end, so no precision is lost in intermediate steps.
+///
+/// * `Err(Error::InvalidAmount)` - If `base_decimals + price_decimals` exceeds [`MAX_DECIMALS`]
+pub fn convert_with_price(
+    amount: i128,
+    base_decimals: u32,
+    price: i128,
+    price_decimals: u32,
+    quote_decimals: u32,
+    rounding: Rounding,
+) -> Result<i128, Error> {
+    validate_non_negative(amount)?;
+    validate_non_negative(price)?;
+    // The raw product carries base_decimals + price_decimals digits of precision.
+    let value = safe_mul(amount, price)?;
+    normalize_decimals(
+        value,
+        base_decimals.saturating_add(price_decimals),
+        quote_decimals,
+        rounding,
+    )
+}
@@ -0,0 +1,563 @@
+//! Keeper-facing helpers: due-soon notifications, billing heartbeat.
+//!
+//! **PRs that only change keeper (billing backend) helpers should edit this file only.**
+//!
+//! # Due-soon notifications
+//!
+//! The keeper periodically calls `notify_due_soon` with the ids it tracks. For every
+//! `Active` subscription whose next charge falls within the configured lead window a
+//! `due_soon` event is emitted, giving off-chain email/push pipelines on-chain provenance.
+//! Each subscription is notified at most once per billing period: we remember the due
+//! timestamp we last notified for, so repeated keeper calls do not spam events.
+//! `Priority` tier subscriptions use their merchant's warning lead when it is longer.
+//!
+//! # Billing heartbeat
+//!
+//! Every successful `batch_charge` / `batch_charge_resumable` run records its timestamp.
+//! `get_last_billing_run` returns it and, if the last run is older than the configured
+//! staleness threshold, emits a `billing_stalled` event so monitoring can alert that the
+//! billing backend is down.
+//!
+//! # Reconciliation
+//!
+//! Every token movement into or out of the vault adjusts a per-token liability total
+//! (everything the vault owes subscribers, merchants and claimants). The admin can
+//! call `emit_reconciliation` to publish that total next to the vault's actual token
+//! balance in one `reconciliation` event, a cheap periodic solvency heartbeat.
+//!
+//! # Retry queue
+//!
+//! Batch charge items that fail with a retryable error (`InsufficientBalance`,
+//! `IntervalNotElapsed`) are queued on-chain with an earliest-retry timestamp.
+//! `process_retry_queue` re-attempts due entries, so the keeper does not need to
+//! track failed ids itself. An `InsufficientBalance` subscription is re-activated
+//! before its retry (it falls back if the charge still fails). Entries are dropped
+//! on success, on a permanent error, or after [`MAX_RETRY_ATTEMPTS`].
+//!
+//! # Keeper registry
+//!
+//! Any address may charge due subscriptions through `keeper_charge`. The first call
+//! registers the keeper; every call updates its [`KeeperStats`] (charges executed,
+//! failures, last active), so operators can judge the health of the keeper set. The
+//! admin may ban an abusive keeper, which blocks further `keeper_charge` calls, and
+//! so its bounties and stats. The ban cannot stop the address from triggering due
+//! charges through the permissionless `charge_subscription`, which identifies no
+//! caller; those charges pay no bounty and are not attributed to it.
+//!
+//! # Keeper bounty
+//!
+//! The admin may set a [`KeeperBounty`], flat or in basis points of the charge, so
+//! third parties are paid to keep billing running when the operator's backend is
+//! down. Each charge that succeeds through `keeper_charge` pays the bounty to the
+//! keeper out of the merchant's pending payout, capped at the charged amount and at
+//! what is pending. `charge_subscription` identifies no caller and pays no bounty.
+
+use crate::admin::{log_admin_action, require_admin};
+use crate::charge_core::{charge_one, get_total_charged};
+use crate::claims::transfer_or_defer;
+use crate::merchant::{get_payout_queue, unqueue_payout};
+use crate::queries::get_subscription;
+use crate::queries::page_bounds;
+use crate::safe_math::{bps_fee, BPS_DENOMINATOR};
+use crate::state_machine::apply_transition;
+use crate::subscription::subscription_token;
+use crate::tiers::priority_policy_for;
+use crate::types::{
+    BatchChargeResult, BillingStalledEvent, DataKey, DueSoonEvent, Error, KeeperBounty,
+    KeeperBountyPaidEvent, KeeperStats, ReconciliationEvent, RetryEntry, RetryResult,
+    SubscriptionStatus,
+};
+use soroban_sdk::{symbol_short, token, Address, Env, Symbol, Vec};
+
+/// Default lead window: notify three days before the charge is due.
+pub const DEFAULT_NOTIFY_LEAD_SECONDS: u64 = 3 * 24 * 60 * 60;
+
+/// Default staleness threshold for the billing heartbeat: two days without a run.
+pub const DEFAULT_BILLING_STALE_SECONDS: u64 = 2 * 24 * 60 * 60;
+
+const KEY_NOTIFIED: Symbol = symbol_short!("ntf");
+
+fn notified_key(subscription_id: u32) -> (Symbol, u32) {
+    (KEY_NOTIFIED, subscription_id)
+}
+
+pub fn do_set_notify_lead_window(env: &Env, admin: Address, seconds: u64) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    log_admin_action(env, &admin, "set_notify_lead_window");
+    store_notify_lead_window(env, seconds);
+    env.events()
+        .publish((Symbol::new(env, "notify_lead_updated"),), seconds);
+    Ok(())
+}
+
+pub fn store_notify_lead_window(env: &Env, seconds: u64) {
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "notify_lead"), &seconds);
+}
+
+pub fn get_notify_lead_window(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "notify_lead"))
+        .unwrap_or(DEFAULT_NOTIFY_LEAD_SECONDS)
+}
+
+/// Emits a `due_soon` event for each subscription due within the lead window.
+///
+/// Ids that do not exist, are not `Active`, are already due, are outside the window,
+/// or were already notified for the current period are skipped silently.
+///
+/// Returns the ids that were notified by this call.
+pub fn do_notify_due_soon(env: &Env, subscription_ids: &Vec<u32>) -> Result<Vec<u32>, Error> {
+    let auth_admin = require_admin(env)?;
+    auth_admin.require_auth();
+
+    let now = env.ledger().timestamp();
+    let global_lead = get_notify_lead_window(env);
+    let mut notified = Vec::new(env);
+
+    for id in subscription_ids.iter() {
+        let sub = match get_subscription(env, id) {
+            Ok(sub) => sub,
+            Err(_) => continue,
+        };
+        if sub.status != SubscriptionStatus::Active {
+            continue;
+        }
+
+        let lead = match priority_policy_for(env, id, &sub.merchant) {
+            Some(policy) => policy.warning_lead_seconds.max(global_lead),
+            None => global_lead,
+        };
+        let due_at = sub
+            .last_payment_timestamp
+            .saturating_add(sub.interval_seconds);
+        if due_at <= now || due_at - now > lead {
+            continue;
+        }
+
+        let last_notified: Option<u64> = env.storage().instance().get(&notified_key(id));
+        if last_notified == Some(due_at) {
+            continue;
+        }
+
+        env.storage().instance().set(&notified_key(id), &due_at);
+        env.events().publish(
+            (Symbol::new(env, "due_soon"), id),
+            DueSoonEvent {
+                subscription_id: id,
+                subscriber: sub.subscriber,
+                merchant: sub.merchant,
+                amount: sub.amount,
+                due_at,
+                seconds_until_due: due_at - now,
+            },
+        );
+        notified.push_back(id);
+    }
+
+    Ok(notified)
+}
+
+pub fn do_set_billing_stale_threshold(
+    env: &Env,
+    admin: Address,
+    seconds: u64,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    log_admin_action(env, &admin, "set_billing_stale_threshold");
+    store_billing_stale_threshold(env, seconds);
+    env.events()
+        .publish((Symbol::new(env, "billing_stale_updated"),), seconds);
+    Ok(())
+}
+
+pub fn store_billing_stale_threshold(env: &Env, seconds: u64) {
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "billing_stale"), &seconds);
+}
+
+pub fn get_billing_stale_threshold(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "billing_stale"))
+        .unwrap_or(DEFAULT_BILLING_STALE_SECONDS)
+}
+
+/// Records a successful billing run (heartbeat).
+pub fn record_billing_run(env: &Env) {
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "last_billing"), &env.ledger().timestamp());
+}
+
+/// Timestamp of the last successful billing run, if any.
+///
+/// Emits `billing_stalled` when that run is older than the staleness threshold
+/// (`0` disables the check).
+pub fn get_last_billing_run(env: &Env) -> Option<u64> {
+    let last_run: Option<u64> = env
+        .storage()
+        .instance()
+        .get(&Symbol::new(env, "last_billing"));
+    let threshold = get_billing_stale_threshold(env);
+    if let Some(last_run) = last_run {
+        let seconds_since = env.ledger().timestamp().saturating_sub(last_run);
+        if threshold > 0 && seconds_since > threshold {
+            env.events().publish(
+                (Symbol::new(env, "billing_stalled"),),
+                BillingStalledEvent {
+                    last_run,
+                    seconds_since,
+                    threshold,
+                },
+            );
+        }
+    }
+    last_run
+}
+
+/// Tracked amount of `token` the vault owes.
+pub fn get_tracked_liabilities(env: &Env, token: &Address) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::TokenLiabilities(token.clone()))
+        .unwrap_or(0)
+}
+
+/// Adjusts the tracked liabilities for tokens moving into (`delta > 0`) or out of
+/// (`delta < 0`) the vault.
+pub fn track_liabilities(env: &Env, token: &Address, delta: i128) {
+    let total = get_tracked_liabilities(env, token).saturating_add(delta);
+    env.storage()
+        .instance()
+        .set(&DataKey::TokenLiabilities(token.clone()), &total);
+}
+
+/// Emits the vault's `token` balance alongside its tracked liabilities. Admin only.
+pub fn do_emit_reconciliation(env: &Env, token: Address) -> Result<ReconciliationEvent, Error> {
+    let auth_admin = require_admin(env)?;
+    auth_admin.require_auth();
+
+    let balance = token::Client::new(env, &token).balance(&env.current_contract_address());
+    let liabilities = get_tracked_liabilities(env, &token);
+    let event = ReconciliationEvent {
+        token: token.clone(),
+        balance,
+        liabilities,
+        surplus: balance.saturating_sub(liabilities),
+        timestamp: env.ledger().timestamp(),
+    };
+    env.events()
+        .publish((Symbol::new(env, "reconciliation"), token), event.clone());
+    Ok(event)
+}
+
+/// Maximum number of entries held in the retry queue.
+pub const MAX_RETRY_QUEUE: u32 = 500;
+
+/// Attempts (including the original failure) after which an entry is dropped.
+pub const MAX_RETRY_ATTEMPTS: u32 = 4;
+
+/// Base delay before retrying an `InsufficientBalance` failure; grows linearly with
+/// the number of attempts.
+pub const RETRY_BACKOFF_SECONDS: u64 = 24 * 60 * 60;
+
+pub fn get_retry_queue(env: &Env) -> Vec<RetryEntry> {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "retry_queue"))
+        .unwrap_or(Vec::new(env))
+}
+
+/// Failed attempts recorded for a subscription in the retry queue (0 if not queued).
+pub fn get_failed_attempts(env: &Env, subscription_id: u32) -> u32 {
+    get_retry_queue(env)
+        .iter()
+        .find(|e| e.subscription_id == subscription_id)
+        .map(|e| e.attempts)
+        .unwrap_or(0)
+}
+
+fn set_retry_queue(env: &Env, queue: &Vec<RetryEntry>) {
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "retry_queue"), queue);
+}
+
+/// Earliest retry time after the `attempts`-th retryable failure with `error`, or
+/// `None` if `error` is permanent.
+fn next_retry_at(env: &Env, subscription_id: u32, error: &Error, attempts: u32) -> Option<u64> {
+    let now = env.ledger().timestamp();
+    match error {
+        Error::InsufficientBalance => {
+            Some(now.saturating_add(RETRY_BACKOFF_SECONDS.saturating_mul(attempts as u64)))
+        }
+        Error::IntervalNotElapsed => get_subscription(env, subscription_id).ok().map(|sub| {
+            sub.last_payment_timestamp
+                .saturating_add(sub.interval_seconds)
+        }),
+        _ => None,
+    }
+}
+
+/// Queues a failed batch item for retry if `error` is retryable. Ids already queued
+/// keep their entry; a full queue drops the item.
+pub fn enqueue_retry(env: &Env, subscription_id: u32, error: &Error) {
+    let mut queue = get_retry_queue(env);
+    if queue.len() >= MAX_RETRY_QUEUE || queue.iter().any(|e| e.subscription_id == subscription_id)
+    {
+        return;
+    }
+    if let Some(retry_at) = next_retry_at(env, subscription_id, error, 1) {
+        queue.push_back(RetryEntry {
+            subscription_id,
+            attempts: 1,
+            next_retry_at: retry_at,
+            last_error_code: error.clone().to_code(),
+        });
+        set_retry_queue(env, &queue);
+    }
+}
+
+/// Re-attempt up to `limit` due entries of the retry queue. Admin only.
+pub fn do_process_retry_queue(env: &Env, limit: u32) -> Result<Vec<RetryResult>, Error> {
+    let auth_admin = require_admin(env)?;
+    auth_admin.require_auth();
+
+    let now = env.ledger().timestamp();
+    let queue = get_retry_queue(env);
+    let mut remaining = Vec::new(env);
+    let mut results = Vec::new(env);
+    for mut entry in queue.iter() {
+        if results.len() >= limit || entry.next_retry_at > now {
+            remaining.push_back(entry);
+            continue;
+        }
+        let id = entry.subscription_id;
+        let mut reactivated = false;
+        if let Ok(mut sub) = get_subscription(env, id) {
+            if sub.status == SubscriptionStatus::InsufficientBalance {
+                apply_transition(env, id, &mut sub, SubscriptionStatus::Active)?;
+                env.storage().instance().set(&id, &sub);
+                reactivated = true;
+            }
+        }
+        let outcome = charge_one(env, id, None);
+        if reactivated && matches!(&outcome, Err(e) if *e != Error::InsufficientBalance) {
+            // charge_one only falls back on its own for a balance shortfall.
+            let mut sub = get_subscription(env, id)?;
+            apply_transition(env, id, &mut sub, SubscriptionStatus::InsufficientBalance)?;
+            env.storage().instance().set(&id, &sub);
+        }
+        let mut requeued = false;
+        let error_code = match &outcome {
+            Ok(()) => 0,
+            Err(e) => {
+                entry.attempts += 1;
+                if entry.attempts < MAX_RETRY_ATTEMPTS {
+                    if let Some(retry_at) = next_retry_at(env, id, e, entry.attempts) {
+                        entry.next_retry_at = retry_at;
+                        entry.last_error_code = e.clone().to_code();
+                        remaining.push_back(entry.clone());
+                        requeued = true;
+                    }
+                }
+                e.clone().to_code()
+            }
+        };
+        results.push_back(RetryResult {
+            subscription_id: id,
+            success: outcome.is_ok(),
+            error_code,
+            requeued,
+        });
+    }
+    set_retry_queue(env, &remaining);
+    record_billing_run(env);
+    Ok(results)
+}
+
+const KEY_KEEPER_STATS: Symbol = symbol_short!("kstat");
+
+pub fn get_keeper_stats(env: &Env, keeper: &Address) -> Option<KeeperStats> {
+    env.storage()
+        .instance()
+        .get(&(KEY_KEEPER_STATS, keeper.clone()))
+}
+
+fn set_keeper_stats(env: &Env, keeper: &Address, stats: &KeeperStats) {
+    if get_keeper_stats(env, keeper).is_none() {
+        let key = Symbol::new(env, "keepers");
+        let mut keepers: Vec<Address> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
+        keepers.push_back(keeper.clone());
+        env.storage().instance().set(&key, &keepers);
+    }
+    env.storage()
+        .instance()
+        .set(&(KEY_KEEPER_STATS, keeper.clone()), stats);
+}
+
+/// Registered keepers in registration order, paginated by offset.
+pub fn list_keepers(env: &Env, cursor: u32, limit: u32) -> Vec<Address> {
+    let keepers: Vec<Address> = env
+        .storage()
+        .instance()
+        .get(&Symbol::new(env, "keepers"))
+        .unwrap_or(Vec::new(env));
+    let (end, _) = page_bounds(cursor, limit, keepers.len());
+    if cursor < end {
+        keepers.slice(cursor..end)
+    } else {
+        Vec::new(env)
+    }
+}
+
+/// Charge `subscription_ids` on behalf of a third-party keeper, recording its
+/// statistics. Auth: keeper. Banned keepers fail with `Unauthorized`.
+pub fn do_keeper_charge(
+    env: &Env,
+    keeper: Address,
+    subscription_ids: &Vec<u32>,
+) -> Result<Vec<BatchChargeResult>, Error> {
+    keeper.require_auth();
+    let mut stats = get_keeper_stats(env, &keeper).unwrap_or(KeeperStats {
+        charges_executed: 0,
+        failures: 0,
+        last_active: 0,
+        banned: false,
+    });
+    if stats.banned {
+        return Err(Error::Unauthorized);
+    }
+
+    let mut results = Vec::new(env);
+    for id in subscription_ids.iter() {
+        let before = get_total_charged(env, id);
+        let res = match charge_one(env, id, None) {
+            Ok(()) => {
+                pay_keeper_bounty(env, &keeper, id, get_total_charged(env, id) - before)?;
+                stats.charges_executed = stats.charges_executed.saturating_add(1);
+                BatchChargeResult {
+                    success: true,
+                    error_code: 0,
+                }
+            }
+            Err(e) => {
+                stats.failures = stats.failures.saturating_add(1);
+                BatchChargeResult {
+                    success: false,
+                    error_code: e.to_code(),
+                }
+            }
+        };
+        results.push_back(res);
+    }
+    stats.last_active = env.ledger().timestamp();
+    set_keeper_stats(env, &keeper, &stats);
+    Ok(results)
+}
+
+pub fn get_keeper_bounty(env: &Env) -> Option<KeeperBounty> {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "keeper_bounty"))
+}
+
+/// Set or clear (`None`) the bounty paid per keeper-triggered charge. Admin only.
+pub fn do_set_keeper_bounty(
+    env: &Env,
+    admin: Address,
+    bounty: Option<KeeperBounty>,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let key = Symbol::new(env, "keeper_bounty");
+    match &bounty {
+        Some(KeeperBounty::Flat(amount)) if *amount < 0 => return Err(Error::InvalidAmount),
+        Some(KeeperBounty::Bps(bps)) if *bps as i128 > BPS_DENOMINATOR => {
+            return Err(Error::InvalidAmount)
+        }
+        Some(b) => env.storage().instance().set(&key, b),
+        None => env.storage().instance().remove(&key),
+    }
+    log_admin_action(env, &admin, "set_keeper_bounty");
+    env.events()
+        .publish((Symbol::new(env, "keeper_bounty_set"),), bounty);
+    Ok(())
+}
+
+/// Pays the keeper's bounty for a charge of `charged` out of the merchant's pending
+/// payout.
+fn pay_keeper_bounty(
+    env: &Env,
+    keeper: &Address,
+    subscription_id: u32,
+    charged: i128,
+) -> Result<(), Error> {
+    let bounty = match get_keeper_bounty(env) {
+        Some(KeeperBounty::Flat(amount)) => amount,
+        Some(KeeperBounty::Bps(bps)) => bps_fee(charged, bps)?,
+        None => return Ok(()),
+    };
+    let sub = get_subscription(env, subscription_id)?;
+    let token = subscription_token(env, subscription_id)?;
+    let amount = bounty
+        .min(charged)
+        .min(get_payout_queue(env, &sub.merchant, &token).pending);
+    if amount <= 0 {
+        return Ok(());
+    }
+    unqueue_payout(env, &sub.merchant, &token, amount);
+    transfer_or_defer(env, &token, keeper, amount)?;
+    env.events().publish(
+        (Symbol::new(env, "keeper_bounty_paid"), keeper.clone()),
+        KeeperBountyPaidEvent {
+            keeper: keeper.clone(),
+            subscription_id,
+            merchant: sub.merchant,
+            token,
+            amount,
+        },
+    );
+    Ok(())
+}
+
+/// Ban or unban a keeper from `keeper_charge`. Admin only.
+pub fn do_set_keeper_banned(
+    env: &Env,
+    admin: Address,
+    keeper: Address,
+    banned: bool,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    log_admin_action(env, &admin, "set_keeper_banned");
+    let mut stats = get_keeper_stats(env, &keeper).unwrap_or(KeeperStats {
+        charges_executed: 0,
+        failures: 0,
+        last_active: 0,
+        banned,
+    });
+    stats.banned = banned;
+    set_keeper_stats(env, &keeper, &stats);
+    env.events()
+        .publish((Symbol::new(env, "keeper_banned"), keeper), banned);
+    Ok(())
+}
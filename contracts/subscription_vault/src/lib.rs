@@ -1,22 +1,54 @@
 #![no_std]
+// Contract entrypoints mirror their full argument lists into generated clients.
+#![allow(clippy::too_many_arguments)]
 
 // ── Modules ──────────────────────────────────────────────────────────────────
 mod admin;
+mod archive;
+mod budget;
 mod charge_core;
+mod claims;
+mod credit;
+pub mod errors;
+mod events;
+mod fees;
+mod keeper;
 mod merchant;
+mod ombudsman;
+mod org;
+mod plans;
+mod platforms;
 mod queries;
+mod receipts;
+mod reentrancy;
+mod referrals;
+mod reports;
+mod seats;
+mod signed;
+mod stake;
 mod state_machine;
 mod subscription;
+mod tax;
+mod tiers;
 pub mod types;
+mod voting;
+mod webhooks;
 
-mod safe_math;
+pub mod fixed_point;
+pub mod safe_math;
+#[cfg(any(test, feature = "testutils"))]
+pub mod testutils;
 
 // ── Re-exports (used by tests and external consumers) ────────────────────────
+pub use errors::ErrorCategory;
 pub use state_machine::{can_transition, get_allowed_transitions, validate_status_transition};
 pub use types::*;
 
+pub use merchant::{PayoutAdapter, PayoutAdapterClient};
+pub use org::{OrgAdapter, OrgAdapterClient};
 pub use queries::compute_next_charge_info;
-use soroban_sdk::{contract, contractimpl, Address, Env, Vec};
+use soroban_sdk::{contract, contractimpl, Address, BytesN, Env, String, Symbol, Vec};
+pub use webhooks::{MerchantWebhook, MerchantWebhookClient};
 
 // ── Contract ─────────────────────────────────────────────────────────────────
 
@@ -28,186 +60,1984 @@ impl SubscriptionVault {
     // ── Admin / Config ───────────────────────────────────────────────────
 
     /// Initialize the contract: set token address, admin, and minimum top-up.
+    ///
+    /// Auth: `admin`. Callable once; later calls fail with `AlreadyInitialized`.
+    ///
+    /// The token's `decimals()` is read from the token contract and stored, rather
+    /// than supplied by the caller.
     pub fn init(env: Env, token: Address, admin: Address, min_topup: i128) -> Result<(), Error> {
         admin::do_init(&env, token, admin, min_topup)
     }
 
-    /// Update the minimum top-up threshold. Only callable by admin.
-    pub fn set_min_topup(env: Env, admin: Address, min_topup: i128) -> Result<(), Error> {
-        admin::do_set_min_topup(&env, admin, min_topup)
+    /// Update the minimum top-up threshold. Only callable by admin.
+    pub fn set_min_topup(env: Env, admin: Address, min_topup: i128) -> Result<(), Error> {
+        admin::do_set_min_topup(&env, admin, min_topup)
+    }
+
+    /// Decimals of the settlement token, as reported by the token contract.
+    pub fn get_token_decimals(env: Env) -> Result<u32, Error> {
+        admin::get_token_decimals(&env)
+    }
+
+    /// Get the current minimum top-up threshold.
+    pub fn get_min_topup(env: Env) -> Result<i128, Error> {
+        admin::get_min_topup(&env)
+    }
+
+    /// Set the cooling-off window in seconds (e.g. 14 days). Only callable by admin.
+    ///
+    /// Cancelling within this window after creation refunds every charge made so
+    /// far, routed per the subscriber's refund split. `0` disables the window.
+    pub fn set_cooling_off_period(env: Env, admin: Address, seconds: u64) -> Result<(), Error> {
+        admin::do_set_cooling_off_period(&env, admin, seconds)
+    }
+
+    /// Get the current cooling-off window in seconds.
+    pub fn get_cooling_off_period(env: Env) -> u64 {
+        admin::get_cooling_off_period(&env)
+    }
+
+    /// Charges to the subscription held by the vault while it is inside its
+    /// cooling-off window, neither payable to the merchant nor taxed yet.
+    pub fn get_cooling_off_hold(env: Env, subscription_id: u32) -> i128 {
+        charge_core::get_cooling_off_hold(&env, subscription_id)
+    }
+
+    /// Settle the subscription's cooling-off hold into the merchant's payout queue
+    /// once the window has closed; the next charge or a cancellation does the same.
+    /// Permissionless. Returns the amount settled.
+    pub fn release_cooling_off_hold(env: Env, subscription_id: u32) -> Result<i128, Error> {
+        reentrancy::guarded(&env, || {
+            charge_core::do_release_cooling_off_hold(&env, subscription_id)
+        })
+    }
+
+    /// Set the fee (basis points) withheld from refunds transferred out to a
+    /// subscriber's address; the merchant keeps it. Refunds kept in the credit
+    /// wallet, and cooling-off refunds, are fee-free. Only callable by admin.
+    pub fn set_external_refund_fee(env: Env, admin: Address, fee_bps: u32) -> Result<(), Error> {
+        admin::do_set_external_refund_fee(&env, admin, fee_bps)
+    }
+
+    /// Get the external refund fee in basis points.
+    pub fn get_external_refund_fee(env: Env) -> u32 {
+        admin::get_external_refund_fee(&env)
+    }
+
+    /// Set or clear the address that receives subscriber round-up donations. Only callable by admin.
+    pub fn set_donation_address(
+        env: Env,
+        admin: Address,
+        donation_address: Option<Address>,
+    ) -> Result<(), Error> {
+        admin::do_set_donation_address(&env, admin, donation_address)
+    }
+
+    /// Get the configured donation address, if any.
+    pub fn get_donation_address(env: Env) -> Option<Address> {
+        admin::get_donation_address(&env)
+    }
+
+    /// Configure the large-withdrawal time lock. Only callable by admin.
+    ///
+    /// Withdrawals above `threshold` must be announced with `request_withdrawal` and
+    /// wait `delay_seconds`; smaller withdrawals stay instant. Zero threshold disables.
+    pub fn set_withdrawal_timelock(
+        env: Env,
+        admin: Address,
+        timelock: WithdrawalTimelock,
+    ) -> Result<(), Error> {
+        admin::do_set_withdrawal_timelock(&env, admin, timelock)
+    }
+
+    /// Get the large-withdrawal time lock configuration.
+    pub fn get_withdrawal_timelock(env: Env) -> WithdrawalTimelock {
+        admin::get_withdrawal_timelock(&env)
+    }
+
+    /// Configure the clock-jump guard: a charge more than `seconds` past its due time
+    /// flags the subscription for review instead of charging. Zero disables the
+    /// check; the default is twenty years. Only callable by admin.
+    pub fn set_max_charge_elapsed(env: Env, admin: Address, seconds: u64) -> Result<(), Error> {
+        admin::do_set_max_charge_elapsed(&env, admin, seconds)
+    }
+
+    /// Get the clock-jump guard threshold in seconds.
+    pub fn get_max_charge_elapsed(env: Env) -> u64 {
+        admin::get_max_charge_elapsed(&env)
+    }
+
+    /// The pending review flag of a subscription, if a charge tripped the
+    /// clock-jump guard.
+    pub fn get_charge_review(env: Env, subscription_id: u32) -> Option<ChargeReview> {
+        charge_core::get_charge_review(&env, subscription_id)
+    }
+
+    /// Clear a review flag and re-anchor the schedule at now, without charging
+    /// the gap. Only callable by admin.
+    pub fn resolve_charge_review(
+        env: Env,
+        admin: Address,
+        subscription_id: u32,
+    ) -> Result<(), Error> {
+        charge_core::do_resolve_charge_review(&env, admin, subscription_id)
+    }
+
+    /// Configure deposit rate limiting per subscription. Only callable by admin.
+    ///
+    /// Prevents dust-grief deposit spam; zero fields disable the respective check.
+    pub fn set_deposit_rate_limit(
+        env: Env,
+        admin: Address,
+        limit: DepositRateLimit,
+    ) -> Result<(), Error> {
+        admin::do_set_deposit_rate_limit(&env, admin, limit)
+    }
+
+    /// Get the current deposit rate limit.
+    pub fn get_deposit_rate_limit(env: Env) -> DepositRateLimit {
+        admin::get_deposit_rate_limit(&env)
+    }
+
+    /// Configure the beta deposit caps: a vault-wide TVL cap on the settlement token's
+    /// tracked liabilities and a per-subscription prepaid balance cap. Zero fields
+    /// disable the respective cap. Only callable by admin.
+    ///
+    /// Deposits that would exceed a cap fail with `DepositCapExceeded`.
+    pub fn set_deposit_caps(env: Env, admin: Address, caps: DepositCaps) -> Result<(), Error> {
+        admin::do_set_deposit_caps(&env, admin, caps)
+    }
+
+    /// Get the current deposit caps.
+    pub fn get_deposit_caps(env: Env) -> DepositCaps {
+        admin::get_deposit_caps(&env)
+    }
+
+    /// Amount that can still be deposited under the caps, into `subscription_id` or,
+    /// when `None`, into a credit wallet. `i128::MAX` when uncapped.
+    pub fn get_deposit_capacity(env: Env, subscription_id: Option<u32>) -> Result<i128, Error> {
+        admin::get_deposit_capacity(&env, subscription_id)
+    }
+
+    /// Read the append-only admin audit log: up to `limit` (max 100) entries
+    /// starting at sequence number `cursor`, oldest first.
+    pub fn get_admin_log(env: Env, cursor: u32, limit: u32) -> AdminLogPage {
+        admin::get_admin_log(&env, cursor, limit)
+    }
+
+    /// Get the current admin address.
+    pub fn get_admin(env: Env) -> Result<Address, Error> {
+        admin::do_get_admin(&env)
+    }
+
+    /// Rotate admin to a new address. Only callable by current admin.
+    ///
+    /// # Security
+    ///
+    /// - Immediate effect — old admin loses access instantly.
+    /// - Irreversible without the new admin's cooperation.
+    /// - Emits an `admin_rotation` event for audit trail.
+    pub fn rotate_admin(env: Env, current_admin: Address, new_admin: Address) -> Result<(), Error> {
+        admin::do_rotate_admin(&env, current_admin, new_admin)
+    }
+
+    /// Get the settlement token used for new subscriptions.
+    pub fn get_token(env: Env) -> Result<Address, Error> {
+        admin::get_token(&env)
+    }
+
+    /// Propose a settlement-token rotation (admin + 2-day timelock).
+    pub fn propose_token_rotation(
+        env: Env,
+        admin: Address,
+        new_token: Address,
+    ) -> Result<(), Error> {
+        admin::do_propose_token_rotation(&env, admin, new_token)
+    }
+
+    /// Cancel the pending settlement-token rotation. Only callable by admin.
+    pub fn cancel_token_rotation(env: Env, admin: Address) -> Result<(), Error> {
+        admin::do_cancel_token_rotation(&env, admin)
+    }
+
+    /// Execute the pending token rotation after its timelock. Only callable by admin.
+    ///
+    /// Existing subscriptions keep settling in the token they were funded in.
+    pub fn execute_token_rotation(env: Env, admin: Address) -> Result<(), Error> {
+        admin::do_execute_token_rotation(&env, admin)
+    }
+
+    /// Get the pending token rotation, if any.
+    pub fn get_pending_token_rotation(env: Env) -> Option<PendingTokenRotation> {
+        admin::get_pending_token_rotation(&env)
+    }
+
+    /// Read every admin-tunable parameter in one call.
+    pub fn get_config(env: Env) -> Result<Config, Error> {
+        admin::get_config(&env)
+    }
+
+    /// Stage a bulk parameter update (admin + 1-day timelock).
+    ///
+    /// The config is validated (including cross-field constraints) when staged and
+    /// applied atomically by `apply_config`.
+    pub fn set_config(env: Env, admin: Address, config: Config) -> Result<(), Error> {
+        admin::do_set_config(&env, admin, config)
+    }
+
+    /// Dry-run a config before staging it: counts the `Active` subscriptions on one
+    /// page of the active set that the change would newly affect (balance below the
+    /// new `min_topup`, or newly under the withdrawal time lock), with sample ids.
+    pub fn simulate_set_config(
+        env: Env,
+        config: Config,
+        cursor: u32,
+        limit: u32,
+    ) -> Result<ConfigImpact, Error> {
+        admin::simulate_set_config(&env, &config, cursor, limit)
+    }
+
+    /// Apply the staged config once its timelock has elapsed. Only callable by admin.
+    pub fn apply_config(env: Env, admin: Address) -> Result<(), Error> {
+        admin::do_apply_config(&env, admin)
+    }
+
+    /// Discard the staged config. Only callable by admin.
+    pub fn cancel_config(env: Env, admin: Address) -> Result<(), Error> {
+        admin::do_cancel_config(&env, admin)
+    }
+
+    /// Get the staged config, if any.
+    pub fn get_pending_config(env: Env) -> Option<PendingConfig> {
+        admin::get_pending_config(&env)
+    }
+
+    /// **ADMIN ONLY**: Recover stranded funds from the contract.
+    ///
+    /// Tightly-scoped mechanism for recovering funds that have become
+    /// inaccessible through normal operations. Each recovery emits a
+    /// `RecoveryEvent` with full audit details.
+    pub fn recover_stranded_funds(
+        env: Env,
+        admin: Address,
+        recipient: Address,
+        amount: i128,
+        reason: RecoveryReason,
+    ) -> Result<(), Error> {
+        admin::do_recover_stranded_funds(&env, admin, recipient, amount, reason)
+    }
+
+    /// **ADMIN ONLY**: Force a settlement-correction charge (e.g. after a dispute ruling).
+    ///
+    /// Ignores interval timing but never exceeds the subscription's per-interval
+    /// `amount` or its prepaid balance. Emits a `force_charge` audit event.
+    pub fn force_charge(
+        env: Env,
+        admin: Address,
+        subscription_id: u32,
+        amount: i128,
+        reason: Symbol,
+    ) -> Result<(), Error> {
+        reentrancy::guarded(&env, || {
+            admin::do_force_charge(&env, admin, subscription_id, amount, reason)
+        })
+    }
+
+    /// Charge a batch of subscriptions in one transaction. Admin only.
+    ///
+    /// Returns a per-subscription result vector so callers can identify
+    /// which charges succeeded and which failed (with error codes). Merchant
+    /// payouts that fall due during the batch are settled once per merchant and
+    /// token at its end.
+    pub fn batch_charge(
+        env: Env,
+        subscription_ids: Vec<u32>,
+    ) -> Result<Vec<BatchChargeResult>, Error> {
+        reentrancy::guarded(&env, || {
+            merchant::with_payout_batch(&env, || admin::do_batch_charge(&env, &subscription_ids))
+        })
+    }
+
+    /// Like `batch_charge`, but returns the charged ids, the `(id, error_code)`
+    /// failures and aggregate totals, which is cheaper to consume for large batches.
+    /// Admin only.
+    pub fn batch_charge_v2(
+        env: Env,
+        subscription_ids: Vec<u32>,
+    ) -> Result<BatchChargeSummary, Error> {
+        reentrancy::guarded(&env, || {
+            merchant::with_payout_batch(&env, || admin::do_batch_charge_v2(&env, &subscription_ids))
+        })
+    }
+
+    /// Charge every Active subscription (and Paused ones with a pause retainer) across
+    /// many transactions. Admin only.
+    ///
+    /// Scans up to `limit` ids (capped at 200) after the given resume token and
+    /// returns a token for the next call; `has_next` is false once all ids have been scanned.
+    pub fn batch_charge_resumable(
+        env: Env,
+        resume: Option<ResumeToken>,
+        limit: u32,
+    ) -> Result<ResumableBatchResult, Error> {
+        reentrancy::guarded(&env, || {
+            merchant::with_payout_batch(&env, || {
+                admin::do_batch_charge_resumable(&env, resume, limit)
+            })
+        })
+    }
+
+    // ── Keeper ───────────────────────────────────────────────────────────
+
+    /// Set the lead window (seconds before due) for due-soon notifications. Admin only.
+    pub fn set_notify_lead_window(env: Env, admin: Address, seconds: u64) -> Result<(), Error> {
+        keeper::do_set_notify_lead_window(&env, admin, seconds)
+    }
+
+    /// Get the lead window used by `notify_due_soon`.
+    pub fn get_notify_lead_window(env: Env) -> u64 {
+        keeper::get_notify_lead_window(&env)
+    }
+
+    /// Set how long billing may go without a successful batch run before it is
+    /// reported as stalled (`0` disables). Admin only.
+    pub fn set_billing_stale_threshold(
+        env: Env,
+        admin: Address,
+        seconds: u64,
+    ) -> Result<(), Error> {
+        keeper::do_set_billing_stale_threshold(&env, admin, seconds)
+    }
+
+    /// Get the billing staleness threshold in seconds (default 2 days).
+    pub fn get_billing_stale_threshold(env: Env) -> u64 {
+        keeper::get_billing_stale_threshold(&env)
+    }
+
+    /// Timestamp of the last successful `batch_charge` run, if any.
+    ///
+    /// Emits `billing_stalled` when the last run is older than the staleness threshold.
+    pub fn get_last_billing_run(env: Env) -> Option<u64> {
+        keeper::get_last_billing_run(&env)
+    }
+
+    /// Define a time-boxed fee waiver campaign. Admin only.
+    ///
+    /// Merchants are enrolled automatically at their first charge while the campaign
+    /// is open (`[enroll_from, enroll_until)`) and keep `waiver_bps` off their fees
+    /// for `duration_seconds` from then. Returns the campaign id.
+    pub fn create_fee_campaign(
+        env: Env,
+        admin: Address,
+        waiver_bps: u32,
+        duration_seconds: u64,
+        enroll_from: u64,
+        enroll_until: u64,
+        new_merchants_only: bool,
+    ) -> Result<u32, Error> {
+        fees::do_create_fee_campaign(
+            &env,
+            admin,
+            waiver_bps,
+            duration_seconds,
+            enroll_from,
+            enroll_until,
+            new_merchants_only,
+        )
+    }
+
+    /// Stop enrollments into a fee waiver campaign. Admin only.
+    pub fn end_fee_campaign(env: Env, admin: Address, campaign_id: u32) -> Result<(), Error> {
+        fees::do_end_fee_campaign(&env, admin, campaign_id)
+    }
+
+    /// List the defined fee waiver campaigns.
+    pub fn get_fee_campaigns(env: Env) -> Vec<FeeWaiverCampaign> {
+        fees::get_fee_campaigns(&env)
+    }
+
+    /// Get the merchant's fee waiver enrollment, if any.
+    pub fn get_merchant_fee_waiver(env: Env, merchant: Address) -> Option<MerchantFeeWaiver> {
+        fees::get_merchant_fee_waiver(&env, &merchant)
+    }
+
+    /// Share of fees (bps) currently waived for `merchant`.
+    pub fn get_fee_waiver_bps(env: Env, merchant: Address) -> u32 {
+        fees::fee_waiver_bps(&env, &merchant)
+    }
+
+    /// Set or clear the protocol fee taken from every charge. Admin only.
+    ///
+    /// `fee_bps` of each charge (net of tax, less any fee waiver) accrues to the fee
+    /// collector; at most `MAX_PROTOCOL_FEE_BPS`.
+    pub fn set_protocol_fee(
+        env: Env,
+        admin: Address,
+        config: Option<ProtocolFeeConfig>,
+    ) -> Result<(), Error> {
+        fees::do_set_protocol_fee(&env, admin, config)
+    }
+
+    /// Get the protocol fee configuration, if any.
+    pub fn get_protocol_fee(env: Env) -> Option<ProtocolFeeConfig> {
+        fees::get_protocol_fee(&env)
+    }
+
+    /// Protocol fees accrued to `collector` in `token` and not yet withdrawn.
+    pub fn get_accrued_fees(env: Env, collector: Address, token: Address) -> i128 {
+        fees::get_accrued_fees(&env, &collector, &token)
+    }
+
+    /// Withdraw the protocol fees accrued to `collector` in `token`, including fees
+    /// accrued while it was a previous collector. Auth: collector.
+    pub fn withdraw_fees(env: Env, collector: Address, token: Address) -> Result<i128, Error> {
+        reentrancy::guarded(&env, || fees::do_withdraw_fees(&env, collector, token))
+    }
+
+    /// Register a marketplace platform with its maximum cut (bps), or deregister it
+    /// with `None`. Admin only.
+    pub fn set_platform(
+        env: Env,
+        admin: Address,
+        platform: Address,
+        max_cut_bps: Option<u32>,
+    ) -> Result<(), Error> {
+        platforms::do_set_platform(&env, admin, platform, max_cut_bps)
+    }
+
+    /// Maximum cut of a registered platform, or `None` if it is not registered.
+    pub fn get_platform_cap(env: Env, platform: Address) -> Option<u32> {
+        platforms::get_platform_cap(&env, &platform)
+    }
+
+    /// Route the merchant's charges through a registered platform that takes
+    /// `cut_bps` of each charge before the merchant's share. Auth: merchant and
+    /// platform.
+    pub fn join_platform(
+        env: Env,
+        merchant: Address,
+        platform: Address,
+        cut_bps: u32,
+    ) -> Result<(), Error> {
+        platforms::do_join_platform(&env, merchant, platform, cut_bps)
+    }
+
+    /// Stop routing the merchant's charges through its platform. Auth: merchant.
+    pub fn leave_platform(env: Env, merchant: Address) -> Result<(), Error> {
+        platforms::do_leave_platform(&env, merchant)
+    }
+
+    /// The merchant's platform and cut, if any.
+    pub fn get_merchant_platform(env: Env, merchant: Address) -> Option<MerchantPlatform> {
+        platforms::get_merchant_platform(&env, &merchant)
+    }
+
+    /// Re-attempt up to `limit` due entries of the on-chain retry queue, which
+    /// `batch_charge` / `batch_charge_resumable` fill with retryable failures. Admin only.
+    pub fn process_retry_queue(env: Env, limit: u32) -> Result<Vec<RetryResult>, Error> {
+        reentrancy::guarded(&env, || {
+            merchant::with_payout_batch(&env, || keeper::do_process_retry_queue(&env, limit))
+        })
+    }
+
+    /// Charge due subscriptions as a third-party keeper. Records the keeper's
+    /// charges, failures and last activity. Auth: keeper; banned keepers are rejected.
+    /// Each successful charge pays the keeper bounty, if one is set.
+    pub fn keeper_charge(
+        env: Env,
+        keeper: Address,
+        subscription_ids: Vec<u32>,
+    ) -> Result<Vec<BatchChargeResult>, Error> {
+        reentrancy::guarded(&env, || {
+            merchant::with_payout_batch(&env, || {
+                keeper::do_keeper_charge(&env, keeper, &subscription_ids)
+            })
+        })
+    }
+
+    /// Set or clear the bounty paid to keepers per charge they trigger through
+    /// `keeper_charge`, out of the merchant's payout. Admin only.
+    pub fn set_keeper_bounty(
+        env: Env,
+        admin: Address,
+        bounty: Option<KeeperBounty>,
+    ) -> Result<(), Error> {
+        keeper::do_set_keeper_bounty(&env, admin, bounty)
+    }
+
+    /// Get the keeper bounty, if any.
+    pub fn get_keeper_bounty(env: Env) -> Option<KeeperBounty> {
+        keeper::get_keeper_bounty(&env)
+    }
+
+    /// Performance statistics of a keeper, if it has ever been registered.
+    pub fn get_keeper_stats(env: Env, keeper: Address) -> Option<KeeperStats> {
+        keeper::get_keeper_stats(&env, &keeper)
+    }
+
+    /// Registered keepers in registration order (limit capped at 100).
+    pub fn list_keepers(env: Env, cursor: u32, limit: u32) -> Vec<Address> {
+        keeper::list_keepers(&env, cursor, limit)
+    }
+
+    /// Ban or unban an abusive keeper. Admin only.
+    ///
+    /// A ban only rejects the keeper's `keeper_charge` calls, withholding bounties
+    /// and stats; `charge_subscription` stays open to every address.
+    pub fn set_keeper_banned(
+        env: Env,
+        admin: Address,
+        keeper: Address,
+        banned: bool,
+    ) -> Result<(), Error> {
+        keeper::do_set_keeper_banned(&env, admin, keeper, banned)
+    }
+
+    /// Get the queued batch charge retries.
+    pub fn get_retry_queue(env: Env) -> Vec<RetryEntry> {
+        keeper::get_retry_queue(&env)
+    }
+
+    /// Emit the vault's `token` balance and tracked liabilities in one `reconciliation`
+    /// event (solvency heartbeat). Admin only.
+    pub fn emit_reconciliation(env: Env, token: Address) -> Result<ReconciliationEvent, Error> {
+        keeper::do_emit_reconciliation(&env, token)
+    }
+
+    /// Tracked amount of `token` the vault owes subscribers, merchants and claimants.
+    pub fn get_tracked_liabilities(env: Env, token: Address) -> i128 {
+        keeper::get_tracked_liabilities(&env, &token)
+    }
+
+    /// Emit `due_soon` events for subscriptions due within the lead window. Admin only.
+    ///
+    /// Each subscription is notified at most once per billing period. Returns the
+    /// ids notified by this call.
+    pub fn notify_due_soon(env: Env, subscription_ids: Vec<u32>) -> Result<Vec<u32>, Error> {
+        keeper::do_notify_due_soon(&env, &subscription_ids)
+    }
+
+    // ── Subscription lifecycle ───────────────────────────────────────────
+
+    /// Create a new subscription. Caller deposits initial USDC; contract stores agreement.
+    ///
+    /// `label` is an optional display name (at most 64 bytes) shown by wallets.
+    /// A non-zero `initial_deposit` is transferred from the subscriber and seeds the
+    /// prepaid balance; like any deposit it must be at least `min_topup`.
+    /// `charge_immediately` charges the first period within this call ("pay now,
+    /// renew every interval"); the creation fails if that charge cannot be paid.
+    pub fn create_subscription(
+        env: Env,
+        subscriber: Address,
+        merchant: Address,
+        amount: i128,
+        interval_seconds: u64,
+        usage_enabled: bool,
+        label: Option<String>,
+        initial_deposit: i128,
+        charge_immediately: bool,
+    ) -> Result<u32, Error> {
+        reentrancy::guarded(&env, || {
+            subscription::do_create_subscription(
+                &env,
+                subscriber,
+                merchant,
+                amount,
+                interval_seconds,
+                usage_enabled,
+                label,
+                initial_deposit,
+                charge_immediately,
+            )
+        })
+    }
+
+    /// Create a pull-billed subscription. Auth: subscriber.
+    ///
+    /// Instead of drawing on a prepaid balance, each interval charge pulls what the
+    /// balance lacks from the subscriber's wallet with `transfer_from`; the subscriber
+    /// must keep a token allowance for the vault (`approve`) covering the charges.
+    pub fn create_pull_subscription(
+        env: Env,
+        subscriber: Address,
+        merchant: Address,
+        amount: i128,
+        interval_seconds: u64,
+        label: Option<String>,
+    ) -> Result<u32, Error> {
+        subscription::do_create_pull_subscription(
+            &env,
+            subscriber,
+            merchant,
+            amount,
+            interval_seconds,
+            label,
+        )
+    }
+
+    /// Whether the subscription was created pull-billed.
+    pub fn is_pull_billing(env: Env, subscription_id: u32) -> bool {
+        subscription::is_pull_billing(&env, subscription_id)
+    }
+
+    /// Whether the subscriber turned on wallet fallback for the subscription.
+    pub fn is_wallet_fallback(env: Env, subscription_id: u32) -> bool {
+        subscription::is_wallet_fallback(&env, subscription_id)
+    }
+
+    /// Opt in or out of falling back to the subscriber's wallet when the prepaid
+    /// balance cannot cover a charge. Auth: subscriber.
+    ///
+    /// Needs a token allowance for the vault (`approve`) covering the shortfall.
+    pub fn set_wallet_fallback(
+        env: Env,
+        subscription_id: u32,
+        subscriber: Address,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        subscription::do_set_wallet_fallback(&env, subscription_id, subscriber, enabled)
+    }
+
+    /// Same as `create_subscription`, under a key external systems can precompute
+    /// with `derive_subscription_key(merchant, subscriber, salt)`.
+    ///
+    /// Each key can be used once (`SubscriptionKeyTaken`).
+    pub fn create_subscription_with_salt(
+        env: Env,
+        subscriber: Address,
+        merchant: Address,
+        amount: i128,
+        interval_seconds: u64,
+        usage_enabled: bool,
+        label: Option<String>,
+        initial_deposit: i128,
+        charge_immediately: bool,
+        salt: BytesN<32>,
+    ) -> Result<u32, Error> {
+        reentrancy::guarded(&env, || {
+            subscription::do_create_subscription_with_salt(
+                &env,
+                subscriber,
+                merchant,
+                amount,
+                interval_seconds,
+                usage_enabled,
+                label,
+                initial_deposit,
+                charge_immediately,
+                salt,
+            )
+        })
+    }
+
+    /// Deterministic subscription key for `salt`, usable before the subscription
+    /// exists.
+    pub fn derive_subscription_key(
+        env: Env,
+        merchant: Address,
+        subscriber: Address,
+        salt: BytesN<32>,
+    ) -> BytesN<32> {
+        subscription::derive_subscription_key(&env, &merchant, &subscriber, &salt)
+    }
+
+    /// Id of the subscription created under a derived key.
+    pub fn get_subscription_id_by_key(env: Env, key: BytesN<32>) -> Result<u32, Error> {
+        subscription::get_subscription_id_by_key(&env, key)
+    }
+
+    /// Set or clear the subscription's display label. Auth: subscriber.
+    pub fn set_subscription_label(
+        env: Env,
+        subscription_id: u32,
+        subscriber: Address,
+        label: Option<String>,
+    ) -> Result<(), Error> {
+        subscription::do_set_subscription_label(&env, subscription_id, subscriber, label)
+    }
+
+    /// Set or clear the ed25519 public key that signs off-chain agreements for
+    /// `account`. Auth: account.
+    pub fn set_signing_key(env: Env, account: Address, key: Option<BytesN<32>>) {
+        signed::do_set_signing_key(&env, account, key)
+    }
+
+    /// Get the account's registered signing key, if any.
+    pub fn get_signing_key(env: Env, account: Address) -> Option<BytesN<32>> {
+        signed::get_signing_key(&env, &account)
+    }
+
+    /// Create a subscription from an agreement signed off-chain by both parties'
+    /// registered keys. Callable by anyone (either party or a relayer).
+    ///
+    /// Each agreement is importable once and only until `expires_at`.
+    pub fn import_agreement(
+        env: Env,
+        agreement: SubscriptionAgreement,
+        subscriber_signature: BytesN<64>,
+        merchant_signature: BytesN<64>,
+    ) -> Result<u32, Error> {
+        signed::do_import_agreement(&env, agreement, subscriber_signature, merchant_signature)
+    }
+
+    /// Execute a subscriber action signed off-chain with the subscriber's registered
+    /// key. Callable by anyone (e.g. a relayer); no Soroban auth from the subscriber.
+    ///
+    /// The signature covers `(vault address, action)`; `action.nonce` must be the
+    /// signer's next nonce. Signed deposits pull tokens via the vault's allowance.
+    pub fn execute_signed(
+        env: Env,
+        action: SignedAction,
+        signature: BytesN<64>,
+    ) -> Result<(), Error> {
+        reentrancy::guarded(&env, || signed::do_execute_signed(&env, action, signature))
+    }
+
+    /// Next nonce `account` must use for `execute_signed`.
+    pub fn get_signer_nonce(env: Env, account: Address) -> u64 {
+        signed::get_signer_nonce(&env, &account)
+    }
+
+    /// Close the subscriber's account once all their subscriptions are cancelled or
+    /// completed and emptied and no credit or claim is held for them. Deletes their
+    /// subscription records and subscriber-keyed data, keeping one tombstone hash per
+    /// record, and emits `account_closed`. Auth: subscriber.
+    pub fn close_account(env: Env, subscriber: Address) -> Result<u32, Error> {
+        subscription::do_close_account(&env, subscriber)
+    }
+
+    /// Hash of a subscription record removed by `close_account`, if any.
+    pub fn get_tombstone(env: Env, subscription_id: u32) -> Option<BytesN<32>> {
+        subscription::get_tombstone(&env, subscription_id)
+    }
+
+    /// Publish a plan whose price is an A/B experiment over `(amount, weight)`
+    /// variants (at most 8). Auth: merchant. Returns the plan id.
+    pub fn create_plan(
+        env: Env,
+        merchant: Address,
+        interval_seconds: u64,
+        usage_enabled: bool,
+        prices: Vec<(i128, u32)>,
+    ) -> Result<u32, Error> {
+        plans::do_create_plan(&env, merchant, interval_seconds, usage_enabled, prices)
+    }
+
+    /// Get a plan, including per-variant subscriber counts.
+    pub fn get_plan(env: Env, plan_id: u32) -> Result<Plan, Error> {
+        plans::get_plan(&env, plan_id)
+    }
+
+    /// Subscribe to a plan. The price variant is chosen deterministically from a hash
+    /// of the subscriber's address and recorded. Auth: subscriber.
+    pub fn subscribe_to_plan(env: Env, subscriber: Address, plan_id: u32) -> Result<u32, Error> {
+        plans::do_subscribe_to_plan(&env, subscriber, plan_id)
+    }
+
+    /// Move a subscription to another plan of the same merchant and interval at the
+    /// subscriber's assigned variant. The price difference for the rest of the
+    /// current interval is charged now (upgrade) or credited to the prepaid balance
+    /// (downgrade). Auth: subscriber.
+    pub fn change_plan(env: Env, subscription_id: u32, plan_id: u32) -> Result<PriceChange, Error> {
+        plans::do_change_plan(&env, subscription_id, plan_id)
+    }
+
+    /// Change a subscription's per-interval amount mid-cycle, prorating the
+    /// difference like `change_plan`. Auth: merchant and subscriber.
+    pub fn change_amount(
+        env: Env,
+        subscription_id: u32,
+        amount: i128,
+    ) -> Result<PriceChange, Error> {
+        plans::do_change_amount(&env, subscription_id, amount)
+    }
+
+    /// Plan and price variant a subscription was created from or last changed to, if any.
+    pub fn get_plan_assignment(env: Env, subscription_id: u32) -> Option<PlanAssignment> {
+        plans::get_plan_assignment(&env, subscription_id)
+    }
+
+    /// Set the grace period and warning lead for the merchant's `Priority` tier
+    /// subscriptions. Auth: merchant.
+    pub fn set_priority_policy(
+        env: Env,
+        merchant: Address,
+        policy: PriorityTierPolicy,
+    ) -> Result<(), Error> {
+        tiers::do_set_priority_policy(&env, merchant, policy)
+    }
+
+    /// The merchant's priority tier policy, if set.
+    pub fn get_priority_policy(env: Env, merchant: Address) -> Option<PriorityTierPolicy> {
+        tiers::get_priority_policy(&env, &merchant)
+    }
+
+    /// Assign a subscription's support tier. Auth: the subscription's merchant.
+    pub fn set_support_tier(
+        env: Env,
+        merchant: Address,
+        subscription_id: u32,
+        tier: SupportTier,
+    ) -> Result<(), Error> {
+        tiers::do_set_support_tier(&env, merchant, subscription_id, tier)
+    }
+
+    /// A subscription's support tier (`Standard` unless set).
+    pub fn get_support_tier(env: Env, subscription_id: u32) -> SupportTier {
+        tiers::get_support_tier(&env, subscription_id)
+    }
+
+    /// Set or clear a cap on what the vault charges the subscriber per 30-day budget
+    /// month, across all merchants. Charges past it fail with `BudgetExceeded`.
+    /// Auth: subscriber.
+    pub fn set_spend_cap(env: Env, subscriber: Address, cap: Option<i128>) -> Result<(), Error> {
+        budget::do_set_spend_cap(&env, subscriber, cap)
+    }
+
+    /// The subscriber's current-month spend against their cap.
+    pub fn get_budget_status(env: Env, subscriber: Address) -> BudgetStatus {
+        budget::get_budget_status(&env, &subscriber)
+    }
+
+    /// Create, replace (`Some`) or retire (`None`) a referral code with a decaying
+    /// affiliate commission schedule. Auth: merchant.
+    pub fn set_referral_code(
+        env: Env,
+        merchant: Address,
+        code: Symbol,
+        referral: Option<ReferralCode>,
+    ) -> Result<(), Error> {
+        referrals::do_set_referral_code(&env, merchant, code, referral)
+    }
+
+    /// Get one of the merchant's referral codes.
+    pub fn get_referral_code(env: Env, merchant: Address, code: Symbol) -> Option<ReferralCode> {
+        referrals::get_referral_code(&env, &merchant, &code)
+    }
+
+    /// Attach a referral code to a subscription (once). Each later charge pays the
+    /// affiliate the commission rate for the subscription's age. Auth: subscriber.
+    pub fn apply_referral(
+        env: Env,
+        subscriber: Address,
+        subscription_id: u32,
+        code: Symbol,
+    ) -> Result<(), Error> {
+        referrals::do_apply_referral(&env, subscriber, subscription_id, code)
+    }
+
+    /// The referral code attached to a subscription, if any.
+    pub fn get_subscription_referral(env: Env, subscription_id: u32) -> Option<Symbol> {
+        referrals::get_subscription_referral(&env, subscription_id)
+    }
+
+    /// Subscriber deposits more USDC into their prepaid vault.
+    ///
+    /// Rejects deposits below the configured minimum threshold. For an org-account
+    /// subscriber, any org member may deposit.
+    pub fn deposit_funds(
+        env: Env,
+        subscription_id: u32,
+        subscriber: Address,
+        amount: i128,
+    ) -> Result<(), Error> {
+        reentrancy::guarded(&env, || {
+            subscription::do_deposit_funds(&env, subscription_id, subscriber, amount)
+        })
+    }
+
+    /// Set or clear the subscription's auto-top-up rule (threshold and refill
+    /// amount). Auth: subscriber.
+    ///
+    /// Refills are pulled with `transfer_from`, so the subscriber must keep a token
+    /// allowance for the vault (`approve`).
+    pub fn set_auto_topup(
+        env: Env,
+        subscription_id: u32,
+        subscriber: Address,
+        rule: Option<AutoTopup>,
+    ) -> Result<(), Error> {
+        subscription::do_set_auto_topup(&env, subscription_id, subscriber, rule)
+    }
+
+    /// Get the subscription's auto-top-up rule, if any.
+    pub fn get_auto_topup(env: Env, subscription_id: u32) -> Option<AutoTopup> {
+        subscription::get_auto_topup(&env, subscription_id)
+    }
+
+    /// Deposit the auto-top-up refill while the prepaid balance is below the rule's
+    /// threshold. Permissionless, so keepers can run it ahead of charges. Returns the
+    /// amount deposited.
+    pub fn execute_auto_topup(env: Env, subscription_id: u32) -> Result<i128, Error> {
+        reentrancy::guarded(&env, || {
+            subscription::do_execute_auto_topup(&env, subscription_id)
+        })
+    }
+
+    /// Withdraw prepaid funds above the merchant's reserve from a live subscription.
+    ///
+    /// Fails with `BelowReserve` if the remaining balance would drop below
+    /// `amount * reserve_intervals`.
+    pub fn withdraw_excess(
+        env: Env,
+        subscription_id: u32,
+        subscriber: Address,
+        amount: i128,
+    ) -> Result<(), Error> {
+        reentrancy::guarded(&env, || {
+            subscription::do_withdraw_excess(&env, subscription_id, subscriber, amount)
+        })
+    }
+
+    /// Announce a withdrawal above the large-withdrawal threshold.
+    ///
+    /// Returns the timestamp from which `withdraw_excess` / `withdraw_subscriber_funds`
+    /// may move that amount. Auth: subscriber (recovery address while locked).
+    pub fn request_withdrawal(
+        env: Env,
+        subscription_id: u32,
+        caller: Address,
+        amount: i128,
+    ) -> Result<u64, Error> {
+        subscription::do_request_withdrawal(&env, subscription_id, caller, amount)
+    }
+
+    /// Cancel an announced large withdrawal.
+    pub fn cancel_withdrawal_request(
+        env: Env,
+        subscription_id: u32,
+        caller: Address,
+    ) -> Result<(), Error> {
+        subscription::do_cancel_withdrawal_request(&env, subscription_id, caller)
+    }
+
+    /// Get the announced withdrawal for a subscription, if any.
+    pub fn get_pending_withdrawal(env: Env, subscription_id: u32) -> Option<PendingWithdrawal> {
+        subscription::get_pending_withdrawal(&env, subscription_id)
+    }
+
+    /// Register (or unregister) `org` as an organization account whose members and
+    /// owners, resolved through its [`OrgAdapter`], share management of its
+    /// subscriptions. Auth: org.
+    pub fn set_org_account(env: Env, org: Address, enabled: bool) {
+        org::do_set_org_account(&env, org, enabled)
+    }
+
+    /// True if `account` is registered as an organization account.
+    pub fn is_org_account(env: Env, account: Address) -> bool {
+        org::is_org_account(&env, &account)
+    }
+
+    /// What the subscriber would get back if the subscription were cancelled right
+    /// now: the withdrawable prepaid balance plus any cooling-off refund, split
+    /// between credit wallet and address; cooling-off refunds carry no external
+    /// refund fee.
+    pub fn get_cancellation_quote(
+        env: Env,
+        subscription_id: u32,
+    ) -> Result<CancellationQuote, Error> {
+        subscription::get_cancellation_quote(&env, subscription_id)
+    }
+
+    /// Grant or revoke the consumer-protection ombudsman role. Only callable by admin.
+    pub fn set_ombudsman(
+        env: Env,
+        admin: Address,
+        account: Address,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        ombudsman::do_set_ombudsman(&env, admin, account, enabled)
+    }
+
+    /// True if `account` holds the ombudsman role.
+    pub fn is_ombudsman(env: Env, account: Address) -> bool {
+        ombudsman::is_ombudsman(&env, &account)
+    }
+
+    /// Cancel a subscription on the subscriber's behalf and refund up to what it was
+    /// charged, citing the hash of the documented abuse case. Recorded in the admin
+    /// audit log and emitted as `ombudsman_action`. Auth: ombudsman.
+    pub fn ombudsman_cancel(
+        env: Env,
+        ombudsman: Address,
+        subscription_id: u32,
+        refund: i128,
+        reason_hash: BytesN<32>,
+    ) -> Result<(), Error> {
+        reentrancy::guarded(&env, || {
+            ombudsman::do_ombudsman_cancel(&env, ombudsman, subscription_id, refund, reason_hash)
+        })
+    }
+
+    /// Cancel the subscription. Allowed from Active, Paused, or InsufficientBalance.
+    /// Transitions to the terminal `Cancelled` state.
+    ///
+    /// Owners of an org-account subscriber may cancel on the org's behalf.
+    pub fn cancel_subscription(
+        env: Env,
+        subscription_id: u32,
+        authorizer: Address,
+    ) -> Result<(), Error> {
+        reentrancy::guarded(&env, || {
+            subscription::do_cancel_subscription(&env, subscription_id, authorizer)
+        })
+    }
+
+    /// Cancel every non-cancelled subscription owned by `subscriber` in one call.
+    ///
+    /// Bounded: scans at most `limit` ids (capped at 100) starting at `cursor`.
+    /// Call again with `next_cursor` while `has_next` is true.
+    pub fn cancel_all_subscriptions(
+        env: Env,
+        subscriber: Address,
+        cursor: u32,
+        limit: u32,
+    ) -> Result<BulkCancelResult, Error> {
+        reentrancy::guarded(&env, || {
+            subscription::do_cancel_all_subscriptions(&env, subscriber, cursor, limit)
+        })
+    }
+
+    /// Subscriber withdraws their remaining prepaid_balance after cancellation.
+    ///
+    /// If the subscriber is locked, only the recovery address may withdraw, and
+    /// the refund is paid to it.
+    pub fn withdraw_subscriber_funds(
+        env: Env,
+        subscription_id: u32,
+        subscriber: Address,
+    ) -> Result<(), Error> {
+        reentrancy::guarded(&env, || {
+            subscription::do_withdraw_subscriber_funds(&env, subscription_id, subscriber)
+        })
+    }
+
+    /// Emergency lock for a compromised subscriber key. Auth: subscriber.
+    ///
+    /// Freezes deposits and withdrawals on the subscriber's subscriptions and
+    /// redirects refunds to `recovery_address`, which alone can unlock.
+    pub fn lock_subscriber(
+        env: Env,
+        subscriber: Address,
+        recovery_address: Address,
+    ) -> Result<(), Error> {
+        subscription::do_lock_subscriber(&env, subscriber, recovery_address)
+    }
+
+    /// Lift the emergency lock. Auth: the recovery address set at lock time.
+    pub fn unlock_subscriber(
+        env: Env,
+        subscriber: Address,
+        recovery_address: Address,
+    ) -> Result<(), Error> {
+        subscription::do_unlock_subscriber(&env, subscriber, recovery_address)
+    }
+
+    /// Return the recovery address if the subscriber is locked.
+    pub fn get_subscriber_lock(env: Env, subscriber: Address) -> Option<Address> {
+        subscription::get_recovery_address(&env, &subscriber)
+    }
+
+    /// Pause subscription (no charges until resumed). Allowed from Active.
+    /// Auth: the subscription's subscriber or merchant.
+    pub fn pause_subscription(
+        env: Env,
+        subscription_id: u32,
+        authorizer: Address,
+    ) -> Result<(), Error> {
+        subscription::do_pause_subscription(&env, subscription_id, authorizer)
+    }
+
+    /// Resume a subscription to Active. Allowed from Paused or InsufficientBalance.
+    /// Auth: the subscription's subscriber or merchant.
+    pub fn resume_subscription(
+        env: Env,
+        subscription_id: u32,
+        authorizer: Address,
+    ) -> Result<(), Error> {
+        subscription::do_resume_subscription(&env, subscription_id, authorizer)
+    }
+
+    /// Switch billing between timestamp and ledger-sequence scheduling. Auth: merchant.
+    ///
+    /// `Some(interval_ledgers)` bills every N ledgers from now (for merchants who
+    /// distrust validator clocks); `None` reverts to `interval_seconds`.
+    pub fn set_ledger_schedule(
+        env: Env,
+        subscription_id: u32,
+        merchant: Address,
+        interval_ledgers: Option<u32>,
+    ) -> Result<(), Error> {
+        subscription::do_set_ledger_schedule(&env, subscription_id, merchant, interval_ledgers)
+    }
+
+    /// Opt into rounding each interval charge up to a multiple of `unit`. Auth: subscriber.
+    ///
+    /// The difference is taken from the prepaid balance and sent to the donation
+    /// address. `unit == 0` opts out.
+    pub fn set_round_up(env: Env, subscriber: Address, unit: i128) -> Result<(), Error> {
+        subscription::do_set_round_up(&env, subscriber, unit)
+    }
+
+    /// A subscriber's round-up setting and total donated so far.
+    pub fn get_round_up(env: Env, subscriber: Address) -> RoundUp {
+        subscription::get_round_up(&env, &subscriber)
+    }
+
+    /// Require pre-approval of merchants for new subscriptions (deny-by-default). Auth: subscriber.
+    pub fn set_allowlist_enforced(
+        env: Env,
+        subscriber: Address,
+        enforced: bool,
+    ) -> Result<(), Error> {
+        subscription::do_set_allowlist_enforced(&env, subscriber, enforced)
+    }
+
+    /// Pre-approve a merchant for new subscriptions (max 50). Auth: subscriber.
+    pub fn approve_merchant(env: Env, subscriber: Address, merchant: Address) -> Result<(), Error> {
+        subscription::do_approve_merchant(&env, subscriber, merchant)
+    }
+
+    /// Remove a merchant from the subscriber's allow-list. Auth: subscriber.
+    pub fn revoke_merchant(env: Env, subscriber: Address, merchant: Address) -> Result<(), Error> {
+        subscription::do_revoke_merchant(&env, subscriber, merchant)
+    }
+
+    /// The subscriber's merchant allow-list and whether it is enforced.
+    pub fn get_merchant_allowlist(env: Env, subscriber: Address) -> MerchantAllowlist {
+        subscription::get_merchant_allowlist(&env, &subscriber)
+    }
+
+    /// Make a subscription fixed-term, completing after `total_periods` charges. Auth: merchant.
+    pub fn set_fixed_term(
+        env: Env,
+        subscription_id: u32,
+        merchant: Address,
+        total_periods: u32,
+    ) -> Result<(), Error> {
+        subscription::do_set_fixed_term(&env, subscription_id, merchant, total_periods)
+    }
+
+    /// Fixed term of a subscription, or `None` if it is open-ended.
+    pub fn get_fixed_term(env: Env, subscription_id: u32) -> Option<FixedTerm> {
+        subscription::get_fixed_term(&env, subscription_id)
+    }
+
+    /// Set or clear the subscription's end date (`expires_at`). Auth: merchant.
+    ///
+    /// From the end date on the subscription is no longer chargeable and moves to
+    /// the terminal `Expired` status (via `expire_subscription`, or automatically
+    /// when the subscriber withdraws the remaining balance).
+    pub fn set_expiry(
+        env: Env,
+        subscription_id: u32,
+        merchant: Address,
+        expires_at: Option<u64>,
+    ) -> Result<(), Error> {
+        subscription::do_set_expiry(&env, subscription_id, merchant, expires_at)
+    }
+
+    /// End date of a subscription, or `None` if it has none.
+    pub fn get_expires_at(env: Env, subscription_id: u32) -> Option<u64> {
+        subscription::get_expires_at(&env, subscription_id)
+    }
+
+    /// Move a subscription that reached its end date to `Expired`. Permissionless.
+    pub fn expire_subscription(env: Env, subscription_id: u32) -> Result<(), Error> {
+        subscription::do_expire_subscription(&env, subscription_id)
+    }
+
+    /// Propose a new `amount` and `interval_seconds` for a subscription. Auth: merchant.
+    ///
+    /// Takes effect only once the subscriber calls `accept_subscription_update`, from
+    /// the next charge on. A new proposal replaces any pending one.
+    pub fn update_subscription(
+        env: Env,
+        subscription_id: u32,
+        merchant: Address,
+        amount: i128,
+        interval_seconds: u64,
+    ) -> Result<(), Error> {
+        subscription::do_propose_update(&env, subscription_id, merchant, amount, interval_seconds)
+    }
+
+    /// Accept the pending update, restating its terms. Auth: subscriber.
+    ///
+    /// The next charge bills the new amount and the new interval runs from it.
+    pub fn accept_subscription_update(
+        env: Env,
+        subscription_id: u32,
+        subscriber: Address,
+        amount: i128,
+        interval_seconds: u64,
+    ) -> Result<(), Error> {
+        subscription::do_accept_update(&env, subscription_id, subscriber, amount, interval_seconds)
+    }
+
+    /// Pending (proposed or accepted, not yet applied) update of a subscription.
+    pub fn get_pending_update(env: Env, subscription_id: u32) -> Option<SubscriptionUpdate> {
+        subscription::get_pending_update(&env, subscription_id)
+    }
+
+    /// Renew a `Completed` fixed-term subscription into a new successor. Auth: subscriber.
+    ///
+    /// Copies the predecessor's terms, optionally at `new_amount`, and links the two ids.
+    /// Returns the successor id.
+    pub fn renew_subscription(
+        env: Env,
+        subscription_id: u32,
+        subscriber: Address,
+        new_amount: Option<i128>,
+    ) -> Result<u32, Error> {
+        subscription::do_renew_subscription(&env, subscription_id, subscriber, new_amount)
+    }
+
+    /// Predecessor/successor renewal links for a subscription.
+    pub fn get_subscription_lineage(env: Env, subscription_id: u32) -> SubscriptionLineage {
+        subscription::get_lineage(&env, subscription_id)
+    }
+
+    /// Merge two duplicate subscriptions (same merchant, price, interval and token)
+    /// of one subscriber. Auth: subscriber.
+    ///
+    /// The one with the older billing anchor survives and takes over the other's
+    /// prepaid balance; the duplicate is cancelled and linked to the survivor.
+    /// Returns the survivor id.
+    pub fn merge_subscriptions(
+        env: Env,
+        subscriber: Address,
+        id_a: u32,
+        id_b: u32,
+    ) -> Result<u32, Error> {
+        subscription::do_merge_subscriptions(&env, subscriber, id_a, id_b)
+    }
+
+    /// Subscription a cancelled duplicate was merged into, if any.
+    pub fn get_merged_into(env: Env, subscription_id: u32) -> Option<u32> {
+        subscription::get_merged_into(&env, subscription_id)
+    }
+
+    /// Governance voting weight of `account` at the end of the checkpoint window
+    /// containing `snapshot_ledger`: what it was charged on live subscriptions plus
+    /// what it earned as a merchant up to then, less refunds.
+    ///
+    /// `snapshot_ledger` must be in a past window.
+    pub fn get_voting_weight(
+        env: Env,
+        account: Address,
+        snapshot_ledger: u32,
+    ) -> Result<i128, Error> {
+        voting::get_voting_weight(&env, account, snapshot_ledger)
+    }
+
+    // ── Charging ─────────────────────────────────────────────────────────
+
+    /// Billing engine calls this to charge one interval.
+    ///
+    /// Enforces strict interval timing and replay protection. Permissionless: no
+    /// authorization is required, so anyone may trigger a due charge; every safety
+    /// check lives in the charge itself.
+    pub fn charge_subscription(env: Env, subscription_id: u32) -> Result<(), Error> {
+        reentrancy::guarded(&env, || {
+            charge_core::charge_one(&env, subscription_id, None)
+        })
+    }
+
+    /// Charge a metered usage amount against the subscription's prepaid balance.
+    ///
+    /// Designed for integration with an **off-chain usage metering service**:
+    /// the service measures consumption, then calls this entrypoint with the
+    /// computed `usage_amount` to debit the subscriber's vault.
+    ///
+    /// # Requirements
+    ///
+    /// * `caller` must be the merchant or one of its `Metering` API keys.
+    /// * The subscription must be `Active`.
+    /// * `usage_enabled` must be `true` on the subscription.
+    /// * `usage_amount` must be positive (`> 0`).
+    /// * `prepaid_balance` must be >= `usage_amount`.
+    ///
+    /// # Behaviour
+    ///
+    /// On success, `prepaid_balance` is reduced by `usage_amount`.  If the
+    /// debit drains the balance to zero the subscription transitions to
+    /// `InsufficientBalance` status, signalling that no further charges
+    /// (interval or usage) can proceed until the subscriber tops up.
+    ///
+    /// # Errors
+    ///
+    /// | Variant | Reason |
+    /// |---------|--------|
+    /// | `NotFound` | Subscription ID does not exist. |
+    /// | `Unauthorized` | `caller` lacks the `Metering` scope for this merchant. |
+    /// | `NotActive` | Subscription is not `Active`. |
+    /// | `UsageNotEnabled` | `usage_enabled` is `false`. |
+    /// | `InvalidAmount` | `usage_amount` is zero or negative. |
+    /// | `InsufficientPrepaidBalance` | Prepaid balance cannot cover the debit. |
+    pub fn charge_usage(
+        env: Env,
+        caller: Address,
+        subscription_id: u32,
+        usage_amount: i128,
+    ) -> Result<(), Error> {
+        reentrancy::guarded(&env, || {
+            charge_core::charge_usage_one(&env, caller, subscription_id, usage_amount)
+        })
+    }
+
+    // ── Merchant ─────────────────────────────────────────────────────────
+
+    /// Merchant withdraws accumulated USDC to their wallet.
+    ///
+    /// `amount` is checked against the merchant's accrued balance (the payout
+    /// queue credited by each charge); exceeding it fails with `InsufficientBalance`.
+    /// Each withdrawal closes the merchant's settlement epoch and emits a
+    /// `settlement` summary (period, charge count, gross, fees, net).
+    pub fn withdraw_merchant_funds(env: Env, merchant: Address, amount: i128) -> Result<(), Error> {
+        reentrancy::guarded(&env, || {
+            merchant::withdraw_merchant_funds(&env, merchant, amount)
+        })
+    }
+
+    /// Get a page of the merchant's most recent settlement summaries (up to 12
+    /// retained), oldest first, starting at `cursor`.
+    pub fn get_settlements(env: Env, merchant: Address, cursor: u32, limit: u32) -> SettlementPage {
+        merchant::get_settlements(&env, &merchant, cursor, limit)
+    }
+
+    /// Merchant bookkeeping summary for `[period_start, period_end)`: charge, refund
+    /// and fee counts and sums plus new subscriptions and cancellations, aggregated
+    /// per UTC day. The returned bounds are widened to whole days; ranges are capped
+    /// at 366 days.
+    pub fn get_merchant_period_summary(
+        env: Env,
+        merchant: Address,
+        period_start: u64,
+        period_end: u64,
+    ) -> Result<MerchantPeriodSummary, Error> {
+        reports::get_merchant_period_summary(&env, merchant, period_start, period_end)
+    }
+
+    /// Vault-wide statistics for explorers: subscriptions ever created, active
+    /// subscriptions, cumulative charged volume, distinct merchants and subscribers.
+    pub fn get_public_stats(env: Env) -> PublicStats {
+        reports::get_public_stats(&env)
+    }
+
+    /// Register or clear the merchant's webhook contract, called best-effort after
+    /// charges and cancellations of its subscriptions. Hook failures never fail
+    /// billing; they emit `webhook_failed`. Auth: merchant.
+    pub fn set_webhook(env: Env, merchant: Address, webhook: Option<Address>) -> Result<(), Error> {
+        webhooks::do_set_webhook(&env, merchant, webhook)
+    }
+
+    /// The merchant's webhook contract, if registered.
+    pub fn get_webhook(env: Env, merchant: Address) -> Option<Address> {
+        webhooks::get_webhook(&env, &merchant)
+    }
+
+    /// Set or clear the merchant's payout batching policy. Auth: merchant.
+    ///
+    /// Charges accumulate in a per-token payout queue and are transferred in one
+    /// go once the threshold is crossed or the payout interval has elapsed.
+    pub fn set_payout_policy(
+        env: Env,
+        merchant: Address,
+        policy: Option<PayoutPolicy>,
+    ) -> Result<(), Error> {
+        merchant::do_set_payout_policy(&env, merchant, policy)
+    }
+
+    /// Set or clear a payout adapter (e.g. a bridge) for the merchant's payouts. Auth: merchant.
+    ///
+    /// Realized payouts are offered to the adapter's `forward_payout`; whatever it
+    /// does not pull is transferred to the merchant directly.
+    pub fn set_payout_route(
+        env: Env,
+        merchant: Address,
+        route: Option<PayoutRoute>,
+    ) -> Result<(), Error> {
+        merchant::do_set_payout_route(&env, merchant, route)
+    }
+
+    /// Get the merchant's payout route, if any.
+    pub fn get_payout_route(env: Env, merchant: Address) -> Option<PayoutRoute> {
+        merchant::get_payout_route(&env, &merchant)
+    }
+
+    /// Set the stake required to register as a merchant and the de-registration
+    /// cooldown. Admin only. An amount of 0 disables the requirement.
+    pub fn set_merchant_stake_policy(
+        env: Env,
+        admin: Address,
+        policy: MerchantStakePolicy,
+    ) -> Result<(), Error> {
+        stake::do_set_merchant_stake_policy(&env, admin, policy)
+    }
+
+    /// Get the merchant stake policy.
+    pub fn get_merchant_stake_policy(env: Env) -> MerchantStakePolicy {
+        stake::get_merchant_stake_policy(&env)
+    }
+
+    /// Register as a merchant, locking the policy's stake in the vault. Auth: merchant.
+    ///
+    /// While a stake is required, subscriptions can only be created against
+    /// registered merchants.
+    pub fn register_merchant(env: Env, merchant: Address) -> Result<MerchantStake, Error> {
+        reentrancy::guarded(&env, || stake::do_register_merchant(&env, merchant))
+    }
+
+    /// De-register and start the stake cooldown. Auth: merchant.
+    /// Returns when the stake becomes withdrawable.
+    pub fn deregister_merchant(env: Env, merchant: Address) -> Result<u64, Error> {
+        stake::do_deregister_merchant(&env, merchant)
+    }
+
+    /// Withdraw the stake once the de-registration cooldown has elapsed. Auth: merchant.
+    pub fn withdraw_merchant_stake(env: Env, merchant: Address) -> Result<i128, Error> {
+        reentrancy::guarded(&env, || stake::do_withdraw_merchant_stake(&env, merchant))
+    }
+
+    /// Get the merchant's registration stake, if registered.
+    pub fn get_merchant_stake(env: Env, merchant: Address) -> Option<MerchantStake> {
+        stake::get_merchant_stake(&env, &merchant)
+    }
+
+    /// Report `merchant` for fraud with a hash of the evidence. Auth: reporter.
+    pub fn report_merchant(
+        env: Env,
+        reporter: Address,
+        merchant: Address,
+        evidence: BytesN<32>,
+    ) -> Result<(), Error> {
+        stake::do_report_merchant(&env, reporter, merchant, evidence)
+    }
+
+    /// Resolve the open fraud report against `merchant`. Admin only.
+    ///
+    /// When `confirmed`, the merchant's stake and queued payouts are slashed into the
+    /// victim compensation pot and credited pro-rata to its subscribers' credit wallets.
+    pub fn resolve_report(
+        env: Env,
+        admin: Address,
+        merchant: Address,
+        confirmed: bool,
+    ) -> Result<Option<MerchantSlashedEvent>, Error> {
+        stake::do_resolve_report(&env, admin, merchant, confirmed)
+    }
+
+    /// Emergency kill switch: halt all charging for `merchant` (e.g. suspected
+    /// fraud) without affecting other merchants. Notifies each affected subscriber
+    /// with a `charging_halted` event. Admin only.
+    pub fn halt_merchant(
+        env: Env,
+        admin: Address,
+        merchant: Address,
+        reason: Symbol,
+    ) -> Result<(), Error> {
+        stake::do_halt_merchant(&env, admin, merchant, reason)
+    }
+
+    /// Lift a merchant halt; subscribers get `charging_resumed` events. Admin only.
+    pub fn reinstate_merchant(env: Env, admin: Address, merchant: Address) -> Result<(), Error> {
+        stake::do_reinstate_merchant(&env, admin, merchant)
+    }
+
+    /// The merchant's active halt, if any.
+    pub fn get_merchant_halt(env: Env, merchant: Address) -> Option<MerchantHalt> {
+        stake::get_merchant_halt(&env, &merchant)
+    }
+
+    /// Get the open fraud report against `merchant`, if any.
+    pub fn get_fraud_report(env: Env, merchant: Address) -> Option<FraudReport> {
+        stake::get_fraud_report(&env, &merchant)
+    }
+
+    /// Undistributed victim compensation from slashing `merchant`, held in `token`.
+    pub fn get_compensation_pot(env: Env, merchant: Address, token: Address) -> i128 {
+        stake::get_compensation_pot(&env, &merchant, &token)
+    }
+
+    /// Define the merchant's CRM tags (e.g. `VIP`, `AtRisk`), at most 8. Tags dropped
+    /// from the set are removed from the merchant's subscriptions. Auth: merchant.
+    pub fn set_merchant_tags(env: Env, merchant: Address, tags: Vec<Symbol>) -> Result<(), Error> {
+        merchant::do_set_merchant_tags(&env, merchant, tags)
+    }
+
+    /// Get the merchant's defined tags.
+    pub fn get_merchant_tags(env: Env, merchant: Address) -> Vec<Symbol> {
+        merchant::get_merchant_tags(&env, &merchant)
+    }
+
+    /// Set or clear the merchant's tag on one of its subscriptions. Billing and status
+    /// are unaffected. Auth: merchant.
+    pub fn tag_subscription(
+        env: Env,
+        merchant: Address,
+        subscription_id: u32,
+        tag: Option<Symbol>,
+    ) -> Result<(), Error> {
+        merchant::do_tag_subscription(&env, merchant, subscription_id, tag)
+    }
+
+    /// Get the merchant tag on a subscription, if any.
+    pub fn get_subscription_tag(env: Env, subscription_id: u32) -> Option<Symbol> {
+        merchant::get_subscription_tag(&env, subscription_id)
+    }
+
+    /// Get a page of ids of the merchant's subscriptions carrying `tag`.
+    pub fn get_subscriptions_by_tag(
+        env: Env,
+        merchant: Address,
+        tag: Symbol,
+        cursor: u32,
+        limit: u32,
+    ) -> SubscriptionIdPage {
+        merchant::get_subscriptions_by_tag(&env, &merchant, &tag, cursor, limit)
+    }
+
+    /// Get the merchant's payout batching policy, if any.
+    pub fn get_payout_policy(env: Env, merchant: Address) -> Option<PayoutPolicy> {
+        merchant::get_payout_policy(&env, &merchant)
+    }
+
+    /// Get the merchant's queued, not yet transferred credits in `token`.
+    pub fn get_payout_queue(env: Env, merchant: Address, token: Address) -> PayoutQueue {
+        merchant::get_payout_queue(&env, &merchant, &token)
+    }
+
+    /// Realize the merchant's queued credits in `token` if the policy's threshold or
+    /// schedule is reached. Callable by anyone (e.g. the keeper). Returns the amount paid.
+    pub fn process_payout(env: Env, merchant: Address, token: Address) -> Result<i128, Error> {
+        reentrancy::guarded(&env, || {
+            merchant::realize_payout_if_due(&env, &merchant, &token)
+        })
+    }
+
+    /// Put a subscription on seat pricing (`amount = unit_price × seats`).
+    /// Auth: merchant and subscriber. Returns the new per-interval amount.
+    pub fn set_seat_plan(
+        env: Env,
+        subscription_id: u32,
+        unit_price: i128,
+        seats: u32,
+        min_seats: u32,
+        max_seats: u32,
+    ) -> Result<i128, Error> {
+        seats::do_set_seat_plan(
+            &env,
+            subscription_id,
+            unit_price,
+            seats,
+            min_seats,
+            max_seats,
+        )
+    }
+
+    /// Change the seat count. Auth: caller (subscriber or merchant).
+    ///
+    /// Subscribers apply changes within the plan's bounds directly; merchants propose a
+    /// count for the subscriber to `accept_seats`. Seats added mid-cycle are charged
+    /// immediately, prorated to the rest of the interval. Returns the prorated charge.
+    pub fn update_seats(
+        env: Env,
+        caller: Address,
+        subscription_id: u32,
+        seats: u32,
+    ) -> Result<i128, Error> {
+        seats::do_update_seats(&env, caller, subscription_id, seats)
     }
 
-    /// Get the current minimum top-up threshold.
-    pub fn get_min_topup(env: Env) -> Result<i128, Error> {
-        admin::get_min_topup(&env)
+    /// Accept the merchant's proposed seat count. Auth: subscriber.
+    pub fn accept_seats(env: Env, subscription_id: u32) -> Result<i128, Error> {
+        seats::do_accept_seats(&env, subscription_id)
     }
 
-    /// Get the current admin address.
-    pub fn get_admin(env: Env) -> Result<Address, Error> {
-        admin::do_get_admin(&env)
+    /// Get the subscription's seat plan, if any.
+    pub fn get_seat_plan(env: Env, subscription_id: u32) -> Option<SeatPlan> {
+        seats::get_seat_plan(&env, subscription_id)
     }
 
-    /// Rotate admin to a new address. Only callable by current admin.
+    /// Set or clear the merchant's charge-time tax configuration. Auth: merchant.
     ///
-    /// # Security
+    /// Prices are tax-inclusive; `rate_bps` of each charge is reported as a tax line
+    /// item and routed to the configured recipient or the merchant's tax sub-balance.
+    pub fn set_tax_config(
+        env: Env,
+        merchant: Address,
+        config: Option<TaxConfig>,
+    ) -> Result<(), Error> {
+        tax::do_set_tax_config(&env, merchant, config)
+    }
+
+    /// Get the merchant's tax configuration, if any.
+    pub fn get_tax_config(env: Env, merchant: Address) -> Option<TaxConfig> {
+        tax::get_tax_config(&env, &merchant)
+    }
+
+    /// Get the merchant's collected, not yet withdrawn tax in `token`.
+    pub fn get_tax_balance(env: Env, merchant: Address, token: Address) -> i128 {
+        tax::get_tax_balance(&env, &merchant, &token)
+    }
+
+    /// Withdraw the merchant's tax sub-balance in `token`. Auth: merchant.
+    pub fn withdraw_tax_balance(
+        env: Env,
+        merchant: Address,
+        token: Address,
+    ) -> Result<i128, Error> {
+        reentrancy::guarded(&env, || tax::do_withdraw_tax_balance(&env, merchant, token))
+    }
+
+    /// Get the head of the merchant's hash-chained charge receipts.
     ///
-    /// - Immediate effect — old admin loses access instantly.
-    /// - Irreversible without the new admin's cooperation.
-    /// - Emits an `admin_rotation` event for audit trail.
-    pub fn rotate_admin(env: Env, current_admin: Address, new_admin: Address) -> Result<(), Error> {
-        admin::do_rotate_admin(&env, current_admin, new_admin)
+    /// Auditors verify an exported receipt set by chaining `hash_receipt` over it in
+    /// sequence order and comparing the final hash with this head.
+    pub fn get_receipt_head(env: Env, merchant: Address) -> ReceiptHead {
+        receipts::get_receipt_head(&env, &merchant)
     }
 
-    /// **ADMIN ONLY**: Recover stranded funds from the contract.
+    /// Chain hash of a receipt (SHA-256 of its XDR encoding).
+    pub fn hash_receipt(env: Env, receipt: ChargeReceipt) -> BytesN<32> {
+        receipts::hash_receipt(&env, &receipt)
+    }
+
+    /// Post a proof-of-service hash for an already charged billing period. Auth: merchant.
     ///
-    /// Tightly-scoped mechanism for recovering funds that have become
-    /// inaccessible through normal operations. Each recovery emits a
-    /// `RecoveryEvent` with full audit details.
-    pub fn recover_stranded_funds(
+    /// Attestations are immutable and give dispute resolution a defensive record.
+    pub fn attest_service(
         env: Env,
-        admin: Address,
-        recipient: Address,
-        amount: i128,
-        reason: RecoveryReason,
+        subscription_id: u32,
+        merchant: Address,
+        period_index: u64,
+        proof_hash: BytesN<32>,
     ) -> Result<(), Error> {
-        admin::do_recover_stranded_funds(&env, admin, recipient, amount, reason)
+        merchant::do_attest_service(&env, subscription_id, merchant, period_index, proof_hash)
     }
 
-    /// Charge a batch of subscriptions in one transaction. Admin only.
+    /// Get the merchant's attestation for a billing period, if posted.
+    pub fn get_attestation(
+        env: Env,
+        subscription_id: u32,
+        period_index: u64,
+    ) -> Option<ServiceAttestation> {
+        merchant::get_attestation(&env, subscription_id, period_index)
+    }
+
+    /// Index of the most recently charged billing period, if any.
+    pub fn get_charged_period(env: Env, subscription_id: u32) -> Option<u64> {
+        charge_core::get_charged_period(&env, subscription_id)
+    }
+
+    /// Pledge a share of the merchant's future receivables in `token` to a lienholder.
     ///
-    /// Returns a per-subscription result vector so callers can identify
-    /// which charges succeeded and which failed (with error codes).
-    pub fn batch_charge(
+    /// `share_bps` of each charge is paid to the lienholder until `target` is repaid.
+    /// Auth: merchant and lienholder.
+    pub fn grant_lien(
         env: Env,
-        subscription_ids: Vec<u32>,
-    ) -> Result<Vec<BatchChargeResult>, Error> {
-        admin::do_batch_charge(&env, &subscription_ids)
+        merchant: Address,
+        lienholder: Address,
+        token: Address,
+        share_bps: u32,
+        target: i128,
+    ) -> Result<(), Error> {
+        merchant::do_grant_lien(&env, merchant, lienholder, token, share_bps, target)
     }
 
-    // ── Subscription lifecycle ───────────────────────────────────────────
+    /// Release a merchant's lien before it is fully repaid. Auth: lienholder.
+    pub fn release_lien(env: Env, lienholder: Address, merchant: Address) -> Result<(), Error> {
+        merchant::do_release_lien(&env, lienholder, merchant)
+    }
 
-    /// Create a new subscription. Caller deposits initial USDC; contract stores agreement.
-    pub fn create_subscription(
+    /// Get the merchant's active lien, if any.
+    pub fn get_lien(env: Env, merchant: Address) -> Option<Lien> {
+        merchant::get_lien(&env, &merchant)
+    }
+
+    /// Subscriber's credit wallet balance in `token`.
+    pub fn get_credit_balance(env: Env, subscriber: Address, token: Address) -> i128 {
+        credit::get_credit_balance(&env, &subscriber, &token)
+    }
+
+    /// Move settlement tokens into the subscriber's credit wallet. Auth: subscriber.
+    ///
+    /// Wallet credit is used by interval charges on any of the subscriber's
+    /// subscriptions before their prepaid balance. Returns the new wallet balance.
+    pub fn deposit_credit(env: Env, subscriber: Address, amount: i128) -> Result<i128, Error> {
+        reentrancy::guarded(&env, || credit::do_deposit_credit(&env, subscriber, amount))
+    }
+
+    /// Preset the share of refunds (basis points) transferred to the subscriber's
+    /// address instead of kept in the credit wallet. Auth: subscriber.
+    ///
+    /// The transferred share pays the external refund fee; the wallet share is
+    /// fee-free. `0` (the default) keeps refunds in the wallet.
+    pub fn set_refund_split(env: Env, subscriber: Address, external_bps: u32) -> Result<(), Error> {
+        credit::do_set_refund_split(&env, subscriber, external_bps)
+    }
+
+    /// Get the subscriber's refund split (basis points sent to their address).
+    pub fn get_refund_split(env: Env, subscriber: Address) -> u32 {
+        credit::get_refund_split(&env, &subscriber)
+    }
+
+    /// Withdraw from the subscriber's credit wallet. Auth: subscriber.
+    pub fn withdraw_credit(
         env: Env,
         subscriber: Address,
-        merchant: Address,
+        token: Address,
         amount: i128,
-        interval_seconds: u64,
-        usage_enabled: bool,
-    ) -> Result<u32, Error> {
-        subscription::do_create_subscription(
-            &env,
-            subscriber,
-            merchant,
-            amount,
-            interval_seconds,
-            usage_enabled,
-        )
+    ) -> Result<i128, Error> {
+        reentrancy::guarded(&env, || {
+            credit::do_withdraw_credit(&env, subscriber, token, amount)
+        })
     }
 
-    /// Subscriber deposits more USDC into their prepaid vault.
+    /// Grant promo credit from the merchant's accrued payout balance in `token`.
     ///
-    /// Rejects deposits below the configured minimum threshold.
-    pub fn deposit_funds(
+    /// Applied automatically to the subscriber's future charges from this merchant
+    /// until `expires_at`. Auth: merchant.
+    pub fn grant_promo_credit(
         env: Env,
-        subscription_id: u32,
+        merchant: Address,
         subscriber: Address,
+        token: Address,
         amount: i128,
+        expires_at: u64,
+    ) -> Result<PromoCredit, Error> {
+        credit::do_grant_promo_credit(&env, merchant, subscriber, token, amount, expires_at)
+    }
+
+    /// Return an expired promo credit's remainder to the merchant. Auth: merchant.
+    pub fn reclaim_promo_credit(
+        env: Env,
+        merchant: Address,
+        subscriber: Address,
+    ) -> Result<i128, Error> {
+        credit::do_reclaim_promo_credit(&env, merchant, subscriber)
+    }
+
+    /// Get the subscriber's promo credit from `merchant`, if any.
+    pub fn get_promo_credit(
+        env: Env,
+        subscriber: Address,
+        merchant: Address,
+    ) -> Option<PromoCredit> {
+        credit::get_promo_credit(&env, &subscriber, &merchant)
+    }
+
+    /// Amount of `token` owed to `recipient` from transfers that could not be delivered.
+    pub fn get_claimable(env: Env, recipient: Address, token: Address) -> i128 {
+        claims::get_claimable(&env, &recipient, &token)
+    }
+
+    /// Pull the recipient's whole claim balance in `token`. Auth: recipient.
+    ///
+    /// Claims accrue when an outbound transfer (payout, withdrawal, donation) fails,
+    /// e.g. due to a frozen trustline. Returns the amount transferred.
+    pub fn claim(env: Env, recipient: Address, token: Address) -> Result<i128, Error> {
+        reentrancy::guarded(&env, || claims::do_claim(&env, recipient, token))
+    }
+
+    /// Register (or re-scope) a named API key address for the merchant.
+    ///
+    /// Keys holding a scope are accepted by merchant-scoped entrypoints requiring it:
+    /// `Metering` for `charge_usage`, `Refunds` for `cancel_subscription`.
+    pub fn register_api_key(
+        env: Env,
+        merchant: Address,
+        key: Address,
+        name: Symbol,
+        scope: ApiKeyScope,
     ) -> Result<(), Error> {
-        subscription::do_deposit_funds(&env, subscription_id, subscriber, amount)
+        merchant::do_register_api_key(&env, merchant, key, name, scope)
     }
 
-    /// Cancel the subscription. Allowed from Active, Paused, or InsufficientBalance.
-    /// Transitions to the terminal `Cancelled` state.
-    pub fn cancel_subscription(
+    /// Revoke one of the merchant's API keys.
+    pub fn revoke_api_key(env: Env, merchant: Address, key: Address) -> Result<(), Error> {
+        merchant::do_revoke_api_key(&env, merchant, key)
+    }
+
+    /// List a page of the merchant's registered API keys.
+    pub fn list_api_keys(env: Env, merchant: Address, cursor: u32, limit: u32) -> ApiKeyPage {
+        merchant::list_api_keys(&env, &merchant, cursor, limit)
+    }
+
+    /// Set the merchant's minimum per-interval amount (`0` removes it). Auth: merchant.
+    ///
+    /// Checked when a subscription is created and whenever its amount changes
+    /// (terms, seats, renewal); existing subscriptions are not affected.
+    pub fn set_merchant_min_amount(
+        env: Env,
+        merchant: Address,
+        min_amount: i128,
+    ) -> Result<(), Error> {
+        merchant::do_set_min_amount(&env, merchant, min_amount)
+    }
+
+    /// Get the merchant's minimum per-interval amount (0 when unset), for checkout UIs.
+    pub fn get_merchant_min_amount(env: Env, merchant: Address) -> i128 {
+        merchant::get_min_amount(&env, &merchant)
+    }
+
+    /// Require subscribers to keep `intervals` charges prepaid. Auth: merchant.
+    ///
+    /// Deposits leaving the balance below the reserve emit `reserve_warning`.
+    pub fn set_reserve_intervals(env: Env, merchant: Address, intervals: u32) -> Result<(), Error> {
+        merchant::do_set_reserve_intervals(&env, merchant, intervals)
+    }
+
+    /// Get the merchant's reserve requirement, in intervals.
+    pub fn get_reserve_intervals(env: Env, merchant: Address) -> u32 {
+        merchant::get_reserve_intervals(&env, &merchant)
+    }
+
+    /// Push a subscription's next due date forward by `seconds` (goodwill credit). Auth: merchant.
+    ///
+    /// Grants are capped at one interval per billing period. Returns the new due timestamp.
+    pub fn grant_credit_time(
         env: Env,
         subscription_id: u32,
-        authorizer: Address,
+        merchant: Address,
+        seconds: u64,
+    ) -> Result<u64, Error> {
+        merchant::do_grant_credit_time(&env, subscription_id, merchant, seconds)
+    }
+
+    /// Propose a reduced retainer billed each interval while Paused, less than the
+    /// subscription's amount. Auth: merchant.
+    ///
+    /// Billed only once the subscriber calls `accept_pause_retainer`. `0` disables the
+    /// retainer immediately.
+    pub fn set_pause_retainer(
+        env: Env,
+        subscription_id: u32,
+        merchant: Address,
+        retainer: i128,
     ) -> Result<(), Error> {
-        subscription::do_cancel_subscription(&env, subscription_id, authorizer)
+        merchant::do_set_pause_retainer(&env, subscription_id, merchant, retainer)
     }
 
-    /// Subscriber withdraws their remaining prepaid_balance after cancellation.
-    pub fn withdraw_subscriber_funds(
+    /// Accept the proposed pause retainer, restating it. Auth: subscriber.
+    pub fn accept_pause_retainer(
         env: Env,
         subscription_id: u32,
         subscriber: Address,
+        retainer: i128,
     ) -> Result<(), Error> {
-        subscription::do_withdraw_subscriber_funds(&env, subscription_id, subscriber)
+        merchant::do_accept_pause_retainer(&env, subscription_id, subscriber, retainer)
     }
 
-    /// Pause subscription (no charges until resumed). Allowed from Active.
-    pub fn pause_subscription(
+    /// Accepted pause retainer for a subscription (`0` if none).
+    pub fn get_pause_retainer(env: Env, subscription_id: u32) -> i128 {
+        merchant::get_pause_retainer(&env, subscription_id)
+    }
+
+    /// Pause retainer proposed by the merchant and not yet accepted (`0` if none).
+    pub fn get_proposed_pause_retainer(env: Env, subscription_id: u32) -> i128 {
+        merchant::get_proposed_pause_retainer(&env, subscription_id)
+    }
+
+    /// Anchor a terms/metadata hash on a subscription. Auth: merchant.
+    ///
+    /// Emits `terms_anchored` with the previous and new hash whenever it changes.
+    pub fn set_terms_hash(
         env: Env,
         subscription_id: u32,
-        authorizer: Address,
+        merchant: Address,
+        terms_hash: BytesN<32>,
     ) -> Result<(), Error> {
-        subscription::do_pause_subscription(&env, subscription_id, authorizer)
+        merchant::do_set_terms_hash(&env, subscription_id, merchant, terms_hash)
     }
 
-    /// Resume a subscription to Active. Allowed from Paused or InsufficientBalance.
-    pub fn resume_subscription(
+    /// Get the terms hash anchored on a subscription, if any.
+    pub fn get_terms_hash(env: Env, subscription_id: u32) -> Option<BytesN<32>> {
+        merchant::get_terms_hash(&env, subscription_id)
+    }
+
+    /// Publish a new terms version, optionally changing the per-interval price.
+    /// Existing subscriptions keep their accepted terms and price until the
+    /// subscriber calls `accept_terms`. Auth: merchant.
+    pub fn publish_terms(
         env: Env,
-        subscription_id: u32,
-        authorizer: Address,
+        merchant: Address,
+        terms_hash: BytesN<32>,
+        amount: Option<i128>,
     ) -> Result<(), Error> {
-        subscription::do_resume_subscription(&env, subscription_id, authorizer)
+        merchant::do_publish_terms(&env, merchant, terms_hash, amount)
     }
 
-    // ── Charging ─────────────────────────────────────────────────────────
+    /// The merchant's current terms version, if published.
+    pub fn get_merchant_terms(env: Env, merchant: Address) -> Option<MerchantTerms> {
+        merchant::get_merchant_terms(&env, &merchant)
+    }
 
-    /// Billing engine calls this to charge one interval.
-    ///
-    /// Enforces strict interval timing and replay protection.
-    pub fn charge_subscription(env: Env, subscription_id: u32) -> Result<(), Error> {
-        charge_core::charge_one(&env, subscription_id, None)
+    /// Accept the merchant's current terms (`new_hash` must match them), applying any
+    /// price change they carry. Auth: subscriber.
+    pub fn accept_terms(
+        env: Env,
+        subscriber: Address,
+        subscription_id: u32,
+        new_hash: BytesN<32>,
+    ) -> Result<(), Error> {
+        merchant::do_accept_terms(&env, subscriber, subscription_id, new_hash)
     }
 
-    /// Charge a metered usage amount against the subscription's prepaid balance.
-    ///
-    /// Designed for integration with an **off-chain usage metering service**:
-    /// the service measures consumption, then calls this entrypoint with the
-    /// computed `usage_amount` to debit the subscriber's vault.
-    ///
-    /// # Requirements
-    ///
-    /// * The subscription must be `Active`.
-    /// * `usage_enabled` must be `true` on the subscription.
-    /// * `usage_amount` must be positive (`> 0`).
-    /// * `prepaid_balance` must be >= `usage_amount`.
-    ///
-    /// # Behaviour
-    ///
-    /// On success, `prepaid_balance` is reduced by `usage_amount`.  If the
-    /// debit drains the balance to zero the subscription transitions to
-    /// `InsufficientBalance` status, signalling that no further charges
-    /// (interval or usage) can proceed until the subscriber tops up.
-    ///
-    /// # Errors
-    ///
-    /// | Variant | Reason |
-    /// |---------|--------|
-    /// | `NotFound` | Subscription ID does not exist. |
-    /// | `NotActive` | Subscription is not `Active`. |
-    /// | `UsageNotEnabled` | `usage_enabled` is `false`. |
-    /// | `InvalidAmount` | `usage_amount` is zero or negative. |
-    /// | `InsufficientPrepaidBalance` | Prepaid balance cannot cover the debit. |
-    pub fn charge_usage(env: Env, subscription_id: u32, usage_amount: i128) -> Result<(), Error> {
-        charge_core::charge_usage_one(&env, subscription_id, usage_amount)
+    /// Terms hash the subscriber accepted for a subscription (at creation or via
+    /// `accept_terms`), if any.
+    pub fn get_accepted_terms(env: Env, subscription_id: u32) -> Option<BytesN<32>> {
+        merchant::get_accepted_terms(&env, subscription_id)
     }
 
-    // ── Merchant ─────────────────────────────────────────────────────────
+    /// Flag (or unflag) a subscription as non-revenue test mode. Auth: merchant.
+    ///
+    /// Test-mode charges advance the schedule and emit events at a zero amount and
+    /// are excluded from totals, settlement reports and payouts.
+    pub fn set_test_mode(
+        env: Env,
+        merchant: Address,
+        subscription_id: u32,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        merchant::do_set_test_mode(&env, merchant, subscription_id, enabled)
+    }
 
-    /// Merchant withdraws accumulated USDC to their wallet.
-    pub fn withdraw_merchant_funds(env: Env, merchant: Address, amount: i128) -> Result<(), Error> {
-        merchant::withdraw_merchant_funds(&env, merchant, amount)
+    /// Whether a subscription is in test mode.
+    pub fn is_test_mode(env: Env, subscription_id: u32) -> bool {
+        merchant::is_test_mode(&env, subscription_id)
     }
 
     // ── Queries ──────────────────────────────────────────────────────────
 
     /// Read subscription by id.
-    pub fn get_subscription(env: Env, subscription_id: u32) -> Result<Subscription, Error> {
-        queries::get_subscription(&env, subscription_id)
+    ///
+    /// Returns a [`SubscriptionDto`], which is versioned separately from storage
+    /// (see `get_dto_version`).
+    pub fn get_subscription(env: Env, subscription_id: u32) -> Result<SubscriptionDto, Error> {
+        queries::get_subscription_dto(&env, subscription_id)
+    }
+
+    /// Version of the query DTO structs this contract returns. Bumped only when a
+    /// DTO changes incompatibly (fields are otherwise only appended).
+    pub fn get_dto_version(_env: Env) -> u32 {
+        DTO_VERSION
+    }
+
+    /// Read a subscription with its derived state: next due time, grace deadline and
+    /// whether it is in grace, failed and remaining retry attempts, and the amount
+    /// the next charge will take.
+    pub fn get_subscription_view(
+        env: Env,
+        subscription_id: u32,
+    ) -> Result<SubscriptionView, Error> {
+        queries::get_subscription_view(&env, subscription_id)
+    }
+
+    /// Get the settlement token a subscription's balance is held in.
+    pub fn get_subscription_token(env: Env, subscription_id: u32) -> Result<Address, Error> {
+        queries::get_subscription(&env, subscription_id)?;
+        subscription::subscription_token(&env, subscription_id)
     }
 
     /// Estimate how much a subscriber needs to deposit to cover N future intervals.
@@ -219,22 +2049,100 @@ impl SubscriptionVault {
         queries::estimate_topup_for_intervals(&env, subscription_id, num_intervals)
     }
 
+    /// Quote a prospective subscription: first charge, recurring amount, and the
+    /// deposit required by `min_topup` and the merchant's reserve policy.
+    pub fn get_quote(
+        env: Env,
+        merchant: Address,
+        amount: i128,
+        usage_estimate: i128,
+    ) -> Result<Quote, Error> {
+        queries::get_quote(&env, merchant, amount, usage_estimate)
+    }
+
     /// Get estimated next charge info (timestamp + whether charge is expected).
     pub fn get_next_charge_info(env: Env, subscription_id: u32) -> Result<NextChargeInfo, Error> {
         let sub = queries::get_subscription(&env, subscription_id)?;
         Ok(compute_next_charge_info(&sub))
     }
 
-    /// Return subscriptions for a merchant, paginated.
+    /// Get the next charge point in both timestamp and ledger-sequence terms.
+    pub fn get_schedule_info(env: Env, subscription_id: u32) -> Result<ScheduleInfo, Error> {
+        queries::get_schedule_info(&env, subscription_id)
+    }
+
+    /// Bucket the merchant's chargeable subscriptions by the hour they next fall due
+    /// within `horizon_seconds` (at most 7 days), so keepers can spread batch charges.
+    pub fn get_charge_schedule(
+        env: Env,
+        merchant: Address,
+        horizon_seconds: u64,
+    ) -> Result<Vec<ChargeBucket>, Error> {
+        queries::get_charge_schedule(&env, merchant, horizon_seconds)
+    }
+
+    /// Up to `limit` ids of chargeable subscriptions whose interval has elapsed at
+    /// `now`, read from the status index, for feeding `batch_charge`.
+    pub fn get_due_subscriptions(env: Env, now: u64, limit: u32) -> Result<Vec<u32>, Error> {
+        queries::get_due_subscriptions(&env, now, limit)
+    }
+
+    /// Expected vs actual interval charges since creation, so merchants can detect
+    /// billing gaps caused by keeper outages.
+    pub fn get_interval_drift(env: Env, subscription_id: u32) -> Result<IntervalDrift, Error> {
+        queries::get_interval_drift(&env, subscription_id)
+    }
+
+    /// Get subscriptions created in `[from, to)` (cohort analysis), in creation
+    /// order, starting at `cursor`. Ranges span at most 366 days.
+    ///
+    /// (`get_subscriptions_created_between`; shortened to fit the 32-character
+    /// contract function name limit.)
+    pub fn get_subs_created_between(
+        env: Env,
+        from: u64,
+        to: u64,
+        cursor: u32,
+        limit: u32,
+    ) -> Result<SubscriptionPage, Error> {
+        queries::get_subscriptions_created_between(&env, from, to, cursor, limit)
+    }
+
+    /// Subscriptions currently in `status`, optionally scoped to one merchant.
+    ///
+    /// Backed by per-status indexes, so dunning tooling can enumerate e.g. every
+    /// `InsufficientBalance` subscription without scanning all ids.
+    pub fn get_subscriptions_by_status(
+        env: Env,
+        status: SubscriptionStatus,
+        merchant: Option<Address>,
+        cursor: u32,
+        limit: u32,
+    ) -> SubscriptionPage {
+        queries::get_subscriptions_by_status(&env, status, merchant, cursor, limit)
+    }
+
+    /// Return a page of subscriptions for a merchant (offset-based, max 100 per call).
     pub fn get_subscriptions_by_merchant(
         env: Env,
         merchant: Address,
         start: u32,
         limit: u32,
-    ) -> Vec<Subscription> {
+    ) -> SubscriptionPage {
         queries::get_subscriptions_by_merchant(&env, merchant, start, limit)
     }
 
+    /// List a merchant's subscriptions as summary records (id, parties, amount,
+    /// interval, status, balance), offset-paginated in creation order (max 100 per call).
+    pub fn list_subscriptions_by_merchant(
+        env: Env,
+        merchant: Address,
+        cursor: u32,
+        limit: u32,
+    ) -> SubscriptionExportPage {
+        queries::list_subscriptions_by_merchant(&env, merchant, cursor, limit)
+    }
+
     /// Return the total number of subscriptions for a merchant.
     pub fn get_merchant_subscription_count(env: Env, merchant: Address) -> u32 {
         queries::get_merchant_subscription_count(&env, merchant)
@@ -248,13 +2156,14 @@ impl SubscriptionVault {
     /// # Arguments
     /// * `subscriber` - The address of the subscriber to query
     /// * `start_from_id` - Inclusive lower bound for pagination (use 0 for the first page)
-    /// * `limit` - Maximum number of subscription IDs to return (recommended: 10-100)
+    /// * `limit` - Maximum number of subscription IDs to return (capped at 100)
     ///
     /// # Returns
-    /// A `SubscriptionsPage` containing subscription IDs and pagination metadata
+    /// A `SubscriptionIdPage` containing subscription IDs and pagination metadata
     ///
     /// # Performance Notes
-    /// - Time complexity: O(n) where n = total subscriptions in contract
+    /// - Served from an on-chain subscriber index: O(k) in the subscriber's own
+    ///   subscription count
     /// - Space complexity: O(limit)
     /// - Suitable for off-chain indexers and UI pagination
     ///
@@ -263,11 +2172,10 @@ impl SubscriptionVault {
     /// ```ignore
     /// // Get first page
     /// let page = client.list_subscriptions_by_subscriber(&subscriber, &0, &10)?;
-    /// println!("Found {} subscriptions", page.subscription_ids.len());
+    /// println!("Found {} of {} subscriptions", page.items.len(), page.total);
     ///
     /// // Get next page if available
-    /// if page.has_next {
-    ///     let next_start = page.subscription_ids.last().unwrap() + 1;
+    /// if let Some(next_start) = page.next_cursor {
     ///     let page2 = client.list_subscriptions_by_subscriber(&subscriber, &next_start, &10)?;
     /// }
     /// ```
@@ -276,10 +2184,18 @@ impl SubscriptionVault {
         subscriber: Address,
         start_from_id: u32,
         limit: u32,
-    ) -> Result<crate::queries::SubscriptionsPage, Error> {
+    ) -> Result<SubscriptionIdPage, Error> {
         crate::queries::list_subscriptions_by_subscriber(&env, subscriber, start_from_id, limit)
     }
 
+    /// Export compact records of all subscriptions for indexer bootstrap.
+    ///
+    /// Scans at most `limit` ids (capped at 100) from `cursor`; continue with
+    /// `next_cursor` until it is `None`.
+    pub fn export_subscriptions(env: Env, cursor: u32, limit: u32) -> SubscriptionExportPage {
+        queries::export_subscriptions(&env, cursor, limit)
+    }
+
     fn _next_id(env: &Env) -> u32 {
         let key = soroban_sdk::Symbol::new(env, "next_id");
         let id: u32 = env.storage().instance().get(&key).unwrap_or(0);
@@ -1,15 +1,1225 @@
-//! Merchant entrypoints: withdraw_merchant_funds.
+//! Merchant entrypoints: withdraw_merchant_funds, settlement reports, API keys, terms anchoring,
+//! minimum amounts, reserve policy, goodwill credit time, payout batching, pause retainers,
+//! receivable liens, proof-of-service attestations.
 //!
 //! **PRs that only change merchant payouts should edit this file only.**
+//!
+//! # API keys
+//!
+//! A merchant may register up to [`MAX_API_KEYS`] named addresses, each holding a single
+//! [`ApiKeyScope`]. Merchant-scoped entrypoints call [`require_merchant_scope`], which
+//! accepts the merchant itself or any registered key with the required scope.
+//!
+//! # Payout batching
+//!
+//! Every charge credits the merchant's [`PayoutQueue`] for the subscription's token
+//! instead of transferring immediately. Once the merchant's [`PayoutPolicy`]
+//! threshold is crossed or its schedule fires, the whole queue is realized as a
+//! single token transfer, either inline with the charge or via `process_payout`.
+//!
+//! Batch charges run inside [`with_payout_batch`]: realizations due during the
+//! batch are deferred to its end and settled once per (merchant, token), so a
+//! batch makes at most one payout transfer per merchant and settlement token
+//! however many of its subscriptions were charged. Liens, tax and commissions are
+//! still paid per charge.
+//!
+//! # Payout adapters
+//!
+//! A merchant may set a [`PayoutRoute`] so realized payouts go through a
+//! [`PayoutAdapter`] contract (e.g. a bridge to another chain). The vault approves
+//! the adapter for the amount and calls `forward_payout`; the adapter pulls the
+//! funds with `transfer_from`. Anything the adapter does not pull, including
+//! everything when the adapter call fails, is paid to the merchant directly.
+//!
+//! # Liens
+//!
+//! A merchant may pledge its future receivables to a lender. While a [`Lien`] is
+//! active, its share of every charge in the lien's token is transferred straight to
+//! the lienholder instead of entering the payout queue, until the target is repaid.
+//!
+//! # Terms versions
+//!
+//! A merchant publishes its current [`MerchantTerms`] with `publish_terms`. Creating a
+//! subscription records the merchant's current terms hash as accepted. When the
+//! merchant publishes a new version, existing subscriptions stay on the terms (and
+//! price) their subscriber accepted until the subscriber calls `accept_terms` with
+//! the new hash; only then does a price change in the new version take effect.
+//!
+//! # Test mode
+//!
+//! A merchant may flag any of its subscriptions as test mode to run end-to-end
+//! checkout tests against the production contract. Test-mode charges advance the
+//! billing schedule and emit the usual events, but move zero tokens and are left out
+//! of totals, settlement reports, budgets and payouts.
+//!
+//! # CRM tags
+//!
+//! A merchant defines up to [`MAX_MERCHANT_TAGS`] labels of its own (e.g. `VIP`,
+//! `AtRisk`) and attaches at most one to each of its subscriptions. Tags are indexed
+//! per (merchant, tag) for listing and never affect billing or status. Dropping a
+//! tag from the merchant's set untags its subscriptions.
+
+use crate::charge_core::get_charged_period;
+use crate::claims::transfer_or_defer;
+use crate::keeper::track_liabilities;
+use crate::queries::{get_subscription, page_bounds};
+use crate::safe_math::{
+    safe_add, safe_mul, safe_sub, split_bps, validate_non_negative, BPS_DENOMINATOR,
+};
+use crate::types::{
+    ApiKeyPage, ApiKeyScope, CreditTimeGrantedEvent, DataKey, Error, LedgerSchedule, Lien,
+    LienPaymentEvent, MerchantApiKey, MerchantTerms, MerchantWithdrawalEvent, PayoutBridgedEvent,
+    PayoutPolicy, PayoutQueue, PayoutRealizedEvent, PayoutRoute, ServiceAttestation,
+    SettlementEpoch, SettlementPage, SettlementSummary, Subscription, SubscriptionIdPage,
+    SubscriptionStatus, TermsAnchoredEvent, AVG_LEDGER_CLOSE_SECONDS,
+};
+use soroban_sdk::{contractclient, symbol_short, token, Address, Bytes, BytesN, Env, Symbol, Vec};
+
+/// Maximum number of API keys a single merchant may hold.
+pub const MAX_API_KEYS: u32 = 20;
 
-use crate::safe_math::validate_non_negative;
-use crate::types::Error;
-use soroban_sdk::{Address, Env, Symbol};
+/// Number of settlement summaries retained per merchant.
+pub const MAX_SETTLEMENTS: u32 = 12;
 
+/// Withdraw `amount` of the merchant's accrued balance in the vault's current token.
+///
+/// The accrued balance is the merchant's payout queue: every successful charge
+/// credits it (net of fees, liens and commissions), and withdrawals draw it down.
+/// The transfer follows the merchant's payout route, if any.
 pub fn withdraw_merchant_funds(env: &Env, merchant: Address, amount: i128) -> Result<(), Error> {
     merchant.require_auth();
     validate_non_negative(amount)?;
+    let token = crate::admin::get_token(env)?;
+    let mut queue = get_payout_queue(env, &merchant, &token);
+    if amount > queue.pending {
+        return Err(Error::InsufficientBalance);
+    }
+    if amount > 0 {
+        queue.pending = safe_sub(queue.pending, amount)?;
+        set_payout_queue(env, &merchant, &token, &queue);
+        pay_merchant(env, &merchant, &token, amount)?;
+    }
+    env.events().publish(
+        (Symbol::new(env, "withdrawn"), merchant.clone()),
+        MerchantWithdrawalEvent {
+            merchant: merchant.clone(),
+            amount,
+            remaining_balance: queue.pending,
+        },
+    );
+    close_settlement_epoch(env, &merchant, amount);
+    Ok(())
+}
+
+fn current_epoch(env: &Env, merchant: &Address) -> SettlementEpoch {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantEpoch(merchant.clone()))
+        .unwrap_or(SettlementEpoch {
+            period_start: 0,
+            charge_count: 0,
+            gross: 0,
+            fees: 0,
+        })
+}
+
+/// Accrue a successful charge into the merchant's current settlement epoch.
+pub fn record_merchant_charge(env: &Env, merchant: &Address, amount: i128) -> Result<(), Error> {
+    let mut epoch = current_epoch(env, merchant);
+    if epoch.charge_count == 0 && epoch.period_start == 0 {
+        epoch.period_start = env.ledger().timestamp();
+    }
+    epoch.charge_count = epoch.charge_count.saturating_add(1);
+    epoch.gross = safe_add(epoch.gross, amount)?;
+    env.storage()
+        .instance()
+        .set(&DataKey::MerchantEpoch(merchant.clone()), &epoch);
+    Ok(())
+}
+
+/// Close the current epoch at a payout: emit a `settlement` event and keep the
+/// summary in the merchant's bounded history.
+fn close_settlement_epoch(env: &Env, merchant: &Address, withdrawn: i128) {
+    let epoch = current_epoch(env, merchant);
+    let now = env.ledger().timestamp();
+    let summary = SettlementSummary {
+        merchant: merchant.clone(),
+        period_start: epoch.period_start,
+        period_end: now,
+        charge_count: epoch.charge_count,
+        gross: epoch.gross,
+        fees: epoch.fees,
+        net: epoch.gross - epoch.fees,
+        withdrawn,
+    };
+
+    let mut history = get_settlement_history(env, merchant);
+    if history.len() >= MAX_SETTLEMENTS {
+        history.pop_front();
+    }
+    history.push_back(summary.clone());
+    env.storage()
+        .instance()
+        .set(&DataKey::MerchantSettlements(merchant.clone()), &history);
+    env.storage().instance().set(
+        &DataKey::MerchantEpoch(merchant.clone()),
+        &SettlementEpoch {
+            period_start: now,
+            charge_count: 0,
+            gross: 0,
+            fees: 0,
+        },
+    );
     env.events()
-        .publish((Symbol::new(env, "withdrawn"), merchant.clone()), amount);
+        .publish((Symbol::new(env, "settlement"), merchant.clone()), summary);
+}
+
+fn get_settlement_history(env: &Env, merchant: &Address) -> Vec<SettlementSummary> {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantSettlements(merchant.clone()))
+        .unwrap_or(Vec::new(env))
+}
+
+/// Returns a page of the merchant's retained settlement summaries starting at
+/// `cursor` (0 = oldest retained).
+pub fn get_settlements(env: &Env, merchant: &Address, cursor: u32, limit: u32) -> SettlementPage {
+    let history = get_settlement_history(env, merchant);
+    let total = history.len();
+    let (end, next_cursor) = page_bounds(cursor, limit, total);
+    SettlementPage {
+        items: history.slice(cursor.min(end)..end),
+        next_cursor,
+        total,
+    }
+}
+
+/// Returns a page of the merchant's API keys in registration order.
+pub fn list_api_keys(env: &Env, merchant: &Address, cursor: u32, limit: u32) -> ApiKeyPage {
+    let keys = get_api_keys(env, merchant);
+    let total = keys.len();
+    let (end, next_cursor) = page_bounds(cursor, limit, total);
+    ApiKeyPage {
+        items: keys.slice(cursor.min(end)..end),
+        next_cursor,
+        total,
+    }
+}
+
+pub fn get_api_keys(env: &Env, merchant: &Address) -> Vec<MerchantApiKey> {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantApiKeys(merchant.clone()))
+        .unwrap_or(Vec::new(env))
+}
+
+/// Register (or re-scope) an API key for `merchant`. Merchant auth required.
+pub fn do_register_api_key(
+    env: &Env,
+    merchant: Address,
+    key: Address,
+    name: Symbol,
+    scope: ApiKeyScope,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let mut keys = get_api_keys(env, &merchant);
+    let entry = MerchantApiKey {
+        key: key.clone(),
+        name: name.clone(),
+        scope: scope.clone(),
+    };
+    match keys.iter().position(|k| k.key == key) {
+        Some(i) => keys.set(i as u32, entry),
+        None => {
+            if keys.len() >= MAX_API_KEYS {
+                return Err(Error::LimitExceeded);
+            }
+            keys.push_back(entry);
+        }
+    }
+    env.storage()
+        .instance()
+        .set(&DataKey::MerchantApiKeys(merchant.clone()), &keys);
+    env.events().publish(
+        (Symbol::new(env, "api_key_registered"), merchant),
+        (key, name, scope),
+    );
+    Ok(())
+}
+
+/// Revoke a previously registered API key. Merchant auth required.
+pub fn do_revoke_api_key(env: &Env, merchant: Address, key: Address) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let mut keys = get_api_keys(env, &merchant);
+    let i = keys
+        .iter()
+        .position(|k| k.key == key)
+        .ok_or(Error::NotFound)?;
+    keys.remove(i as u32);
+    env.storage()
+        .instance()
+        .set(&DataKey::MerchantApiKeys(merchant.clone()), &keys);
+    env.events()
+        .publish((Symbol::new(env, "api_key_revoked"), merchant), key);
     Ok(())
 }
+
+/// Authorize `caller` to act for `merchant` within `scope`.
+///
+/// Requires `caller`'s auth. Succeeds when `caller` is the merchant itself or a
+/// registered API key of that merchant holding `scope`.
+pub fn require_merchant_scope(
+    env: &Env,
+    merchant: &Address,
+    caller: &Address,
+    scope: ApiKeyScope,
+) -> Result<(), Error> {
+    caller.require_auth();
+    if caller == merchant || has_scope(env, merchant, caller, scope) {
+        Ok(())
+    } else {
+        Err(Error::Unauthorized)
+    }
+}
+
+/// Returns true if `caller` is a registered API key of `merchant` holding `scope`.
+pub fn has_scope(env: &Env, merchant: &Address, caller: &Address, scope: ApiKeyScope) -> bool {
+    get_api_keys(env, merchant)
+        .iter()
+        .any(|k| k.key == *caller && k.scope == scope)
+}
+
+pub fn get_terms_hash(env: &Env, subscription_id: u32) -> Option<BytesN<32>> {
+    env.storage()
+        .instance()
+        .get(&DataKey::SubTermsHash(subscription_id))
+}
+
+/// Anchor a terms-of-service / plan description hash on a subscription so the
+/// subscriber can later prove which terms applied. Auth: the subscription's merchant.
+pub fn do_set_terms_hash(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+    terms_hash: BytesN<32>,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    let sub = get_subscription(env, subscription_id)?;
+    if merchant != sub.merchant {
+        return Err(Error::Unauthorized);
+    }
+
+    let previous_hash = get_terms_hash(env, subscription_id);
+    if previous_hash.as_ref() == Some(&terms_hash) {
+        return Ok(());
+    }
+    env.storage()
+        .instance()
+        .set(&DataKey::SubTermsHash(subscription_id), &terms_hash);
+    env.events().publish(
+        (Symbol::new(env, "terms_anchored"), subscription_id),
+        TermsAnchoredEvent {
+            subscription_id,
+            merchant,
+            previous_hash,
+            terms_hash,
+            timestamp: env.ledger().timestamp(),
+        },
+    );
+    Ok(())
+}
+
+const KEY_MERCHANT_TERMS: Symbol = symbol_short!("mterms");
+const KEY_ACCEPTED_TERMS: Symbol = symbol_short!("tacc");
+
+pub fn get_merchant_terms(env: &Env, merchant: &Address) -> Option<MerchantTerms> {
+    env.storage()
+        .instance()
+        .get(&(KEY_MERCHANT_TERMS, merchant.clone()))
+}
+
+/// Terms hash the subscriber last accepted for a subscription.
+pub fn get_accepted_terms(env: &Env, subscription_id: u32) -> Option<BytesN<32>> {
+    env.storage()
+        .instance()
+        .get(&(KEY_ACCEPTED_TERMS, subscription_id))
+}
+
+/// Drops a subscription's terms acceptance (used when its record is deleted).
+pub fn remove_accepted_terms(env: &Env, subscription_id: u32) {
+    env.storage()
+        .instance()
+        .remove(&(KEY_ACCEPTED_TERMS, subscription_id));
+}
+
+const KEY_TEST_MODE: Symbol = symbol_short!("testmode");
+
+/// Whether the merchant flagged the subscription as non-revenue test mode.
+pub fn is_test_mode(env: &Env, subscription_id: u32) -> bool {
+    env.storage()
+        .instance()
+        .has(&(KEY_TEST_MODE, subscription_id))
+}
+
+/// Drops a subscription's test-mode flag (used when its record is deleted).
+pub fn remove_test_mode(env: &Env, subscription_id: u32) {
+    env.storage()
+        .instance()
+        .remove(&(KEY_TEST_MODE, subscription_id));
+}
+
+/// Flag (or unflag) one of the merchant's subscriptions as test mode. Auth: merchant.
+pub fn do_set_test_mode(
+    env: &Env,
+    merchant: Address,
+    subscription_id: u32,
+    enabled: bool,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.merchant != merchant {
+        return Err(Error::Unauthorized);
+    }
+    if enabled {
+        env.storage()
+            .instance()
+            .set(&(KEY_TEST_MODE, subscription_id), &true);
+    } else {
+        remove_test_mode(env, subscription_id);
+    }
+    env.events().publish(
+        (Symbol::new(env, "test_mode_set"), subscription_id),
+        enabled,
+    );
+    Ok(())
+}
+
+fn set_accepted_terms(env: &Env, subscription_id: u32, terms_hash: &BytesN<32>) {
+    env.storage()
+        .instance()
+        .set(&(KEY_ACCEPTED_TERMS, subscription_id), terms_hash);
+    env.events().publish(
+        (Symbol::new(env, "terms_accepted"), subscription_id),
+        terms_hash.clone(),
+    );
+}
+
+/// Records the merchant's current terms, if any, as accepted by a new subscription.
+pub fn record_terms_at_creation(env: &Env, subscription_id: u32, merchant: &Address) {
+    if let Some(terms) = get_merchant_terms(env, merchant) {
+        set_accepted_terms(env, subscription_id, &terms.terms_hash);
+    }
+}
+
+/// Publish a new terms version, optionally with a new per-interval price. Auth:
+/// merchant.
+pub fn do_publish_terms(
+    env: &Env,
+    merchant: Address,
+    terms_hash: BytesN<32>,
+    amount: Option<i128>,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    if let Some(amount) = amount {
+        validate_non_negative(amount)?;
+        require_min_amount(env, &merchant, amount)?;
+    }
+    let terms = MerchantTerms {
+        terms_hash,
+        amount,
+        published_at: env.ledger().timestamp(),
+    };
+    env.storage()
+        .instance()
+        .set(&(KEY_MERCHANT_TERMS, merchant.clone()), &terms);
+    env.events()
+        .publish((Symbol::new(env, "terms_published"), merchant), terms);
+    Ok(())
+}
+
+/// Accept the merchant's current terms for a subscription, applying their price.
+/// Auth: subscriber. `terms_hash` must match the current version.
+pub fn do_accept_terms(
+    env: &Env,
+    subscriber: Address,
+    subscription_id: u32,
+    terms_hash: BytesN<32>,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+    let mut sub = get_subscription(env, subscription_id)?;
+    if sub.subscriber != subscriber {
+        return Err(Error::Unauthorized);
+    }
+    let terms = get_merchant_terms(env, &sub.merchant).ok_or(Error::NotFound)?;
+    if terms.terms_hash != terms_hash {
+        return Err(Error::TermsMismatch);
+    }
+    if get_accepted_terms(env, subscription_id).as_ref() == Some(&terms_hash) {
+        return Ok(());
+    }
+    if let Some(amount) = terms.amount {
+        require_min_amount(env, &sub.merchant, amount)?;
+        sub.amount = amount;
+        env.storage().instance().set(&subscription_id, &sub);
+    }
+    set_accepted_terms(env, subscription_id, &terms_hash);
+    Ok(())
+}
+
+const KEY_MIN_AMOUNT: Symbol = symbol_short!("minamt");
+
+/// Merchant's minimum per-interval amount (0 when unset).
+pub fn get_min_amount(env: &Env, merchant: &Address) -> i128 {
+    env.storage()
+        .instance()
+        .get(&(KEY_MIN_AMOUNT, merchant.clone()))
+        .unwrap_or(0)
+}
+
+/// Set the minimum per-interval amount for new subscriptions and price changes.
+/// `0` removes it. Auth: merchant. Existing subscriptions keep their amount.
+pub fn do_set_min_amount(env: &Env, merchant: Address, min_amount: i128) -> Result<(), Error> {
+    merchant.require_auth();
+    validate_non_negative(min_amount)?;
+    let key = (KEY_MIN_AMOUNT, merchant.clone());
+    if min_amount == 0 {
+        env.storage().instance().remove(&key);
+    } else {
+        env.storage().instance().set(&key, &min_amount);
+    }
+    env.events()
+        .publish((Symbol::new(env, "min_amount_set"), merchant), min_amount);
+    Ok(())
+}
+
+/// Fails with `BelowMerchantMinimum` if `amount` is below the merchant's minimum.
+pub fn require_min_amount(env: &Env, merchant: &Address, amount: i128) -> Result<(), Error> {
+    if amount < get_min_amount(env, merchant) {
+        return Err(Error::BelowMerchantMinimum);
+    }
+    Ok(())
+}
+
+pub fn get_reserve_intervals(env: &Env, merchant: &Address) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantReserve(merchant.clone()))
+        .unwrap_or(0)
+}
+
+/// Require subscribers to keep at least `intervals` charges prepaid. `0` disables.
+pub fn do_set_reserve_intervals(env: &Env, merchant: Address, intervals: u32) -> Result<(), Error> {
+    merchant.require_auth();
+    env.storage()
+        .instance()
+        .set(&DataKey::MerchantReserve(merchant.clone()), &intervals);
+    env.events()
+        .publish((Symbol::new(env, "reserve_updated"), merchant), intervals);
+    Ok(())
+}
+
+/// Minimum prepaid balance the subscription must hold under its merchant's reserve policy.
+pub fn required_reserve(env: &Env, sub: &Subscription) -> Result<i128, Error> {
+    let intervals: i128 = get_reserve_intervals(env, &sub.merchant).into();
+    safe_mul(sub.amount, intervals)
+}
+
+const KEY_CREDIT_TIME: Symbol = symbol_short!("gct");
+
+/// Push a subscription's next due date forward by `seconds` as a goodwill credit
+/// (e.g. extending a trial). Auth: the subscription's merchant.
+///
+/// Grants within one billing period may total at most one interval
+/// (`LimitExceeded`). Ledger-scheduled subscriptions are shifted by the
+/// equivalent number of ledgers. Terminal subscriptions are rejected (`NotActive`).
+pub fn do_grant_credit_time(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+    seconds: u64,
+) -> Result<u64, Error> {
+    merchant.require_auth();
+    let mut sub = get_subscription(env, subscription_id)?;
+    if merchant != sub.merchant {
+        return Err(Error::Unauthorized);
+    }
+    if matches!(
+        sub.status,
+        SubscriptionStatus::Cancelled | SubscriptionStatus::Completed | SubscriptionStatus::Expired
+    ) {
+        return Err(Error::NotActive);
+    }
+    if seconds == 0 {
+        return Err(Error::InvalidAmount);
+    }
+
+    let period = env.ledger().timestamp() / sub.interval_seconds;
+    let key = (KEY_CREDIT_TIME, subscription_id);
+    let granted = match env.storage().instance().get::<_, (u64, u64)>(&key) {
+        Some((p, granted)) if p == period => granted,
+        _ => 0,
+    };
+    let granted = granted.checked_add(seconds).ok_or(Error::Overflow)?;
+    if granted > sub.interval_seconds {
+        return Err(Error::LimitExceeded);
+    }
+    env.storage().instance().set(&key, &(period, granted));
+
+    sub.last_payment_timestamp = sub
+        .last_payment_timestamp
+        .checked_add(seconds)
+        .ok_or(Error::Overflow)?;
+    env.storage().instance().set(&subscription_id, &sub);
+
+    let schedule_key = DataKey::SubLedgerSchedule(subscription_id);
+    if let Some(mut ls) = env
+        .storage()
+        .instance()
+        .get::<_, LedgerSchedule>(&schedule_key)
+    {
+        let ledgers = (seconds / AVG_LEDGER_CLOSE_SECONDS) as u32;
+        ls.last_payment_ledger = ls.last_payment_ledger.saturating_add(ledgers);
+        env.storage().instance().set(&schedule_key, &ls);
+    }
+
+    let next_due = sub
+        .last_payment_timestamp
+        .saturating_add(sub.interval_seconds);
+    env.events().publish(
+        (Symbol::new(env, "credit_time_granted"), subscription_id),
+        CreditTimeGrantedEvent {
+            subscription_id,
+            merchant,
+            seconds,
+            next_due,
+        },
+    );
+    Ok(next_due)
+}
+
+pub fn get_payout_policy(env: &Env, merchant: &Address) -> Option<PayoutPolicy> {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantPayoutPolicy(merchant.clone()))
+}
+
+/// Set or clear the merchant's payout batching policy. Auth: merchant.
+///
+/// Without a policy, credits stay queued until one is set.
+pub fn do_set_payout_policy(
+    env: &Env,
+    merchant: Address,
+    policy: Option<PayoutPolicy>,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    let key = DataKey::MerchantPayoutPolicy(merchant.clone());
+    match &policy {
+        Some(p) => {
+            validate_non_negative(p.threshold)?;
+            env.storage().instance().set(&key, p);
+        }
+        None => env.storage().instance().remove(&key),
+    }
+    env.events().publish(
+        (Symbol::new(env, "payout_policy_updated"), merchant),
+        policy,
+    );
+    Ok(())
+}
+
+pub fn get_payout_queue(env: &Env, merchant: &Address, token: &Address) -> PayoutQueue {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantPayoutQueue(
+            merchant.clone(),
+            token.clone(),
+        ))
+        .unwrap_or(PayoutQueue {
+            pending: 0,
+            last_payout_at: 0,
+        })
+}
+
+fn set_payout_queue(env: &Env, merchant: &Address, token: &Address, queue: &PayoutQueue) {
+    env.storage().instance().set(
+        &DataKey::MerchantPayoutQueue(merchant.clone(), token.clone()),
+        queue,
+    );
+}
+
+/// Interface a payout adapter contract (e.g. a bridge) implements.
+///
+/// On `forward_payout` the adapter may pull up to `amount` of `token` from `vault`
+/// with `transfer_from` (the vault has approved it) and deliver it to `destination`.
+#[contractclient(name = "PayoutAdapterClient")]
+pub trait PayoutAdapter {
+    fn forward_payout(
+        env: Env,
+        vault: Address,
+        token: Address,
+        merchant: Address,
+        amount: i128,
+        destination: Bytes,
+    );
+}
+
+pub fn get_payout_route(env: &Env, merchant: &Address) -> Option<PayoutRoute> {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantPayoutRoute(merchant.clone()))
+}
+
+/// Set or clear the adapter the merchant's payouts are routed through. Auth: merchant.
+pub fn do_set_payout_route(
+    env: &Env,
+    merchant: Address,
+    route: Option<PayoutRoute>,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    let key = DataKey::MerchantPayoutRoute(merchant.clone());
+    match &route {
+        Some(r) => env.storage().instance().set(&key, r),
+        None => env.storage().instance().remove(&key),
+    }
+    // The destination is caller-sized, so a long one is published as a hash.
+    crate::events::publish_bounded(
+        env,
+        (Symbol::new(env, "payout_route_updated"), merchant),
+        route,
+        key,
+    );
+    Ok(())
+}
+
+/// Pays a realized payout, through the merchant's adapter if one is configured.
+fn pay_merchant(env: &Env, merchant: &Address, token: &Address, amount: i128) -> Result<(), Error> {
+    let route = match get_payout_route(env, merchant) {
+        Some(route) => route,
+        None => return transfer_or_defer(env, token, merchant, amount).map(|_| ()),
+    };
+
+    let vault = env.current_contract_address();
+    let token_client = token::Client::new(env, token);
+    let before = token_client.balance(&vault);
+    let expiration = env.ledger().sequence().saturating_add(1);
+    token_client.approve(&vault, &route.adapter, &amount, &expiration);
+    // A failing adapter has its own effects (including any pull) rolled back.
+    let _ = PayoutAdapterClient::new(env, &route.adapter).try_forward_payout(
+        &vault,
+        token,
+        merchant,
+        &amount,
+        &route.destination,
+    );
+    token_client.approve(&vault, &route.adapter, &0, &expiration);
+
+    let forwarded = (before - token_client.balance(&vault)).clamp(0, amount);
+    track_liabilities(env, token, -forwarded);
+    if forwarded > 0 {
+        env.events().publish(
+            (Symbol::new(env, "payout_bridged"), merchant.clone()),
+            PayoutBridgedEvent {
+                merchant: merchant.clone(),
+                adapter: route.adapter,
+                token: token.clone(),
+                amount: forwarded,
+            },
+        );
+    }
+    if forwarded < amount {
+        transfer_or_defer(env, token, merchant, amount - forwarded)?;
+    }
+    Ok(())
+}
+
+/// Credit a charge to the merchant's payout queue and realize it if due.
+///
+/// Any active lien's share is paid to the lienholder first.
+pub fn queue_payout(
+    env: &Env,
+    merchant: &Address,
+    token: &Address,
+    amount: i128,
+) -> Result<(), Error> {
+    let amount = apply_lien(env, merchant, token, amount)?;
+    let mut queue = get_payout_queue(env, merchant, token);
+    if queue.last_payout_at == 0 {
+        // The payout schedule runs from the first credit.
+        queue.last_payout_at = env.ledger().timestamp();
+    }
+    queue.pending = safe_add(queue.pending, amount)?;
+    set_payout_queue(env, merchant, token, &queue);
+    if let Some(mut batch) = env
+        .storage()
+        .instance()
+        .get::<_, Vec<(Address, Address)>>(&KEY_PAYOUT_BATCH)
+    {
+        let pair = (merchant.clone(), token.clone());
+        if !batch.contains(&pair) {
+            batch.push_back(pair);
+            env.storage().instance().set(&KEY_PAYOUT_BATCH, &batch);
+        }
+        return Ok(());
+    }
+    realize_payout_if_due(env, merchant, token)?;
+    Ok(())
+}
+
+const KEY_PAYOUT_BATCH: Symbol = symbol_short!("pbatch");
+
+/// Runs the batch charge `f` with inline payout realization deferred, then realizes
+/// each (merchant, token) queue credited during the batch that is due.
+pub fn with_payout_batch<T>(env: &Env, f: impl FnOnce() -> Result<T, Error>) -> Result<T, Error> {
+    env.storage()
+        .instance()
+        .set(&KEY_PAYOUT_BATCH, &Vec::<(Address, Address)>::new(env));
+    let result = f();
+    let batch: Vec<(Address, Address)> = env
+        .storage()
+        .instance()
+        .get(&KEY_PAYOUT_BATCH)
+        .unwrap_or(Vec::new(env));
+    env.storage().instance().remove(&KEY_PAYOUT_BATCH);
+    let result = result?;
+    for (merchant, token) in batch.iter() {
+        realize_payout_if_due(env, &merchant, &token)?;
+    }
+    Ok(result)
+}
+
+/// Take back up to `amount` of not yet realized credits (e.g. for a refund).
+/// Returns the amount taken back.
+pub fn unqueue_payout(env: &Env, merchant: &Address, token: &Address, amount: i128) -> i128 {
+    let mut queue = get_payout_queue(env, merchant, token);
+    let taken = amount.min(queue.pending).max(0);
+    queue.pending -= taken;
+    set_payout_queue(env, merchant, token, &queue);
+    taken
+}
+
+/// Return `amount` to the merchant's payout queue without lien deductions (e.g. an
+/// expired promo credit being reclaimed).
+pub fn requeue_payout(
+    env: &Env,
+    merchant: &Address,
+    token: &Address,
+    amount: i128,
+) -> Result<(), Error> {
+    let mut queue = get_payout_queue(env, merchant, token);
+    queue.pending = safe_add(queue.pending, amount)?;
+    set_payout_queue(env, merchant, token, &queue);
+    Ok(())
+}
+
+/// Transfers the queued credits to the merchant if the policy's threshold or
+/// schedule has been reached. Returns the amount paid out (a failed transfer is
+/// deferred to the merchant's claim balance).
+pub fn realize_payout_if_due(
+    env: &Env,
+    merchant: &Address,
+    token: &Address,
+) -> Result<i128, Error> {
+    let policy = match get_payout_policy(env, merchant) {
+        Some(policy) => policy,
+        None => return Ok(0),
+    };
+    let mut queue = get_payout_queue(env, merchant, token);
+    if queue.pending <= 0 {
+        return Ok(0);
+    }
+    let now = env.ledger().timestamp();
+    let threshold_hit = policy.threshold > 0 && queue.pending >= policy.threshold;
+    let schedule_hit = policy.interval_seconds > 0
+        && now >= queue.last_payout_at.saturating_add(policy.interval_seconds);
+    if !threshold_hit && !schedule_hit {
+        return Ok(0);
+    }
+
+    let amount = queue.pending;
+    queue.pending = 0;
+    queue.last_payout_at = now;
+    set_payout_queue(env, merchant, token, &queue);
+    pay_merchant(env, merchant, token, amount)?;
+    env.events().publish(
+        (Symbol::new(env, "payout"), merchant.clone()),
+        PayoutRealizedEvent {
+            merchant: merchant.clone(),
+            token: token.clone(),
+            amount,
+        },
+    );
+    Ok(amount)
+}
+
+pub fn get_pause_retainer(env: &Env, subscription_id: u32) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::SubPauseRetainer(subscription_id))
+        .unwrap_or(0)
+}
+
+/// Retainer the merchant proposed and the subscriber has not yet accepted (`0` if none).
+pub fn get_proposed_pause_retainer(env: &Env, subscription_id: u32) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::SubPauseRetainerOffer(subscription_id))
+        .unwrap_or(0)
+}
+
+/// Propose a reduced amount billed each interval while the subscription is `Paused`
+/// (e.g. a storage-only fee). Auth: the subscription's merchant.
+///
+/// Nothing is billed until the subscriber accepts; a new proposal replaces the
+/// previous one. `0` withdraws the proposal and disables the retainer at once. The
+/// retainer must be less than the subscription's full `amount` (`InvalidAmount`).
+pub fn do_set_pause_retainer(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+    retainer: i128,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    let sub = get_subscription(env, subscription_id)?;
+    if merchant != sub.merchant {
+        return Err(Error::Unauthorized);
+    }
+    if retainer < 0 || retainer >= sub.amount {
+        return Err(Error::InvalidAmount);
+    }
+    let offer = DataKey::SubPauseRetainerOffer(subscription_id);
+    if retainer == 0 {
+        env.storage().instance().remove(&offer);
+        env.storage()
+            .instance()
+            .remove(&DataKey::SubPauseRetainer(subscription_id));
+        env.events().publish(
+            (Symbol::new(env, "pause_retainer_set"), subscription_id),
+            0i128,
+        );
+    } else {
+        env.storage().instance().set(&offer, &retainer);
+        env.events().publish(
+            (Symbol::new(env, "pause_retainer_proposed"), subscription_id),
+            retainer,
+        );
+    }
+    Ok(())
+}
+
+/// Accept the merchant's proposed pause retainer, restating it. Auth: subscriber.
+///
+/// A proposal replaced in the meantime is not accepted by mistake (`InvalidAmount`).
+/// The retainer applies from the next charge while `Paused`.
+pub fn do_accept_pause_retainer(
+    env: &Env,
+    subscription_id: u32,
+    subscriber: Address,
+    retainer: i128,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+    let sub = get_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    let proposed = get_proposed_pause_retainer(env, subscription_id);
+    if proposed == 0 {
+        return Err(Error::NotFound);
+    }
+    if proposed != retainer {
+        return Err(Error::InvalidAmount);
+    }
+    env.storage()
+        .instance()
+        .remove(&DataKey::SubPauseRetainerOffer(subscription_id));
+    env.storage()
+        .instance()
+        .set(&DataKey::SubPauseRetainer(subscription_id), &retainer);
+    env.events().publish(
+        (Symbol::new(env, "pause_retainer_set"), subscription_id),
+        retainer,
+    );
+    Ok(())
+}
+
+pub fn get_lien(env: &Env, merchant: &Address) -> Option<Lien> {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantLien(merchant.clone()))
+}
+
+/// Place a lien on the merchant's future receivables in `token`.
+///
+/// Auth: both the merchant (consent) and the lienholder. A merchant carries at most
+/// one lien at a time (`LimitExceeded`).
+pub fn do_grant_lien(
+    env: &Env,
+    merchant: Address,
+    lienholder: Address,
+    token: Address,
+    share_bps: u32,
+    target: i128,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    lienholder.require_auth();
+    if share_bps == 0 || share_bps as i128 > BPS_DENOMINATOR || target <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    if get_lien(env, &merchant).is_some() {
+        return Err(Error::LimitExceeded);
+    }
+    let lien = Lien {
+        lienholder,
+        token,
+        share_bps,
+        target,
+        repaid: 0,
+    };
+    env.storage()
+        .instance()
+        .set(&DataKey::MerchantLien(merchant.clone()), &lien);
+    env.events()
+        .publish((Symbol::new(env, "lien_granted"), merchant), lien);
+    Ok(())
+}
+
+/// Release a lien before it is fully repaid. Auth: lienholder.
+pub fn do_release_lien(env: &Env, lienholder: Address, merchant: Address) -> Result<(), Error> {
+    lienholder.require_auth();
+    let lien = get_lien(env, &merchant).ok_or(Error::NotFound)?;
+    if lien.lienholder != lienholder {
+        return Err(Error::Unauthorized);
+    }
+    env.storage()
+        .instance()
+        .remove(&DataKey::MerchantLien(merchant.clone()));
+    env.events()
+        .publish((Symbol::new(env, "lien_released"), merchant), lien);
+    Ok(())
+}
+
+/// Redirects the lien's share of a charge to the lienholder and returns what is
+/// left for the merchant. Discharges the lien once the target is repaid.
+fn apply_lien(env: &Env, merchant: &Address, token: &Address, amount: i128) -> Result<i128, Error> {
+    let mut lien = match get_lien(env, merchant) {
+        Some(lien) if lien.token == *token => lien,
+        _ => return Ok(amount),
+    };
+    let (share, _) = split_bps(amount, lien.share_bps)?;
+    let payment = share.min(lien.target - lien.repaid);
+    if payment <= 0 {
+        return Ok(amount);
+    }
+
+    lien.repaid = safe_add(lien.repaid, payment)?;
+    let key = DataKey::MerchantLien(merchant.clone());
+    if lien.repaid >= lien.target {
+        env.storage().instance().remove(&key);
+    } else {
+        env.storage().instance().set(&key, &lien);
+    }
+    transfer_or_defer(env, token, &lien.lienholder, payment)?;
+    env.events().publish(
+        (Symbol::new(env, "lien_payment"), merchant.clone()),
+        LienPaymentEvent {
+            merchant: merchant.clone(),
+            lienholder: lien.lienholder.clone(),
+            amount: payment,
+            repaid: lien.repaid,
+            target: lien.target,
+        },
+    );
+    if lien.repaid >= lien.target {
+        env.events()
+            .publish((Symbol::new(env, "lien_repaid"), merchant.clone()), lien);
+    }
+    Ok(amount - payment)
+}
+
+const KEY_ATTESTATION: Symbol = symbol_short!("att");
+
+fn attestation_key(subscription_id: u32, period_index: u64) -> (Symbol, u32, u64) {
+    (KEY_ATTESTATION, subscription_id, period_index)
+}
+
+pub fn get_attestation(
+    env: &Env,
+    subscription_id: u32,
+    period_index: u64,
+) -> Option<ServiceAttestation> {
+    env.storage()
+        .instance()
+        .get(&attestation_key(subscription_id, period_index))
+}
+
+/// Post a proof-of-service hash for a billing period that has already been charged.
+///
+/// Auth: the subscription's merchant. Each period can be attested once (`Replay`);
+/// periods after the last charge are rejected with `NotFound`.
+pub fn do_attest_service(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+    period_index: u64,
+    proof_hash: BytesN<32>,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    let sub = get_subscription(env, subscription_id)?;
+    if merchant != sub.merchant {
+        return Err(Error::Unauthorized);
+    }
+    match get_charged_period(env, subscription_id) {
+        Some(charged) if period_index <= charged => {}
+        _ => return Err(Error::NotFound),
+    }
+    if get_attestation(env, subscription_id, period_index).is_some() {
+        return Err(Error::Replay);
+    }
+
+    let attestation = ServiceAttestation {
+        subscription_id,
+        period_index,
+        proof_hash,
+        attested_at: env.ledger().timestamp(),
+    };
+    env.storage().instance().set(
+        &attestation_key(subscription_id, period_index),
+        &attestation,
+    );
+    env.events().publish(
+        (Symbol::new(env, "service_attested"), subscription_id),
+        attestation,
+    );
+    Ok(())
+}
+
+/// Maximum number of tags a merchant may define.
+pub const MAX_MERCHANT_TAGS: u32 = 8;
+
+pub fn get_merchant_tags(env: &Env, merchant: &Address) -> Vec<Symbol> {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantTags(merchant.clone()))
+        .unwrap_or(Vec::new(env))
+}
+
+fn get_tag_index(env: &Env, merchant: &Address, tag: &Symbol) -> Vec<u32> {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantTagIndex(merchant.clone(), tag.clone()))
+        .unwrap_or(Vec::new(env))
+}
+
+fn set_tag_index(env: &Env, merchant: &Address, tag: &Symbol, ids: &Vec<u32>) {
+    let key = DataKey::MerchantTagIndex(merchant.clone(), tag.clone());
+    if ids.is_empty() {
+        env.storage().instance().remove(&key);
+    } else {
+        env.storage().instance().set(&key, ids);
+    }
+}
+
+/// Replace the merchant's tag set. Tags no longer in the set are removed from the
+/// merchant's subscriptions. Auth: merchant.
+pub fn do_set_merchant_tags(env: &Env, merchant: Address, tags: Vec<Symbol>) -> Result<(), Error> {
+    merchant.require_auth();
+    if tags.len() > MAX_MERCHANT_TAGS {
+        return Err(Error::LimitExceeded);
+    }
+    for old in get_merchant_tags(env, &merchant).iter() {
+        if tags.contains(&old) {
+            continue;
+        }
+        for id in get_tag_index(env, &merchant, &old).iter() {
+            env.storage()
+                .instance()
+                .remove(&DataKey::SubMerchantTag(id));
+        }
+        set_tag_index(env, &merchant, &old, &Vec::new(env));
+    }
+    env.storage()
+        .instance()
+        .set(&DataKey::MerchantTags(merchant.clone()), &tags);
+    env.events()
+        .publish((Symbol::new(env, "merchant_tags_set"), merchant), tags);
+    Ok(())
+}
+
+pub fn get_subscription_tag(env: &Env, subscription_id: u32) -> Option<Symbol> {
+    env.storage()
+        .instance()
+        .get(&DataKey::SubMerchantTag(subscription_id))
+}
+
+/// Set or clear the merchant's tag on one of its subscriptions. The tag must be in
+/// the merchant's tag set (`InvalidLabel` otherwise). Auth: merchant.
+pub fn do_tag_subscription(
+    env: &Env,
+    merchant: Address,
+    subscription_id: u32,
+    tag: Option<Symbol>,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.merchant != merchant {
+        return Err(Error::Unauthorized);
+    }
+    if let Some(tag) = &tag {
+        if !get_merchant_tags(env, &merchant).contains(tag) {
+            return Err(Error::InvalidLabel);
+        }
+    }
+    untag_subscription(env, &merchant, subscription_id);
+    if let Some(tag) = &tag {
+        env.storage()
+            .instance()
+            .set(&DataKey::SubMerchantTag(subscription_id), tag);
+        let mut ids = get_tag_index(env, &merchant, tag);
+        ids.push_back(subscription_id);
+        set_tag_index(env, &merchant, tag, &ids);
+    }
+    env.events().publish(
+        (Symbol::new(env, "subscription_tagged"), subscription_id),
+        tag,
+    );
+    Ok(())
+}
+
+/// Removes the subscription's tag, if any, and its entry in the tag index.
+pub fn untag_subscription(env: &Env, merchant: &Address, subscription_id: u32) {
+    if let Some(old) = get_subscription_tag(env, subscription_id) {
+        let mut ids = get_tag_index(env, merchant, &old);
+        if let Some(pos) = ids.first_index_of(subscription_id) {
+            ids.remove(pos);
+        }
+        set_tag_index(env, merchant, &old, &ids);
+        env.storage()
+            .instance()
+            .remove(&DataKey::SubMerchantTag(subscription_id));
+    }
+}
+
+/// Returns a page of the ids of the merchant's subscriptions carrying `tag`, in
+/// tagging order.
+pub fn get_subscriptions_by_tag(
+    env: &Env,
+    merchant: &Address,
+    tag: &Symbol,
+    cursor: u32,
+    limit: u32,
+) -> SubscriptionIdPage {
+    let ids = get_tag_index(env, merchant, tag);
+    let total = ids.len();
+    let (end, next_cursor) = page_bounds(cursor, limit, total);
+    let items = if cursor < end {
+        ids.slice(cursor..end)
+    } else {
+        Vec::new(env)
+    };
+    SubscriptionIdPage {
+        items,
+        next_cursor,
+        total,
+    }
+}
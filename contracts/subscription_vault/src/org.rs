@@ -0,0 +1,61 @@
+//! Organization accounts: subscriptions owned by a team's org contract.
+//!
+//! **PRs that only change organization account behavior should edit this file only.**
+//!
+//! An org contract that implements [`OrgAdapter`] and opts in with
+//! `set_org_account` can be a subscription's subscriber. Management rights are then
+//! shared with its members as reported by the adapter: any member can fund the
+//! subscription (other outside payers are rejected), and owners can also cancel it
+//! on the org's behalf.
+
+use crate::types::DataKey;
+use soroban_sdk::{contractclient, Address, Env, Symbol};
+
+/// Interface an organization contract exposes so the vault can resolve member roles.
+#[contractclient(name = "OrgAdapterClient")]
+pub trait OrgAdapter {
+    /// True if `account` belongs to the organization.
+    fn is_member(env: Env, account: Address) -> bool;
+    /// True if `account` is an owner (owners are also members).
+    fn is_owner(env: Env, account: Address) -> bool;
+}
+
+pub fn is_org_account(env: &Env, account: &Address) -> bool {
+    env.storage()
+        .instance()
+        .get(&DataKey::OrgAccount(account.clone()))
+        .unwrap_or(false)
+}
+
+/// Opt `org` in or out of org-account role resolution. Auth: org.
+pub fn do_set_org_account(env: &Env, org: Address, enabled: bool) {
+    org.require_auth();
+    let key = DataKey::OrgAccount(org.clone());
+    if enabled {
+        env.storage().instance().set(&key, &true);
+    } else {
+        env.storage().instance().remove(&key);
+    }
+    env.events()
+        .publish((Symbol::new(env, "org_account_set"), org), enabled);
+}
+
+/// True if `subscriber` is an org account and its adapter reports `account` as an
+/// owner. A failing adapter call grants nothing.
+pub fn is_org_owner(env: &Env, subscriber: &Address, account: &Address) -> bool {
+    is_org_account(env, subscriber)
+        && matches!(
+            OrgAdapterClient::new(env, subscriber).try_is_owner(account),
+            Ok(Ok(true))
+        )
+}
+
+/// True if `subscriber` is an org account and its adapter reports `account` as a
+/// member. A failing adapter call grants nothing.
+pub fn is_org_member(env: &Env, subscriber: &Address, account: &Address) -> bool {
+    is_org_account(env, subscriber)
+        && matches!(
+            OrgAdapterClient::new(env, subscriber).try_is_member(account),
+            Ok(Ok(true))
+        )
+}
@@ -0,0 +1,267 @@
+//! Merchant plans with A/B price variants.
+//!
+//! **PRs that only change plans or pricing experiments should edit this file only.**
+//!
+//! A merchant publishes a [`Plan`] with one or more weighted price variants.
+//! `subscribe_to_plan` assigns each subscriber a variant deterministically, from
+//! the SHA-256 of their address, so the same subscriber always sees the same price
+//! for a plan. The assignment is recorded per subscription and each variant counts
+//! its subscribers, so experiments can be analyzed from on-chain data.
+//!
+//! # Mid-cycle price changes
+//!
+//! `change_plan` (subscriber) and `change_amount` (merchant and subscriber together)
+//! reprice a live subscription without cancel-and-recreate. The difference between
+//! the new and old price, prorated to the time left in the current interval, is
+//! charged at once on an upgrade or credited back to the prepaid balance on a
+//! downgrade. The credit is taken back from the merchant's unrealized payout, so it
+//! is reduced to what is still pending there (`prorated_credit` reports the
+//! amount credited). The next charge uses the new price.
+
+use crate::charge_core::{force_debit, reverse_charged};
+use crate::merchant::require_min_amount;
+use crate::queries::get_subscription;
+use crate::reports::record_refund;
+use crate::safe_math::{prorate_refund, safe_add_balance, validate_non_negative};
+use crate::seats::get_seat_plan;
+use crate::subscription::{create_subscription_record, subscription_token};
+use crate::types::{
+    Error, Plan, PlanAssignment, PriceChange, PriceVariant, SubscriptionStatus, MAX_PLAN_VARIANTS,
+};
+use soroban_sdk::xdr::ToXdr;
+use soroban_sdk::{symbol_short, Address, Env, Symbol, Vec};
+
+const KEY_PLAN: Symbol = symbol_short!("plan");
+const KEY_ASSIGNMENT: Symbol = symbol_short!("plnv");
+
+pub fn get_plan(env: &Env, plan_id: u32) -> Result<Plan, Error> {
+    env.storage()
+        .instance()
+        .get(&(KEY_PLAN, plan_id))
+        .ok_or(Error::NotFound)
+}
+
+pub fn get_plan_assignment(env: &Env, subscription_id: u32) -> Option<PlanAssignment> {
+    env.storage()
+        .instance()
+        .get(&(KEY_ASSIGNMENT, subscription_id))
+}
+
+/// Drops a subscription's plan assignment (used when its record is deleted).
+pub fn remove_plan_assignment(env: &Env, subscription_id: u32) {
+    env.storage()
+        .instance()
+        .remove(&(KEY_ASSIGNMENT, subscription_id));
+}
+
+/// Publish a plan priced by `(amount, weight)` variants. Auth: merchant.
+///
+/// Every weight must be positive and every amount non-negative.
+pub fn do_create_plan(
+    env: &Env,
+    merchant: Address,
+    interval_seconds: u64,
+    usage_enabled: bool,
+    prices: Vec<(i128, u32)>,
+) -> Result<u32, Error> {
+    merchant.require_auth();
+    if prices.is_empty() {
+        return Err(Error::InvalidAmount);
+    }
+    if prices.len() > MAX_PLAN_VARIANTS {
+        return Err(Error::LimitExceeded);
+    }
+    let mut variants = Vec::new(env);
+    for (amount, weight) in prices.iter() {
+        if amount < 0 || weight == 0 {
+            return Err(Error::InvalidAmount);
+        }
+        variants.push_back(PriceVariant {
+            amount,
+            weight,
+            subscribers: 0,
+        });
+    }
+
+    let key = Symbol::new(env, "next_plan_id");
+    let plan_id: u32 = env.storage().instance().get(&key).unwrap_or(0);
+    env.storage().instance().set(&key, &(plan_id + 1));
+    let plan = Plan {
+        merchant: merchant.clone(),
+        interval_seconds,
+        usage_enabled,
+        variants,
+    };
+    env.storage().instance().set(&(KEY_PLAN, plan_id), &plan);
+    env.events().publish(
+        (Symbol::new(env, "plan_created"), merchant),
+        (plan_id, plan),
+    );
+    Ok(plan_id)
+}
+
+/// Index of the variant `subscriber` falls into, by weight.
+fn assign_variant(env: &Env, plan: &Plan, subscriber: &Address) -> u32 {
+    let digest = env
+        .crypto()
+        .sha256(&subscriber.clone().to_xdr(env))
+        .to_array();
+    let total: u32 = plan.variants.iter().map(|v| v.weight).sum();
+    let mut point = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]) % total;
+    for (i, variant) in plan.variants.iter().enumerate() {
+        if point < variant.weight {
+            return i as u32;
+        }
+        point -= variant.weight;
+    }
+    plan.variants.len() - 1
+}
+
+/// Create a subscription on a plan at the subscriber's assigned price variant.
+/// Auth: subscriber.
+pub fn do_subscribe_to_plan(env: &Env, subscriber: Address, plan_id: u32) -> Result<u32, Error> {
+    subscriber.require_auth();
+    let mut plan = get_plan(env, plan_id)?;
+    let variant = assign_variant(env, &plan, &subscriber);
+    let mut price = plan.variants.get_unchecked(variant);
+
+    let id = create_subscription_record(
+        env,
+        subscriber,
+        plan.merchant.clone(),
+        price.amount,
+        plan.interval_seconds,
+        plan.usage_enabled,
+        None,
+    )?;
+
+    price.subscribers = price.subscribers.saturating_add(1);
+    plan.variants.set(variant, price);
+    env.storage().instance().set(&(KEY_PLAN, plan_id), &plan);
+    let assignment = PlanAssignment { plan_id, variant };
+    env.storage()
+        .instance()
+        .set(&(KEY_ASSIGNMENT, id), &assignment);
+    env.events()
+        .publish((Symbol::new(env, "plan_subscribed"), id), assignment);
+    Ok(id)
+}
+
+/// Move a live subscription to another plan of the same merchant and interval, at
+/// the subscriber's assigned variant, prorating the price difference. Auth:
+/// subscriber.
+///
+/// A different merchant is `Unauthorized`; a different interval is `InvalidAmount`.
+pub fn do_change_plan(env: &Env, subscription_id: u32, plan_id: u32) -> Result<PriceChange, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    sub.subscriber.require_auth();
+    let mut plan = get_plan(env, plan_id)?;
+    if plan.merchant != sub.merchant {
+        return Err(Error::Unauthorized);
+    }
+    if plan.interval_seconds != sub.interval_seconds {
+        return Err(Error::InvalidAmount);
+    }
+    let variant = assign_variant(env, &plan, &sub.subscriber);
+    let mut price = plan.variants.get_unchecked(variant);
+    let change = apply_price_change(env, subscription_id, price.amount)?;
+
+    if let Some(old) = get_plan_assignment(env, subscription_id) {
+        if let Ok(mut old_plan) = get_plan(env, old.plan_id) {
+            let mut old_price = old_plan.variants.get_unchecked(old.variant);
+            old_price.subscribers = old_price.subscribers.saturating_sub(1);
+            old_plan.variants.set(old.variant, old_price);
+            env.storage()
+                .instance()
+                .set(&(KEY_PLAN, old.plan_id), &old_plan);
+            if old.plan_id == plan_id {
+                plan = old_plan;
+                price = plan.variants.get_unchecked(variant);
+            }
+        }
+    }
+    price.subscribers = price.subscribers.saturating_add(1);
+    plan.variants.set(variant, price);
+    env.storage().instance().set(&(KEY_PLAN, plan_id), &plan);
+    let assignment = PlanAssignment { plan_id, variant };
+    env.storage()
+        .instance()
+        .set(&(KEY_ASSIGNMENT, subscription_id), &assignment);
+    env.events().publish(
+        (Symbol::new(env, "plan_changed"), subscription_id),
+        assignment,
+    );
+    Ok(change)
+}
+
+/// Set a live subscription's per-interval amount, prorating the difference. Auth:
+/// merchant and subscriber.
+pub fn do_change_amount(
+    env: &Env,
+    subscription_id: u32,
+    amount: i128,
+) -> Result<PriceChange, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    sub.merchant.require_auth();
+    sub.subscriber.require_auth();
+    apply_price_change(env, subscription_id, amount)
+}
+
+/// Reprices an `Active` subscription and settles the prorated difference for the
+/// rest of the current interval. Seat-priced subscriptions change through their
+/// seat count instead (`InvalidStatusTransition`).
+fn apply_price_change(env: &Env, subscription_id: u32, amount: i128) -> Result<PriceChange, Error> {
+    validate_non_negative(amount)?;
+    let mut sub = get_subscription(env, subscription_id)?;
+    if sub.status != SubscriptionStatus::Active {
+        return Err(Error::NotActive);
+    }
+    if get_seat_plan(env, subscription_id).is_some() {
+        return Err(Error::InvalidStatusTransition);
+    }
+    require_min_amount(env, &sub.merchant, amount)?;
+
+    let previous_amount = sub.amount;
+    let next_charge = sub
+        .last_payment_timestamp
+        .saturating_add(sub.interval_seconds);
+    let remaining = next_charge
+        .saturating_sub(env.ledger().timestamp())
+        .min(sub.interval_seconds);
+    let difference = prorate_refund(
+        (amount - previous_amount).abs(),
+        remaining,
+        sub.interval_seconds,
+    )?;
+    sub.amount = amount;
+    env.storage().instance().set(&subscription_id, &sub);
+
+    let mut change = PriceChange {
+        subscription_id,
+        previous_amount,
+        amount,
+        prorated_charge: 0,
+        prorated_credit: 0,
+    };
+    if amount > previous_amount && difference > 0 {
+        force_debit(env, subscription_id, difference)?;
+        change.prorated_charge = difference;
+    } else if amount < previous_amount && difference > 0 {
+        let token = subscription_token(env, subscription_id)?;
+        // Also lowers the total charged, so the credit is not refundable again at
+        // cancellation.
+        let (credit, from_merchant) =
+            reverse_charged(env, subscription_id, &sub, &token, difference)?;
+        if credit > 0 {
+            record_refund(env, &sub.merchant, from_merchant)?;
+            sub.prepaid_balance = safe_add_balance(sub.prepaid_balance, credit)?;
+            env.storage().instance().set(&subscription_id, &sub);
+        }
+        change.prorated_credit = credit;
+    }
+    env.events().publish(
+        (Symbol::new(env, "price_changed"), subscription_id),
+        change.clone(),
+    );
+    Ok(change)
+}
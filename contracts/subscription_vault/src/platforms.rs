@@ -0,0 +1,217 @@
+//! Marketplace platforms taking a cut of their merchants' charges.
+//!
+//! **PRs that only change platform routing should edit this file only.**
+//!
+//! The admin registers platform contracts, each with a maximum cut in basis points.
+//! A merchant selling through a platform joins it with a cut both of them authorize,
+//! at most the platform's cap. On every charge, after tax and the protocol fee, the
+//! cut is credited to the platform's payout queue in the vault before the merchant's
+//! share; the platform withdraws it like any payee. If the admin deregisters the
+//! platform or lowers its cap, cuts stop or are clamped from the next charge on.
+//! A refund takes the subscription's cuts back pro rata from the platforms' still
+//! pending payouts, so the merchant does not pay the platform's share of a refund.
+
+use crate::admin::{log_admin_action, require_admin};
+use crate::merchant::{get_payout_queue, queue_payout, unqueue_payout};
+use crate::safe_math::{bps_fee, mul_div, safe_add, Rounding, BPS_DENOMINATOR};
+use crate::types::{Error, MerchantPlatform, PlatformCutEvent};
+use soroban_sdk::{symbol_short, Address, Env, Symbol, Vec};
+
+const KEY_PLATFORM_CAP: Symbol = symbol_short!("platcap");
+const KEY_MERCHANT_PLATFORM: Symbol = symbol_short!("mplat");
+const KEY_SUB_CUTS: Symbol = symbol_short!("subcuts");
+
+/// Maximum cut (bps) of a registered platform, or `None` if it is not registered.
+pub fn get_platform_cap(env: &Env, platform: &Address) -> Option<u32> {
+    env.storage()
+        .instance()
+        .get(&(KEY_PLATFORM_CAP, platform.clone()))
+}
+
+/// Register a platform with a maximum cut, or deregister it (`None`). Admin only.
+pub fn do_set_platform(
+    env: &Env,
+    admin: Address,
+    platform: Address,
+    max_cut_bps: Option<u32>,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let key = (KEY_PLATFORM_CAP, platform.clone());
+    match max_cut_bps {
+        Some(bps) if bps as i128 > BPS_DENOMINATOR => return Err(Error::InvalidAmount),
+        Some(bps) => env.storage().instance().set(&key, &bps),
+        None => env.storage().instance().remove(&key),
+    }
+    log_admin_action(env, &admin, "set_platform");
+    env.events()
+        .publish((Symbol::new(env, "platform_set"), platform), max_cut_bps);
+    Ok(())
+}
+
+pub fn get_merchant_platform(env: &Env, merchant: &Address) -> Option<MerchantPlatform> {
+    env.storage()
+        .instance()
+        .get(&(KEY_MERCHANT_PLATFORM, merchant.clone()))
+}
+
+/// Route the merchant's charges through `platform` with `cut_bps`, replacing any
+/// previous platform. Auth: merchant and platform.
+pub fn do_join_platform(
+    env: &Env,
+    merchant: Address,
+    platform: Address,
+    cut_bps: u32,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    platform.require_auth();
+    let cap = get_platform_cap(env, &platform).ok_or(Error::Unauthorized)?;
+    if cut_bps > cap {
+        return Err(Error::InvalidAmount);
+    }
+    let link = MerchantPlatform { platform, cut_bps };
+    env.storage()
+        .instance()
+        .set(&(KEY_MERCHANT_PLATFORM, merchant.clone()), &link);
+    env.events()
+        .publish((Symbol::new(env, "platform_joined"), merchant), link);
+    Ok(())
+}
+
+/// Stop routing the merchant's charges through its platform. Auth: merchant.
+pub fn do_leave_platform(env: &Env, merchant: Address) -> Result<(), Error> {
+    merchant.require_auth();
+    env.storage()
+        .instance()
+        .remove(&(KEY_MERCHANT_PLATFORM, merchant.clone()));
+    env.events()
+        .publish((Symbol::new(env, "platform_left"), merchant), ());
+    Ok(())
+}
+
+/// Credits the platform's cut of `amount` charged to a subscription of `merchant`.
+/// Returns the cut.
+pub fn apply_platform_cut(
+    env: &Env,
+    subscription_id: u32,
+    merchant: &Address,
+    token: &Address,
+    amount: i128,
+) -> Result<i128, Error> {
+    let link = match get_merchant_platform(env, merchant) {
+        Some(link) => link,
+        None => return Ok(0),
+    };
+    let cap = match get_platform_cap(env, &link.platform) {
+        Some(cap) => cap,
+        None => return Ok(0),
+    };
+    let cut = bps_fee(amount, link.cut_bps.min(cap))?;
+    if cut == 0 {
+        return Ok(0);
+    }
+    queue_payout(env, &link.platform, token, cut)?;
+    let mut paid = get_subscription_cuts(env, subscription_id);
+    match paid.iter().position(|(p, _)| p == link.platform) {
+        Some(i) => {
+            let (_, total) = paid.get(i as u32).unwrap();
+            paid.set(i as u32, (link.platform.clone(), safe_add(total, cut)?));
+        }
+        None => paid.push_back((link.platform.clone(), cut)),
+    }
+    env.storage()
+        .instance()
+        .set(&(KEY_SUB_CUTS, subscription_id), &paid);
+    env.events().publish(
+        (Symbol::new(env, "platform_cut"), subscription_id),
+        PlatformCutEvent {
+            subscription_id,
+            merchant: merchant.clone(),
+            platform: link.platform,
+            token: token.clone(),
+            gross: amount,
+            cut,
+        },
+    );
+    Ok(cut)
+}
+
+/// Platform cuts taken from a subscription's charges and not yet reversed, per
+/// platform.
+fn get_subscription_cuts(env: &Env, subscription_id: u32) -> Vec<(Address, i128)> {
+    env.storage()
+        .instance()
+        .get(&(KEY_SUB_CUTS, subscription_id))
+        .unwrap_or(Vec::new(env))
+}
+
+pub fn remove_subscription_cuts(env: &Env, subscription_id: u32) {
+    env.storage()
+        .instance()
+        .remove(&(KEY_SUB_CUTS, subscription_id));
+}
+
+/// Platform cut part of refunding `amount` out of the subscription's `charged`
+/// total: its cuts pro rata, rounded down.
+pub fn platform_cut_share(
+    env: &Env,
+    subscription_id: u32,
+    amount: i128,
+    charged: i128,
+) -> Result<i128, Error> {
+    if amount <= 0 || charged <= 0 {
+        return Ok(0);
+    }
+    let mut cuts = 0i128;
+    for (_, cut) in get_subscription_cuts(env, subscription_id).iter() {
+        cuts = safe_add(cuts, cut)?;
+    }
+    Ok(mul_div(cuts, amount.min(charged), charged, Rounding::Floor)?.min(cuts))
+}
+
+/// How much of `share` could be taken back from the platforms' pending payouts
+/// right now.
+pub fn reversible_platform_cut(
+    env: &Env,
+    subscription_id: u32,
+    token: &Address,
+    share: i128,
+) -> i128 {
+    let mut left = share;
+    for (platform, cut) in get_subscription_cuts(env, subscription_id).iter() {
+        let pending = get_payout_queue(env, &platform, token).pending;
+        left -= cut.min(pending).min(left).max(0);
+    }
+    share - left
+}
+
+/// Takes back up to `share` of the subscription's platform cuts from the pending
+/// payouts of the platforms they were credited to. Returns the amount taken back.
+pub fn reverse_platform_cut(env: &Env, subscription_id: u32, token: &Address, share: i128) -> i128 {
+    let mut remaining = Vec::new(env);
+    let mut left = share;
+    for (platform, cut) in get_subscription_cuts(env, subscription_id).iter() {
+        let taken = unqueue_payout(env, &platform, token, cut.min(left));
+        if taken > 0 {
+            left -= taken;
+            env.events().publish(
+                (Symbol::new(env, "platform_cut_reversed"), subscription_id),
+                (platform.clone(), token.clone(), taken),
+            );
+        }
+        if cut > taken {
+            remaining.push_back((platform, cut - taken));
+        }
+    }
+    if remaining.is_empty() {
+        remove_subscription_cuts(env, subscription_id);
+    } else {
+        env.storage()
+            .instance()
+            .set(&(KEY_SUB_CUTS, subscription_id), &remaining);
+    }
+    share - left
+}
@@ -1,11 +1,34 @@
-//! Read-only entrypoints and helpers: get_subscription, estimate_topup, list_subscriptions_by_subscriber.
+//! Read-only entrypoints and helpers: get_subscription, get_subscription_view, estimate_topup,
+//! list_subscriptions_by_subscriber, export_subscriptions.
 //!
 //! **PRs that only add or change read-only/query behavior should edit this file only.**
 
 #![allow(dead_code)]
 
-use crate::types::{DataKey, Error, NextChargeInfo, Subscription, SubscriptionStatus};
-use soroban_sdk::{contracttype, Address, Env, Symbol, Vec};
+use crate::archive;
+use crate::charge_core::{get_periods_charged, grace_deadline};
+use crate::safe_math::{safe_add, safe_mul};
+use crate::state_machine::get_status_bucket;
+use crate::subscription::SECONDS_PER_DAY;
+use crate::types::{
+    ChargeBucket, DataKey, Error, IntervalDrift, LedgerSchedule, NextChargeInfo, Quote,
+    ScheduleBasis, ScheduleInfo, Subscription, SubscriptionDto, SubscriptionExportPage,
+    SubscriptionIdPage, SubscriptionPage, SubscriptionRecord, SubscriptionStatus, SubscriptionView,
+    AVG_LEDGER_CLOSE_SECONDS, MAX_PAGE_LIMIT,
+};
+use soroban_sdk::{Address, Env, Symbol, Vec};
+
+/// Page bounds for an index-addressed list of `total` items: returns the exclusive
+/// end index for a page starting at `cursor` (limit capped at [`MAX_PAGE_LIMIT`])
+/// and the cursor of the following page, if any.
+pub fn page_bounds(cursor: u32, limit: u32, total: u32) -> (u32, Option<u32>) {
+    let end = cursor
+        .saturating_add(limit.min(MAX_PAGE_LIMIT))
+        .min(total)
+        .max(cursor.min(total));
+    let next_cursor = if end < total { Some(end) } else { None };
+    (end, next_cursor)
+}
 
 pub fn get_subscription(env: &Env, subscription_id: u32) -> Result<Subscription, Error> {
     env.storage()
@@ -14,6 +37,27 @@ pub fn get_subscription(env: &Env, subscription_id: u32) -> Result<Subscription,
         .ok_or(Error::NotFound)
 }
 
+/// Maps a stored subscription to its client-facing DTO.
+pub fn to_dto(id: u32, sub: Subscription) -> SubscriptionDto {
+    SubscriptionDto {
+        id,
+        subscriber: sub.subscriber,
+        merchant: sub.merchant,
+        amount: sub.amount,
+        interval_seconds: sub.interval_seconds,
+        last_payment_timestamp: sub.last_payment_timestamp,
+        status: sub.status,
+        prepaid_balance: sub.prepaid_balance,
+        usage_enabled: sub.usage_enabled,
+        label: sub.label,
+    }
+}
+
+/// Reads a subscription as a [`SubscriptionDto`].
+pub fn get_subscription_dto(env: &Env, subscription_id: u32) -> Result<SubscriptionDto, Error> {
+    get_subscription(env, subscription_id).map(|sub| to_dto(subscription_id, sub))
+}
+
 pub fn estimate_topup_for_intervals(
     env: &Env,
     subscription_id: u32,
@@ -26,10 +70,7 @@ pub fn estimate_topup_for_intervals(
     }
 
     let intervals_i128: i128 = num_intervals.into();
-    let required = sub
-        .amount
-        .checked_mul(intervals_i128)
-        .ok_or(Error::Overflow)?;
+    let required = safe_mul(sub.amount, intervals_i128)?;
 
     let topup = required
         .checked_sub(sub.prepaid_balance)
@@ -42,41 +83,169 @@ pub fn estimate_topup_for_intervals(
 ///
 /// * `merchant` – the merchant address to query.
 /// * `start`    – 0-based offset into the merchant's subscription list.
-/// * `limit`    – maximum number of subscriptions to return.
+/// * `limit`    – maximum number of subscriptions to return (capped at [`MAX_PAGE_LIMIT`]).
 ///
-/// Results are ordered chronologically (insertion order).
-/// Returns an empty `Vec` when the merchant has no subscriptions or
-/// `start` is beyond the end of the list.
+/// Results are ordered chronologically (insertion order). `total` is the
+/// merchant's subscription count; `next_cursor` is the offset of the next page.
 pub fn get_subscriptions_by_merchant(
     env: &Env,
     merchant: Address,
     start: u32,
     limit: u32,
-) -> Vec<Subscription> {
+) -> SubscriptionPage {
     let key = DataKey::MerchantSubs(merchant);
     let ids: Vec<u32> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
 
-    let len = ids.len();
-    if start >= len || limit == 0 {
-        return Vec::new(env);
-    }
-
-    let end = if start + limit > len {
-        len
-    } else {
-        start + limit
-    };
+    let total = ids.len();
+    let (end, next_cursor) = page_bounds(start, limit, total);
 
-    let mut result = Vec::new(env);
+    let mut items = Vec::new(env);
     let mut i = start;
     while i < end {
         let sub_id = ids.get(i).unwrap();
         if let Some(sub) = env.storage().instance().get::<u32, Subscription>(&sub_id) {
-            result.push_back(sub);
+            items.push_back(to_dto(sub_id, sub));
         }
         i += 1;
     }
-    result
+    SubscriptionPage {
+        items,
+        next_cursor,
+        total,
+    }
+}
+
+/// Lists a merchant's subscriptions as id-bearing summary records, paginated by
+/// offset into the merchant's index (insertion order). Deleted subscriptions are
+/// dropped from the index, so every page entry resolves.
+pub fn list_subscriptions_by_merchant(
+    env: &Env,
+    merchant: Address,
+    cursor: u32,
+    limit: u32,
+) -> SubscriptionExportPage {
+    let ids: Vec<u32> = env
+        .storage()
+        .instance()
+        .get(&DataKey::MerchantSubs(merchant))
+        .unwrap_or(Vec::new(env));
+    let total = ids.len();
+    let (end, next_cursor) = page_bounds(cursor, limit, total);
+
+    let mut items = Vec::new(env);
+    let mut i = cursor;
+    while i < end {
+        let id = ids.get(i).unwrap();
+        if let Some(sub) = env.storage().instance().get::<u32, Subscription>(&id) {
+            items.push_back(subscription_record(id, sub));
+        }
+        i += 1;
+    }
+    SubscriptionExportPage {
+        items,
+        next_cursor,
+        total,
+    }
+}
+
+fn subscription_record(id: u32, sub: Subscription) -> SubscriptionRecord {
+    SubscriptionRecord {
+        id,
+        subscriber: sub.subscriber,
+        merchant: sub.merchant,
+        amount: sub.amount,
+        interval_seconds: sub.interval_seconds,
+        status: sub.status,
+        prepaid_balance: sub.prepaid_balance,
+    }
+}
+
+/// Returns subscriptions currently in `status`, optionally only those of `merchant`,
+/// paginated by offset into the status bucket (after the merchant filter).
+pub fn get_subscriptions_by_status(
+    env: &Env,
+    status: SubscriptionStatus,
+    merchant: Option<Address>,
+    cursor: u32,
+    limit: u32,
+) -> SubscriptionPage {
+    let bucket = get_status_bucket(env, &status);
+    let mut subs: Vec<SubscriptionDto> = Vec::new(env);
+    for id in bucket.iter() {
+        if let Some(sub) = env.storage().instance().get::<u32, Subscription>(&id) {
+            if merchant.as_ref().is_none_or(|m| *m == sub.merchant) {
+                subs.push_back(to_dto(id, sub));
+            }
+        }
+    }
+
+    let total = subs.len();
+    let (end, next_cursor) = page_bounds(cursor, limit, total);
+    let items = if cursor < end {
+        subs.slice(cursor..end)
+    } else {
+        Vec::new(env)
+    };
+    SubscriptionPage {
+        items,
+        next_cursor,
+        total,
+    }
+}
+
+/// Maximum span, in days, of a creation-time range query.
+pub const MAX_CREATION_RANGE_DAYS: u64 = 366;
+
+/// Returns subscriptions created in `[from, to)`, in creation order, paginated by
+/// offset into the matching set. Backed by per-day creation buckets, so ranges
+/// longer than [`MAX_CREATION_RANGE_DAYS`] fail with `LimitExceeded`.
+pub fn get_subscriptions_created_between(
+    env: &Env,
+    from: u64,
+    to: u64,
+    cursor: u32,
+    limit: u32,
+) -> Result<SubscriptionPage, Error> {
+    if to <= from {
+        return Err(Error::InvalidAmount);
+    }
+    let first_day = from / SECONDS_PER_DAY;
+    let last_day = (to - 1) / SECONDS_PER_DAY;
+    if last_day - first_day >= MAX_CREATION_RANGE_DAYS {
+        return Err(Error::LimitExceeded);
+    }
+
+    let mut subs: Vec<SubscriptionDto> = Vec::new(env);
+    for day in first_day..=last_day {
+        let ids: Vec<u32> =
+            archive::get(env, &DataKey::CreatedDayBucket(day)).unwrap_or(Vec::new(env));
+        for id in ids.iter() {
+            let created_at: u64 = env
+                .storage()
+                .instance()
+                .get(&DataKey::SubCreatedAt(id))
+                .unwrap_or(0);
+            if created_at < from || created_at >= to {
+                continue;
+            }
+            if let Some(sub) = env.storage().instance().get::<u32, Subscription>(&id) {
+                subs.push_back(to_dto(id, sub));
+            }
+        }
+    }
+
+    let total = subs.len();
+    let (end, next_cursor) = page_bounds(cursor, limit, total);
+    let items = if cursor < end {
+        subs.slice(cursor..end)
+    } else {
+        Vec::new(env)
+    };
+    Ok(SubscriptionPage {
+        items,
+        next_cursor,
+        total,
+    })
 }
 
 /// Returns the number of subscriptions for a given merchant.
@@ -88,6 +257,42 @@ pub fn get_merchant_subscription_count(env: &Env, merchant: Address) -> u32 {
     ids.len()
 }
 
+/// Reads a subscription together with its derived billing state.
+pub fn get_subscription_view(env: &Env, subscription_id: u32) -> Result<SubscriptionView, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    let now = env.ledger().timestamp();
+    let next_due = get_schedule_info(env, subscription_id)?.next_charge_timestamp;
+    let grace_deadline = grace_deadline(env, subscription_id, &sub);
+    let in_grace =
+        sub.status == SubscriptionStatus::Active && now >= next_due && now < grace_deadline;
+    let failed_attempts = crate::keeper::get_failed_attempts(env, subscription_id);
+
+    let retainer = crate::merchant::get_pause_retainer(env, subscription_id);
+    let effective_amount = if crate::merchant::is_test_mode(env, subscription_id) {
+        0
+    } else {
+        let amount = if sub.status == SubscriptionStatus::Paused && retainer > 0 {
+            retainer
+        } else {
+            sub.amount
+        };
+        let token = crate::subscription::subscription_token(env, subscription_id)?;
+        let promo =
+            crate::credit::available_promo_credit(env, &sub.subscriber, &sub.merchant, &token);
+        amount - promo.min(amount)
+    };
+
+    Ok(SubscriptionView {
+        next_due,
+        grace_deadline,
+        in_grace,
+        failed_attempts,
+        remaining_attempts: crate::keeper::MAX_RETRY_ATTEMPTS.saturating_sub(failed_attempts),
+        effective_amount,
+        subscription: to_dto(subscription_id, sub),
+    })
+}
+
 /// Computes the estimated next charge timestamp for a subscription.
 ///
 /// This is a readonly helper that does not mutate contract state. It provides
@@ -102,6 +307,8 @@ pub fn compute_next_charge_info(subscription: &Subscription) -> NextChargeInfo {
         SubscriptionStatus::InsufficientBalance => true,
         SubscriptionStatus::Paused => false,
         SubscriptionStatus::Cancelled => false,
+        SubscriptionStatus::Completed => false,
+        SubscriptionStatus::Expired => false,
     };
 
     NextChargeInfo {
@@ -110,17 +317,6 @@ pub fn compute_next_charge_info(subscription: &Subscription) -> NextChargeInfo {
     }
 }
 
-/// Result of a paginated query for subscriptions by subscriber.
-/// Contains the subscription IDs and metadata for pagination.
-#[contracttype]
-#[derive(Clone, Debug)]
-pub struct SubscriptionsPage {
-    /// List of subscription IDs owned by the subscriber (ordered by ID).
-    pub subscription_ids: Vec<u32>,
-    /// Whether there are more subscriptions beyond this page.
-    pub has_next: bool,
-}
-
 /// Get all subscription IDs for a given subscriber with pagination support.
 ///
 /// This function retrieves subscription IDs owned by a subscriber in a paginated manner.
@@ -131,29 +327,29 @@ pub struct SubscriptionsPage {
 /// - `subscriber`: The address of the subscriber to query
 /// - `start_from_id`: Inclusive lower bound for pagination (use 0 for the first page).
 ///   Only subscription IDs >= this value will be returned.
-/// - `limit`: Maximum number of subscription IDs to return (recommended: 10-100 for efficiency).
+/// - `limit`: Maximum number of subscription IDs to return (capped at [`MAX_PAGE_LIMIT`]).
 ///   Must be greater than 0.
 ///
 /// # Returns
-/// A `SubscriptionsPage` containing:
-/// - `subscription_ids`: Vec of subscription IDs (sorted ascending)
-/// - `has_next`: True if there are more subscriptions after the last returned ID
+/// A `SubscriptionIdPage` containing:
+/// - `items`: Vec of subscription IDs (sorted ascending)
+/// - `next_cursor`: `start_from_id` for the next page, or `None` if this is the last page
+/// - `total`: number of subscriptions owned by the subscriber
 ///
 /// # Performance Notes
-/// - Time complexity: O(n) where n = total number of subscriptions in the contract
+/// - Served from the subscriber → subscription-ID index maintained at creation, so
+///   cost is O(k) in the subscriber's own subscription count, not the contract's
 /// - Space complexity: O(limit)
-/// - On-chain storage usage is minimal (only subscription objects are stored)
-/// - Suitable for off-chain indexers and UI pagination
+/// - Suitable for wallets, off-chain indexers and UI pagination
 ///
 /// # Pagination Example
 /// ```ignore
 /// // Get first page (subscriptions with ID >= 0)
 /// let page1 = list_subscriptions_by_subscriber(env, subscriber, 0, 10)?;
 ///
-/// // Get next page: pass last_returned_id + 1 as start_from_id
-/// if page1.has_next {
-///     let last_id = page1.subscription_ids.last().unwrap();
-///     let page2 = list_subscriptions_by_subscriber(env, subscriber, last_id + 1, 10)?;
+/// // Get next page
+/// if let Some(next) = page1.next_cursor {
+///     let page2 = list_subscriptions_by_subscriber(env, subscriber, next, 10)?;
 /// }
 /// ```
 pub fn list_subscriptions_by_subscriber(
@@ -161,57 +357,248 @@ pub fn list_subscriptions_by_subscriber(
     subscriber: Address,
     start_from_id: u32,
     limit: u32,
-) -> Result<SubscriptionsPage, Error> {
+) -> Result<SubscriptionIdPage, Error> {
     if limit == 0 {
         return Err(Error::NotFound);
     }
+    let limit = limit.min(MAX_PAGE_LIMIT);
+
+    let ids = crate::subscription::get_subscriber_subscription_ids(env, &subscriber);
+    let mut items = Vec::new(env);
+    let mut next_cursor = None;
+    for id in ids.iter() {
+        if id < start_from_id {
+            continue;
+        }
+        if items.len() < limit {
+            items.push_back(id);
+        } else {
+            next_cursor = Some(id);
+            break;
+        }
+    }
 
-    // Get the next_id counter to determine the range of valid subscription IDs
-    let next_id_key = Symbol::new(env, "next_id");
-    let next_id: u32 = env.storage().instance().get(&next_id_key).unwrap_or(0);
-
-    let mut subscription_ids = Vec::new(env);
-    let mut count = 0u32;
-    let mut last_found_id = start_from_id;
-
-    // Iterate through all subscription IDs from start_from_id (inclusive) and filter by subscriber
-    for id in start_from_id..next_id {
-        match env.storage().instance().get::<u32, Subscription>(&id) {
-            Some(sub) => {
-                if sub.subscriber == subscriber {
-                    subscription_ids.push_back(id);
-                    count += 1;
-                    last_found_id = id;
-                    if count >= limit {
-                        break;
-                    }
-                }
+    Ok(SubscriptionIdPage {
+        items,
+        next_cursor,
+        total: ids.len(),
+    })
+}
+
+/// Export compact records of all subscriptions, ordered by id.
+///
+/// Scans ids in `[cursor, cursor + limit)` (limit capped at [`MAX_PAGE_LIMIT`]) so
+/// a new indexer can bootstrap state page by page without replaying the ledger.
+/// `total` is the number of ids ever allocated.
+pub fn export_subscriptions(env: &Env, cursor: u32, limit: u32) -> SubscriptionExportPage {
+    let next_id: u32 = env
+        .storage()
+        .instance()
+        .get(&Symbol::new(env, "next_id"))
+        .unwrap_or(0);
+    let (end, next_cursor) = page_bounds(cursor, limit, next_id);
+
+    let mut items = Vec::new(env);
+    let mut id = cursor;
+    while id < end {
+        if let Some(sub) = env.storage().instance().get::<u32, Subscription>(&id) {
+            items.push_back(subscription_record(id, sub));
+        }
+        id += 1;
+    }
+
+    SubscriptionExportPage {
+        items,
+        next_cursor,
+        total: next_id,
+    }
+}
+
+/// Returns the next due point of a subscription in both timestamp and ledger terms.
+///
+/// The value in the subscription's native basis is exact; the other is converted
+/// using [`AVG_LEDGER_CLOSE_SECONDS`].
+pub fn get_schedule_info(env: &Env, subscription_id: u32) -> Result<ScheduleInfo, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    let now = env.ledger().timestamp();
+    let seq = env.ledger().sequence();
+
+    let ledger_schedule: Option<LedgerSchedule> = env
+        .storage()
+        .instance()
+        .get(&DataKey::SubLedgerSchedule(subscription_id));
+    let info = match ledger_schedule {
+        Some(ls) => {
+            let next_charge_ledger = ls.last_payment_ledger.saturating_add(ls.interval_ledgers);
+            let ledgers_left = next_charge_ledger.saturating_sub(seq) as u64;
+            ScheduleInfo {
+                basis: ScheduleBasis::LedgerSequence,
+                next_charge_timestamp: now
+                    .saturating_add(ledgers_left.saturating_mul(AVG_LEDGER_CLOSE_SECONDS)),
+                next_charge_ledger,
             }
-            None => {
-                // Subscription was deleted or ID skipped; continue to next
+        }
+        None => {
+            let next_charge_timestamp = sub
+                .last_payment_timestamp
+                .saturating_add(sub.interval_seconds);
+            let ledgers_left = next_charge_timestamp
+                .saturating_sub(now)
+                .div_ceil(AVG_LEDGER_CLOSE_SECONDS);
+            ScheduleInfo {
+                basis: ScheduleBasis::Timestamp,
+                next_charge_timestamp,
+                next_charge_ledger: seq.saturating_add(ledgers_left.min(u32::MAX as u64) as u32),
             }
         }
+    };
+    Ok(info)
+}
+
+/// Longest look-ahead accepted by [`get_charge_schedule`].
+pub const MAX_SCHEDULE_HORIZON_SECONDS: u64 = 7 * SECONDS_PER_DAY;
+
+const SECONDS_PER_HOUR: u64 = 60 * 60;
+
+/// Buckets the merchant's chargeable subscriptions by the hour they next fall due,
+/// looking `horizon_seconds` ahead, so a keeper can spread `batch_charge` calls
+/// instead of spiking at popular anchor times.
+///
+/// Overdue subscriptions land in the current hour. Buckets are sorted by hour and
+/// empty hours are omitted. Horizons longer than [`MAX_SCHEDULE_HORIZON_SECONDS`]
+/// fail with `LimitExceeded`.
+pub fn get_charge_schedule(
+    env: &Env,
+    merchant: Address,
+    horizon_seconds: u64,
+) -> Result<Vec<ChargeBucket>, Error> {
+    if horizon_seconds > MAX_SCHEDULE_HORIZON_SECONDS {
+        return Err(Error::LimitExceeded);
     }
+    let now = env.ledger().timestamp();
+    let current_hour = now - now % SECONDS_PER_HOUR;
+    let until = now.saturating_add(horizon_seconds);
+    let ids: Vec<u32> = env
+        .storage()
+        .instance()
+        .get(&DataKey::MerchantSubs(merchant))
+        .unwrap_or(Vec::new(env));
 
-    // Determine if there are more subscriptions by checking beyond the last found
-    let has_next = if count >= limit {
-        // We hit the limit; check if there is at least one more subscriber match
-        let mut found_next = false;
-        for id in (last_found_id + 1)..next_id {
-            if let Some(sub) = env.storage().instance().get::<u32, Subscription>(&id) {
-                if sub.subscriber == subscriber {
-                    found_next = true;
-                    break;
-                }
+    let mut buckets: Vec<ChargeBucket> = Vec::new(env);
+    for id in ids.iter() {
+        let sub = get_subscription(env, id)?;
+        if !compute_next_charge_info(&sub).is_charge_expected {
+            continue;
+        }
+        let due = get_schedule_info(env, id)?.next_charge_timestamp;
+        if due > until {
+            continue;
+        }
+        let hour_start = (due - due % SECONDS_PER_HOUR).max(current_hour);
+
+        let mut pos = 0;
+        while pos < buckets.len() && buckets.get_unchecked(pos).hour_start < hour_start {
+            pos += 1;
+        }
+        match buckets.get(pos) {
+            Some(mut bucket) if bucket.hour_start == hour_start => {
+                bucket.subscription_ids.push_back(id);
+                buckets.set(pos, bucket);
             }
+            _ => buckets.insert(
+                pos,
+                ChargeBucket {
+                    hour_start,
+                    subscription_ids: Vec::from_array(env, [id]),
+                },
+            ),
         }
-        found_next
-    } else {
-        false
+    }
+    Ok(buckets)
+}
+
+/// Returns up to `limit` (capped at [`MAX_PAGE_LIMIT`]) ids of subscriptions whose
+/// interval has elapsed at `now`, so the billing engine knows what to pass to
+/// `batch_charge`.
+///
+/// Walks the `Active` status index, then `Paused` subscriptions that carry a pause
+/// retainer (the only statuses `charge_subscription` accepts), in index order,
+/// skipping subscriptions past their end date or flagged for charge review.
+/// Ledger-basis subscriptions are compared by their estimated due timestamp.
+pub fn get_due_subscriptions(env: &Env, now: u64, limit: u32) -> Result<Vec<u32>, Error> {
+    let limit = limit.min(MAX_PAGE_LIMIT);
+    let mut due: Vec<u32> = Vec::new(env);
+    for status in [SubscriptionStatus::Active, SubscriptionStatus::Paused] {
+        for id in get_status_bucket(env, &status).iter() {
+            if due.len() >= limit {
+                return Ok(due);
+            }
+            if (status == SubscriptionStatus::Paused
+                && crate::merchant::get_pause_retainer(env, id) == 0)
+                || crate::subscription::is_past_expiry(env, id)
+                || crate::charge_core::get_charge_review(env, id).is_some()
+            {
+                continue;
+            }
+            if get_schedule_info(env, id)?.next_charge_timestamp <= now {
+                due.push_back(id);
+            }
+        }
+    }
+    Ok(due)
+}
+
+/// Compares the number of intervals elapsed since creation with the number of
+/// interval charges made, exposing billing gaps (e.g. from keeper outages).
+///
+/// Elapsed intervals are counted on the timestamp schedule anchored at creation,
+/// also for ledger-basis subscriptions (where it is an estimate).
+pub fn get_interval_drift(env: &Env, subscription_id: u32) -> Result<IntervalDrift, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    let created_at: u64 = env
+        .storage()
+        .instance()
+        .get(&DataKey::SubCreatedAt(subscription_id))
+        .unwrap_or(sub.last_payment_timestamp);
+    let elapsed = env.ledger().timestamp().saturating_sub(created_at);
+    let expected_charges = match sub.interval_seconds {
+        0 => 0,
+        interval => (elapsed / interval).min(u32::MAX as u64) as u32,
     };
+    let periods_charged = get_periods_charged(env, subscription_id);
+    Ok(IntervalDrift {
+        expected_charges,
+        periods_charged,
+        drift: expected_charges as i64 - periods_charged as i64,
+    })
+}
+
+/// Computes every pricing input for a prospective subscription in one read call,
+/// so frontends don't duplicate pricing logic.
+///
+/// `usage_estimate` is the expected metered usage per interval (0 for flat plans).
+/// The required deposit covers at least one interval (or the merchant's reserve,
+/// if larger) and never falls below `min_topup`.
+pub fn get_quote(
+    env: &Env,
+    merchant: Address,
+    amount: i128,
+    usage_estimate: i128,
+) -> Result<Quote, Error> {
+    if amount < 0 || usage_estimate < 0 {
+        return Err(Error::InvalidAmount);
+    }
+    let per_interval_total = safe_add(amount, usage_estimate)?;
+    let intervals: i128 = crate::merchant::get_reserve_intervals(env, &merchant)
+        .max(1)
+        .into();
+    let covered = safe_mul(per_interval_total, intervals)?;
+    let min_topup = crate::admin::get_min_topup(env)?;
 
-    Ok(SubscriptionsPage {
-        subscription_ids,
-        has_next,
+    Ok(Quote {
+        first_charge_amount: amount,
+        recurring_amount: amount,
+        per_interval_total,
+        required_initial_deposit: covered.max(min_topup),
     })
 }
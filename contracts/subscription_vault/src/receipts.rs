@@ -0,0 +1,60 @@
+//! Hash-chained charge receipts.
+//!
+//! **PRs that only change receipt export should edit this file only.**
+//!
+//! Every charge emits a [`ChargeReceipt`] (`receipt` event) carrying the merchant's
+//! previous chain head as `prev_hash`. The new head is the SHA-256 of the receipt's
+//! XDR encoding and is the only thing stored on-chain, so an auditor holding an
+//! exported receipt set can check it is complete and untampered by re-hashing it in
+//! sequence and comparing the result with `get_receipt_head`.
+
+use crate::types::{ChargeReceipt, DataKey, ReceiptHead};
+use soroban_sdk::xdr::ToXdr;
+use soroban_sdk::{Address, BytesN, Env, Symbol};
+
+/// The merchant's latest receipt hash and count (all-zero hash before the first charge).
+pub fn get_receipt_head(env: &Env, merchant: &Address) -> ReceiptHead {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantReceiptHead(merchant.clone()))
+        .unwrap_or(ReceiptHead {
+            hash: BytesN::from_array(env, &[0; 32]),
+            sequence: 0,
+        })
+}
+
+/// Chain hash of a receipt: SHA-256 of its XDR encoding.
+pub fn hash_receipt(env: &Env, receipt: &ChargeReceipt) -> BytesN<32> {
+    env.crypto().sha256(&receipt.clone().to_xdr(env)).into()
+}
+
+/// Appends a receipt for a charge to the merchant's chain and emits it.
+pub fn append_receipt(
+    env: &Env,
+    subscription_id: u32,
+    merchant: &Address,
+    token: &Address,
+    amount: i128,
+    tax: i128,
+) {
+    let head = get_receipt_head(env, merchant);
+    let receipt = ChargeReceipt {
+        merchant: merchant.clone(),
+        sequence: head.sequence + 1,
+        subscription_id,
+        token: token.clone(),
+        amount,
+        tax,
+        timestamp: env.ledger().timestamp(),
+        prev_hash: head.hash,
+    };
+    let next = ReceiptHead {
+        hash: hash_receipt(env, &receipt),
+        sequence: receipt.sequence,
+    };
+    env.storage()
+        .instance()
+        .set(&DataKey::MerchantReceiptHead(merchant.clone()), &next);
+    env.events()
+        .publish((Symbol::new(env, "receipt"), merchant.clone()), receipt);
+}
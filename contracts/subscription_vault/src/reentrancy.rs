@@ -0,0 +1,22 @@
+//! Reentrancy guard for entrypoints that call out to token contracts.
+//!
+//! Deposits, charges (payouts, liens, donations) and withdrawals all invoke an
+//! external token contract. [`guarded`] sets a storage flag for the duration of such
+//! an entrypoint, so a malicious token (or hook) that calls back into the vault
+//! fails with `Error::Reentrancy` instead of observing half-updated state.
+
+use crate::types::Error;
+use soroban_sdk::{symbol_short, Env, Symbol};
+
+const KEY_REENTRANCY: Symbol = symbol_short!("reentry");
+
+/// Runs `f` with the reentrancy flag held, clearing it on both success and error.
+pub fn guarded<T>(env: &Env, f: impl FnOnce() -> Result<T, Error>) -> Result<T, Error> {
+    if env.storage().instance().has(&KEY_REENTRANCY) {
+        return Err(Error::Reentrancy);
+    }
+    env.storage().instance().set(&KEY_REENTRANCY, &true);
+    let result = f();
+    env.storage().instance().remove(&KEY_REENTRANCY);
+    result
+}
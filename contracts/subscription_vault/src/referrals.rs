@@ -0,0 +1,149 @@
+//! Merchant referral codes and decaying affiliate commissions.
+//!
+//! **PRs that only change referrals or affiliate payouts should edit this file only.**
+//!
+//! A merchant publishes [`ReferralCode`]s, each naming an affiliate and a commission
+//! schedule keyed by subscription age (e.g. 20% for the first 90 days, 5% until the
+//! first year, nothing after). A subscriber attaches one code to a subscription. On
+//! every charge, `charge_core` looks up the rate for the subscription's age and the
+//! commission is split off the merchant's net (post-tax) revenue and paid to the
+//! affiliate.
+
+use crate::claims::transfer_or_defer;
+use crate::queries::get_subscription;
+use crate::safe_math::{bps_fee, BPS_DENOMINATOR};
+use crate::types::{CommissionPaidEvent, Error, ReferralCode, MAX_COMMISSION_TIERS};
+use soroban_sdk::{symbol_short, Address, Env, Symbol};
+
+const KEY_CODE: Symbol = symbol_short!("refc");
+const KEY_SUB_REFERRAL: Symbol = symbol_short!("sref");
+
+pub fn get_referral_code(env: &Env, merchant: &Address, code: &Symbol) -> Option<ReferralCode> {
+    env.storage()
+        .instance()
+        .get(&(KEY_CODE, merchant.clone(), code.clone()))
+}
+
+pub fn get_subscription_referral(env: &Env, subscription_id: u32) -> Option<Symbol> {
+    env.storage()
+        .instance()
+        .get(&(KEY_SUB_REFERRAL, subscription_id))
+}
+
+/// Drops a subscription's referral (used when its record is deleted).
+pub fn remove_subscription_referral(env: &Env, subscription_id: u32) {
+    env.storage()
+        .instance()
+        .remove(&(KEY_SUB_REFERRAL, subscription_id));
+}
+
+/// Create, replace or retire a referral code. Auth: merchant.
+///
+/// Schedule tiers must have strictly increasing ages and rates of at most 100%.
+/// Subscriptions already referred by a replaced code follow the new schedule.
+pub fn do_set_referral_code(
+    env: &Env,
+    merchant: Address,
+    code: Symbol,
+    referral: Option<ReferralCode>,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    let key = (KEY_CODE, merchant.clone(), code.clone());
+    match &referral {
+        Some(r) => {
+            if r.schedule.len() > MAX_COMMISSION_TIERS {
+                return Err(Error::LimitExceeded);
+            }
+            let mut prev_age = 0u64;
+            for (i, tier) in r.schedule.iter().enumerate() {
+                if tier.bps as i128 > BPS_DENOMINATOR
+                    || (i > 0 && tier.until_age_seconds <= prev_age)
+                {
+                    return Err(Error::InvalidAmount);
+                }
+                prev_age = tier.until_age_seconds;
+            }
+            env.storage().instance().set(&key, r);
+        }
+        None => env.storage().instance().remove(&key),
+    }
+    env.events().publish(
+        (Symbol::new(env, "referral_code_set"), merchant, code),
+        referral,
+    );
+    Ok(())
+}
+
+/// Attach a referral code of the subscription's merchant. Auth: subscriber. A
+/// subscription can be referred only once.
+pub fn do_apply_referral(
+    env: &Env,
+    subscriber: Address,
+    subscription_id: u32,
+    code: Symbol,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.subscriber != subscriber {
+        return Err(Error::Unauthorized);
+    }
+    if get_subscription_referral(env, subscription_id).is_some() {
+        return Err(Error::Replay);
+    }
+    if get_referral_code(env, &sub.merchant, &code).is_none() {
+        return Err(Error::NotFound);
+    }
+    env.storage()
+        .instance()
+        .set(&(KEY_SUB_REFERRAL, subscription_id), &code);
+    env.events().publish(
+        (Symbol::new(env, "referral_applied"), subscription_id),
+        code,
+    );
+    Ok(())
+}
+
+/// Commission rate for a subscription of `age_seconds` under `referral`.
+pub fn commission_bps(referral: &ReferralCode, age_seconds: u64) -> u32 {
+    referral
+        .schedule
+        .iter()
+        .find(|tier| age_seconds < tier.until_age_seconds)
+        .map(|tier| tier.bps)
+        .unwrap_or(0)
+}
+
+/// Pays the affiliate's commission on `net_amount` of a charge, if the
+/// subscription was referred. Returns the commission.
+pub fn pay_commission(
+    env: &Env,
+    subscription_id: u32,
+    merchant: &Address,
+    token: &Address,
+    net_amount: i128,
+    age_seconds: u64,
+) -> Result<i128, Error> {
+    let referral = match get_subscription_referral(env, subscription_id)
+        .and_then(|code| get_referral_code(env, merchant, &code))
+    {
+        Some(referral) => referral,
+        None => return Ok(0),
+    };
+    let bps = commission_bps(&referral, age_seconds);
+    let commission = bps_fee(net_amount, bps)?;
+    if commission == 0 {
+        return Ok(0);
+    }
+    transfer_or_defer(env, token, &referral.affiliate, commission)?;
+    env.events().publish(
+        (Symbol::new(env, "commission_paid"), subscription_id),
+        CommissionPaidEvent {
+            subscription_id,
+            affiliate: referral.affiliate,
+            token: token.clone(),
+            amount: commission,
+            bps,
+        },
+    );
+    Ok(commission)
+}
@@ -0,0 +1,181 @@
+//! Merchant bookkeeping aggregates and period summaries.
+//!
+//! **PRs that only change merchant reporting should edit this file only.**
+//!
+//! Every charge, refund, new subscription and cancellation is added to the
+//! merchant's [`MerchantDayStats`] for the UTC day it happened on. A period summary
+//! sums the day buckets overlapping the requested range, so it costs one read per
+//! day instead of a scan over subscriptions, and ranges are capped at
+//! [`MAX_SUMMARY_DAYS`]. Test-mode charges are not recorded.
+//!
+//! # Public statistics
+//!
+//! Vault-wide counters (charged volume, distinct merchants and subscribers) are
+//! maintained alongside, so `get_public_stats` answers with a few reads and no scan.
+//! An address counts once from its first subscription, even after its
+//! subscriptions are pruned.
+
+use crate::safe_math::safe_add;
+use crate::state_machine::get_status_bucket;
+use crate::subscription::SECONDS_PER_DAY;
+use crate::types::{
+    Error, MerchantDayStats, MerchantPeriodSummary, PublicStats, SubscriptionStatus,
+};
+use soroban_sdk::{symbol_short, Address, Env, Symbol};
+
+/// Longest range, in days, accepted by [`get_merchant_period_summary`].
+pub const MAX_SUMMARY_DAYS: u64 = 366;
+
+const KEY_DAY_STATS: Symbol = symbol_short!("mday");
+const KEY_SEEN_MERCHANT: Symbol = symbol_short!("seenm");
+const KEY_SEEN_SUBSCRIBER: Symbol = symbol_short!("seens");
+
+fn today(env: &Env) -> u64 {
+    env.ledger().timestamp() / SECONDS_PER_DAY
+}
+
+pub fn get_day_stats(env: &Env, merchant: &Address, day: u64) -> MerchantDayStats {
+    env.storage()
+        .instance()
+        .get(&(KEY_DAY_STATS, merchant.clone(), day))
+        .unwrap_or(MerchantDayStats {
+            charge_count: 0,
+            charged: 0,
+            fees: 0,
+            refund_count: 0,
+            refunded: 0,
+            new_subscriptions: 0,
+            cancellations: 0,
+        })
+}
+
+fn update_today(
+    env: &Env,
+    merchant: &Address,
+    f: impl FnOnce(&mut MerchantDayStats) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let day = today(env);
+    let mut stats = get_day_stats(env, merchant, day);
+    f(&mut stats)?;
+    env.storage()
+        .instance()
+        .set(&(KEY_DAY_STATS, merchant.clone(), day), &stats);
+    Ok(())
+}
+
+/// Adds a charge of `amount`, of which `fees` (tax, commission) did not reach the
+/// merchant.
+pub fn record_charge(env: &Env, merchant: &Address, amount: i128, fees: i128) -> Result<(), Error> {
+    let volume_key = Symbol::new(env, "total_volume");
+    let volume: i128 = env.storage().instance().get(&volume_key).unwrap_or(0);
+    env.storage()
+        .instance()
+        .set(&volume_key, &safe_add(volume, amount)?);
+    update_today(env, merchant, |s| {
+        s.charge_count = s.charge_count.saturating_add(1);
+        s.charged = safe_add(s.charged, amount)?;
+        s.fees = safe_add(s.fees, fees)?;
+        Ok(())
+    })
+}
+
+/// Adds a refund of `amount` taken back from the merchant's revenue.
+pub fn record_refund(env: &Env, merchant: &Address, amount: i128) -> Result<(), Error> {
+    update_today(env, merchant, |s| {
+        s.refund_count = s.refund_count.saturating_add(1);
+        s.refunded = safe_add(s.refunded, amount)?;
+        Ok(())
+    })
+}
+
+pub fn record_new_subscription(
+    env: &Env,
+    merchant: &Address,
+    subscriber: &Address,
+) -> Result<(), Error> {
+    count_first_seen(env, KEY_SEEN_MERCHANT, merchant, "n_merchants");
+    count_first_seen(env, KEY_SEEN_SUBSCRIBER, subscriber, "n_subscribers");
+    update_today(env, merchant, |s| {
+        s.new_subscriptions = s.new_subscriptions.saturating_add(1);
+        Ok(())
+    })
+}
+
+fn count_first_seen(env: &Env, prefix: Symbol, account: &Address, counter: &str) {
+    let key = (prefix, account.clone());
+    if env.storage().instance().has(&key) {
+        return;
+    }
+    env.storage().instance().set(&key, &true);
+    let counter = Symbol::new(env, counter);
+    let count: u32 = env.storage().instance().get(&counter).unwrap_or(0);
+    env.storage()
+        .instance()
+        .set(&counter, &count.saturating_add(1));
+}
+
+pub fn get_public_stats(env: &Env) -> PublicStats {
+    let storage = env.storage().instance();
+    PublicStats {
+        total_subscriptions: storage.get(&Symbol::new(env, "next_id")).unwrap_or(0),
+        active_subscriptions: get_status_bucket(env, &SubscriptionStatus::Active).len(),
+        total_volume: storage.get(&Symbol::new(env, "total_volume")).unwrap_or(0),
+        unique_merchants: storage.get(&Symbol::new(env, "n_merchants")).unwrap_or(0),
+        unique_subscribers: storage.get(&Symbol::new(env, "n_subscribers")).unwrap_or(0),
+    }
+}
+
+pub fn record_cancellation(env: &Env, merchant: &Address) -> Result<(), Error> {
+    update_today(env, merchant, |s| {
+        s.cancellations = s.cancellations.saturating_add(1);
+        Ok(())
+    })
+}
+
+/// Sums the merchant's day buckets overlapping `[period_start, period_end)`.
+///
+/// Aggregates are kept per UTC day, so the summary covers whole days: the returned
+/// `period_start` and `period_end` are the range widened to day boundaries. Fails
+/// with `InvalidAmount` for an empty range and `LimitExceeded` beyond
+/// [`MAX_SUMMARY_DAYS`].
+pub fn get_merchant_period_summary(
+    env: &Env,
+    merchant: Address,
+    period_start: u64,
+    period_end: u64,
+) -> Result<MerchantPeriodSummary, Error> {
+    if period_end <= period_start {
+        return Err(Error::InvalidAmount);
+    }
+    let first_day = period_start / SECONDS_PER_DAY;
+    let last_day = (period_end - 1) / SECONDS_PER_DAY;
+    if last_day - first_day >= MAX_SUMMARY_DAYS {
+        return Err(Error::LimitExceeded);
+    }
+
+    let mut summary = MerchantPeriodSummary {
+        merchant: merchant.clone(),
+        period_start: first_day * SECONDS_PER_DAY,
+        period_end: (last_day + 1) * SECONDS_PER_DAY,
+        charge_count: 0,
+        charged: 0,
+        fees: 0,
+        refund_count: 0,
+        refunded: 0,
+        new_subscriptions: 0,
+        cancellations: 0,
+    };
+    for day in first_day..=last_day {
+        let s = get_day_stats(env, &merchant, day);
+        summary.charge_count = summary.charge_count.saturating_add(s.charge_count);
+        summary.charged = safe_add(summary.charged, s.charged)?;
+        summary.fees = safe_add(summary.fees, s.fees)?;
+        summary.refund_count = summary.refund_count.saturating_add(s.refund_count);
+        summary.refunded = safe_add(summary.refunded, s.refunded)?;
+        summary.new_subscriptions = summary
+            .new_subscriptions
+            .saturating_add(s.new_subscriptions);
+        summary.cancellations = summary.cancellations.saturating_add(s.cancellations);
+    }
+    Ok(summary)
+}
@@ -190,3 +190,133 @@ pub fn safe_sub_balance(balance: i128, amount: i128) -> Result<i128, Error> {
         Ok(result)
     }
 }
+
+/// Basis points in one whole (100%).
+pub const BPS_DENOMINATOR: i128 = 10_000;
+
+/// Explicit rounding direction for division.
+///
+/// Fees use `Floor` so the protocol never takes more than its share; refunds use
+/// `Ceil` so rounding dust favours the user.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Rounding {
+    Floor,
+    Ceil,
+}
+
+/// Computes `amount * numerator / denominator` with an explicit rounding mode.
+///
+/// # Arguments
+///
+/// * `amount` - Non-negative base amount
+/// * `numerator` - Non-negative multiplier
+/// * `denominator` - Strictly positive divisor
+/// * `rounding` - Direction to round any remainder
+///
+/// # Returns
+///
+/// * `Ok(i128)` - The rounded quotient
+/// * `Err(Error::Underflow)` - If `amount` or `numerator` is negative
+/// * `Err(Error::InvalidAmount)` - If `denominator` is not positive
+/// * `Err(Error::Overflow)` - If the intermediate product exceeds `i128::MAX`
+///
+/// # Examples
+///
+/// ```
+/// use subscription_vault::safe_math::{mul_div, Rounding};
+///
+/// assert_eq!(mul_div(10, 1, 3, Rounding::Floor), Ok(3));
+/// assert_eq!(mul_div(10, 1, 3, Rounding::Ceil), Ok(4));
+/// ```
+pub fn mul_div(
+    amount: i128,
+    numerator: i128,
+    denominator: i128,
+    rounding: Rounding,
+) -> Result<i128, Error> {
+    validate_non_negative(amount)?;
+    validate_non_negative(numerator)?;
+    if denominator <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    let product = safe_mul(amount, numerator)?;
+    let quotient = product / denominator;
+    match rounding {
+        Rounding::Ceil if product % denominator != 0 => safe_add(quotient, 1),
+        _ => Ok(quotient),
+    }
+}
+
+/// Fee of `bps` basis points on `amount`, rounded down.
+///
+/// Rejects `bps` above `BPS_DENOMINATOR` with `Error::InvalidAmount`, so the fee
+/// never exceeds `amount`.
+pub fn bps_fee(amount: i128, bps: u32) -> Result<i128, Error> {
+    let bps = bps as i128;
+    if bps > BPS_DENOMINATOR {
+        return Err(Error::InvalidAmount);
+    }
+    mul_div(amount, bps, BPS_DENOMINATOR, Rounding::Floor)
+}
+
+/// Splits `amount` into `(share, remainder)` where `share` is `bps` basis points
+/// rounded down and `remainder` takes the dust.
+///
+/// # Guarantees
+///
+/// `share + remainder == amount` exactly; no dust is created or lost.
+pub fn split_bps(amount: i128, bps: u32) -> Result<(i128, i128), Error> {
+    let share = bps_fee(amount, bps)?;
+    Ok((share, safe_sub_balance(amount, share)?))
+}
+
+/// Prorates `amount` by `elapsed / period` for a user-facing refund, rounded up
+/// and capped at `amount`.
+///
+/// # Examples
+///
+/// ```
+/// use subscription_vault::safe_math::prorate_refund;
+///
+/// // One third of 100 left in the period refunds 34, not 33.
+/// assert_eq!(prorate_refund(100, 1, 3), Ok(34));
+/// ```
+pub fn prorate_refund(amount: i128, remaining: u64, period: u64) -> Result<i128, Error> {
+    let remaining = remaining.min(period);
+    mul_div(amount, remaining as i128, period as i128, Rounding::Ceil)
+}
+
+/// Rounds `amount` to a multiple of `unit` in the given direction.
+///
+/// A non-positive `unit` leaves `amount` unchanged.
+pub fn round_to_multiple(amount: i128, unit: i128, rounding: Rounding) -> Result<i128, Error> {
+    if unit <= 0 {
+        return Ok(amount);
+    }
+    let rem = amount.rem_euclid(unit);
+    match rounding {
+        Rounding::Floor => safe_sub(amount, rem),
+        Rounding::Ceil if rem != 0 => safe_add(amount, unit - rem),
+        _ => Ok(amount),
+    }
+}
+
+/// Safely multiplies two i128 values, preventing overflow.
+///
+/// # Returns
+///
+/// * `Ok(i128)` - The product of `a` and `b` if no overflow occurs
+/// * `Err(Error::Overflow)` - If the product does not fit in an i128
+///
+/// # Examples
+///
+/// ```
+/// use subscription_vault::safe_math::safe_mul;
+/// use subscription_vault::Error;
+///
+/// assert_eq!(safe_mul(1_000_000, 12), Ok(12_000_000));
+/// assert_eq!(safe_mul(i128::MAX, 2), Err(Error::Overflow));
+/// ```
+pub fn safe_mul(a: i128, b: i128) -> Result<i128, Error> {
+    a.checked_mul(b).ok_or(Error::Overflow)
+}
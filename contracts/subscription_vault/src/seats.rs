@@ -0,0 +1,158 @@
+//! Seat-based pricing: `amount = unit_price × seats`.
+//!
+//! **PRs that only change seat pricing should edit this file only.**
+//!
+//! Merchant and subscriber jointly put a subscription on a [`SeatPlan`]. The
+//! subscriber can then change the seat count on their own within
+//! `[min_seats, max_seats]`; any other count must be proposed by the merchant and
+//! accepted by the subscriber. Seats added mid-cycle are charged immediately, prorated
+//! to the time left in the current interval; removed seats take effect at the next
+//! charge.
+
+use crate::charge_core::force_debit;
+use crate::merchant::require_min_amount;
+use crate::queries::get_subscription;
+use crate::safe_math::{prorate_refund, safe_mul};
+use crate::types::{DataKey, Error, SeatPlan, SeatsUpdatedEvent, SubscriptionStatus};
+use soroban_sdk::{Address, Env, Symbol};
+
+pub fn get_seat_plan(env: &Env, subscription_id: u32) -> Option<SeatPlan> {
+    env.storage()
+        .instance()
+        .get(&DataKey::SubSeatPlan(subscription_id))
+}
+
+fn set_seat_plan(env: &Env, subscription_id: u32, plan: &SeatPlan) {
+    env.storage()
+        .instance()
+        .set(&DataKey::SubSeatPlan(subscription_id), plan);
+}
+
+/// Put a subscription on seat pricing. Auth: merchant and subscriber.
+///
+/// Replaces the per-interval amount with `unit_price × seats` from the next charge.
+pub fn do_set_seat_plan(
+    env: &Env,
+    subscription_id: u32,
+    unit_price: i128,
+    seats: u32,
+    min_seats: u32,
+    max_seats: u32,
+) -> Result<i128, Error> {
+    let mut sub = get_subscription(env, subscription_id)?;
+    sub.merchant.require_auth();
+    sub.subscriber.require_auth();
+    if unit_price <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    if min_seats == 0 || min_seats > max_seats || seats < min_seats || seats > max_seats {
+        return Err(Error::LimitExceeded);
+    }
+    sub.amount = safe_mul(unit_price, seats as i128)?;
+    require_min_amount(env, &sub.merchant, sub.amount)?;
+    env.storage().instance().set(&subscription_id, &sub);
+    set_seat_plan(
+        env,
+        subscription_id,
+        &SeatPlan {
+            unit_price,
+            seats,
+            min_seats,
+            max_seats,
+            proposed_seats: 0,
+        },
+    );
+    env.events().publish(
+        (Symbol::new(env, "seat_plan_set"), subscription_id),
+        (unit_price, seats, min_seats, max_seats),
+    );
+    Ok(sub.amount)
+}
+
+/// Change the seat count. Auth: `caller`, the subscriber or the merchant.
+///
+/// The subscriber's change within bounds applies immediately and returns the prorated
+/// amount charged for added seats. The merchant's change is stored as a proposal for
+/// the subscriber to accept and returns 0.
+pub fn do_update_seats(
+    env: &Env,
+    caller: Address,
+    subscription_id: u32,
+    seats: u32,
+) -> Result<i128, Error> {
+    caller.require_auth();
+    let sub = get_subscription(env, subscription_id)?;
+    let mut plan = get_seat_plan(env, subscription_id).ok_or(Error::NotFound)?;
+    if seats == 0 {
+        return Err(Error::InvalidAmount);
+    }
+    if caller == sub.subscriber {
+        if seats < plan.min_seats || seats > plan.max_seats {
+            return Err(Error::LimitExceeded);
+        }
+        apply_seats(env, subscription_id, plan, seats)
+    } else if caller == sub.merchant {
+        plan.proposed_seats = seats;
+        set_seat_plan(env, subscription_id, &plan);
+        env.events()
+            .publish((Symbol::new(env, "seats_proposed"), subscription_id), seats);
+        Ok(0)
+    } else {
+        Err(Error::Unauthorized)
+    }
+}
+
+/// Accept the merchant's proposed seat count. Auth: subscriber.
+pub fn do_accept_seats(env: &Env, subscription_id: u32) -> Result<i128, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    sub.subscriber.require_auth();
+    let plan = get_seat_plan(env, subscription_id).ok_or(Error::NotFound)?;
+    if plan.proposed_seats == 0 {
+        return Err(Error::NotFound);
+    }
+    let seats = plan.proposed_seats;
+    apply_seats(env, subscription_id, plan, seats)
+}
+
+fn apply_seats(
+    env: &Env,
+    subscription_id: u32,
+    mut plan: SeatPlan,
+    seats: u32,
+) -> Result<i128, Error> {
+    let mut sub = get_subscription(env, subscription_id)?;
+    if sub.status != SubscriptionStatus::Active {
+        return Err(Error::NotActive);
+    }
+    let previous = plan.seats;
+    plan.seats = seats;
+    plan.proposed_seats = 0;
+    sub.amount = safe_mul(plan.unit_price, seats as i128)?;
+    require_min_amount(env, &sub.merchant, sub.amount)?;
+    env.storage().instance().set(&subscription_id, &sub);
+    set_seat_plan(env, subscription_id, &plan);
+
+    let mut prorated = 0;
+    if seats > previous {
+        let added = safe_mul(plan.unit_price, (seats - previous) as i128)?;
+        let next_charge = sub
+            .last_payment_timestamp
+            .saturating_add(sub.interval_seconds);
+        let remaining = next_charge.saturating_sub(env.ledger().timestamp());
+        prorated = prorate_refund(added, remaining, sub.interval_seconds)?;
+        if prorated > 0 {
+            force_debit(env, subscription_id, prorated)?;
+        }
+    }
+    env.events().publish(
+        (Symbol::new(env, "seats_updated"), subscription_id),
+        SeatsUpdatedEvent {
+            subscription_id,
+            previous_seats: previous,
+            seats,
+            amount: sub.amount,
+            prorated_charge: prorated,
+        },
+    );
+    Ok(prorated)
+}
@@ -0,0 +1,142 @@
+//! Off-chain signed agreements, verified on-chain with ed25519.
+//!
+//! **PRs that only change signed-agreement handling should edit this file only.**
+//!
+//! An account registers an ed25519 public key with `set_signing_key` (Soroban auth).
+//! A [`SubscriptionAgreement`] signed off-chain by both the subscriber's and the
+//! merchant's registered keys can then be posted by either party or any relayer with
+//! `import_agreement`. The signed message is the agreement's XDR encoding; each
+//! agreement can be imported once and not after `expires_at`.
+//!
+//! # Meta-transactions
+//!
+//! `execute_signed` lets a relayer submit a subscriber's [`SignedAction`] (deposit,
+//! pause, resume, cancel) without Soroban auth from the subscriber's account. The
+//! signature covers the XDR of `(vault address, action)`, and `action.nonce` must
+//! equal the signer's next nonce, so each signed action runs at most once. Signed
+//! deposits pull tokens through an allowance the subscriber granted the vault.
+
+use crate::queries::get_subscription;
+use crate::subscription::{
+    apply_cancel, apply_deposit, apply_pause, apply_resume, create_subscription_record,
+};
+use crate::types::{DataKey, Error, SignedAction, SignedOp, SubscriptionAgreement};
+use soroban_sdk::xdr::ToXdr;
+use soroban_sdk::{symbol_short, Address, BytesN, Env, Symbol};
+
+pub fn get_signing_key(env: &Env, account: &Address) -> Option<BytesN<32>> {
+    env.storage()
+        .instance()
+        .get(&DataKey::SigningKey(account.clone()))
+}
+
+/// Set or clear the ed25519 public key that signs on behalf of `account`. Auth: account.
+pub fn do_set_signing_key(env: &Env, account: Address, key: Option<BytesN<32>>) {
+    account.require_auth();
+    let storage_key = DataKey::SigningKey(account.clone());
+    match &key {
+        Some(key) => env.storage().instance().set(&storage_key, key),
+        None => env.storage().instance().remove(&storage_key),
+    }
+    env.events()
+        .publish((Symbol::new(env, "signing_key_set"), account), key);
+}
+
+/// Verifies `signature` over `message` by `account`'s registered key. Panics (aborting
+/// the invocation) on a bad signature; `Unauthorized` if no key is registered.
+pub fn verify_signed(
+    env: &Env,
+    account: &Address,
+    message: &soroban_sdk::Bytes,
+    signature: &BytesN<64>,
+) -> Result<(), Error> {
+    let key = get_signing_key(env, account).ok_or(Error::Unauthorized)?;
+    env.crypto().ed25519_verify(&key, message, signature);
+    Ok(())
+}
+
+/// Create the subscription described by a doubly signed agreement. Returns its id.
+pub fn do_import_agreement(
+    env: &Env,
+    agreement: SubscriptionAgreement,
+    subscriber_signature: BytesN<64>,
+    merchant_signature: BytesN<64>,
+) -> Result<u32, Error> {
+    if env.ledger().timestamp() > agreement.expires_at {
+        return Err(Error::AgreementExpired);
+    }
+    let message = agreement.clone().to_xdr(env);
+    let digest: BytesN<32> = env.crypto().sha256(&message).into();
+    let used_key = (symbol_short!("agr"), digest.clone());
+    if env.storage().instance().has(&used_key) {
+        return Err(Error::Replay);
+    }
+    verify_signed(env, &agreement.subscriber, &message, &subscriber_signature)?;
+    verify_signed(env, &agreement.merchant, &message, &merchant_signature)?;
+    env.storage().instance().set(&used_key, &true);
+
+    let id = create_subscription_record(
+        env,
+        agreement.subscriber,
+        agreement.merchant,
+        agreement.amount,
+        agreement.interval_seconds,
+        agreement.usage_enabled,
+        None,
+    )?;
+    env.events()
+        .publish((Symbol::new(env, "agreement_imported"), id), digest);
+    Ok(id)
+}
+
+/// Next nonce `account` must sign for `execute_signed`.
+pub fn get_signer_nonce(env: &Env, account: &Address) -> u64 {
+    env.storage()
+        .instance()
+        .get(&DataKey::SignerNonce(account.clone()))
+        .unwrap_or(0)
+}
+
+/// Run a subscriber action signed off-chain by the subscriber's registered key.
+pub fn do_execute_signed(
+    env: &Env,
+    action: SignedAction,
+    signature: BytesN<64>,
+) -> Result<(), Error> {
+    if env.ledger().timestamp() > action.expires_at {
+        return Err(Error::AgreementExpired);
+    }
+    let nonce = get_signer_nonce(env, &action.signer);
+    if action.nonce != nonce {
+        return Err(Error::Replay);
+    }
+    let message = (env.current_contract_address(), action.clone()).to_xdr(env);
+    verify_signed(env, &action.signer, &message, &signature)?;
+    env.storage()
+        .instance()
+        .set(&DataKey::SignerNonce(action.signer.clone()), &(nonce + 1));
+
+    let subscription_id = match action.op {
+        SignedOp::Deposit(id, _)
+        | SignedOp::Pause(id)
+        | SignedOp::Resume(id)
+        | SignedOp::Cancel(id) => id,
+    };
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.subscriber != action.signer {
+        return Err(Error::Unauthorized);
+    }
+    match action.op {
+        SignedOp::Deposit(id, amount) => {
+            apply_deposit(env, id, action.signer.clone(), amount, true)?
+        }
+        SignedOp::Pause(id) => apply_pause(env, id)?,
+        SignedOp::Resume(id) => apply_resume(env, id)?,
+        SignedOp::Cancel(id) => apply_cancel(env, id, sub)?,
+    }
+    env.events().publish(
+        (Symbol::new(env, "signed_action"), action.signer),
+        (subscription_id, nonce),
+    );
+    Ok(())
+}
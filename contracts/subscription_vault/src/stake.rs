@@ -0,0 +1,410 @@
+//! Merchant registry and onboarding stake.
+//!
+//! **PRs that only change merchant staking should edit this file only.**
+//!
+//! When the admin sets a non-zero [`MerchantStakePolicy::amount`], a merchant must
+//! `register_merchant` (locking that amount of the settlement token in the vault)
+//! before subscriptions can be created against it. De-registering starts a cooldown;
+//! the stake stays in the vault, and remains slashable, until the cooldown elapses
+//! and the merchant calls `withdraw_merchant_stake`.
+//!
+//! # Fraud reports and slashing
+//!
+//! Anyone may `report_merchant` with an evidence hash; the admin (governance)
+//! resolves the report. A confirmed report slashes the merchant's stake and its
+//! queued payout balance in the stake token into the merchant's victim compensation
+//! pot, which is credited to the merchant's subscribers' credit wallets pro-rata to
+//! the amount each subscription has been charged. Rounding dust, or the whole pot if
+//! no subscriber has been charged yet, stays in that merchant's pot and is only ever
+//! distributed to its own subscribers, when another report against it is confirmed.
+//!
+//! # Kill switch
+//!
+//! On suspected fraud the admin can `halt_merchant` before a report is resolved. Every
+//! charge against the merchant's subscriptions then fails with `MerchantHalted`, while
+//! other merchants are unaffected and subscribers keep full control of their funds.
+//! Each affected subscriber gets a `charging_halted` event, and a `charging_resumed`
+//! event when the admin calls `reinstate_merchant`.
+
+use crate::admin::{get_token, log_admin_action, require_admin};
+use crate::claims::transfer_or_defer;
+use crate::credit::credit_wallet;
+use crate::keeper::track_liabilities;
+use crate::merchant::{get_payout_queue, unqueue_payout};
+use crate::safe_math::{mul_div, safe_add, Rounding};
+use crate::subscription::subscription_token;
+use crate::types::{
+    DataKey, Error, FraudReport, MerchantHalt, MerchantSlashedEvent, MerchantStake,
+    MerchantStakePolicy, Subscription, SubscriptionStatus,
+};
+use soroban_sdk::{symbol_short, token, Address, BytesN, Env, Map, Symbol, Vec};
+
+const KEY_COMP_POT: Symbol = symbol_short!("cpot");
+
+pub fn get_merchant_stake_policy(env: &Env) -> MerchantStakePolicy {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "merchant_stake"))
+        .unwrap_or(MerchantStakePolicy {
+            amount: 0,
+            cooldown_seconds: 0,
+        })
+}
+
+/// Set the stake required of new merchants and the de-registration cooldown. Admin only.
+pub fn do_set_merchant_stake_policy(
+    env: &Env,
+    admin: Address,
+    policy: MerchantStakePolicy,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    if policy.amount < 0 {
+        return Err(Error::InvalidAmount);
+    }
+    log_admin_action(env, &admin, "set_merchant_stake_policy");
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "merchant_stake"), &policy);
+    env.events()
+        .publish((Symbol::new(env, "merchant_stake_policy_updated"),), policy);
+    Ok(())
+}
+
+pub fn get_merchant_stake(env: &Env, merchant: &Address) -> Option<MerchantStake> {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantStake(merchant.clone()))
+}
+
+pub fn set_merchant_stake(env: &Env, merchant: &Address, stake: &MerchantStake) {
+    env.storage()
+        .instance()
+        .set(&DataKey::MerchantStake(merchant.clone()), stake);
+}
+
+/// Fails with `MerchantNotStaked` if a stake is required and `merchant` is not
+/// registered, or is de-registering.
+pub fn require_merchant_staked(env: &Env, merchant: &Address) -> Result<(), Error> {
+    if get_merchant_stake_policy(env).amount == 0 {
+        return Ok(());
+    }
+    match get_merchant_stake(env, merchant) {
+        Some(stake) if stake.unstake_requested_at == 0 => Ok(()),
+        _ => Err(Error::MerchantNotStaked),
+    }
+}
+
+/// Register `merchant`, transferring the policy's stake into the vault. Auth: merchant.
+pub fn do_register_merchant(env: &Env, merchant: Address) -> Result<MerchantStake, Error> {
+    merchant.require_auth();
+    if get_merchant_stake(env, &merchant).is_some() {
+        return Err(Error::LimitExceeded);
+    }
+    let token_addr = get_token(env)?;
+    let amount = get_merchant_stake_policy(env).amount;
+    if amount > 0 {
+        token::Client::new(env, &token_addr).transfer(
+            &merchant,
+            &env.current_contract_address(),
+            &amount,
+        );
+        track_liabilities(env, &token_addr, amount);
+    }
+    let stake = MerchantStake {
+        token: token_addr,
+        amount,
+        staked_at: env.ledger().timestamp(),
+        unstake_requested_at: 0,
+    };
+    set_merchant_stake(env, &merchant, &stake);
+    env.events().publish(
+        (Symbol::new(env, "merchant_registered"), merchant),
+        stake.clone(),
+    );
+    Ok(stake)
+}
+
+/// Stop accepting new subscriptions and start the stake cooldown. Auth: merchant.
+///
+/// Returns the timestamp from which the stake can be withdrawn.
+pub fn do_deregister_merchant(env: &Env, merchant: Address) -> Result<u64, Error> {
+    merchant.require_auth();
+    let mut stake = get_merchant_stake(env, &merchant).ok_or(Error::NotFound)?;
+    if stake.unstake_requested_at != 0 {
+        return Err(Error::Replay);
+    }
+    let now = env.ledger().timestamp();
+    stake.unstake_requested_at = now;
+    set_merchant_stake(env, &merchant, &stake);
+    let withdrawable_at = now.saturating_add(get_merchant_stake_policy(env).cooldown_seconds);
+    env.events().publish(
+        (Symbol::new(env, "merchant_deregistered"), merchant),
+        withdrawable_at,
+    );
+    Ok(withdrawable_at)
+}
+
+/// Return the stake of a de-registered merchant once the cooldown has elapsed.
+/// Auth: merchant. Returns the amount returned.
+pub fn do_withdraw_merchant_stake(env: &Env, merchant: Address) -> Result<i128, Error> {
+    merchant.require_auth();
+    let stake = get_merchant_stake(env, &merchant).ok_or(Error::NotFound)?;
+    let cooldown = get_merchant_stake_policy(env).cooldown_seconds;
+    if stake.unstake_requested_at == 0
+        || env.ledger().timestamp() < stake.unstake_requested_at.saturating_add(cooldown)
+    {
+        return Err(Error::TimelockNotElapsed);
+    }
+    env.storage()
+        .instance()
+        .remove(&DataKey::MerchantStake(merchant.clone()));
+    if stake.amount > 0 {
+        transfer_or_defer(env, &stake.token, &merchant, stake.amount)?;
+    }
+    env.events().publish(
+        (Symbol::new(env, "merchant_stake_withdrawn"), merchant),
+        (stake.token, stake.amount),
+    );
+    Ok(stake.amount)
+}
+
+pub fn get_fraud_report(env: &Env, merchant: &Address) -> Option<FraudReport> {
+    env.storage()
+        .instance()
+        .get(&DataKey::FraudReport(merchant.clone()))
+}
+
+/// Open a fraud report against `merchant`. Auth: reporter. One open report per merchant.
+pub fn do_report_merchant(
+    env: &Env,
+    reporter: Address,
+    merchant: Address,
+    evidence: BytesN<32>,
+) -> Result<(), Error> {
+    reporter.require_auth();
+    if get_fraud_report(env, &merchant).is_some() {
+        return Err(Error::LimitExceeded);
+    }
+    let report = FraudReport {
+        reporter,
+        evidence,
+        reported_at: env.ledger().timestamp(),
+    };
+    env.storage()
+        .instance()
+        .set(&DataKey::FraudReport(merchant.clone()), &report);
+    env.events()
+        .publish((Symbol::new(env, "merchant_reported"), merchant), report);
+    Ok(())
+}
+
+/// Undistributed victim compensation from slashing `merchant`, held in `token`.
+pub fn get_compensation_pot(env: &Env, merchant: &Address, token: &Address) -> i128 {
+    env.storage()
+        .instance()
+        .get(&(KEY_COMP_POT, merchant.clone(), token.clone()))
+        .unwrap_or(0)
+}
+
+/// Resolve the open report against `merchant`. Admin only.
+///
+/// A dismissed report is simply closed. A confirmed one slashes the merchant and
+/// distributes the compensation pot; the returned event summarizes the slash.
+pub fn do_resolve_report(
+    env: &Env,
+    admin: Address,
+    merchant: Address,
+    confirmed: bool,
+) -> Result<Option<MerchantSlashedEvent>, Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    if get_fraud_report(env, &merchant).is_none() {
+        return Err(Error::NotFound);
+    }
+    env.storage()
+        .instance()
+        .remove(&DataKey::FraudReport(merchant.clone()));
+    if !confirmed {
+        log_admin_action(env, &admin, "dismiss_report");
+        env.events()
+            .publish((Symbol::new(env, "report_dismissed"), merchant), ());
+        return Ok(None);
+    }
+    log_admin_action(env, &admin, "slash_merchant");
+    let event = slash_merchant(env, &merchant)?;
+    env.events().publish(
+        (Symbol::new(env, "merchant_slashed"), merchant),
+        event.clone(),
+    );
+    Ok(Some(event))
+}
+
+fn slash_merchant(env: &Env, merchant: &Address) -> Result<MerchantSlashedEvent, Error> {
+    let (token_addr, stake_slashed) = match get_merchant_stake(env, merchant) {
+        Some(stake) => {
+            env.storage()
+                .instance()
+                .remove(&DataKey::MerchantStake(merchant.clone()));
+            (stake.token, stake.amount)
+        }
+        None => (get_token(env)?, 0),
+    };
+    let pending_slashed = get_payout_queue(env, merchant, &token_addr).pending;
+    unqueue_payout(env, merchant, &token_addr, pending_slashed);
+
+    let pot = safe_add(
+        get_compensation_pot(env, merchant, &token_addr),
+        safe_add(stake_slashed, pending_slashed)?,
+    )?;
+
+    // Weight each affected subscriber by what they have been charged in this token.
+    let ids: Vec<u32> = env
+        .storage()
+        .instance()
+        .get(&DataKey::MerchantSubs(merchant.clone()))
+        .unwrap_or(Vec::new(env));
+    let mut weights: Map<Address, i128> = Map::new(env);
+    let mut total_weight = 0i128;
+    for id in ids.iter() {
+        if subscription_token(env, id)? != token_addr {
+            continue;
+        }
+        let charged: i128 = env
+            .storage()
+            .instance()
+            .get(&DataKey::SubTotalCharged(id))
+            .unwrap_or(0);
+        if charged <= 0 {
+            continue;
+        }
+        let sub: crate::types::Subscription =
+            env.storage().instance().get(&id).ok_or(Error::NotFound)?;
+        let weight = safe_add(weights.get(sub.subscriber.clone()).unwrap_or(0), charged)?;
+        weights.set(sub.subscriber, weight);
+        total_weight = safe_add(total_weight, charged)?;
+    }
+
+    let mut distributed = 0i128;
+    if total_weight > 0 {
+        for (subscriber, weight) in weights.iter() {
+            let share = mul_div(pot, weight, total_weight, Rounding::Floor)?;
+            if share > 0 {
+                credit_wallet(env, &subscriber, &token_addr, share)?;
+                distributed = safe_add(distributed, share)?;
+            }
+        }
+    }
+    let key = (KEY_COMP_POT, merchant.clone(), token_addr.clone());
+    if pot == distributed {
+        env.storage().instance().remove(&key);
+    } else {
+        env.storage().instance().set(&key, &(pot - distributed));
+    }
+
+    Ok(MerchantSlashedEvent {
+        merchant: merchant.clone(),
+        token: token_addr,
+        stake_slashed,
+        pending_slashed,
+        distributed,
+        recipients: weights.len(),
+    })
+}
+
+const KEY_HALT: Symbol = symbol_short!("halt");
+
+pub fn get_merchant_halt(env: &Env, merchant: &Address) -> Option<MerchantHalt> {
+    env.storage().instance().get(&(KEY_HALT, merchant.clone()))
+}
+
+/// Fails with `MerchantHalted` while the admin kill switch is on for `merchant`.
+pub fn require_merchant_not_halted(env: &Env, merchant: &Address) -> Result<(), Error> {
+    if get_merchant_halt(env, merchant).is_some() {
+        return Err(Error::MerchantHalted);
+    }
+    Ok(())
+}
+
+/// Publishes `topic` to the subscriber of each of the merchant's live subscriptions.
+fn notify_subscribers(env: &Env, merchant: &Address, topic: &str) {
+    let ids: Vec<u32> = env
+        .storage()
+        .instance()
+        .get(&DataKey::MerchantSubs(merchant.clone()))
+        .unwrap_or(Vec::new(env));
+    for id in ids.iter() {
+        if let Some(sub) = env.storage().instance().get::<u32, Subscription>(&id) {
+            if !matches!(
+                sub.status,
+                SubscriptionStatus::Cancelled
+                    | SubscriptionStatus::Completed
+                    | SubscriptionStatus::Expired
+            ) {
+                env.events().publish(
+                    (Symbol::new(env, topic), sub.subscriber),
+                    (id, merchant.clone()),
+                );
+            }
+        }
+    }
+}
+
+/// Halt all charging for `merchant`. Admin only.
+pub fn do_halt_merchant(
+    env: &Env,
+    admin: Address,
+    merchant: Address,
+    reason: Symbol,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    if get_merchant_halt(env, &merchant).is_some() {
+        return Err(Error::Replay);
+    }
+    log_admin_action(env, &admin, "halt_merchant");
+    let halt = MerchantHalt {
+        reason,
+        halted_at: env.ledger().timestamp(),
+    };
+    env.storage()
+        .instance()
+        .set(&(KEY_HALT, merchant.clone()), &halt);
+    env.events().publish(
+        (Symbol::new(env, "merchant_halted"), merchant.clone()),
+        halt,
+    );
+    notify_subscribers(env, &merchant, "charging_halted");
+    Ok(())
+}
+
+/// Lift the kill switch on `merchant`. Admin only.
+pub fn do_reinstate_merchant(env: &Env, admin: Address, merchant: Address) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    if get_merchant_halt(env, &merchant).is_none() {
+        return Err(Error::NotFound);
+    }
+    log_admin_action(env, &admin, "reinstate_merchant");
+    env.storage()
+        .instance()
+        .remove(&(KEY_HALT, merchant.clone()));
+    env.events().publish(
+        (Symbol::new(env, "merchant_reinstated"), merchant.clone()),
+        (),
+    );
+    notify_subscribers(env, &merchant, "charging_resumed");
+    Ok(())
+}
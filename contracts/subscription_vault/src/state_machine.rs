@@ -2,8 +2,17 @@
 //!
 //! Kept in a separate module so PRs touching state transitions do not conflict
 //! with PRs touching billing, batch charge, or top-up estimation.
+//!
+//! # Status buckets
+//!
+//! Every status change goes through [`apply_transition`], which also keeps a
+//! per-status index of subscription ids so `get_subscriptions_by_status` can
+//! enumerate e.g. all `InsufficientBalance` subscriptions without a full scan.
 
-use crate::types::{Error, SubscriptionStatus};
+use crate::archive;
+use crate::reports::record_cancellation;
+use crate::types::{DataKey, Error, Subscription, SubscriptionStatus};
+use soroban_sdk::{Env, Vec};
 
 /// Validates if a status transition is allowed by the state machine.
 ///
@@ -18,7 +27,13 @@ use crate::types::{Error, SubscriptionStatus};
 /// | Paused            | Cancelled           | Yes     |
 /// | InsufficientBalance | Active            | Yes     |
 /// | InsufficientBalance | Cancelled         | Yes     |
+/// | Active            | Completed           | Yes     |
+/// | Active            | Expired             | Yes     |
+/// | Paused            | Expired             | Yes     |
+/// | InsufficientBalance | Expired           | Yes     |
 /// | Cancelled         | *any*               | No      |
+/// | Completed         | *any*               | No      |
+/// | Expired           | *any*               | No      |
 /// | *any*             | Same status         | Yes (idempotent) |
 ///
 /// # Arguments
@@ -42,18 +57,26 @@ pub fn validate_status_transition(
             SubscriptionStatus::Paused
                 | SubscriptionStatus::Cancelled
                 | SubscriptionStatus::InsufficientBalance
+                | SubscriptionStatus::Completed
+                | SubscriptionStatus::Expired
         ),
         SubscriptionStatus::Paused => {
             matches!(
                 to,
-                SubscriptionStatus::Active | SubscriptionStatus::Cancelled
+                SubscriptionStatus::Active
+                    | SubscriptionStatus::Cancelled
+                    | SubscriptionStatus::Expired
             )
         }
-        SubscriptionStatus::Cancelled => false,
+        SubscriptionStatus::Cancelled
+        | SubscriptionStatus::Completed
+        | SubscriptionStatus::Expired => false,
         SubscriptionStatus::InsufficientBalance => {
             matches!(
                 to,
-                SubscriptionStatus::Active | SubscriptionStatus::Cancelled
+                SubscriptionStatus::Active
+                    | SubscriptionStatus::Cancelled
+                    | SubscriptionStatus::Expired
             )
         }
     };
@@ -74,12 +97,22 @@ pub fn get_allowed_transitions(status: &SubscriptionStatus) -> &'static [Subscri
             SubscriptionStatus::Paused,
             SubscriptionStatus::Cancelled,
             SubscriptionStatus::InsufficientBalance,
+            SubscriptionStatus::Completed,
+            SubscriptionStatus::Expired,
+        ],
+        SubscriptionStatus::Paused => &[
+            SubscriptionStatus::Active,
+            SubscriptionStatus::Cancelled,
+            SubscriptionStatus::Expired,
+        ],
+        SubscriptionStatus::Cancelled
+        | SubscriptionStatus::Completed
+        | SubscriptionStatus::Expired => &[],
+        SubscriptionStatus::InsufficientBalance => &[
+            SubscriptionStatus::Active,
+            SubscriptionStatus::Cancelled,
+            SubscriptionStatus::Expired,
         ],
-        SubscriptionStatus::Paused => &[SubscriptionStatus::Active, SubscriptionStatus::Cancelled],
-        SubscriptionStatus::Cancelled => &[],
-        SubscriptionStatus::InsufficientBalance => {
-            &[SubscriptionStatus::Active, SubscriptionStatus::Cancelled]
-        }
     }
 }
 
@@ -89,3 +122,49 @@ pub fn get_allowed_transitions(status: &SubscriptionStatus) -> &'static [Subscri
 pub fn can_transition(from: &SubscriptionStatus, to: &SubscriptionStatus) -> bool {
     validate_status_transition(from, to).is_ok()
 }
+
+/// Subscription ids currently in `status`, in the order they entered it. Buckets
+/// live in persistent storage.
+pub fn get_status_bucket(env: &Env, status: &SubscriptionStatus) -> Vec<u32> {
+    archive::get(env, &DataKey::StatusBucket(status.clone())).unwrap_or(Vec::new(env))
+}
+
+/// Adds a subscription to its status bucket (used on creation).
+pub fn index_status(env: &Env, subscription_id: u32, status: &SubscriptionStatus) {
+    let mut ids = get_status_bucket(env, status);
+    ids.push_back(subscription_id);
+    archive::set(env, &DataKey::StatusBucket(status.clone()), &ids);
+}
+
+pub fn unindex_status(env: &Env, subscription_id: u32, status: &SubscriptionStatus) {
+    let mut ids = get_status_bucket(env, status);
+    if let Some(i) = ids.first_index_of(subscription_id) {
+        ids.remove(i);
+        let key = DataKey::StatusBucket(status.clone());
+        if ids.is_empty() {
+            archive::remove(env, &key);
+        } else {
+            archive::set(env, &key, &ids);
+        }
+    }
+}
+
+/// Validates and applies a status change to `sub`, moving it between status
+/// buckets. The caller persists `sub`.
+pub fn apply_transition(
+    env: &Env,
+    subscription_id: u32,
+    sub: &mut Subscription,
+    to: SubscriptionStatus,
+) -> Result<(), Error> {
+    validate_status_transition(&sub.status, &to)?;
+    if sub.status != to {
+        unindex_status(env, subscription_id, &sub.status);
+        index_status(env, subscription_id, &to);
+        if to == SubscriptionStatus::Cancelled {
+            record_cancellation(env, &sub.merchant)?;
+        }
+        sub.status = to;
+    }
+    Ok(())
+}
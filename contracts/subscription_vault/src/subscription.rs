@@ -1,12 +1,44 @@
-//! Subscription lifecycle: create, deposit.
+//! Subscription lifecycle: create, deposit, cancel (single and bulk), pause, resume,
+//! merge, expiry, account closure.
 //!
 //! **PRs that only change subscription lifecycle or billing should edit this file only.**
+//!
+//! # Account closure
+//!
+//! Once every subscription of a subscriber is terminal and emptied, and no credit or
+//! claim is held for them, `close_account` deletes their subscription records and
+//! every subscriber- or subscription-keyed entry that identifies them (indexes,
+//! settings, signing key). Each removed record leaves only a tombstone hash, so past
+//! receipts can still be matched against it. Per-period charge counters and merchant
+//! attestations, which carry no subscriber data, are kept.
 
+use crate::admin::{get_withdrawal_timelock, require_deposit_capacity};
+use crate::archive;
+use crate::charge_core::{
+    get_total_charged, record_charge_weight, refund_charged, refundable_charged,
+    release_cooling_off_hold, reset_charged_period,
+};
+use crate::claims::transfer_or_defer;
+use crate::credit::{remove_refund_split, split_refund};
+use crate::keeper::track_liabilities;
+use crate::merchant::{has_scope, require_min_amount, required_reserve, untag_subscription};
+use crate::org::{is_org_account, is_org_member, is_org_owner};
 use crate::queries::get_subscription;
-use crate::safe_math::{safe_add_balance, validate_non_negative};
-use crate::state_machine::validate_status_transition;
-use crate::types::{DataKey, Error, Subscription, SubscriptionStatus};
-use soroban_sdk::{Address, Env, Symbol, Vec};
+use crate::reports::record_new_subscription;
+use crate::safe_math::{safe_add_balance, safe_sub_balance, validate_non_negative};
+use crate::stake::require_merchant_staked;
+use crate::state_machine::{
+    apply_transition, index_status, unindex_status, validate_status_transition,
+};
+use crate::types::{
+    AccountClosedEvent, ApiKeyScope, AutoTopup, BulkCancelResult, CancellationQuote, DataKey,
+    DepositActivity, Error, FixedTerm, LedgerSchedule, MerchantAllowlist, PendingWithdrawal,
+    RoundUp, Subscription, SubscriptionLineage, SubscriptionRenewedEvent, SubscriptionStatus,
+    SubscriptionUpdate, SubscriptionsMergedEvent, MAX_APPROVED_MERCHANTS, MAX_LABEL_LEN,
+};
+use crate::webhooks::notify_cancelled;
+use soroban_sdk::xdr::ToXdr;
+use soroban_sdk::{symbol_short, Address, Bytes, BytesN, Env, String, Symbol, Vec};
 
 pub fn next_id(env: &Env) -> u32 {
     let key = Symbol::new(env, "next_id");
@@ -15,6 +47,10 @@ pub fn next_id(env: &Env) -> u32 {
     id
 }
 
+/// Creates a subscription for `subscriber` and, when `initial_deposit` is non-zero,
+/// funds it through the regular deposit path (so `min_topup` and the deposit rate
+/// limit apply). With `charge_immediately`, the first period is charged in the same
+/// call and the schedule runs from now; if it cannot be paid, nothing is created.
 pub fn do_create_subscription(
     env: &Env,
     subscriber: Address,
@@ -22,21 +58,237 @@ pub fn do_create_subscription(
     amount: i128,
     interval_seconds: u64,
     usage_enabled: bool,
+    label: Option<String>,
+    initial_deposit: i128,
+    charge_immediately: bool,
+) -> Result<u32, Error> {
+    subscriber.require_auth();
+    let id = create_subscription_record(
+        env,
+        subscriber.clone(),
+        merchant,
+        amount,
+        interval_seconds,
+        usage_enabled,
+        label,
+    )?;
+    if initial_deposit != 0 {
+        apply_deposit(env, id, subscriber, initial_deposit, false)?;
+    }
+    if charge_immediately {
+        crate::charge_core::charge_first(env, id)?;
+    }
+    Ok(id)
+}
+
+const KEY_PULL: Symbol = symbol_short!("pull");
+const KEY_FALLBACK: Symbol = symbol_short!("fallback");
+
+/// Whether the subscription was created pull-billed, see `charge_core`.
+pub fn is_pull_billing(env: &Env, subscription_id: u32) -> bool {
+    env.storage().instance().has(&(KEY_PULL, subscription_id))
+}
+
+/// Whether the subscriber turned on wallet fallback, see `charge_core`.
+pub fn is_wallet_fallback(env: &Env, subscription_id: u32) -> bool {
+    env.storage()
+        .instance()
+        .has(&(KEY_FALLBACK, subscription_id))
+}
+
+/// Whether charges pull shortfalls from the subscriber's wallet: pull-billed or
+/// wallet fallback on.
+pub fn pulls_from_wallet(env: &Env, subscription_id: u32) -> bool {
+    is_pull_billing(env, subscription_id) || is_wallet_fallback(env, subscription_id)
+}
+
+/// Create a subscription billed by pulling each charge from the subscriber's wallet
+/// against a token allowance granted to the vault, instead of a prepaid balance.
+/// Auth: subscriber.
+pub fn do_create_pull_subscription(
+    env: &Env,
+    subscriber: Address,
+    merchant: Address,
+    amount: i128,
+    interval_seconds: u64,
+    label: Option<String>,
 ) -> Result<u32, Error> {
     subscriber.require_auth();
+    let id = create_subscription_record(
+        env,
+        subscriber,
+        merchant,
+        amount,
+        interval_seconds,
+        false,
+        label,
+    )?;
+    env.storage().instance().set(&(KEY_PULL, id), &true);
+    Ok(id)
+}
+
+/// Turn wallet fallback on or off for a subscription. Auth: subscriber.
+///
+/// While on, an interval charge the prepaid balance cannot cover pulls the
+/// shortfall from the subscriber's wallet against the vault's token allowance
+/// before the subscription is treated as underfunded. Pull-billed subscriptions
+/// always pull, whatever this setting.
+pub fn do_set_wallet_fallback(
+    env: &Env,
+    subscription_id: u32,
+    subscriber: Address,
+    enabled: bool,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+    let sub = get_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    if enabled {
+        env.storage()
+            .instance()
+            .set(&(KEY_FALLBACK, subscription_id), &true);
+    } else {
+        env.storage()
+            .instance()
+            .remove(&(KEY_FALLBACK, subscription_id));
+    }
+    env.events().publish(
+        (Symbol::new(env, "wallet_fallback_set"), subscription_id),
+        enabled,
+    );
+    Ok(())
+}
+
+const KEY_SUB_KEY: Symbol = symbol_short!("skey");
+
+/// Deterministic key of a subscription created with `salt`, computable off-chain
+/// before the creation confirms: `sha256(xdr((vault, merchant, subscriber, salt)))`.
+pub fn derive_subscription_key(
+    env: &Env,
+    merchant: &Address,
+    subscriber: &Address,
+    salt: &BytesN<32>,
+) -> BytesN<32> {
+    let preimage = (
+        env.current_contract_address(),
+        merchant.clone(),
+        subscriber.clone(),
+        salt.clone(),
+    )
+        .to_xdr(env);
+    env.crypto().sha256(&preimage).into()
+}
+
+/// Id of the subscription created under `key`. A key stays taken after its
+/// subscription is pruned, so it never refers to another subscription.
+pub fn get_subscription_id_by_key(env: &Env, key: BytesN<32>) -> Result<u32, Error> {
+    env.storage()
+        .instance()
+        .get(&(KEY_SUB_KEY, key))
+        .ok_or(Error::NotFound)
+}
+
+/// [`do_create_subscription`] under the key derived from `salt`. Fails with
+/// `SubscriptionKeyTaken` if the subscriber already used `salt` with this merchant.
+pub fn do_create_subscription_with_salt(
+    env: &Env,
+    subscriber: Address,
+    merchant: Address,
+    amount: i128,
+    interval_seconds: u64,
+    usage_enabled: bool,
+    label: Option<String>,
+    initial_deposit: i128,
+    charge_immediately: bool,
+    salt: BytesN<32>,
+) -> Result<u32, Error> {
+    let key = derive_subscription_key(env, &merchant, &subscriber, &salt);
+    if env.storage().instance().has(&(KEY_SUB_KEY, key.clone())) {
+        return Err(Error::SubscriptionKeyTaken);
+    }
+    let id = do_create_subscription(
+        env,
+        subscriber,
+        merchant,
+        amount,
+        interval_seconds,
+        usage_enabled,
+        label,
+        initial_deposit,
+        charge_immediately,
+    )?;
+    env.storage()
+        .instance()
+        .set(&(KEY_SUB_KEY, key.clone()), &id);
+    env.events()
+        .publish((Symbol::new(env, "subscription_keyed"), id), key);
+    Ok(id)
+}
+
+/// Validates and stores a new subscription. Callers are responsible for the
+/// subscriber's authorization (Soroban auth or a verified off-chain signature).
+pub fn create_subscription_record(
+    env: &Env,
+    subscriber: Address,
+    merchant: Address,
+    amount: i128,
+    interval_seconds: u64,
+    usage_enabled: bool,
+    label: Option<String>,
+) -> Result<u32, Error> {
     validate_non_negative(amount)?;
+    require_min_amount(env, &merchant, amount)?;
+    validate_label(&label)?;
+    require_merchant_approved(env, &subscriber, &merchant)?;
+    require_merchant_staked(env, &merchant)?;
     let sub = Subscription {
-        subscriber: subscriber.clone(),
-        merchant: merchant.clone(),
+        subscriber,
+        merchant,
         amount,
         interval_seconds,
         last_payment_timestamp: env.ledger().timestamp(),
         status: SubscriptionStatus::Active,
         prepaid_balance: 0i128,
         usage_enabled,
+        label,
     };
+    let id = insert_subscription(env, &sub)?;
+    crate::merchant::record_terms_at_creation(env, id, &sub.merchant);
+    Ok(id)
+}
+
+const KEY_SUBSCRIBER_SUBS: Symbol = symbol_short!("ssubs");
+
+/// Ids of every subscription the subscriber owns (any status), ascending. Entries
+/// are only dropped when the record itself is deleted by `close_account`.
+pub fn get_subscriber_subscription_ids(env: &Env, subscriber: &Address) -> Vec<u32> {
+    env.storage()
+        .instance()
+        .get(&(KEY_SUBSCRIBER_SUBS, subscriber.clone()))
+        .unwrap_or(Vec::new(env))
+}
+
+/// Stores a new subscription under a fresh id and indexes it by status, creation
+/// day, merchant and subscriber.
+fn insert_subscription(env: &Env, sub: &Subscription) -> Result<u32, Error> {
     let id = next_id(env);
-    env.storage().instance().set(&id, &sub);
+    env.storage().instance().set(&id, sub);
+    env.storage()
+        .instance()
+        .set(&DataKey::SubToken(id), &crate::admin::get_token(env)?);
+    let created_at = env.ledger().timestamp();
+    env.storage()
+        .instance()
+        .set(&DataKey::SubCreatedAt(id), &created_at);
+
+    index_status(env, id, &sub.status);
+
+    // Maintain creation-day → subscription-ID index
+    let key = DataKey::CreatedDayBucket(created_at / SECONDS_PER_DAY);
+    let mut ids: Vec<u32> = archive::get(env, &key).unwrap_or(Vec::new(env));
+    ids.push_back(id);
+    archive::set(env, &key, &ids);
 
     // Maintain merchant → subscription-ID index
     let key = DataKey::MerchantSubs(sub.merchant.clone());
@@ -44,9 +296,100 @@ pub fn do_create_subscription(
     ids.push_back(id);
     env.storage().instance().set(&key, &ids);
 
+    // Maintain subscriber → subscription-ID index
+    let mut ids = get_subscriber_subscription_ids(env, &sub.subscriber);
+    ids.push_back(id);
+    env.storage()
+        .instance()
+        .set(&(KEY_SUBSCRIBER_SUBS, sub.subscriber.clone()), &ids);
+    record_new_subscription(env, &sub.merchant, &sub.subscriber)?;
+
     Ok(id)
 }
 
+/// Settlement token a subscription was funded in.
+///
+/// Fixed at creation so balances stay accounted per token across token rotations;
+/// subscriptions created before per-subscription tracking use the current token.
+pub fn subscription_token(env: &Env, subscription_id: u32) -> Result<Address, Error> {
+    match env
+        .storage()
+        .instance()
+        .get(&DataKey::SubToken(subscription_id))
+    {
+        Some(token) => Ok(token),
+        None => crate::admin::get_token(env),
+    }
+}
+
+fn validate_label(label: &Option<String>) -> Result<(), Error> {
+    match label {
+        Some(l) if l.len() > MAX_LABEL_LEN => Err(Error::InvalidLabel),
+        _ => Ok(()),
+    }
+}
+
+/// Set or clear the subscription's display label. Auth: subscriber.
+pub fn do_set_subscription_label(
+    env: &Env,
+    subscription_id: u32,
+    subscriber: Address,
+    label: Option<String>,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+    let mut sub = get_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    validate_label(&label)?;
+    sub.label = label;
+    env.storage().instance().set(&subscription_id, &sub);
+    Ok(())
+}
+
+const KEY_DEPOSIT_ACTIVITY: Symbol = symbol_short!("dep");
+pub const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Enforces the admin deposit rate limit and records this deposit.
+fn check_deposit_rate_limit(env: &Env, subscription_id: u32) -> Result<(), Error> {
+    let limit = crate::admin::get_deposit_rate_limit(env);
+    if limit.min_interval_seconds == 0 && limit.max_per_day == 0 {
+        return Ok(());
+    }
+
+    let now = env.ledger().timestamp();
+    let day_index = now / SECONDS_PER_DAY;
+    let key = (KEY_DEPOSIT_ACTIVITY, subscription_id);
+    let mut activity = match env.storage().instance().get::<_, DepositActivity>(&key) {
+        Some(a) => {
+            if limit.min_interval_seconds > 0
+                && now
+                    < a.last_deposit_timestamp
+                        .saturating_add(limit.min_interval_seconds)
+            {
+                return Err(Error::RateLimited);
+            }
+            a
+        }
+        None => DepositActivity {
+            last_deposit_timestamp: now,
+            day_index,
+            count: 0,
+        },
+    };
+    if activity.day_index != day_index {
+        activity.day_index = day_index;
+        activity.count = 0;
+    }
+    if limit.max_per_day > 0 && activity.count >= limit.max_per_day {
+        return Err(Error::RateLimited);
+    }
+    activity.count += 1;
+    activity.last_deposit_timestamp = now;
+    env.storage().instance().set(&key, &activity);
+    Ok(())
+}
+
 pub fn do_deposit_funds(
     env: &Env,
     subscription_id: u32,
@@ -54,7 +397,20 @@ pub fn do_deposit_funds(
     amount: i128,
 ) -> Result<(), Error> {
     subscriber.require_auth();
+    apply_deposit(env, subscription_id, subscriber, amount, false)
+}
 
+/// Credits a deposit of `amount` paid by `subscriber` to the subscription. The tokens
+/// are pulled with `transfer_from` against the vault's allowance when
+/// `via_allowance` is set (no Soroban auth from the payer), else with `transfer`.
+/// Callers are responsible for authorization.
+pub fn apply_deposit(
+    env: &Env,
+    subscription_id: u32,
+    subscriber: Address,
+    amount: i128,
+    via_allowance: bool,
+) -> Result<(), Error> {
     let min_topup: i128 = crate::admin::get_min_topup(env)?;
     if amount < min_topup {
         return Err(Error::BelowMinimumTopup);
@@ -62,20 +418,159 @@ pub fn do_deposit_funds(
     validate_non_negative(amount)?;
 
     let mut sub = get_subscription(env, subscription_id)?;
+    if is_locked(env, &sub.subscriber) {
+        return Err(Error::SubscriberLocked);
+    }
+    // Org-owned subscriptions are funded by the org or its members only, so outsiders
+    // cannot use up the deposit rate limit.
+    if subscriber != sub.subscriber
+        && is_org_account(env, &sub.subscriber)
+        && !is_org_member(env, &sub.subscriber, &subscriber)
+    {
+        return Err(Error::Unauthorized);
+    }
+    check_deposit_rate_limit(env, subscription_id)?;
+    let token_addr = subscription_token(env, subscription_id)?;
+    require_deposit_capacity(
+        env,
+        Some(subscription_id),
+        &token_addr,
+        Some(sub.prepaid_balance),
+        amount,
+    )?;
     sub.prepaid_balance = safe_add_balance(sub.prepaid_balance, amount)?;
-    let token_addr: Address = env
-        .storage()
-        .instance()
-        .get(&Symbol::new(env, "token"))
-        .ok_or(Error::NotFound)?;
     let token_client = soroban_sdk::token::Client::new(env, &token_addr);
 
-    token_client.transfer(&subscriber, &env.current_contract_address(), &amount);
+    let vault = env.current_contract_address();
+    if via_allowance {
+        token_client.transfer_from(&vault, &subscriber, &vault, &amount);
+    } else {
+        token_client.transfer(&subscriber, &vault, &amount);
+    }
+    track_liabilities(env, &token_addr, amount);
     env.storage().instance().set(&subscription_id, &sub);
     env.events().publish(
         (Symbol::new(env, "deposited"), subscription_id),
         (subscriber, amount, sub.prepaid_balance),
     );
+
+    let reserve = required_reserve(env, &sub)?;
+    if sub.prepaid_balance < reserve {
+        env.events().publish(
+            (Symbol::new(env, "reserve_warning"), subscription_id),
+            (sub.prepaid_balance, reserve),
+        );
+    }
+    Ok(())
+}
+
+const KEY_AUTO_TOPUP: Symbol = symbol_short!("autotop");
+
+pub fn get_auto_topup(env: &Env, subscription_id: u32) -> Option<AutoTopup> {
+    env.storage()
+        .instance()
+        .get(&(KEY_AUTO_TOPUP, subscription_id))
+}
+
+/// Set or clear (`None`) the subscription's auto-top-up rule. Auth: subscriber.
+///
+/// The subscriber must keep a token allowance for the vault covering the refills.
+pub fn do_set_auto_topup(
+    env: &Env,
+    subscription_id: u32,
+    subscriber: Address,
+    rule: Option<AutoTopup>,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+    let sub = get_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    let key = (KEY_AUTO_TOPUP, subscription_id);
+    match &rule {
+        Some(rule) => {
+            validate_non_negative(rule.threshold)?;
+            if rule.refill <= 0 {
+                return Err(Error::InvalidAmount);
+            }
+            env.storage().instance().set(&key, rule);
+        }
+        None => env.storage().instance().remove(&key),
+    }
+    env.events()
+        .publish((Symbol::new(env, "auto_topup_set"), subscription_id), rule);
+    Ok(())
+}
+
+/// Deposit the rule's refill from the subscriber's wallet, pulled against the
+/// vault's allowance, while the prepaid balance is below the threshold.
+/// Permissionless. Fails with `AboveThreshold` at or above the threshold.
+///
+/// The refill is a regular deposit: `min_topup`, deposit caps and the deposit rate
+/// limit apply.
+pub fn do_execute_auto_topup(env: &Env, subscription_id: u32) -> Result<i128, Error> {
+    let rule = get_auto_topup(env, subscription_id).ok_or(Error::NotFound)?;
+    let sub = get_subscription(env, subscription_id)?;
+    if matches!(
+        sub.status,
+        SubscriptionStatus::Cancelled | SubscriptionStatus::Completed | SubscriptionStatus::Expired
+    ) {
+        return Err(Error::NotActive);
+    }
+    if sub.prepaid_balance >= rule.threshold {
+        return Err(Error::AboveThreshold);
+    }
+    apply_deposit(env, subscription_id, sub.subscriber, rule.refill, true)?;
+    env.events().publish(
+        (Symbol::new(env, "auto_topup"), subscription_id),
+        rule.refill,
+    );
+    Ok(rule.refill)
+}
+
+/// Withdraw prepaid funds above the merchant's reserve from a live subscription.
+/// Auth: subscriber.
+pub fn do_withdraw_excess(
+    env: &Env,
+    subscription_id: u32,
+    subscriber: Address,
+    amount: i128,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+    if amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+
+    let mut sub = get_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    if is_locked(env, &sub.subscriber) {
+        return Err(Error::SubscriberLocked);
+    }
+    if matches!(
+        sub.status,
+        SubscriptionStatus::Cancelled | SubscriptionStatus::Completed | SubscriptionStatus::Expired
+    ) {
+        // Terminal subscriptions use withdraw_subscriber_funds.
+        return Err(Error::NotActive);
+    }
+
+    let remaining = safe_sub_balance(sub.prepaid_balance, amount)
+        .map_err(|_| Error::InsufficientPrepaidBalance)?;
+    if remaining < required_reserve(env, &sub)? {
+        return Err(Error::BelowReserve);
+    }
+    consume_withdrawal_timelock(env, subscription_id, amount)?;
+    sub.prepaid_balance = remaining;
+    env.storage().instance().set(&subscription_id, &sub);
+
+    let token_addr = subscription_token(env, subscription_id)?;
+    transfer_or_defer(env, &token_addr, &subscriber, amount)?;
+    env.events().publish(
+        (Symbol::new(env, "excess_withdrawn"), subscription_id),
+        (amount, remaining),
+    );
     Ok(())
 }
 
@@ -86,16 +581,168 @@ pub fn do_cancel_subscription(
 ) -> Result<(), Error> {
     authorizer.require_auth();
 
-    let mut sub = get_subscription(env, subscription_id)?;
+    let sub = get_subscription(env, subscription_id)?;
 
-    if authorizer != sub.subscriber && authorizer != sub.merchant {
+    if authorizer != sub.subscriber
+        && authorizer != sub.merchant
+        && !has_scope(env, &sub.merchant, &authorizer, ApiKeyScope::Refunds)
+        && !is_org_owner(env, &sub.subscriber, &authorizer)
+    {
         return Err(Error::Unauthorized);
     }
 
+    apply_cancel(env, subscription_id, sub)
+}
+
+/// Moves an already-authorized subscription to `Cancelled`, applying any
+/// cooling-off refund.
+pub fn apply_cancel(env: &Env, subscription_id: u32, mut sub: Subscription) -> Result<(), Error> {
     validate_status_transition(&sub.status, &SubscriptionStatus::Cancelled)?;
-    sub.status = SubscriptionStatus::Cancelled;
+    if sub.status != SubscriptionStatus::Cancelled && in_cooling_off(env, subscription_id) {
+        // Statutory-style cooling-off: refund everything charged so far,
+        // regardless of any merchant refund policy and without the external refund
+        // fee. Charges made inside the window are still held by the vault, so they
+        // are always refunded in full. Refunds are split between the subscriber's
+        // credit wallet and their address per their refund split.
+        let charged = get_total_charged(env, subscription_id);
+        let refund = refund_charged(env, subscription_id, &sub, charged, false)?;
+        if refund > 0 {
+            env.events().publish(
+                (Symbol::new(env, "cooling_off_refund"), subscription_id),
+                refund,
+            );
+        }
+    } else if sub.status != SubscriptionStatus::Cancelled {
+        release_cooling_off_hold(env, subscription_id, &sub)?;
+    }
+    if sub.status != SubscriptionStatus::Cancelled {
+        // The subscriber's weight follows live spend: what is left charged on a
+        // cancelled subscription stops counting.
+        let charged = get_total_charged(env, subscription_id);
+        record_charge_weight(env, &sub, -charged, 0)?;
+    }
+    apply_transition(
+        env,
+        subscription_id,
+        &mut sub,
+        SubscriptionStatus::Cancelled,
+    )?;
 
     env.storage().instance().set(&subscription_id, &sub);
+    notify_cancelled(env, &sub.merchant, subscription_id);
+    Ok(())
+}
+
+/// What the subscriber would get back if the subscription were cancelled now,
+/// following [`apply_cancel`].
+///
+/// Inside the cooling-off window everything charged so far is refunded without the
+/// external refund fee, split per the subscriber's refund split: charges made in the
+/// window in full, earlier ones as far as they are still pending in the merchant's
+/// payout queue. The prepaid balance is returned in full by
+/// `withdraw_subscriber_funds` after cancelling. There is no early-termination fee
+/// and the current period is not prorated: outside the cooling-off window the
+/// merchant keeps what was charged.
+pub fn get_cancellation_quote(env: &Env, subscription_id: u32) -> Result<CancellationQuote, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    validate_status_transition(&sub.status, &SubscriptionStatus::Cancelled)?;
+    let cooling_off =
+        sub.status != SubscriptionStatus::Cancelled && in_cooling_off(env, subscription_id);
+    let refund: i128 = if cooling_off {
+        let token = subscription_token(env, subscription_id)?;
+        refundable_charged(env, subscription_id, &sub.merchant, &token, i128::MAX)?
+    } else {
+        0
+    };
+    let (refund_to_wallet, refund_to_external, refund_fee) =
+        split_refund(env, &sub.subscriber, refund, false)?;
+    Ok(CancellationQuote {
+        subscription_id,
+        prepaid_balance: sub.prepaid_balance,
+        in_cooling_off: cooling_off,
+        refund,
+        refund_to_wallet,
+        refund_to_external,
+        refund_fee,
+        total_returned: safe_add_balance(
+            sub.prepaid_balance,
+            refund_to_wallet + refund_to_external,
+        )?,
+    })
+}
+
+/// Maximum number of subscription ids scanned by one `cancel_all_subscriptions` call.
+pub const MAX_BULK_CANCEL_SCAN: u32 = 100;
+
+/// Cancels every non-cancelled subscription owned by `subscriber`, scanning ids
+/// from `cursor` (inclusive) for at most `limit` ids (capped at
+/// [`MAX_BULK_CANCEL_SCAN`]). Continue with the returned `next_cursor` while
+/// `has_next` is true.
+pub fn do_cancel_all_subscriptions(
+    env: &Env,
+    subscriber: Address,
+    cursor: u32,
+    limit: u32,
+) -> Result<BulkCancelResult, Error> {
+    subscriber.require_auth();
+
+    let next_id: u32 = env
+        .storage()
+        .instance()
+        .get(&Symbol::new(env, "next_id"))
+        .unwrap_or(0);
+    let scan = limit.min(MAX_BULK_CANCEL_SCAN);
+    let end = cursor.saturating_add(scan).min(next_id);
+
+    let mut cancelled_ids = Vec::new(env);
+    let mut id = cursor;
+    while id < end {
+        if let Ok(sub) = get_subscription(env, id) {
+            if sub.subscriber == subscriber
+                && !matches!(
+                    sub.status,
+                    SubscriptionStatus::Cancelled
+                        | SubscriptionStatus::Completed
+                        | SubscriptionStatus::Expired
+                )
+            {
+                apply_cancel(env, id, sub)?;
+                cancelled_ids.push_back(id);
+            }
+        }
+        id += 1;
+    }
+
+    Ok(BulkCancelResult {
+        cancelled_ids,
+        next_cursor: end.max(cursor),
+        has_next: end < next_id,
+    })
+}
+
+/// True if the subscription is still inside the admin-configured cooling-off window.
+pub fn in_cooling_off(env: &Env, subscription_id: u32) -> bool {
+    let window = crate::admin::get_cooling_off_period(env);
+    if window == 0 {
+        return false;
+    }
+    match env
+        .storage()
+        .instance()
+        .get::<_, u64>(&DataKey::SubCreatedAt(subscription_id))
+    {
+        Some(created_at) => env.ledger().timestamp() <= created_at.saturating_add(window),
+        None => false,
+    }
+}
+
+/// Pausing starts retainer billing and resuming restarts full billing, so only the
+/// subscription's subscriber or merchant may do either (`Unauthorized`).
+fn require_party(env: &Env, subscription_id: u32, authorizer: &Address) -> Result<(), Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    if *authorizer != sub.subscriber && *authorizer != sub.merchant {
+        return Err(Error::Unauthorized);
+    }
     Ok(())
 }
 
@@ -105,10 +752,14 @@ pub fn do_pause_subscription(
     authorizer: Address,
 ) -> Result<(), Error> {
     authorizer.require_auth();
+    require_party(env, subscription_id, &authorizer)?;
+    apply_pause(env, subscription_id)
+}
 
+/// Moves an already-authorized subscription to `Paused`.
+pub fn apply_pause(env: &Env, subscription_id: u32) -> Result<(), Error> {
     let mut sub = get_subscription(env, subscription_id)?;
-    validate_status_transition(&sub.status, &SubscriptionStatus::Paused)?;
-    sub.status = SubscriptionStatus::Paused;
+    apply_transition(env, subscription_id, &mut sub, SubscriptionStatus::Paused)?;
 
     env.storage().instance().set(&subscription_id, &sub);
     Ok(())
@@ -120,10 +771,14 @@ pub fn do_resume_subscription(
     authorizer: Address,
 ) -> Result<(), Error> {
     authorizer.require_auth();
+    require_party(env, subscription_id, &authorizer)?;
+    apply_resume(env, subscription_id)
+}
 
+/// Moves an already-authorized subscription back to `Active`.
+pub fn apply_resume(env: &Env, subscription_id: u32) -> Result<(), Error> {
     let mut sub = get_subscription(env, subscription_id)?;
-    validate_status_transition(&sub.status, &SubscriptionStatus::Active)?;
-    sub.status = SubscriptionStatus::Active;
+    apply_transition(env, subscription_id, &mut sub, SubscriptionStatus::Active)?;
 
     env.storage().instance().set(&subscription_id, &sub);
     Ok(())
@@ -138,32 +793,918 @@ pub fn do_withdraw_subscriber_funds(
 
     let mut sub = get_subscription(env, subscription_id)?;
 
-    if subscriber != sub.subscriber {
-        return Err(Error::Unauthorized);
-    }
+    authorize_withdrawal(env, &sub, &subscriber)?;
+    expire_if_due(env, subscription_id, &mut sub)?;
 
-    if sub.status != SubscriptionStatus::Cancelled {
+    if !matches!(
+        sub.status,
+        SubscriptionStatus::Cancelled | SubscriptionStatus::Completed | SubscriptionStatus::Expired
+    ) {
         return Err(Error::InvalidStatusTransition); // Or Unauthorized/InvalidState
     }
 
     let amount_to_refund = sub.prepaid_balance;
     if amount_to_refund > 0 {
+        consume_withdrawal_timelock(env, subscription_id, amount_to_refund)?;
         sub.prepaid_balance = 0;
         env.storage().instance().set(&subscription_id, &sub);
 
-        let token_addr: Address = env
-            .storage()
-            .instance()
-            .get(&Symbol::new(env, "token"))
-            .ok_or(Error::NotFound)?;
-        let token_client = soroban_sdk::token::Client::new(env, &token_addr);
-
-        token_client.transfer(
-            &env.current_contract_address(),
-            &subscriber,
-            &amount_to_refund,
-        );
+        let token_addr = subscription_token(env, subscription_id)?;
+
+        // `subscriber` is the validated recipient: the owner, or the recovery address if locked.
+        transfer_or_defer(env, &token_addr, &subscriber, amount_to_refund)?;
     }
 
     Ok(())
 }
+
+/// A locked subscriber's refunds may only be pulled by, and paid to, the
+/// recovery address.
+fn authorize_withdrawal(env: &Env, sub: &Subscription, caller: &Address) -> Result<(), Error> {
+    match get_recovery_address(env, &sub.subscriber) {
+        Some(recovery) if *caller != recovery => Err(Error::SubscriberLocked),
+        Some(_) => Ok(()),
+        None if *caller != sub.subscriber => Err(Error::Unauthorized),
+        None => Ok(()),
+    }
+}
+
+const KEY_PENDING_WITHDRAWAL: Symbol = symbol_short!("pwd");
+
+fn pending_withdrawal_key(subscription_id: u32) -> (Symbol, u32) {
+    (KEY_PENDING_WITHDRAWAL, subscription_id)
+}
+
+pub fn get_pending_withdrawal(env: &Env, subscription_id: u32) -> Option<PendingWithdrawal> {
+    env.storage()
+        .instance()
+        .get(&pending_withdrawal_key(subscription_id))
+}
+
+/// Announce a withdrawal above the time-lock threshold.
+///
+/// Auth: subscriber, or the recovery address while the subscriber is locked.
+/// Replaces any earlier request (restarting the delay). Returns the timestamp
+/// from which the withdrawal may be executed.
+pub fn do_request_withdrawal(
+    env: &Env,
+    subscription_id: u32,
+    caller: Address,
+    amount: i128,
+) -> Result<u64, Error> {
+    caller.require_auth();
+    if amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    let sub = get_subscription(env, subscription_id)?;
+    authorize_withdrawal(env, &sub, &caller)?;
+    if amount > sub.prepaid_balance {
+        return Err(Error::InsufficientPrepaidBalance);
+    }
+
+    let executable_at = env
+        .ledger()
+        .timestamp()
+        .saturating_add(get_withdrawal_timelock(env).delay_seconds);
+    let pending = PendingWithdrawal {
+        amount,
+        executable_at,
+    };
+    env.storage()
+        .instance()
+        .set(&pending_withdrawal_key(subscription_id), &pending);
+    env.events().publish(
+        (Symbol::new(env, "withdrawal_requested"), subscription_id),
+        pending,
+    );
+    Ok(executable_at)
+}
+
+/// Cancel an announced withdrawal, e.g. after spotting one the subscriber did not make.
+///
+/// Auth: subscriber, or the recovery address while the subscriber is locked.
+pub fn do_cancel_withdrawal_request(
+    env: &Env,
+    subscription_id: u32,
+    caller: Address,
+) -> Result<(), Error> {
+    caller.require_auth();
+    let sub = get_subscription(env, subscription_id)?;
+    authorize_withdrawal(env, &sub, &caller)?;
+    if get_pending_withdrawal(env, subscription_id).is_none() {
+        return Err(Error::NotFound);
+    }
+    env.storage()
+        .instance()
+        .remove(&pending_withdrawal_key(subscription_id));
+    env.events().publish(
+        (
+            Symbol::new(env, "withdrawal_request_cancelled"),
+            subscription_id,
+        ),
+        (),
+    );
+    Ok(())
+}
+
+/// Withdrawals at or below the threshold pass straight through. Larger ones need
+/// an announced request covering `amount` whose delay has elapsed; it is consumed.
+fn consume_withdrawal_timelock(env: &Env, subscription_id: u32, amount: i128) -> Result<(), Error> {
+    let timelock = get_withdrawal_timelock(env);
+    if timelock.threshold == 0 || amount <= timelock.threshold {
+        return Ok(());
+    }
+    let pending = get_pending_withdrawal(env, subscription_id).ok_or(Error::TimelockNotElapsed)?;
+    if amount > pending.amount {
+        return Err(Error::InvalidAmount);
+    }
+    if env.ledger().timestamp() < pending.executable_at {
+        return Err(Error::TimelockNotElapsed);
+    }
+    env.storage()
+        .instance()
+        .remove(&pending_withdrawal_key(subscription_id));
+    Ok(())
+}
+
+/// Returns the recovery address if `subscriber` has engaged the emergency lock.
+pub fn get_recovery_address(env: &Env, subscriber: &Address) -> Option<Address> {
+    env.storage()
+        .instance()
+        .get(&DataKey::SubscriberLock(subscriber.clone()))
+}
+
+pub fn is_locked(env: &Env, subscriber: &Address) -> bool {
+    env.storage()
+        .instance()
+        .has(&DataKey::SubscriberLock(subscriber.clone()))
+}
+
+/// Emergency lock for a (possibly) compromised subscriber key.
+///
+/// Freezes deposits into and withdrawals from the subscriber's subscriptions and
+/// redirects refunds to `recovery_address`. Only the recovery address can unlock,
+/// and an existing lock cannot be overwritten (so a thief cannot swap it).
+pub fn do_lock_subscriber(
+    env: &Env,
+    subscriber: Address,
+    recovery_address: Address,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+    if is_locked(env, &subscriber) {
+        return Err(Error::SubscriberLocked);
+    }
+    env.storage().instance().set(
+        &DataKey::SubscriberLock(subscriber.clone()),
+        &recovery_address,
+    );
+    env.events().publish(
+        (Symbol::new(env, "subscriber_locked"), subscriber),
+        recovery_address,
+    );
+    Ok(())
+}
+
+pub fn do_unlock_subscriber(
+    env: &Env,
+    subscriber: Address,
+    recovery_address: Address,
+) -> Result<(), Error> {
+    recovery_address.require_auth();
+    match get_recovery_address(env, &subscriber) {
+        Some(stored) if stored == recovery_address => {}
+        Some(_) => return Err(Error::Unauthorized),
+        None => return Err(Error::NotFound),
+    }
+    env.storage()
+        .instance()
+        .remove(&DataKey::SubscriberLock(subscriber.clone()));
+    env.events().publish(
+        (Symbol::new(env, "subscriber_unlocked"), subscriber),
+        recovery_address,
+    );
+    Ok(())
+}
+
+/// Switch a subscription between timestamp and ledger-sequence scheduling.
+/// Auth: merchant.
+///
+/// `Some(interval_ledgers)` bills every `interval_ledgers` ledgers counted from the
+/// current ledger; `None` returns to timestamp scheduling.
+pub fn do_set_ledger_schedule(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+    interval_ledgers: Option<u32>,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    let sub = get_subscription(env, subscription_id)?;
+    if merchant != sub.merchant {
+        return Err(Error::Unauthorized);
+    }
+
+    let key = DataKey::SubLedgerSchedule(subscription_id);
+    match interval_ledgers {
+        Some(0) => return Err(Error::InvalidAmount),
+        Some(interval_ledgers) => env.storage().instance().set(
+            &key,
+            &LedgerSchedule {
+                interval_ledgers,
+                last_payment_ledger: env.ledger().sequence(),
+            },
+        ),
+        None => env.storage().instance().remove(&key),
+    }
+    reset_charged_period(env, subscription_id);
+    env.events().publish(
+        (Symbol::new(env, "schedule_changed"), subscription_id),
+        interval_ledgers,
+    );
+    Ok(())
+}
+
+pub fn get_fixed_term(env: &Env, subscription_id: u32) -> Option<FixedTerm> {
+    env.storage()
+        .instance()
+        .get(&DataKey::SubFixedTerm(subscription_id))
+}
+
+/// Make a subscription fixed-term: it completes after `total_periods` interval charges.
+/// Auth: merchant.
+///
+/// Periods already charged under the current term are kept, so `total_periods`
+/// must exceed them (`InvalidAmount`). Terminal subscriptions are rejected (`NotActive`).
+pub fn do_set_fixed_term(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+    total_periods: u32,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    let sub = get_subscription(env, subscription_id)?;
+    if merchant != sub.merchant {
+        return Err(Error::Unauthorized);
+    }
+    if matches!(
+        sub.status,
+        SubscriptionStatus::Cancelled | SubscriptionStatus::Completed | SubscriptionStatus::Expired
+    ) {
+        return Err(Error::NotActive);
+    }
+
+    let periods_charged = get_fixed_term(env, subscription_id)
+        .map(|t| t.periods_charged)
+        .unwrap_or(0);
+    if total_periods <= periods_charged {
+        return Err(Error::InvalidAmount);
+    }
+    env.storage().instance().set(
+        &DataKey::SubFixedTerm(subscription_id),
+        &FixedTerm {
+            total_periods,
+            periods_charged,
+        },
+    );
+    env.events().publish(
+        (Symbol::new(env, "fixed_term_set"), subscription_id),
+        total_periods,
+    );
+    Ok(())
+}
+
+/// Counts one charged period against a fixed term, completing the subscription
+/// once all periods are charged. No-op for open-ended subscriptions.
+pub fn record_term_period(
+    env: &Env,
+    subscription_id: u32,
+    sub: &mut Subscription,
+) -> Result<(), Error> {
+    let mut term = match get_fixed_term(env, subscription_id) {
+        Some(term) => term,
+        None => return Ok(()),
+    };
+    term.periods_charged = term.periods_charged.saturating_add(1);
+    env.storage()
+        .instance()
+        .set(&DataKey::SubFixedTerm(subscription_id), &term);
+
+    if term.periods_charged >= term.total_periods {
+        apply_transition(env, subscription_id, sub, SubscriptionStatus::Completed)?;
+        env.storage().instance().set(&subscription_id, sub);
+        env.events().publish(
+            (Symbol::new(env, "completed"), subscription_id),
+            term.total_periods,
+        );
+    }
+    Ok(())
+}
+
+const KEY_UPDATE: Symbol = symbol_short!("upd");
+
+pub fn get_pending_update(env: &Env, subscription_id: u32) -> Option<SubscriptionUpdate> {
+    env.storage().instance().get(&(KEY_UPDATE, subscription_id))
+}
+
+pub fn remove_pending_update(env: &Env, subscription_id: u32) {
+    env.storage()
+        .instance()
+        .remove(&(KEY_UPDATE, subscription_id));
+}
+
+/// Propose new `amount` and `interval_seconds` for a live subscription. Auth: merchant.
+///
+/// Nothing changes until the subscriber accepts; a new proposal replaces the
+/// previous one, including an accepted one not yet applied. Seat-priced
+/// subscriptions change their amount through seats (`InvalidStatusTransition`).
+pub fn do_propose_update(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+    amount: i128,
+    interval_seconds: u64,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    let sub = get_subscription(env, subscription_id)?;
+    if merchant != sub.merchant {
+        return Err(Error::Unauthorized);
+    }
+    if matches!(
+        sub.status,
+        SubscriptionStatus::Cancelled | SubscriptionStatus::Completed | SubscriptionStatus::Expired
+    ) {
+        return Err(Error::NotActive);
+    }
+    if crate::seats::get_seat_plan(env, subscription_id).is_some() {
+        return Err(Error::InvalidStatusTransition);
+    }
+    validate_non_negative(amount)?;
+    if interval_seconds == 0 {
+        return Err(Error::InvalidAmount);
+    }
+    // Ledger-scheduled subscriptions bill on a ledger interval, not on seconds.
+    if interval_seconds != sub.interval_seconds
+        && env
+            .storage()
+            .instance()
+            .has(&DataKey::SubLedgerSchedule(subscription_id))
+    {
+        return Err(Error::InvalidAmount);
+    }
+    require_min_amount(env, &sub.merchant, amount)?;
+    let update = SubscriptionUpdate {
+        amount,
+        interval_seconds,
+        accepted: false,
+    };
+    env.storage()
+        .instance()
+        .set(&(KEY_UPDATE, subscription_id), &update);
+    env.events().publish(
+        (Symbol::new(env, "update_proposed"), subscription_id),
+        update,
+    );
+    Ok(())
+}
+
+/// Accept the merchant's proposed update. Auth: subscriber.
+///
+/// The subscriber restates the terms they agree to, so a proposal replaced in the
+/// meantime is not accepted by mistake (`TermsMismatch`). The update applies from
+/// the next cycle: the charge due under the current interval bills the new amount,
+/// and the new interval runs from that charge.
+pub fn do_accept_update(
+    env: &Env,
+    subscription_id: u32,
+    subscriber: Address,
+    amount: i128,
+    interval_seconds: u64,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+    let sub = get_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    let mut update = get_pending_update(env, subscription_id).ok_or(Error::NotFound)?;
+    if update.amount != amount || update.interval_seconds != interval_seconds {
+        return Err(Error::TermsMismatch);
+    }
+    update.accepted = true;
+    env.storage()
+        .instance()
+        .set(&(KEY_UPDATE, subscription_id), &update);
+    env.events().publish(
+        (Symbol::new(env, "update_accepted"), subscription_id),
+        update,
+    );
+    Ok(())
+}
+
+const KEY_EXPIRES_AT: Symbol = symbol_short!("expires");
+
+/// End date after which the subscription is no longer chargeable, if any.
+pub fn get_expires_at(env: &Env, subscription_id: u32) -> Option<u64> {
+    env.storage()
+        .instance()
+        .get(&(KEY_EXPIRES_AT, subscription_id))
+}
+
+/// Whether the subscription has reached its end date (whatever its status).
+pub fn is_past_expiry(env: &Env, subscription_id: u32) -> bool {
+    get_expires_at(env, subscription_id).is_some_and(|t| env.ledger().timestamp() >= t)
+}
+
+/// Set or clear (`None`) the subscription's end date. Auth: merchant.
+///
+/// From `expires_at` on, charges fail with `NotActive` and the subscription can be
+/// moved to `Expired`. The end date must be in the future (`InvalidAmount`).
+/// Terminal subscriptions are rejected (`NotActive`). To end after a number of
+/// charged intervals instead, use a fixed term.
+pub fn do_set_expiry(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+    expires_at: Option<u64>,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    let sub = get_subscription(env, subscription_id)?;
+    if merchant != sub.merchant {
+        return Err(Error::Unauthorized);
+    }
+    if matches!(
+        sub.status,
+        SubscriptionStatus::Cancelled | SubscriptionStatus::Completed | SubscriptionStatus::Expired
+    ) {
+        return Err(Error::NotActive);
+    }
+    let key = (KEY_EXPIRES_AT, subscription_id);
+    match expires_at {
+        Some(t) if t <= env.ledger().timestamp() => return Err(Error::InvalidAmount),
+        Some(t) => env.storage().instance().set(&key, &t),
+        None => env.storage().instance().remove(&key),
+    }
+    env.events().publish(
+        (Symbol::new(env, "expiry_set"), subscription_id),
+        expires_at,
+    );
+    Ok(())
+}
+
+/// Moves a live subscription past its end date to `Expired`. Returns whether it
+/// did; the prepaid balance stays withdrawable via `withdraw_subscriber_funds`.
+pub fn expire_if_due(
+    env: &Env,
+    subscription_id: u32,
+    sub: &mut Subscription,
+) -> Result<bool, Error> {
+    let expires_at = match get_expires_at(env, subscription_id) {
+        Some(t) if env.ledger().timestamp() >= t => t,
+        _ => return Ok(false),
+    };
+    if matches!(
+        sub.status,
+        SubscriptionStatus::Cancelled | SubscriptionStatus::Completed | SubscriptionStatus::Expired
+    ) {
+        return Ok(false);
+    }
+    apply_transition(env, subscription_id, sub, SubscriptionStatus::Expired)?;
+    env.storage().instance().set(&subscription_id, sub);
+    env.events()
+        .publish((Symbol::new(env, "expired"), subscription_id), expires_at);
+    Ok(true)
+}
+
+/// Expire a subscription that reached its end date. Permissionless, so keepers
+/// can settle expirations; fails with `InvalidStatusTransition` before the end
+/// date or for terminal subscriptions.
+pub fn do_expire_subscription(env: &Env, subscription_id: u32) -> Result<(), Error> {
+    let mut sub = get_subscription(env, subscription_id)?;
+    if !expire_if_due(env, subscription_id, &mut sub)? {
+        return Err(Error::InvalidStatusTransition);
+    }
+    Ok(())
+}
+
+pub fn get_lineage(env: &Env, subscription_id: u32) -> SubscriptionLineage {
+    env.storage()
+        .instance()
+        .get(&DataKey::SubLineage(subscription_id))
+        .unwrap_or(SubscriptionLineage {
+            predecessor: None,
+            successor: None,
+        })
+}
+
+/// Renew a `Completed` fixed-term subscription into a new `Active` successor.
+/// Auth: subscriber.
+///
+/// The successor copies merchant, interval, usage flag, label, and term length, and
+/// starts with a zero balance; the predecessor's leftover balance stays withdrawable.
+/// `new_amount` re-prices the successor; the merchant's terms hash is only carried
+/// over when the price is unchanged. Each subscription can be renewed once
+/// (`InvalidStatusTransition` otherwise, or if it is not `Completed`).
+pub fn do_renew_subscription(
+    env: &Env,
+    subscription_id: u32,
+    subscriber: Address,
+    new_amount: Option<i128>,
+) -> Result<u32, Error> {
+    subscriber.require_auth();
+    let prev = get_subscription(env, subscription_id)?;
+    if subscriber != prev.subscriber {
+        return Err(Error::Unauthorized);
+    }
+    if is_locked(env, &subscriber) {
+        return Err(Error::SubscriberLocked);
+    }
+    let mut lineage = get_lineage(env, subscription_id);
+    if prev.status != SubscriptionStatus::Completed || lineage.successor.is_some() {
+        return Err(Error::InvalidStatusTransition);
+    }
+    let amount = new_amount.unwrap_or(prev.amount);
+    if amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    require_min_amount(env, &prev.merchant, amount)?;
+    require_merchant_approved(env, &subscriber, &prev.merchant)?;
+
+    let successor = Subscription {
+        subscriber,
+        merchant: prev.merchant.clone(),
+        amount,
+        interval_seconds: prev.interval_seconds,
+        last_payment_timestamp: env.ledger().timestamp(),
+        status: SubscriptionStatus::Active,
+        prepaid_balance: 0,
+        usage_enabled: prev.usage_enabled,
+        label: prev.label.clone(),
+    };
+    let new_id = insert_subscription(env, &successor)?;
+
+    if let Some(term) = get_fixed_term(env, subscription_id) {
+        env.storage().instance().set(
+            &DataKey::SubFixedTerm(new_id),
+            &FixedTerm {
+                total_periods: term.total_periods,
+                periods_charged: 0,
+            },
+        );
+    }
+    if amount == prev.amount {
+        if let Some(hash) = env
+            .storage()
+            .instance()
+            .get::<_, soroban_sdk::BytesN<32>>(&DataKey::SubTermsHash(subscription_id))
+        {
+            env.storage()
+                .instance()
+                .set(&DataKey::SubTermsHash(new_id), &hash);
+        }
+    }
+
+    lineage.successor = Some(new_id);
+    env.storage()
+        .instance()
+        .set(&DataKey::SubLineage(subscription_id), &lineage);
+    env.storage().instance().set(
+        &DataKey::SubLineage(new_id),
+        &SubscriptionLineage {
+            predecessor: Some(subscription_id),
+            successor: None,
+        },
+    );
+
+    env.events().publish(
+        (Symbol::new(env, "renewed"), subscription_id),
+        SubscriptionRenewedEvent {
+            predecessor_id: subscription_id,
+            successor_id: new_id,
+            amount,
+        },
+    );
+    Ok(new_id)
+}
+
+const KEY_MERGED_INTO: Symbol = symbol_short!("merged");
+
+/// Subscription a cancelled duplicate was merged into, if any.
+pub fn get_merged_into(env: &Env, subscription_id: u32) -> Option<u32> {
+    env.storage()
+        .instance()
+        .get(&(KEY_MERGED_INTO, subscription_id))
+}
+
+/// Merge two live duplicate subscriptions of the same subscriber. Auth: subscriber.
+///
+/// Both must bill the same merchant the same amount at the same interval in the same
+/// token (`SubscriptionsMismatch` otherwise). The one with the older billing anchor
+/// (earlier `last_payment_timestamp`, lower id on ties) survives and receives the
+/// other's prepaid balance; the duplicate is cancelled without a cooling-off refund
+/// and records the survivor for [`get_merged_into`]. Returns the survivor id.
+pub fn do_merge_subscriptions(
+    env: &Env,
+    subscriber: Address,
+    id_a: u32,
+    id_b: u32,
+) -> Result<u32, Error> {
+    subscriber.require_auth();
+    if is_locked(env, &subscriber) {
+        return Err(Error::SubscriberLocked);
+    }
+    if id_a == id_b {
+        return Err(Error::SubscriptionsMismatch);
+    }
+    let a = get_subscription(env, id_a)?;
+    let b = get_subscription(env, id_b)?;
+    if a.subscriber != subscriber || b.subscriber != subscriber {
+        return Err(Error::Unauthorized);
+    }
+    for sub in [&a, &b] {
+        if matches!(
+            sub.status,
+            SubscriptionStatus::Cancelled
+                | SubscriptionStatus::Completed
+                | SubscriptionStatus::Expired
+        ) {
+            return Err(Error::InvalidStatusTransition);
+        }
+    }
+    if a.merchant != b.merchant
+        || a.amount != b.amount
+        || a.interval_seconds != b.interval_seconds
+        || subscription_token(env, id_a)? != subscription_token(env, id_b)?
+    {
+        return Err(Error::SubscriptionsMismatch);
+    }
+
+    let a_survives = (a.last_payment_timestamp, id_a) <= (b.last_payment_timestamp, id_b);
+    let (survivor_id, mut survivor, merged_id, mut merged) = if a_survives {
+        (id_a, a, id_b, b)
+    } else {
+        (id_b, b, id_a, a)
+    };
+
+    let moved = merged.prepaid_balance;
+    survivor.prepaid_balance = safe_add_balance(survivor.prepaid_balance, moved)?;
+    env.storage().instance().set(&survivor_id, &survivor);
+
+    merged.prepaid_balance = 0;
+    apply_transition(env, merged_id, &mut merged, SubscriptionStatus::Cancelled)?;
+    env.storage().instance().set(&merged_id, &merged);
+    env.storage()
+        .instance()
+        .set(&(KEY_MERGED_INTO, merged_id), &survivor_id);
+
+    env.events().publish(
+        (Symbol::new(env, "subscriptions_merged"), survivor_id),
+        SubscriptionsMergedEvent {
+            survivor_id,
+            merged_id,
+            balance_moved: moved,
+        },
+    );
+    Ok(survivor_id)
+}
+
+pub fn get_round_up(env: &Env, subscriber: &Address) -> RoundUp {
+    env.storage()
+        .instance()
+        .get(&DataKey::SubscriberRoundUp(subscriber.clone()))
+        .unwrap_or(RoundUp {
+            unit: 0,
+            total_donated: 0,
+        })
+}
+
+/// Opt into (or, with `unit == 0`, out of) rounding interval charges up to a
+/// multiple of `unit`. Auth: subscriber. The donated total is kept across changes.
+pub fn do_set_round_up(env: &Env, subscriber: Address, unit: i128) -> Result<(), Error> {
+    subscriber.require_auth();
+    if unit < 0 {
+        return Err(Error::InvalidAmount);
+    }
+    let mut round_up = get_round_up(env, &subscriber);
+    round_up.unit = unit;
+    env.storage()
+        .instance()
+        .set(&DataKey::SubscriberRoundUp(subscriber.clone()), &round_up);
+    env.events()
+        .publish((Symbol::new(env, "round_up_updated"), subscriber), unit);
+    Ok(())
+}
+
+pub fn get_merchant_allowlist(env: &Env, subscriber: &Address) -> MerchantAllowlist {
+    env.storage()
+        .instance()
+        .get(&DataKey::SubscriberAllowlist(subscriber.clone()))
+        .unwrap_or(MerchantAllowlist {
+            enforced: false,
+            merchants: Vec::new(env),
+        })
+}
+
+fn set_merchant_allowlist(env: &Env, subscriber: &Address, allowlist: &MerchantAllowlist) {
+    env.storage()
+        .instance()
+        .set(&DataKey::SubscriberAllowlist(subscriber.clone()), allowlist);
+}
+
+/// Fails with `MerchantNotApproved` if the subscriber enforces an allow-list
+/// that does not contain `merchant`.
+fn require_merchant_approved(
+    env: &Env,
+    subscriber: &Address,
+    merchant: &Address,
+) -> Result<(), Error> {
+    let allowlist = get_merchant_allowlist(env, subscriber);
+    if allowlist.enforced && !allowlist.merchants.contains(merchant) {
+        return Err(Error::MerchantNotApproved);
+    }
+    Ok(())
+}
+
+/// Turn deny-by-default merchant approval on or off. Auth: subscriber.
+///
+/// Protects against dApps creating unwanted subscriptions on the subscriber's
+/// behalf. Existing subscriptions are unaffected.
+pub fn do_set_allowlist_enforced(
+    env: &Env,
+    subscriber: Address,
+    enforced: bool,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+    let mut allowlist = get_merchant_allowlist(env, &subscriber);
+    allowlist.enforced = enforced;
+    set_merchant_allowlist(env, &subscriber, &allowlist);
+    env.events().publish(
+        (Symbol::new(env, "allowlist_enforced"), subscriber),
+        enforced,
+    );
+    Ok(())
+}
+
+/// Pre-approve a merchant (idempotent). Auth: subscriber.
+///
+/// At most [`MAX_APPROVED_MERCHANTS`] merchants may be approved (`LimitExceeded`).
+pub fn do_approve_merchant(env: &Env, subscriber: Address, merchant: Address) -> Result<(), Error> {
+    subscriber.require_auth();
+    let mut allowlist = get_merchant_allowlist(env, &subscriber);
+    if allowlist.merchants.contains(&merchant) {
+        return Ok(());
+    }
+    if allowlist.merchants.len() >= MAX_APPROVED_MERCHANTS {
+        return Err(Error::LimitExceeded);
+    }
+    allowlist.merchants.push_back(merchant.clone());
+    set_merchant_allowlist(env, &subscriber, &allowlist);
+    env.events().publish(
+        (Symbol::new(env, "merchant_approved"), subscriber),
+        merchant,
+    );
+    Ok(())
+}
+
+/// Remove a merchant from the subscriber's allow-list. Auth: subscriber.
+pub fn do_revoke_merchant(env: &Env, subscriber: Address, merchant: Address) -> Result<(), Error> {
+    subscriber.require_auth();
+    let mut allowlist = get_merchant_allowlist(env, &subscriber);
+    let index = allowlist
+        .merchants
+        .first_index_of(&merchant)
+        .ok_or(Error::NotFound)?;
+    allowlist.merchants.remove(index);
+    set_merchant_allowlist(env, &subscriber, &allowlist);
+    env.events()
+        .publish((Symbol::new(env, "merchant_revoked"), subscriber), merchant);
+    Ok(())
+}
+
+const KEY_TOMBSTONE: Symbol = symbol_short!("tomb");
+
+/// Hash of a subscription record removed by `close_account`. Tombstones live in
+/// persistent storage.
+pub fn get_tombstone(env: &Env, subscription_id: u32) -> Option<BytesN<32>> {
+    archive::get(env, &(KEY_TOMBSTONE, subscription_id))
+}
+
+/// Delete the subscriber's settled subscriptions and subscriber-keyed data, keeping
+/// only hashes. Auth: subscriber. Returns the number of subscriptions removed.
+pub fn do_close_account(env: &Env, subscriber: Address) -> Result<u32, Error> {
+    subscriber.require_auth();
+    if is_locked(env, &subscriber) {
+        return Err(Error::SubscriberLocked);
+    }
+
+    let mut owned: Vec<(u32, Subscription)> = Vec::new(env);
+    let mut tokens: Vec<Address> = Vec::new(env);
+    tokens.push_back(crate::admin::get_token(env)?);
+    for id in get_subscriber_subscription_ids(env, &subscriber).iter() {
+        if let Some(sub) = env.storage().instance().get::<u32, Subscription>(&id) {
+            let terminal = matches!(
+                sub.status,
+                SubscriptionStatus::Cancelled
+                    | SubscriptionStatus::Completed
+                    | SubscriptionStatus::Expired
+            );
+            if !terminal || sub.prepaid_balance != 0 {
+                return Err(Error::AccountNotSettled);
+            }
+            let token = subscription_token(env, id)?;
+            if !tokens.contains(&token) {
+                tokens.push_back(token);
+            }
+            owned.push_back((id, sub));
+        }
+    }
+    for token in tokens.iter() {
+        let claim: i128 = env
+            .storage()
+            .instance()
+            .get(&DataKey::Claim(subscriber.clone(), token.clone()))
+            .unwrap_or(0);
+        if crate::credit::get_credit_balance(env, &subscriber, &token) != 0 || claim != 0 {
+            return Err(Error::AccountNotSettled);
+        }
+    }
+
+    let mut history = Bytes::new(env);
+    for (id, sub) in owned.iter() {
+        let tombstone: BytesN<32> = env.crypto().sha256(&sub.clone().to_xdr(env)).into();
+        history.append(&tombstone.clone().into());
+        archive::set(env, &(KEY_TOMBSTONE, id), &tombstone);
+        remove_subscription_record(env, id, &sub);
+    }
+
+    let storage = env.storage().instance();
+    storage.remove(&DataKey::SubscriberAllowlist(subscriber.clone()));
+    crate::voting::remove_weight(env, &subscriber);
+    storage.remove(&DataKey::SubscriberRoundUp(subscriber.clone()));
+    storage.remove(&DataKey::SigningKey(subscriber.clone()));
+    storage.remove(&DataKey::OrgAccount(subscriber.clone()));
+    storage.remove(&(KEY_SUBSCRIBER_SUBS, subscriber.clone()));
+    crate::budget::remove_budget(env, &subscriber);
+    remove_refund_split(env, &subscriber);
+
+    let event = AccountClosedEvent {
+        subscriber: subscriber.clone(),
+        subscriptions_removed: owned.len(),
+        history_hash: env.crypto().sha256(&history).into(),
+    };
+    env.events()
+        .publish((Symbol::new(env, "account_closed"), subscriber), event);
+    Ok(owned.len())
+}
+
+/// Deletes a subscription record and every index and per-subscription entry that
+/// refers to it.
+fn remove_subscription_record(env: &Env, id: u32, sub: &Subscription) {
+    let storage = env.storage().instance();
+    unindex_status(env, id, &sub.status);
+
+    let key = DataKey::MerchantSubs(sub.merchant.clone());
+    let mut ids: Vec<u32> = storage.get(&key).unwrap_or(Vec::new(env));
+    if let Some(i) = ids.first_index_of(id) {
+        ids.remove(i);
+        storage.set(&key, &ids);
+    }
+    if let Some(created_at) = storage.get::<_, u64>(&DataKey::SubCreatedAt(id)) {
+        let key = DataKey::CreatedDayBucket(created_at / SECONDS_PER_DAY);
+        let mut ids: Vec<u32> = archive::get(env, &key).unwrap_or(Vec::new(env));
+        if let Some(i) = ids.first_index_of(id) {
+            ids.remove(i);
+            if ids.is_empty() {
+                archive::remove(env, &key);
+            } else {
+                archive::set(env, &key, &ids);
+            }
+        }
+    }
+    untag_subscription(env, &sub.merchant, id);
+    crate::plans::remove_plan_assignment(env, id);
+    crate::tiers::remove_support_tier(env, id);
+    crate::referrals::remove_subscription_referral(env, id);
+    crate::merchant::remove_accepted_terms(env, id);
+    crate::merchant::remove_test_mode(env, id);
+    storage.remove(&(KEY_EXPIRES_AT, id));
+    remove_pending_update(env, id);
+    storage.remove(&(KEY_PULL, id));
+    storage.remove(&(KEY_FALLBACK, id));
+    storage.remove(&(KEY_AUTO_TOPUP, id));
+    crate::fees::remove_subscription_fees(env, id);
+    crate::platforms::remove_subscription_cuts(env, id);
+    crate::charge_core::remove_charge_review(env, id);
+
+    storage.remove(&id);
+    storage.remove(&DataKey::SubCreatedAt(id));
+    storage.remove(&DataKey::SubTotalCharged(id));
+    storage.remove(&DataKey::SubToken(id));
+    storage.remove(&DataKey::SubTermsHash(id));
+    storage.remove(&DataKey::SubLedgerSchedule(id));
+    storage.remove(&DataKey::SubFixedTerm(id));
+    storage.remove(&DataKey::SubLineage(id));
+    storage.remove(&DataKey::SubPauseRetainer(id));
+    storage.remove(&DataKey::SubPauseRetainerOffer(id));
+    storage.remove(&DataKey::SubSeatPlan(id));
+    storage.remove(&(KEY_DEPOSIT_ACTIVITY, id));
+    storage.remove(&(KEY_MERGED_INTO, id));
+    storage.remove(&pending_withdrawal_key(id));
+}
@@ -0,0 +1,120 @@
+//! Charge-time tax (e.g. VAT) line items.
+//!
+//! **PRs that only change tax handling should edit this file only.**
+//!
+//! A merchant may configure a [`TaxConfig`]. Prices are tax-inclusive: on every
+//! charge, `rate_bps` of the charged amount is split off as a separate tax line item
+//! (reported in the `charged` and `tax_charged` events) and routed either straight to
+//! a tax recipient or into the merchant's tax sub-balance, which the merchant
+//! withdraws separately from its payouts.
+
+use crate::claims::transfer_or_defer;
+use crate::safe_math::{bps_fee, safe_add_balance, BPS_DENOMINATOR};
+use crate::types::{DataKey, Error, TaxChargedEvent, TaxConfig, TaxDestination};
+use soroban_sdk::{Address, Env, Symbol};
+
+pub fn get_tax_config(env: &Env, merchant: &Address) -> Option<TaxConfig> {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantTaxConfig(merchant.clone()))
+}
+
+/// Set or clear the merchant's tax configuration. Auth: merchant.
+pub fn do_set_tax_config(
+    env: &Env,
+    merchant: Address,
+    config: Option<TaxConfig>,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    let key = DataKey::MerchantTaxConfig(merchant.clone());
+    match &config {
+        Some(config) => {
+            if config.rate_bps as i128 > BPS_DENOMINATOR {
+                return Err(Error::InvalidAmount);
+            }
+            env.storage().instance().set(&key, config);
+        }
+        None => env.storage().instance().remove(&key),
+    }
+    env.events()
+        .publish((Symbol::new(env, "tax_config_set"), merchant), config);
+    Ok(())
+}
+
+pub fn get_tax_balance(env: &Env, merchant: &Address, token: &Address) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantTaxBalance(
+            merchant.clone(),
+            token.clone(),
+        ))
+        .unwrap_or(0)
+}
+
+/// Splits the merchant's tax off a charge of `amount` and routes it. Returns the tax.
+pub fn apply_tax(
+    env: &Env,
+    subscription_id: u32,
+    merchant: &Address,
+    token: &Address,
+    amount: i128,
+) -> Result<i128, Error> {
+    let config = match get_tax_config(env, merchant) {
+        Some(config) => config,
+        None => return Ok(0),
+    };
+    let tax = bps_fee(amount, config.rate_bps)?;
+    if tax == 0 {
+        return Ok(0);
+    }
+    match &config.destination {
+        TaxDestination::Recipient(recipient) => {
+            transfer_or_defer(env, token, recipient, tax)?;
+        }
+        TaxDestination::SubBalance => {
+            let balance = safe_add_balance(get_tax_balance(env, merchant, token), tax)?;
+            env.storage().instance().set(
+                &DataKey::MerchantTaxBalance(merchant.clone(), token.clone()),
+                &balance,
+            );
+        }
+    }
+    env.events().publish(
+        (Symbol::new(env, "tax_charged"), subscription_id),
+        TaxChargedEvent {
+            subscription_id,
+            merchant: merchant.clone(),
+            token: token.clone(),
+            gross: amount,
+            tax,
+            rate_bps: config.rate_bps,
+            destination: config.destination,
+        },
+    );
+    Ok(tax)
+}
+
+/// Withdraw the merchant's whole tax sub-balance in `token`. Auth: merchant.
+pub fn do_withdraw_tax_balance(
+    env: &Env,
+    merchant: Address,
+    token: Address,
+) -> Result<i128, Error> {
+    merchant.require_auth();
+    let amount = get_tax_balance(env, &merchant, &token);
+    if amount == 0 {
+        return Ok(0);
+    }
+    env.storage()
+        .instance()
+        .remove(&DataKey::MerchantTaxBalance(
+            merchant.clone(),
+            token.clone(),
+        ));
+    transfer_or_defer(env, &token, &merchant, amount)?;
+    env.events().publish(
+        (Symbol::new(env, "tax_withdrawn"), merchant),
+        (token, amount),
+    );
+    Ok(amount)
+}
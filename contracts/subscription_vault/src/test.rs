@@ -1,10 +1,17 @@
+// Batch-charge tests spell out `as u32` on subscription ids.
+#![allow(clippy::unnecessary_cast)]
+
+extern crate std;
+
 use crate::safe_math::*;
 use crate::{
-    can_transition, get_allowed_transitions, validate_status_transition, Error, RecoveryReason,
-    Subscription, SubscriptionStatus, SubscriptionVault, SubscriptionVaultClient,
+    can_transition, get_allowed_transitions, validate_status_transition, ApiKeyScope, Config,
+    DepositCaps, DepositRateLimit, Error, MerchantStakePolicy, PayoutRoute, RecoveryReason,
+    ScheduleBasis, SignedAction, SignedOp, Subscription, SubscriptionAgreement, SubscriptionStatus,
+    SubscriptionVault, SubscriptionVaultClient, TaxConfig, TaxDestination, WithdrawalTimelock,
 };
 use soroban_sdk::testutils::{Address as _, Events as _, Ledger as _};
-use soroban_sdk::{Address, Env, IntoVal, Vec as SorobanVec};
+use soroban_sdk::{Address, BytesN, Env, IntoVal, String, Symbol, Vec as SorobanVec};
 
 /// Baseline creation timestamp used by test helpers.
 const T0: u64 = 1_000;
@@ -167,16 +174,19 @@ fn test_can_transition_helper() {
 fn test_get_allowed_transitions() {
     // Active
     let active_targets = get_allowed_transitions(&SubscriptionStatus::Active);
-    assert_eq!(active_targets.len(), 3);
+    assert_eq!(active_targets.len(), 5);
     assert!(active_targets.contains(&SubscriptionStatus::Paused));
     assert!(active_targets.contains(&SubscriptionStatus::Cancelled));
     assert!(active_targets.contains(&SubscriptionStatus::InsufficientBalance));
+    assert!(active_targets.contains(&SubscriptionStatus::Completed));
+    assert!(active_targets.contains(&SubscriptionStatus::Expired));
 
     // Paused
     let paused_targets = get_allowed_transitions(&SubscriptionStatus::Paused);
-    assert_eq!(paused_targets.len(), 2);
+    assert_eq!(paused_targets.len(), 3);
     assert!(paused_targets.contains(&SubscriptionStatus::Active));
     assert!(paused_targets.contains(&SubscriptionStatus::Cancelled));
+    assert!(paused_targets.contains(&SubscriptionStatus::Expired));
 
     // Cancelled
     let cancelled_targets = get_allowed_transitions(&SubscriptionStatus::Cancelled);
@@ -184,9 +194,10 @@ fn test_get_allowed_transitions() {
 
     // InsufficientBalance
     let ib_targets = get_allowed_transitions(&SubscriptionStatus::InsufficientBalance);
-    assert_eq!(ib_targets.len(), 2);
+    assert_eq!(ib_targets.len(), 3);
     assert!(ib_targets.contains(&SubscriptionStatus::Active));
     assert!(ib_targets.contains(&SubscriptionStatus::Cancelled));
+    assert!(ib_targets.contains(&SubscriptionStatus::Expired));
 }
 
 // =============================================================================
@@ -227,6 +238,9 @@ fn create_test_subscription(
         &amount,
         &interval_seconds,
         &usage_enabled,
+        &None,
+        &0i128,
+        &false,
     );
 
     // Manually set status if not Active (bypassing state machine for test setup)
@@ -234,9 +248,9 @@ fn create_test_subscription(
     if status != SubscriptionStatus::Active {
         // We need to manipulate storage directly for test setup
         // This is a test-only pattern
-        let mut sub = client.get_subscription(&id);
-        sub.status = status;
         env.as_contract(&client.address, || {
+            let mut sub = crate::queries::get_subscription(env, id).unwrap();
+            sub.status = status;
             env.storage().instance().set(&id, &sub);
         });
     }
@@ -452,9 +466,9 @@ fn test_all_valid_transitions_coverage() {
             create_test_subscription(&env, &client, SubscriptionStatus::Active);
 
         // Simulate transition by updating storage directly
-        let mut sub = client.get_subscription(&id);
-        sub.status = SubscriptionStatus::InsufficientBalance;
         env.as_contract(&client.address, || {
+            let mut sub = crate::queries::get_subscription(&env, id).unwrap();
+            sub.status = SubscriptionStatus::InsufficientBalance;
             env.storage().instance().set(&id, &sub);
         });
 
@@ -497,9 +511,9 @@ fn test_all_valid_transitions_coverage() {
             create_test_subscription(&env, &client, SubscriptionStatus::Active);
 
         // Set to InsufficientBalance
-        let mut sub = client.get_subscription(&id);
-        sub.status = SubscriptionStatus::InsufficientBalance;
         env.as_contract(&client.address, || {
+            let mut sub = crate::queries::get_subscription(&env, id).unwrap();
+            sub.status = SubscriptionStatus::InsufficientBalance;
             env.storage().instance().set(&id, &sub);
         });
 
@@ -518,9 +532,9 @@ fn test_all_valid_transitions_coverage() {
             create_test_subscription(&env, &client, SubscriptionStatus::Active);
 
         // Set to InsufficientBalance
-        let mut sub = client.get_subscription(&id);
-        sub.status = SubscriptionStatus::InsufficientBalance;
         env.as_contract(&client.address, || {
+            let mut sub = crate::queries::get_subscription(&env, id).unwrap();
+            sub.status = SubscriptionStatus::InsufficientBalance;
             env.storage().instance().set(&id, &sub);
         });
 
@@ -554,9 +568,9 @@ fn test_invalid_insufficient_balance_to_paused() {
     let (id, subscriber, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
 
     // Set to InsufficientBalance
-    let mut sub = client.get_subscription(&id);
-    sub.status = SubscriptionStatus::InsufficientBalance;
     env.as_contract(&client.address, || {
+        let mut sub = crate::queries::get_subscription(&env, id).unwrap();
+        sub.status = SubscriptionStatus::InsufficientBalance;
         env.storage().instance().set(&id, &sub);
     });
 
@@ -577,6 +591,7 @@ fn test_subscription_struct_status_field() {
         status: SubscriptionStatus::Active,
         prepaid_balance: 500_000_000,
         usage_enabled: false,
+        label: None,
     };
     assert_eq!(sub.status, SubscriptionStatus::Active);
 }
@@ -867,14 +882,25 @@ fn test_cancel_subscription_by_subscriber() {
     let contract_id = env.register(SubscriptionVault, ());
     let client = SubscriptionVaultClient::new(&env, &contract_id);
 
-    let token = Address::generate(&env);
+    let token = env
+        .register_stellar_asset_contract_v2(Address::generate(&env))
+        .address();
     let admin = Address::generate(&env);
     let subscriber = Address::generate(&env);
     let merchant = Address::generate(&env);
 
     client.init(&token, &admin, &1_000_000);
 
-    let sub_id = client.create_subscription(&subscriber, &merchant, &1000, &86400, &true);
+    let sub_id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000,
+        &86400,
+        &true,
+        &None,
+        &0i128,
+        &false,
+    );
 
     client.cancel_subscription(&sub_id, &subscriber);
 
@@ -898,7 +924,9 @@ fn test_min_topup_below_threshold() {
     let contract_id = env.register(SubscriptionVault, ());
     let client = SubscriptionVaultClient::new(&env, &contract_id);
 
-    let token = Address::generate(&env);
+    let token = env
+        .register_stellar_asset_contract_v2(Address::generate(&env))
+        .address();
     let admin = Address::generate(&env);
     let subscriber = Address::generate(&env);
     let merchant = Address::generate(&env);
@@ -911,6 +939,9 @@ fn test_min_topup_below_threshold() {
         &min_topup,
         &(30 * 24 * 60 * 60),
         &false,
+        &None,
+        &0i128,
+        &false,
     );
 
     let result = client.try_deposit_funds(&sub_id, &subscriber, &4_999999);
@@ -941,6 +972,9 @@ fn test_min_topup_exactly_at_threshold() {
         &min_topup,
         &(30 * 24 * 60 * 60),
         &false,
+        &None,
+        &0i128,
+        &false,
     );
 
     let result = client.try_deposit_funds(&sub_id, &subscriber, &min_topup);
@@ -973,6 +1007,9 @@ fn test_min_topup_above_threshold() {
         &deposit_amount,
         &(30 * 24 * 60 * 60),
         &false,
+        &None,
+        &0i128,
+        &false,
     );
 
     let result = client.try_deposit_funds(&sub_id, &subscriber, &deposit_amount);
@@ -986,7 +1023,9 @@ fn test_set_min_topup_by_admin() {
     let contract_id = env.register(SubscriptionVault, ());
     let client = SubscriptionVaultClient::new(&env, &contract_id);
 
-    let token = Address::generate(&env);
+    let token = env
+        .register_stellar_asset_contract_v2(Address::generate(&env))
+        .address();
     let admin = Address::generate(&env);
     let initial_min = 1_000000i128;
     let new_min = 10_000000i128;
@@ -1008,7 +1047,9 @@ fn setup(env: &Env, interval: u64) -> (SubscriptionVaultClient<'_>, u32) {
     let contract_id = env.register(SubscriptionVault, ());
     let client = SubscriptionVaultClient::new(env, &contract_id);
 
-    let token = Address::generate(env);
+    let token = env
+        .register_stellar_asset_contract_v2(Address::generate(env))
+        .address();
     let admin = Address::generate(env);
     client.init(&token, &admin, &1_000000i128);
 
@@ -1022,12 +1063,15 @@ fn setup(env: &Env, interval: u64) -> (SubscriptionVaultClient<'_>, u32) {
         &10_000_000i128,
         &interval,
         &false, // usage_enabled
+        &None,
+        &0i128,
+        &false,
     );
 
     // Seed prepaid balance.
-    let mut sub = client.get_subscription(&id);
-    sub.prepaid_balance = PREPAID;
     env.as_contract(&contract_id, || {
+        let mut sub = crate::queries::get_subscription(env, id).unwrap();
+        sub.prepaid_balance = PREPAID;
         env.storage().instance().set(&id, &sub);
     });
 
@@ -1040,7 +1084,9 @@ fn setup_usage(env: &Env) -> (SubscriptionVaultClient<'_>, u32) {
     let contract_id = env.register(SubscriptionVault, ());
     let client = SubscriptionVaultClient::new(env, &contract_id);
 
-    let token = Address::generate(env);
+    let token = env
+        .register_stellar_asset_contract_v2(Address::generate(env))
+        .address();
     let admin = Address::generate(env);
     client.init(&token, &admin, &1_000000i128);
 
@@ -1054,12 +1100,15 @@ fn setup_usage(env: &Env) -> (SubscriptionVaultClient<'_>, u32) {
         &10_000_000i128,
         &INTERVAL,
         &true, // usage_enabled
+        &None,
+        &0i128,
+        &false,
     );
 
     // Seed prepaid balance by writing the subscription back with funds.
-    let mut sub = client.get_subscription(&id);
-    sub.prepaid_balance = PREPAID;
     env.as_contract(&contract_id, || {
+        let mut sub = crate::queries::get_subscription(env, id).unwrap();
+        sub.prepaid_balance = PREPAID;
         env.storage().instance().set(&id, &sub);
     });
 
@@ -1072,8 +1121,9 @@ fn test_usage_charge_debits_balance() {
     let env = Env::default();
     env.mock_all_auths();
     let (client, id) = setup_usage(&env);
+    let merchant = client.get_subscription(&id).merchant;
 
-    client.charge_usage(&id, &10_000_000i128);
+    client.charge_usage(&merchant, &id, &10_000_000i128);
 
     let sub = client.get_subscription(&id);
     assert_eq!(sub.prepaid_balance, PREPAID - 10_000_000);
@@ -1086,8 +1136,9 @@ fn test_usage_charge_drains_balance_to_insufficient() {
     let env = Env::default();
     env.mock_all_auths();
     let (client, id) = setup_usage(&env);
+    let merchant = client.get_subscription(&id).merchant;
 
-    client.charge_usage(&id, &PREPAID);
+    client.charge_usage(&merchant, &id, &PREPAID);
 
     let sub = client.get_subscription(&id);
     assert_eq!(sub.prepaid_balance, 0);
@@ -1101,8 +1152,9 @@ fn test_usage_charge_rejected_when_disabled() {
     env.mock_all_auths();
     // Use the regular setup helper which creates usage_enabled = false.
     let (client, id) = setup(&env, INTERVAL);
+    let merchant = client.get_subscription(&id).merchant;
 
-    let res = client.try_charge_usage(&id, &1_000_000i128);
+    let res = client.try_charge_usage(&merchant, &id, &1_000_000i128);
     assert_eq!(res, Err(Ok(Error::UsageNotEnabled)));
 }
 
@@ -1112,8 +1164,9 @@ fn test_usage_charge_rejected_insufficient_balance() {
     let env = Env::default();
     env.mock_all_auths();
     let (client, id) = setup_usage(&env);
+    let merchant = client.get_subscription(&id).merchant;
 
-    let res = client.try_charge_usage(&id, &(PREPAID + 1));
+    let res = client.try_charge_usage(&merchant, &id, &(PREPAID + 1));
     assert_eq!(res, Err(Ok(Error::InsufficientPrepaidBalance)));
 
     // Balance unchanged.
@@ -1127,11 +1180,12 @@ fn test_usage_charge_rejected_invalid_amount() {
     let env = Env::default();
     env.mock_all_auths();
     let (client, id) = setup_usage(&env);
+    let merchant = client.get_subscription(&id).merchant;
 
-    let res_zero = client.try_charge_usage(&id, &0i128);
+    let res_zero = client.try_charge_usage(&merchant, &id, &0i128);
     assert_eq!(res_zero, Err(Ok(Error::InvalidAmount)));
 
-    let res_neg = client.try_charge_usage(&id, &(-1i128));
+    let res_neg = client.try_charge_usage(&merchant, &id, &(-1i128));
     assert_eq!(res_neg, Err(Ok(Error::InvalidAmount)));
 
     // Balance unchanged.
@@ -1146,7 +1200,9 @@ fn test_set_min_topup_unauthorized() {
     let contract_id = env.register(SubscriptionVault, ());
     let client = SubscriptionVaultClient::new(&env, &contract_id);
 
-    let token = Address::generate(&env);
+    let token = env
+        .register_stellar_asset_contract_v2(Address::generate(&env))
+        .address();
     let admin = Address::generate(&env);
     let non_admin = Address::generate(&env);
     let min_topup = 1_000000i128;
@@ -1180,6 +1236,7 @@ fn test_compute_next_charge_info_active_subscription() {
         status: SubscriptionStatus::Active,
         prepaid_balance: 100_000_000i128,
         usage_enabled: false,
+        label: None,
     };
 
     let info = compute_next_charge_info(&subscription);
@@ -1210,6 +1267,7 @@ fn test_compute_next_charge_info_paused_subscription() {
         status: SubscriptionStatus::Paused,
         prepaid_balance: 50_000_000i128,
         usage_enabled: false,
+        label: None,
     };
 
     let info = compute_next_charge_info(&subscription);
@@ -1240,6 +1298,7 @@ fn test_compute_next_charge_info_cancelled_subscription() {
         status: SubscriptionStatus::Cancelled,
         prepaid_balance: 0i128,
         usage_enabled: false,
+        label: None,
     };
 
     let info = compute_next_charge_info(&subscription);
@@ -1270,6 +1329,7 @@ fn test_compute_next_charge_info_insufficient_balance_subscription() {
         status: SubscriptionStatus::InsufficientBalance,
         prepaid_balance: 1_000_000i128, // Not enough for next charge
         usage_enabled: false,
+        label: None,
     };
 
     let info = compute_next_charge_info(&subscription);
@@ -1300,6 +1360,7 @@ fn test_compute_next_charge_info_short_interval() {
         status: SubscriptionStatus::Active,
         prepaid_balance: 10_000i128,
         usage_enabled: true,
+        label: None,
     };
 
     let info = compute_next_charge_info(&subscription);
@@ -1328,6 +1389,7 @@ fn test_compute_next_charge_info_long_interval() {
         status: SubscriptionStatus::Active,
         prepaid_balance: 1_000_000_000i128,
         usage_enabled: false,
+        label: None,
     };
 
     let info = compute_next_charge_info(&subscription);
@@ -1357,6 +1419,7 @@ fn test_compute_next_charge_info_overflow_protection() {
         status: SubscriptionStatus::Active,
         prepaid_balance: 100_000_000i128,
         usage_enabled: false,
+        label: None,
     };
 
     let info = compute_next_charge_info(&subscription);
@@ -1379,7 +1442,16 @@ fn test_get_next_charge_info_contract_method() {
     env.ledger().with_mut(|li| li.timestamp = 1000);
 
     // Create subscription
-    let id = client.create_subscription(&subscriber, &merchant, &amount, &interval_seconds, &false);
+    let id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &amount,
+        &interval_seconds,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
 
     // Get next charge info
     let info = client.get_next_charge_info(&id);
@@ -1401,7 +1473,16 @@ fn test_get_next_charge_info_all_statuses() {
     env.ledger().with_mut(|li| li.timestamp = 5000);
 
     // Create subscription (starts as Active)
-    let id = client.create_subscription(&subscriber, &merchant, &amount, &interval_seconds, &false);
+    let id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &amount,
+        &interval_seconds,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
 
     // Test Active status
     let info = client.get_next_charge_info(&id);
@@ -1446,12 +1527,21 @@ fn test_get_next_charge_info_insufficient_balance_status() {
     env.ledger().with_mut(|li| li.timestamp = 2000);
 
     // Create subscription
-    let id = client.create_subscription(&subscriber, &merchant, &amount, &interval_seconds, &false);
+    let id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &amount,
+        &interval_seconds,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
 
     // Manually set to InsufficientBalance for testing
-    let mut sub = client.get_subscription(&id);
-    sub.status = SubscriptionStatus::InsufficientBalance;
     env.as_contract(&client.address, || {
+        let mut sub = crate::queries::get_subscription(&env, id).unwrap();
+        sub.status = SubscriptionStatus::InsufficientBalance;
         env.storage().instance().set(&id, &sub);
     });
 
@@ -1487,6 +1577,9 @@ fn test_get_next_charge_info_multiple_intervals() {
         &1_000_000i128,
         &(24 * 60 * 60), // 1 day
         &false,
+        &None,
+        &0i128,
+        &false,
     );
 
     // Weekly subscription
@@ -1497,6 +1590,9 @@ fn test_get_next_charge_info_multiple_intervals() {
         &5_000_000i128,
         &(7 * 24 * 60 * 60), // 7 days
         &false,
+        &None,
+        &0i128,
+        &false,
     );
 
     // Monthly subscription
@@ -1507,6 +1603,9 @@ fn test_get_next_charge_info_multiple_intervals() {
         &20_000_000i128,
         &(30 * 24 * 60 * 60), // 30 days
         &false,
+        &None,
+        &0i128,
+        &false,
     );
 
     // Check each subscription has correct next charge time
@@ -1546,6 +1645,7 @@ fn test_compute_next_charge_info_zero_interval() {
         status: SubscriptionStatus::Active,
         prepaid_balance: 10_000_000i128,
         usage_enabled: false,
+        label: None,
     };
 
     let info = compute_next_charge_info(&subscription);
@@ -1584,7 +1684,9 @@ fn test_cancel_subscription_unauthorized() {
     let contract_id = env.register(SubscriptionVault, ());
     let client = SubscriptionVaultClient::new(&env, &contract_id);
 
-    let token = Address::generate(&env);
+    let token = env
+        .register_stellar_asset_contract_v2(Address::generate(&env))
+        .address();
     let admin = Address::generate(&env);
     let subscriber = Address::generate(&env);
     let merchant = Address::generate(&env);
@@ -1592,7 +1694,16 @@ fn test_cancel_subscription_unauthorized() {
 
     client.init(&token, &admin, &1_000_000);
 
-    let sub_id = client.create_subscription(&subscriber, &merchant, &1000, &86400, &true);
+    let sub_id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000,
+        &86400,
+        &true,
+        &None,
+        &0i128,
+        &false,
+    );
 
     let result = client.try_cancel_subscription(&sub_id, &other);
     assert_eq!(result, Err(Ok(Error::Unauthorized)));
@@ -1623,7 +1734,16 @@ fn test_withdraw_subscriber_funds() {
     // Mint some to the subscriber
     token_admin.mint(&subscriber, &5000);
 
-    let sub_id = client.create_subscription(&subscriber, &merchant, &1000, &86400, &true);
+    let sub_id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000,
+        &86400,
+        &true,
+        &None,
+        &0i128,
+        &false,
+    );
 
     // Deposit funds to increase prepaid balance
     client.deposit_funds(&sub_id, &subscriber, &5000);
@@ -1655,7 +1775,7 @@ fn test_recover_stranded_funds_unauthorized_caller() {
 }
 
 #[test]
-#[should_panic(expected = "Error(Contract, #1008)")]
+#[should_panic(expected = "Error(Contract, #505)")]
 fn test_recover_stranded_funds_zero_amount() {
     let (_, client, _, admin) = setup_test_env();
 
@@ -1668,7 +1788,7 @@ fn test_recover_stranded_funds_zero_amount() {
 }
 
 #[test]
-#[should_panic(expected = "Error(Contract, #1008)")]
+#[should_panic(expected = "Error(Contract, #505)")]
 fn test_recover_stranded_funds_negative_amount() {
     let (_, client, _, admin) = setup_test_env();
 
@@ -1892,6 +2012,9 @@ fn test_recover_stranded_funds_does_not_affect_subscriptions() {
         &10_000_000i128,
         &(30 * 24 * 60 * 60),
         &false,
+        &None,
+        &0i128,
+        &false,
     );
 
     // Perform recovery (should not affect subscription)
@@ -1923,6 +2046,9 @@ fn test_recover_stranded_funds_with_cancelled_subscription() {
         &10_000_000i128,
         &(30 * 24 * 60 * 60),
         &false,
+        &None,
+        &0i128,
+        &false,
     );
     client.cancel_subscription(&sub_id, &subscriber);
 
@@ -1955,18 +2081,38 @@ fn setup_batch_env(env: &Env) -> (SubscriptionVaultClient<'static>, Address, u32
     let contract_id = env.register(SubscriptionVault, ());
     let client = SubscriptionVaultClient::new(env, &contract_id);
     let admin = Address::generate(env);
-    let token_addr = env
-        .register_stellar_asset_contract_v2(admin.clone())
-        .address();
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    // Revocable so tests can freeze trustlines to exercise deferred transfers.
+    sac.issuer()
+        .set_flag(soroban_sdk::testutils::IssuerFlags::RevocableFlag);
+    let token_addr = sac.address();
     let token_admin = soroban_sdk::token::StellarAssetClient::new(env, &token_addr);
     client.init(&token_addr, &admin, &1_000000i128);
 
     let subscriber = Address::generate(env);
     token_admin.mint(&subscriber, &100_000_000i128);
     let merchant = Address::generate(env);
-    let id0 = client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false);
+    let id0 = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
     client.deposit_funds(&id0, &subscriber, &10_000000i128);
-    let id1 = client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false);
+    let id1 = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
     env.ledger().set_timestamp(T0 + INTERVAL);
     (client, admin, id0, id1)
 }
@@ -2008,7 +2154,16 @@ fn test_batch_charge_small_batch_5_subscriptions() {
 
     // Create 5 subscriptions with sufficient balance
     for _ in 0..5 {
-        let id = client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false);
+        let id = client.create_subscription(
+            &subscriber,
+            &merchant,
+            &1000i128,
+            &INTERVAL,
+            &false,
+            &None,
+            &0i128,
+            &false,
+        );
         client.deposit_funds(&id, &subscriber, &10_000000i128);
         ids.push_back(id as u32);
     }
@@ -2044,7 +2199,16 @@ fn test_batch_charge_medium_batch_20_subscriptions() {
 
     // Create 20 subscriptions
     for _ in 0..20 {
-        let id = client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false);
+        let id = client.create_subscription(
+            &subscriber,
+            &merchant,
+            &1000i128,
+            &INTERVAL,
+            &false,
+            &None,
+            &0i128,
+            &false,
+        );
         client.deposit_funds(&id, &subscriber, &10_000000i128);
         ids.push_back(id as u32);
     }
@@ -2079,7 +2243,16 @@ fn test_batch_charge_large_batch_50_subscriptions() {
 
     // Create 50 subscriptions to test scalability
     for _ in 0..50 {
-        let id = client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false);
+        let id = client.create_subscription(
+            &subscriber,
+            &merchant,
+            &1000i128,
+            &INTERVAL,
+            &false,
+            &None,
+            &0i128,
+            &false,
+        );
         client.deposit_funds(&id, &subscriber, &10_000000i128);
         ids.push_back(id as u32);
     }
@@ -2118,7 +2291,16 @@ fn test_batch_charge_mixed_success_and_insufficient_balance() {
 
     // Create alternating pattern: funded, unfunded, funded, unfunded
     for i in 0..4 {
-        let id = client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false);
+        let id = client.create_subscription(
+            &subscriber,
+            &merchant,
+            &1000i128,
+            &INTERVAL,
+            &false,
+            &None,
+            &0i128,
+            &false,
+        );
         if i % 2 == 0 {
             client.deposit_funds(&id, &subscriber, &10_000000i128);
         }
@@ -2165,8 +2347,26 @@ fn test_batch_charge_mixed_interval_not_elapsed() {
     let merchant = Address::generate(&env);
 
     // Create subscriptions with different intervals
-    let id_short = client.create_subscription(&subscriber, &merchant, &1000i128, &1800, &false); // 30 min
-    let id_long = client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false); // 30 days
+    let id_short = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &1800,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    ); // 30 min
+    let id_long = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    ); // 30 days
 
     client.deposit_funds(&id_short, &subscriber, &10_000000i128);
     client.deposit_funds(&id_long, &subscriber, &10_000000i128);
@@ -2207,10 +2407,28 @@ fn test_batch_charge_mixed_paused_and_active() {
     token_admin.mint(&subscriber, &100_000_000i128);
     let merchant = Address::generate(&env);
 
-    let id0 = client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false);
+    let id0 = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
     client.deposit_funds(&id0, &subscriber, &10_000000i128);
 
-    let id1 = client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false);
+    let id1 = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
     client.deposit_funds(&id1, &subscriber, &10_000000i128);
     client.pause_subscription(&id1, &subscriber); // Pause this one
 
@@ -2249,10 +2467,28 @@ fn test_batch_charge_mixed_cancelled_and_active() {
     token_admin.mint(&subscriber, &100_000_000i128);
     let merchant = Address::generate(&env);
 
-    let id0 = client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false);
+    let id0 = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
     client.deposit_funds(&id0, &subscriber, &10_000000i128);
 
-    let id1 = client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false);
+    let id1 = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
     client.deposit_funds(&id1, &subscriber, &10_000000i128);
     client.cancel_subscription(&id1, &subscriber); // Cancel this one
 
@@ -2318,17 +2554,41 @@ fn test_batch_charge_all_different_error_types() {
     let merchant = Address::generate(&env);
 
     // Sub 0: Success case
-    let id_success =
-        client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false);
+    let id_success = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
     client.deposit_funds(&id_success, &subscriber, &10_000000i128);
 
     // Sub 1: Insufficient balance
-    let id_no_funds =
-        client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false);
+    let id_no_funds = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
 
     // Sub 2: Paused
-    let id_paused =
-        client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false);
+    let id_paused = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
     client.deposit_funds(&id_paused, &subscriber, &10_000000i128);
     client.pause_subscription(&id_paused, &subscriber);
 
@@ -2391,7 +2651,16 @@ fn test_batch_charge_successful_charges_update_state() {
     let merchant = Address::generate(&env);
     let charge_amount = 1_000_000i128; // 1 USDC
 
-    let id = client.create_subscription(&subscriber, &merchant, &charge_amount, &INTERVAL, &false);
+    let id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &charge_amount,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
     let initial_balance = 10_000_000i128;
     client.deposit_funds(&id, &subscriber, &initial_balance);
 
@@ -2429,7 +2698,16 @@ fn test_batch_charge_failed_charges_leave_state_unchanged() {
     token_admin.mint(&subscriber, &100_000_000i128);
     let merchant = Address::generate(&env);
 
-    let id = client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false);
+    let id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
     // No deposit - will fail with InsufficientBalance
 
     let sub_before = client.get_subscription(&id);
@@ -2471,13 +2749,40 @@ fn test_batch_charge_partial_batch_correct_final_state() {
     let merchant = Address::generate(&env);
     let amount = 1_000_000i128;
 
-    let id0 = client.create_subscription(&subscriber, &merchant, &amount, &INTERVAL, &false);
+    let id0 = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &amount,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
     client.deposit_funds(&id0, &subscriber, &10_000_000i128);
 
-    let id1 = client.create_subscription(&subscriber, &merchant, &amount, &INTERVAL, &false);
+    let id1 = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &amount,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
     // id1 has no funds - will fail
 
-    let id2 = client.create_subscription(&subscriber, &merchant, &amount, &INTERVAL, &false);
+    let id2 = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &amount,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
     client.deposit_funds(&id2, &subscriber, &10_000_000i128);
 
     env.ledger().set_timestamp(T0 + INTERVAL);
@@ -2527,7 +2832,16 @@ fn test_batch_charge_multiple_rounds_state_consistency() {
     let merchant = Address::generate(&env);
     let amount = 1_000_000i128;
 
-    let id = client.create_subscription(&subscriber, &merchant, &amount, &INTERVAL, &false);
+    let id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &amount,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
     client.deposit_funds(&id, &subscriber, &10_000_000i128);
 
     let mut ids = SorobanVec::<u32>::new(&env);
@@ -2557,13 +2871,24 @@ fn test_batch_charge_requires_admin_auth() {
     env.ledger().set_timestamp(T0);
     let contract_id = env.register(SubscriptionVault, ());
     let client = SubscriptionVaultClient::new(&env, &contract_id);
-    let token = Address::generate(&env);
+    let token = env
+        .register_stellar_asset_contract_v2(Address::generate(&env))
+        .address();
     let admin = Address::generate(&env);
     client.init(&token, &admin, &1_000000i128);
 
     let subscriber = Address::generate(&env);
     let merchant = Address::generate(&env);
-    let id = client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false);
+    let id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
 
     let non_admin = Address::generate(&env);
 
@@ -2633,7 +2958,16 @@ fn test_batch_charge_exhausts_balance_exactly() {
     let merchant = Address::generate(&env);
     let amount = 5_000_000i128;
 
-    let id = client.create_subscription(&subscriber, &merchant, &amount, &INTERVAL, &false);
+    let id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &amount,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
     client.deposit_funds(&id, &subscriber, &amount); // Exact amount for one charge
 
     env.ledger().set_timestamp(T0 + INTERVAL);
@@ -2667,7 +3001,16 @@ fn test_batch_charge_balance_off_by_one_insufficient() {
     let merchant = Address::generate(&env);
     let amount = 5_000_000i128;
 
-    let id = client.create_subscription(&subscriber, &merchant, &amount, &INTERVAL, &false);
+    let id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &amount,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
     client.deposit_funds(&id, &subscriber, &(amount - 1)); // One stroops short
 
     env.ledger().set_timestamp(T0 + INTERVAL);
@@ -2701,14 +3044,41 @@ fn test_batch_charge_result_indices_match_input_order() {
     token_admin.mint(&subscriber, &100_000_000i128);
     let merchant = Address::generate(&env);
 
-    let id0 = client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false);
+    let id0 = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
     client.deposit_funds(&id0, &subscriber, &10_000000i128);
 
-    let id1 = client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false);
+    let id1 = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
     // No funds for id1
 
-    let id2 = client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false);
-    client.deposit_funds(&id2, &subscriber, &10_000000i128);
+    let id2 = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+    client.deposit_funds(&id2, &subscriber, &10_000000i128);
 
     env.ledger().set_timestamp(T0 + INTERVAL);
 
@@ -2779,6 +3149,9 @@ fn test_create_subscription_with_usage_disabled() {
         &amount,
         &interval_seconds,
         &usage_enabled,
+        &None,
+        &0i128,
+        &false,
     );
 
     let subscription = client.get_subscription(&id);
@@ -2803,6 +3176,9 @@ fn test_create_subscription_with_usage_enabled() {
         &amount,
         &interval_seconds,
         &usage_enabled,
+        &None,
+        &0i128,
+        &false,
     );
 
     let subscription = client.get_subscription(&id);
@@ -2825,6 +3201,9 @@ fn test_usage_flag_persists_through_state_transitions() {
         &10_000_000i128,
         &(30 * 24 * 60 * 60),
         &usage_enabled,
+        &None,
+        &0i128,
+        &false,
     );
 
     // Verify initial state
@@ -2871,6 +3250,9 @@ fn test_multiple_subscriptions_different_usage_modes() {
         &10_000_000i128,
         &(30 * 24 * 60 * 60),
         &false,
+        &None,
+        &0i128,
+        &false,
     );
 
     // Create subscription with usage enabled
@@ -2880,6 +3262,9 @@ fn test_multiple_subscriptions_different_usage_modes() {
         &5_000_000i128,
         &(7 * 24 * 60 * 60),
         &true,
+        &None,
+        &0i128,
+        &false,
     );
 
     // Create another with usage disabled
@@ -2889,6 +3274,9 @@ fn test_multiple_subscriptions_different_usage_modes() {
         &20_000_000i128,
         &(90 * 24 * 60 * 60),
         &false,
+        &None,
+        &0i128,
+        &false,
     );
 
     // Verify each subscription has correct usage_enabled value
@@ -2916,6 +3304,9 @@ fn test_usage_enabled_with_different_intervals() {
         &1_000_000i128,
         &(24 * 60 * 60), // 1 day
         &true,
+        &None,
+        &0i128,
+        &false,
     );
 
     // Weekly subscription with usage disabled
@@ -2925,6 +3316,9 @@ fn test_usage_enabled_with_different_intervals() {
         &5_000_000i128,
         &(7 * 24 * 60 * 60), // 7 days
         &false,
+        &None,
+        &0i128,
+        &false,
     );
 
     // Monthly subscription with usage enabled
@@ -2934,6 +3328,9 @@ fn test_usage_enabled_with_different_intervals() {
         &20_000_000i128,
         &(30 * 24 * 60 * 60), // 30 days
         &true,
+        &None,
+        &0i128,
+        &false,
     );
 
     // Verify usage_enabled is independent of interval
@@ -2956,6 +3353,9 @@ fn test_usage_enabled_with_zero_interval() {
         &1_000_000i128,
         &0, // Zero interval
         &true,
+        &None,
+        &0i128,
+        &false,
     );
 
     let subscription = client.get_subscription(&id);
@@ -2979,6 +3379,9 @@ fn test_usage_flag_with_next_charge_info() {
         &10_000_000i128,
         &(30 * 24 * 60 * 60),
         &true,
+        &None,
+        &0i128,
+        &false,
     );
 
     // Create subscription with usage disabled
@@ -2988,6 +3391,9 @@ fn test_usage_flag_with_next_charge_info() {
         &10_000_000i128,
         &(30 * 24 * 60 * 60),
         &false,
+        &None,
+        &0i128,
+        &false,
     );
 
     // Both should compute next charge info regardless of usage_enabled
@@ -3016,6 +3422,9 @@ fn test_usage_enabled_default_behavior() {
         &10_000_000i128,
         &(30 * 24 * 60 * 60),
         &false,
+        &None,
+        &0i128,
+        &false,
     );
 
     let subscription = client.get_subscription(&id);
@@ -3040,6 +3449,9 @@ fn test_usage_enabled_immutable_after_creation() {
         &10_000_000i128,
         &(30 * 24 * 60 * 60),
         &false,
+        &None,
+        &0i128,
+        &false,
     );
 
     assert!(!client.get_subscription(&id).usage_enabled);
@@ -3071,6 +3483,9 @@ fn test_usage_enabled_with_all_subscription_statuses() {
         &10_000_000i128,
         &(30 * 24 * 60 * 60),
         &true,
+        &None,
+        &0i128,
+        &false,
     );
 
     // Test Active status
@@ -3120,6 +3535,9 @@ fn test_usage_enabled_true_semantics() {
         &10_000_000i128,
         &(30 * 24 * 60 * 60),
         &true,
+        &None,
+        &0i128,
+        &false,
     );
 
     let subscription = client.get_subscription(&id);
@@ -3154,6 +3572,9 @@ fn test_usage_enabled_false_semantics() {
         &10_000_000i128,
         &(30 * 24 * 60 * 60),
         &false,
+        &None,
+        &0i128,
+        &false,
     );
 
     let subscription = client.get_subscription(&id);
@@ -3181,7 +3602,16 @@ fn test_usage_enabled_with_different_amounts() {
     let merchant = Address::generate(&env);
 
     // Small amount with usage enabled
-    let id1 = client.create_subscription(&subscriber, &merchant, &100i128, &(24 * 60 * 60), &true);
+    let id1 = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &100i128,
+        &(24 * 60 * 60),
+        &true,
+        &None,
+        &0i128,
+        &false,
+    );
 
     // Large amount with usage disabled
     let id2 = client.create_subscription(
@@ -3190,6 +3620,9 @@ fn test_usage_enabled_with_different_amounts() {
         &1_000_000_000i128,
         &(30 * 24 * 60 * 60),
         &false,
+        &None,
+        &0i128,
+        &false,
     );
 
     // Medium amount with usage enabled
@@ -3199,6 +3632,9 @@ fn test_usage_enabled_with_different_amounts() {
         &50_000_000i128,
         &(7 * 24 * 60 * 60),
         &true,
+        &None,
+        &0i128,
+        &false,
     );
 
     // Verify amounts and usage_enabled are independent
@@ -3230,6 +3666,9 @@ fn test_usage_enabled_field_storage() {
         &10_000_000i128,
         &(30 * 24 * 60 * 60),
         &true,
+        &None,
+        &0i128,
+        &false,
     );
 
     let id1 = client.create_subscription(
@@ -3238,6 +3677,9 @@ fn test_usage_enabled_field_storage() {
         &10_000_000i128,
         &(30 * 24 * 60 * 60),
         &false,
+        &None,
+        &0i128,
+        &false,
     );
 
     let id2 = client.create_subscription(
@@ -3246,6 +3688,9 @@ fn test_usage_enabled_field_storage() {
         &10_000_000i128,
         &(30 * 24 * 60 * 60),
         &true,
+        &None,
+        &0i128,
+        &false,
     );
 
     let id3 = client.create_subscription(
@@ -3254,6 +3699,9 @@ fn test_usage_enabled_field_storage() {
         &10_000_000i128,
         &(30 * 24 * 60 * 60),
         &false,
+        &None,
+        &0i128,
+        &false,
     );
 
     let id4 = client.create_subscription(
@@ -3262,6 +3710,9 @@ fn test_usage_enabled_field_storage() {
         &10_000_000i128,
         &(30 * 24 * 60 * 60),
         &true,
+        &None,
+        &0i128,
+        &false,
     );
 
     // Verify each subscription has the correct usage_enabled value
@@ -3286,6 +3737,9 @@ fn test_usage_enabled_with_recovery_operations() {
         &10_000_000i128,
         &(30 * 24 * 60 * 60),
         &true,
+        &None,
+        &0i128,
+        &false,
     );
 
     assert!(client.get_subscription(&id).usage_enabled);
@@ -3424,12 +3878,21 @@ fn test_batch_charge_admin_rotation() {
 
     env.ledger().with_mut(|li| li.timestamp = T0);
 
-    let id = client.create_subscription(&subscriber, &merchant, &amount, &interval_seconds, &false);
+    let id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &amount,
+        &interval_seconds,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
 
     // Seed prepaid balance and advance time so charge can succeed
-    let mut sub = client.get_subscription(&id);
-    sub.prepaid_balance = 50_000_000i128;
     env.as_contract(&client.address, || {
+        let mut sub = crate::queries::get_subscription(&env, id).unwrap();
+        sub.prepaid_balance = 50_000_000i128;
         env.storage().instance().set(&id, &sub);
     });
     env.ledger()
@@ -3500,6 +3963,9 @@ fn test_admin_rotation_does_not_affect_subscriptions() {
         &10_000_000i128,
         &(30 * 24 * 60 * 60),
         &false,
+        &None,
+        &0i128,
+        &false,
     );
 
     let subscription_before = client.get_subscription(&sub_id);
@@ -3722,6 +4188,9 @@ fn test_admin_rotation_with_subscriptions_active() {
         &10_000_000i128,
         &(30 * 24 * 60 * 60),
         &false,
+        &None,
+        &0i128,
+        &false,
     );
 
     let id2 = client.create_subscription(
@@ -3730,6 +4199,9 @@ fn test_admin_rotation_with_subscriptions_active() {
         &5_000_000i128,
         &(7 * 24 * 60 * 60),
         &true,
+        &None,
+        &0i128,
+        &false,
     );
 
     // Perform state changes
@@ -3814,8 +4286,8 @@ fn test_list_subscriptions_zero_subscriptions() {
     let subscriber = Address::generate(&env);
     let page = client.list_subscriptions_by_subscriber(&subscriber, &0u32, &10u32);
 
-    assert_eq!(page.subscription_ids.len(), 0);
-    assert!(!page.has_next);
+    assert_eq!(page.items.len(), 0);
+    assert!(page.next_cursor.is_none());
 }
 
 #[test]
@@ -3827,13 +4299,22 @@ fn test_list_subscriptions_one_subscription() {
     let merchant = Address::generate(&env);
     let balance = 10_000_000i128;
 
-    let id = client.create_subscription(&subscriber, &merchant, &balance, &INTERVAL, &false);
+    let id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &balance,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
 
     let page = client.list_subscriptions_by_subscriber(&subscriber, &0u32, &10u32);
 
-    assert_eq!(page.subscription_ids.len(), 1);
-    assert_eq!(page.subscription_ids.get(0).unwrap(), id);
-    assert!(!page.has_next);
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items.get(0).unwrap(), id);
+    assert!(page.next_cursor.is_none());
 }
 
 #[test]
@@ -3852,21 +4333,21 @@ fn test_list_subscriptions_many_subscriptions() {
             &10_000_000i128,
             &(30 * 24 * 60 * 60),
             &false,
+            &None,
+            &0i128,
+            &false,
         );
         ids.push_back(id);
     }
 
     let page = client.list_subscriptions_by_subscriber(&subscriber, &0u32, &10u32);
 
-    assert_eq!(page.subscription_ids.len(), 5);
-    assert!(!page.has_next);
+    assert_eq!(page.items.len(), 5);
+    assert!(page.next_cursor.is_none());
 
     // Verify subscriptions are returned in order by ID
     for i in 0..5 {
-        assert_eq!(
-            page.subscription_ids.get(i).unwrap(),
-            ids.get(i as u32).unwrap()
-        );
+        assert_eq!(page.items.get(i).unwrap(), ids.get(i).unwrap());
     }
 }
 
@@ -3886,21 +4367,21 @@ fn test_list_subscriptions_pagination_first_page() {
             &10_000_000i128,
             &(30 * 24 * 60 * 60),
             &false,
+            &None,
+            &0i128,
+            &false,
         );
         ids.push_back(id);
     }
 
     let page1 = client.list_subscriptions_by_subscriber(&subscriber, &0u32, &10u32);
 
-    assert_eq!(page1.subscription_ids.len(), 10);
-    assert!(page1.has_next);
+    assert_eq!(page1.items.len(), 10);
+    assert!(page1.next_cursor.is_some());
 
     // Verify first page contains the first 10 subscriptions
     for i in 0..10 {
-        assert_eq!(
-            page1.subscription_ids.get(i).unwrap(),
-            ids.get(i as u32).unwrap()
-        );
+        assert_eq!(page1.items.get(i).unwrap(), ids.get(i).unwrap());
     }
 }
 
@@ -3920,28 +4401,28 @@ fn test_list_subscriptions_pagination_second_page() {
             &10_000_000i128,
             &(30 * 24 * 60 * 60),
             &false,
+            &None,
+            &0i128,
+            &false,
         );
         ids.push_back(id);
     }
 
     // Get first page
     let page1 = client.list_subscriptions_by_subscriber(&subscriber, &0u32, &10u32);
-    assert_eq!(page1.subscription_ids.len(), 10);
-    let last_id_page1 = page1.subscription_ids.get(9).unwrap();
+    assert_eq!(page1.items.len(), 10);
+    let last_id_page1 = page1.items.get(9).unwrap();
 
     // Get second page using start_from_id = last_id + 1
     let next_start = last_id_page1 + 1;
     let page2 = client.list_subscriptions_by_subscriber(&subscriber, &next_start, &10u32);
 
-    assert_eq!(page2.subscription_ids.len(), 5);
-    assert!(!page2.has_next);
+    assert_eq!(page2.items.len(), 5);
+    assert!(page2.next_cursor.is_none());
 
     // Verify second page contains the remaining 5 subscriptions
     for i in 0..5 {
-        assert_eq!(
-            page2.subscription_ids.get(i).unwrap(),
-            ids.get((10 + i) as u32).unwrap()
-        );
+        assert_eq!(page2.items.get(i).unwrap(), ids.get(10 + i).unwrap());
     }
 }
 
@@ -3962,6 +4443,9 @@ fn test_list_subscriptions_filters_by_subscriber() {
             &10_000_000i128,
             &(30 * 24 * 60 * 60),
             &false,
+            &None,
+            &0i128,
+            &false,
         );
     }
 
@@ -3973,16 +4457,19 @@ fn test_list_subscriptions_filters_by_subscriber() {
             &10_000_000i128,
             &(30 * 24 * 60 * 60),
             &false,
+            &None,
+            &0i128,
+            &false,
         );
     }
 
     // Query subscriber1
     let page1 = client.list_subscriptions_by_subscriber(&subscriber1, &0u32, &10u32);
-    assert_eq!(page1.subscription_ids.len(), 3);
+    assert_eq!(page1.items.len(), 3);
 
     // Query subscriber2
     let page2 = client.list_subscriptions_by_subscriber(&subscriber2, &0u32, &10u32);
-    assert_eq!(page2.subscription_ids.len(), 2);
+    assert_eq!(page2.items.len(), 2);
 }
 
 #[test]
@@ -4001,6 +4488,9 @@ fn test_list_subscriptions_small_limit() {
             &10_000_000i128,
             &(30 * 24 * 60 * 60),
             &false,
+            &None,
+            &0i128,
+            &false,
         );
         ids.push_back(id);
     }
@@ -4012,12 +4502,12 @@ fn test_list_subscriptions_small_limit() {
 
     while has_next {
         let page = client.list_subscriptions_by_subscriber(&subscriber, &start_id, &1u32);
-        if page.subscription_ids.len() > 0 {
-            let current_id = page.subscription_ids.get(0).unwrap();
+        if !page.items.is_empty() {
+            let current_id = page.items.get(0).unwrap();
             all_ids.push_back(current_id);
             // Advance start cursor past the current ID
             start_id = current_id + 1;
-            has_next = page.has_next;
+            has_next = page.next_cursor.is_some();
         } else {
             has_next = false;
         }
@@ -4056,6 +4546,9 @@ fn test_list_subscriptions_respects_start_from_id() {
             &10_000_000i128,
             &(30 * 24 * 60 * 60),
             &false,
+            &None,
+            &0i128,
+            &false,
         );
         ids.push_back(id);
     }
@@ -4065,14 +4558,11 @@ fn test_list_subscriptions_respects_start_from_id() {
     let page = client.list_subscriptions_by_subscriber(&subscriber, &start_id, &10u32);
 
     // Should contain subscriptions 5-9 (5 subscriptions, inclusive)
-    assert_eq!(page.subscription_ids.len(), 5);
+    assert_eq!(page.items.len(), 5);
 
     // Verify these are subscriptions at indices 5-9
     for i in 0..5 {
-        assert_eq!(
-            page.subscription_ids.get(i).unwrap(),
-            ids.get((5 + i) as u32).unwrap()
-        );
+        assert_eq!(page.items.get(i).unwrap(), ids.get(5 + i).unwrap());
     }
 }
 
@@ -4091,6 +4581,9 @@ fn test_list_subscriptions_stable_ordering() {
             &10_000_000i128,
             &(30 * 24 * 60 * 60),
             &false,
+            &None,
+            &0i128,
+            &false,
         );
     }
 
@@ -4098,12 +4591,9 @@ fn test_list_subscriptions_stable_ordering() {
     let page1 = client.list_subscriptions_by_subscriber(&subscriber, &0u32, &10u32);
     let page2 = client.list_subscriptions_by_subscriber(&subscriber, &0u32, &10u32);
 
-    assert_eq!(page1.subscription_ids.len(), page2.subscription_ids.len());
-    for i in 0..page1.subscription_ids.len() {
-        assert_eq!(
-            page1.subscription_ids.get(i).unwrap(),
-            page2.subscription_ids.get(i).unwrap()
-        );
+    assert_eq!(page1.items.len(), page2.items.len());
+    for i in 0..page1.items.len() {
+        assert_eq!(page1.items.get(i).unwrap(), page2.items.get(i).unwrap());
     }
 }
 
@@ -4126,18 +4616,6489 @@ fn test_list_subscriptions_multiple_merchants() {
             &10_000_000i128,
             &(30 * 24 * 60 * 60),
             &false,
+            &None,
+            &0i128,
+            &false,
         );
         ids.push_back(id);
     }
 
     let page = client.list_subscriptions_by_subscriber(&subscriber, &0u32, &10u32);
 
-    assert_eq!(page.subscription_ids.len(), 10);
+    assert_eq!(page.items.len(), 10);
     // All subscriptions should be from this subscriber regardless of merchant
     for i in 0..10 {
-        assert_eq!(
-            page.subscription_ids.get(i).unwrap(),
-            ids.get(i as u32).unwrap()
+        assert_eq!(page.items.get(i).unwrap(), ids.get(i).unwrap());
+    }
+}
+
+// =============================================================================
+// Keeper: Due-Soon Notification Tests
+// =============================================================================
+
+#[test]
+fn test_notify_lead_window_default_and_update() {
+    let (_, client, _, admin) = setup_test_env();
+    assert_eq!(client.get_notify_lead_window(), 3 * 24 * 60 * 60);
+
+    client.set_notify_lead_window(&admin, &3600);
+    assert_eq!(client.get_notify_lead_window(), 3600);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #401)")]
+fn test_notify_lead_window_unauthorized() {
+    let (env, client, _, _) = setup_test_env();
+    let non_admin = Address::generate(&env);
+    client.set_notify_lead_window(&non_admin, &3600);
+}
+
+#[test]
+fn test_notify_due_soon_only_within_window() {
+    let (env, client, _, _) = setup_test_env();
+    env.ledger().set_timestamp(T0);
+    let (id, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    let ids = SorobanVec::from_array(&env, [id]);
+
+    // Far from due: nothing notified.
+    assert_eq!(client.notify_due_soon(&ids).len(), 0);
+
+    // Inside the default 3-day window.
+    env.ledger().set_timestamp(T0 + INTERVAL - 24 * 60 * 60);
+    let notified = client.notify_due_soon(&ids);
+    assert_eq!(notified.len(), 1);
+    assert_eq!(notified.get(0).unwrap(), id);
+
+    // Once already due, keeper should charge rather than notify.
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    assert_eq!(client.notify_due_soon(&ids).len(), 0);
+}
+
+#[test]
+fn test_notify_due_soon_once_per_period() {
+    let (env, client, _, _) = setup_test_env();
+    env.ledger().set_timestamp(T0);
+    let (id, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    let ids = SorobanVec::from_array(&env, [id]);
+
+    env.ledger().set_timestamp(T0 + INTERVAL - 60);
+    assert_eq!(client.notify_due_soon(&ids).len(), 1);
+    env.ledger().set_timestamp(T0 + INTERVAL - 30);
+    assert_eq!(client.notify_due_soon(&ids).len(), 0);
+}
+
+#[test]
+fn test_notify_due_soon_skips_inactive_and_missing() {
+    let (env, client, _, _) = setup_test_env();
+    env.ledger().set_timestamp(T0);
+    let (paused, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Paused);
+    let (active, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    let ids = SorobanVec::from_array(&env, [paused, 999, active]);
+
+    env.ledger().set_timestamp(T0 + INTERVAL - 60);
+    let notified = client.notify_due_soon(&ids);
+    assert_eq!(notified.len(), 1);
+    assert_eq!(notified.get(0).unwrap(), active);
+}
+
+// =============================================================================
+// Merchant API Key Tests
+// =============================================================================
+
+#[test]
+fn test_register_and_list_api_keys() {
+    let (env, client, _, _) = setup_test_env();
+    let merchant = Address::generate(&env);
+    let metering_key = Address::generate(&env);
+    let refunds_key = Address::generate(&env);
+
+    client.register_api_key(
+        &merchant,
+        &metering_key,
+        &Symbol::new(&env, "meter"),
+        &ApiKeyScope::Metering,
+    );
+    client.register_api_key(
+        &merchant,
+        &refunds_key,
+        &Symbol::new(&env, "support"),
+        &ApiKeyScope::Refunds,
+    );
+
+    let keys = client.list_api_keys(&merchant, &0, &100).items;
+    assert_eq!(keys.len(), 2);
+    assert_eq!(keys.get(0).unwrap().key, metering_key);
+    assert_eq!(keys.get(0).unwrap().scope, ApiKeyScope::Metering);
+    assert_eq!(keys.get(1).unwrap().name, Symbol::new(&env, "support"));
+
+    // Re-registering an existing key updates it in place.
+    client.register_api_key(
+        &merchant,
+        &metering_key,
+        &Symbol::new(&env, "meter2"),
+        &ApiKeyScope::Refunds,
+    );
+    let keys = client.list_api_keys(&merchant, &0, &100).items;
+    assert_eq!(keys.len(), 2);
+    assert_eq!(keys.get(0).unwrap().scope, ApiKeyScope::Refunds);
+}
+
+#[test]
+fn test_revoke_api_key() {
+    let (env, client, _, _) = setup_test_env();
+    let merchant = Address::generate(&env);
+    let key = Address::generate(&env);
+    client.register_api_key(
+        &merchant,
+        &key,
+        &Symbol::new(&env, "k"),
+        &ApiKeyScope::Metering,
+    );
+
+    client.revoke_api_key(&merchant, &key);
+    assert_eq!(client.list_api_keys(&merchant, &0, &100).items.len(), 0);
+
+    let res = client.try_revoke_api_key(&merchant, &key);
+    assert_eq!(res, Err(Ok(Error::NotFound)));
+}
+
+#[test]
+fn test_api_key_limit() {
+    let (env, client, _, _) = setup_test_env();
+    let merchant = Address::generate(&env);
+    for _ in 0..20 {
+        client.register_api_key(
+            &merchant,
+            &Address::generate(&env),
+            &Symbol::new(&env, "k"),
+            &ApiKeyScope::Metering,
         );
     }
+    let res = client.try_register_api_key(
+        &merchant,
+        &Address::generate(&env),
+        &Symbol::new(&env, "k"),
+        &ApiKeyScope::Metering,
+    );
+    assert_eq!(res, Err(Ok(Error::LimitExceeded)));
+}
+
+#[test]
+fn test_metering_key_can_charge_usage() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup_usage(&env);
+    let merchant = client.get_subscription(&id).merchant;
+    let key = Address::generate(&env);
+    client.register_api_key(
+        &merchant,
+        &key,
+        &Symbol::new(&env, "meter"),
+        &ApiKeyScope::Metering,
+    );
+
+    client.charge_usage(&key, &id, &1_000_000i128);
+    assert_eq!(
+        client.get_subscription(&id).prepaid_balance,
+        PREPAID - 1_000_000
+    );
+
+    // Once revoked, the key loses access.
+    client.revoke_api_key(&merchant, &key);
+    let res = client.try_charge_usage(&key, &id, &1_000_000i128);
+    assert_eq!(res, Err(Ok(Error::Unauthorized)));
+}
+
+#[test]
+fn test_wrong_scope_key_cannot_charge_usage() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup_usage(&env);
+    let merchant = client.get_subscription(&id).merchant;
+    let key = Address::generate(&env);
+    client.register_api_key(
+        &merchant,
+        &key,
+        &Symbol::new(&env, "support"),
+        &ApiKeyScope::Refunds,
+    );
+
+    let res = client.try_charge_usage(&key, &id, &1_000_000i128);
+    assert_eq!(res, Err(Ok(Error::Unauthorized)));
+
+    let stranger = Address::generate(&env);
+    let res = client.try_charge_usage(&stranger, &id, &1_000_000i128);
+    assert_eq!(res, Err(Ok(Error::Unauthorized)));
+}
+
+#[test]
+fn test_refunds_key_can_cancel_subscription() {
+    let (env, client, _, _) = setup_test_env();
+    let (id, _, merchant) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    let key = Address::generate(&env);
+
+    let res = client.try_cancel_subscription(&id, &key);
+    assert_eq!(res, Err(Ok(Error::Unauthorized)));
+
+    client.register_api_key(
+        &merchant,
+        &key,
+        &Symbol::new(&env, "support"),
+        &ApiKeyScope::Refunds,
+    );
+    client.cancel_subscription(&id, &key);
+    assert_eq!(
+        client.get_subscription(&id).status,
+        SubscriptionStatus::Cancelled
+    );
+}
+
+// =============================================================================
+// Cooling-Off Period Tests
+// =============================================================================
+
+const COOLING_OFF: u64 = 14 * 24 * 60 * 60;
+
+#[test]
+fn test_cooling_off_period_default_and_update() {
+    let (_, client, _, admin) = setup_test_env();
+    assert_eq!(client.get_cooling_off_period(), 0);
+    client.set_cooling_off_period(&admin, &COOLING_OFF);
+    assert_eq!(client.get_cooling_off_period(), COOLING_OFF);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #401)")]
+fn test_cooling_off_period_unauthorized() {
+    let (env, client, _, _) = setup_test_env();
+    client.set_cooling_off_period(&Address::generate(&env), &COOLING_OFF);
+}
+
+#[test]
+fn test_cancel_within_cooling_off_refunds_charges() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    client.set_cooling_off_period(&admin, &(INTERVAL + COOLING_OFF));
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        10_000000 - 1000
+    );
+
+    let subscriber = client.get_subscription(&id0).subscriber;
+    let token = client.get_token();
+    client.cancel_subscription(&id0, &subscriber);
+    let sub = client.get_subscription(&id0);
+    assert_eq!(sub.status, SubscriptionStatus::Cancelled);
+    // The refund lands in the subscriber's credit wallet.
+    assert_eq!(sub.prepaid_balance, 10_000000 - 1000);
+    assert_eq!(client.get_credit_balance(&subscriber, &token), 1000);
+
+    // Cancelling again does not refund twice.
+    client.cancel_subscription(&id0, &subscriber);
+    assert_eq!(client.get_credit_balance(&subscriber, &token), 1000);
+}
+
+#[test]
+fn test_cancel_after_cooling_off_keeps_charges() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    client.set_cooling_off_period(&admin, &COOLING_OFF);
+    client.charge_subscription(&id0);
+
+    let subscriber = client.get_subscription(&id0).subscriber;
+    client.cancel_subscription(&id0, &subscriber);
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        10_000000 - 1000
+    );
+}
+
+#[test]
+fn test_cooling_off_charges_held_from_merchant_until_window_closes() {
+    let env = Env::default();
+    let (client, admin, id0, id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+    client.set_cooling_off_period(&admin, &(INTERVAL + COOLING_OFF));
+    client.set_external_refund_fee(&admin, &1000u32);
+    client.set_refund_split(&sub.subscriber, &10_000u32);
+    client.set_payout_policy(
+        &sub.merchant,
+        &Some(crate::PayoutPolicy {
+            threshold: 1000,
+            interval_seconds: 0,
+        }),
+    );
+
+    // The charge is held by the vault: nothing is paid out or withdrawable.
+    client.charge_subscription(&id0);
+    assert_eq!(client.get_cooling_off_hold(&id0), 1000);
+    assert_eq!(token.balance(&sub.merchant), 0);
+    assert_eq!(client.process_payout(&sub.merchant, &token.address), 0);
+    assert_eq!(
+        client.try_withdraw_merchant_funds(&sub.merchant, &1000),
+        Err(Ok(Error::InsufficientBalance))
+    );
+    // Still held until the window closes.
+    assert_eq!(client.release_cooling_off_hold(&id0), 0);
+    assert_eq!(client.get_cancellation_quote(&id0).refund, 1000);
+
+    // The whole charge goes back to the subscriber's address, fee-free.
+    let before = token.balance(&sub.subscriber);
+    client.cancel_subscription(&id0, &sub.subscriber);
+    assert_eq!(token.balance(&sub.subscriber), before + 1000);
+    assert_eq!(client.get_cooling_off_hold(&id0), 0);
+    assert_eq!(token.balance(&sub.merchant), 0);
+    let owed = client.get_subscription(&id0).prepaid_balance
+        + client.get_subscription(&id1).prepaid_balance
+        + client
+            .get_payout_queue(&sub.merchant, &token.address)
+            .pending;
+    assert!(token.balance(&client.address) >= owed);
+}
+
+#[test]
+fn test_cooling_off_hold_released_after_window() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token = client.get_token();
+    client.set_cooling_off_period(&admin, &(INTERVAL + COOLING_OFF));
+
+    client.charge_subscription(&id0);
+    assert_eq!(client.get_payout_queue(&sub.merchant, &token).pending, 0);
+
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL + COOLING_OFF);
+    assert_eq!(client.release_cooling_off_hold(&id0), 1000);
+    assert_eq!(client.get_cooling_off_hold(&id0), 0);
+    assert_eq!(client.get_payout_queue(&sub.merchant, &token).pending, 1000);
+    assert_eq!(client.release_cooling_off_hold(&id0), 0);
+
+    // Cancelling after the window refunds nothing.
+    client.cancel_subscription(&id0, &sub.subscriber);
+    assert_eq!(client.get_payout_queue(&sub.merchant, &token).pending, 1000);
+    assert_eq!(client.get_credit_balance(&sub.subscriber, &token), 0);
+}
+
+// =============================================================================
+// Bulk Cancel Tests
+// =============================================================================
+
+#[test]
+fn test_cancel_all_subscriptions_only_cancels_own() {
+    let (env, client, _, _) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let other = Address::generate(&env);
+    let merchant = Address::generate(&env);
+
+    let a = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+    let b = client.create_subscription(
+        &other, &merchant, &1000i128, &INTERVAL, &false, &None, &0i128, &false,
+    );
+    let c = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+    client.pause_subscription(&c, &subscriber);
+
+    let res = client.cancel_all_subscriptions(&subscriber, &0, &10);
+    assert_eq!(res.cancelled_ids, SorobanVec::from_array(&env, [a, c]));
+    assert!(!res.has_next);
+    assert_eq!(
+        client.get_subscription(&a).status,
+        SubscriptionStatus::Cancelled
+    );
+    assert_eq!(
+        client.get_subscription(&b).status,
+        SubscriptionStatus::Active
+    );
+    assert_eq!(
+        client.get_subscription(&c).status,
+        SubscriptionStatus::Cancelled
+    );
+}
+
+#[test]
+fn test_cancel_all_subscriptions_cursor_continuation() {
+    let (env, client, _, _) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    for _ in 0..5 {
+        client.create_subscription(
+            &subscriber,
+            &merchant,
+            &1000i128,
+            &INTERVAL,
+            &false,
+            &None,
+            &0i128,
+            &false,
+        );
+    }
+
+    let page1 = client.cancel_all_subscriptions(&subscriber, &0, &3);
+    assert_eq!(page1.cancelled_ids.len(), 3);
+    assert!(page1.has_next);
+    assert_eq!(page1.next_cursor, 3);
+
+    let page2 = client.cancel_all_subscriptions(&subscriber, &page1.next_cursor, &3);
+    assert_eq!(page2.cancelled_ids.len(), 2);
+    assert!(!page2.has_next);
+
+    // Nothing left to cancel.
+    let again = client.cancel_all_subscriptions(&subscriber, &0, &10);
+    assert_eq!(again.cancelled_ids.len(), 0);
+}
+
+// =============================================================================
+// Subscriber Emergency Lock Tests
+// =============================================================================
+
+#[test]
+fn test_lock_subscriber_freezes_deposits() {
+    let env = Env::default();
+    let (client, _, id0, _) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    let recovery = Address::generate(&env);
+
+    client.lock_subscriber(&subscriber, &recovery);
+    assert_eq!(
+        client.get_subscriber_lock(&subscriber),
+        Some(recovery.clone())
+    );
+
+    let res = client.try_deposit_funds(&id0, &subscriber, &1_000000i128);
+    assert_eq!(res, Err(Ok(Error::SubscriberLocked)));
+
+    // A thief holding the key cannot swap the recovery address.
+    let res = client.try_lock_subscriber(&subscriber, &Address::generate(&env));
+    assert_eq!(res, Err(Ok(Error::SubscriberLocked)));
+}
+
+#[test]
+fn test_locked_subscriber_refund_goes_to_recovery() {
+    let env = Env::default();
+    let (client, _, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let recovery = Address::generate(&env);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+
+    client.lock_subscriber(&sub.subscriber, &recovery);
+    client.cancel_subscription(&id0, &sub.subscriber);
+
+    let res = client.try_withdraw_subscriber_funds(&id0, &sub.subscriber);
+    assert_eq!(res, Err(Ok(Error::SubscriberLocked)));
+
+    client.withdraw_subscriber_funds(&id0, &recovery);
+    assert_eq!(token.balance(&recovery), sub.prepaid_balance);
+    assert_eq!(client.get_subscription(&id0).prepaid_balance, 0);
+}
+
+#[test]
+fn test_unlock_subscriber_only_by_recovery() {
+    let env = Env::default();
+    let (client, _, id0, _) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    let recovery = Address::generate(&env);
+    client.lock_subscriber(&subscriber, &recovery);
+
+    let res = client.try_unlock_subscriber(&subscriber, &subscriber);
+    assert_eq!(res, Err(Ok(Error::Unauthorized)));
+
+    client.unlock_subscriber(&subscriber, &recovery);
+    assert_eq!(client.get_subscriber_lock(&subscriber), None);
+    client.deposit_funds(&id0, &subscriber, &1_000000i128);
+}
+
+// =============================================================================
+// Subscription Label Tests
+// =============================================================================
+
+#[test]
+fn test_create_subscription_with_label() {
+    let (env, client, _, _) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let label = String::from_str(&env, "Netflix family plan");
+
+    let id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &Some(label.clone()),
+        &0i128,
+        &false,
+    );
+    assert_eq!(client.get_subscription(&id).label, Some(label));
+}
+
+#[test]
+fn test_set_subscription_label_by_subscriber() {
+    let (env, client, _, _) = setup_test_env();
+    let (id, subscriber, merchant) =
+        create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    assert_eq!(client.get_subscription(&id).label, None);
+
+    let label = String::from_str(&env, "Music");
+    client.set_subscription_label(&id, &subscriber, &Some(label.clone()));
+    assert_eq!(client.get_subscription(&id).label, Some(label));
+
+    let res = client.try_set_subscription_label(&id, &merchant, &None);
+    assert_eq!(res, Err(Ok(Error::Unauthorized)));
+
+    client.set_subscription_label(&id, &subscriber, &None);
+    assert_eq!(client.get_subscription(&id).label, None);
+}
+
+#[test]
+fn test_label_too_long_rejected() {
+    let (env, client, _, _) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let long = String::from_str(&env, &"x".repeat(65));
+
+    let res = client.try_create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &Some(long),
+        &0i128,
+        &false,
+    );
+    assert_eq!(res, Err(Ok(Error::InvalidLabel)));
+}
+
+// =============================================================================
+// Terms Hash Anchoring Tests
+// =============================================================================
+
+#[test]
+fn test_set_terms_hash_by_merchant() {
+    let (env, client, _, _) = setup_test_env();
+    let (id, _, merchant) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    assert_eq!(client.get_terms_hash(&id), None);
+
+    let v1 = BytesN::from_array(&env, &[1u8; 32]);
+    client.set_terms_hash(&id, &merchant, &v1);
+    assert_eq!(client.get_terms_hash(&id), Some(v1));
+
+    let v2 = BytesN::from_array(&env, &[2u8; 32]);
+    client.set_terms_hash(&id, &merchant, &v2);
+    assert_eq!(env.events().all().len(), 1);
+    assert_eq!(client.get_terms_hash(&id), Some(v2));
+}
+
+#[test]
+fn test_set_terms_hash_rejects_non_merchant() {
+    let (env, client, _, _) = setup_test_env();
+    let (id, subscriber, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    let res = client.try_set_terms_hash(&id, &subscriber, &BytesN::from_array(&env, &[1u8; 32]));
+    assert_eq!(res, Err(Ok(Error::Unauthorized)));
+}
+
+// =============================================================================
+// Indexer Export Tests
+// =============================================================================
+
+#[test]
+fn test_export_subscriptions_pages() {
+    let (env, client, _, _) = setup_test_env();
+    let (a, subscriber, merchant) =
+        create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    let (b, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Paused);
+    let (c, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Cancelled);
+
+    let page1 = client.export_subscriptions(&0, &2);
+    assert_eq!(page1.items.len(), 2);
+    assert_eq!(page1.next_cursor, Some(2));
+    assert_eq!(page1.total, 3);
+    let first = page1.items.get(0).unwrap();
+    assert_eq!(first.id, a);
+    assert_eq!(first.subscriber, subscriber);
+    assert_eq!(first.merchant, merchant);
+    assert_eq!(first.amount, 10_000_000);
+    assert_eq!(page1.items.get(1).unwrap().id, b);
+    assert_eq!(
+        page1.items.get(1).unwrap().status,
+        SubscriptionStatus::Paused
+    );
+
+    let page2 = client.export_subscriptions(&page1.next_cursor.unwrap(), &2);
+    assert_eq!(page2.items.len(), 1);
+    assert_eq!(page2.items.get(0).unwrap().id, c);
+    assert_eq!(page2.next_cursor, None);
+}
+
+#[test]
+fn test_export_subscriptions_empty() {
+    let (_, client, _, _) = setup_test_env();
+    let page = client.export_subscriptions(&0, &10);
+    assert_eq!(page.items.len(), 0);
+    assert_eq!(page.next_cursor, None);
+    assert_eq!(page.total, 0);
+}
+
+// =============================================================================
+// Ledger-Sequence Scheduling Tests
+// =============================================================================
+
+#[test]
+fn test_ledger_schedule_charges_by_sequence() {
+    let env = Env::default();
+    let (client, _, id0, _) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    env.ledger().set_sequence_number(100);
+    client.set_ledger_schedule(&id0, &merchant, &Some(10));
+
+    // Timestamp has elapsed, but ledger interval has not.
+    let res = client.try_charge_subscription(&id0);
+    assert_eq!(res, Err(Ok(Error::IntervalNotElapsed)));
+
+    env.ledger().set_sequence_number(110);
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        10_000000 - 1000
+    );
+
+    // Same ledger period cannot be charged twice.
+    let res = client.try_charge_subscription(&id0);
+    assert_eq!(res, Err(Ok(Error::Replay)));
+}
+
+#[test]
+fn test_schedule_info_conversions() {
+    let env = Env::default();
+    let (client, _, id0, _) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    env.ledger().set_sequence_number(100);
+
+    let info = client.get_schedule_info(&id0);
+    assert_eq!(info.basis, ScheduleBasis::Timestamp);
+    assert_eq!(info.next_charge_timestamp, T0 + INTERVAL);
+    assert_eq!(info.next_charge_ledger, 100);
+
+    client.set_ledger_schedule(&id0, &merchant, &Some(10));
+    let info = client.get_schedule_info(&id0);
+    assert_eq!(info.basis, ScheduleBasis::LedgerSequence);
+    assert_eq!(info.next_charge_ledger, 110);
+    assert_eq!(info.next_charge_timestamp, T0 + INTERVAL + 50);
+
+    client.set_ledger_schedule(&id0, &merchant, &None);
+    assert_eq!(
+        client.get_schedule_info(&id0).basis,
+        ScheduleBasis::Timestamp
+    );
+}
+
+#[test]
+fn test_set_ledger_schedule_validation() {
+    let env = Env::default();
+    let (client, _, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+
+    let res = client.try_set_ledger_schedule(&id0, &sub.subscriber, &Some(10));
+    assert_eq!(res, Err(Ok(Error::Unauthorized)));
+    let res = client.try_set_ledger_schedule(&id0, &sub.merchant, &Some(0));
+    assert_eq!(res, Err(Ok(Error::InvalidAmount)));
+}
+
+// =============================================================================
+// Merchant Reserve Policy Tests
+// =============================================================================
+
+#[test]
+fn test_reserve_intervals_default_and_update() {
+    let (env, client, _, _) = setup_test_env();
+    let merchant = Address::generate(&env);
+    assert_eq!(client.get_reserve_intervals(&merchant), 0);
+    client.set_reserve_intervals(&merchant, &3);
+    assert_eq!(client.get_reserve_intervals(&merchant), 3);
+}
+
+#[test]
+fn test_withdraw_excess_without_reserve() {
+    let env = Env::default();
+    let (client, _, id0, _) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+    let before = token.balance(&subscriber);
+
+    client.withdraw_excess(&id0, &subscriber, &4_000000i128);
+    assert_eq!(client.get_subscription(&id0).prepaid_balance, 6_000000);
+    assert_eq!(token.balance(&subscriber), before + 4_000000);
+
+    let res = client.try_withdraw_excess(&id0, &subscriber, &7_000000i128);
+    assert_eq!(res, Err(Ok(Error::InsufficientPrepaidBalance)));
+}
+
+#[test]
+fn test_withdraw_excess_enforces_reserve() {
+    let env = Env::default();
+    let (client, _, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    // amount = 1000, 3 intervals => reserve 3000
+    client.set_reserve_intervals(&sub.merchant, &3);
+
+    let res = client.try_withdraw_excess(&id0, &sub.subscriber, &(10_000000 - 2999));
+    assert_eq!(res, Err(Ok(Error::BelowReserve)));
+
+    client.withdraw_excess(&id0, &sub.subscriber, &(10_000000 - 3000));
+    assert_eq!(client.get_subscription(&id0).prepaid_balance, 3000);
+}
+
+#[test]
+fn test_withdraw_excess_rejects_other_callers_and_cancelled() {
+    let env = Env::default();
+    let (client, _, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+
+    let res = client.try_withdraw_excess(&id0, &sub.merchant, &1000i128);
+    assert_eq!(res, Err(Ok(Error::Unauthorized)));
+
+    client.cancel_subscription(&id0, &sub.subscriber);
+    let res = client.try_withdraw_excess(&id0, &sub.subscriber, &1000i128);
+    assert_eq!(res, Err(Ok(Error::NotActive)));
+}
+
+#[test]
+fn test_deposit_below_reserve_emits_warning() {
+    let env = Env::default();
+    let (client, _, _, id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id1);
+    client.set_reserve_intervals(&sub.merchant, &5_000);
+
+    client.deposit_funds(&id1, &sub.subscriber, &1_000000i128);
+    // token transfer + deposited + reserve_warning
+    let events = env.events().all();
+    assert_eq!(events.len(), 3);
+}
+
+// =============================================================================
+// Quote Tests
+// =============================================================================
+
+#[test]
+fn test_get_quote_basic() {
+    let (env, client, _, _) = setup_test_env();
+    let merchant = Address::generate(&env);
+
+    let quote = client.get_quote(&merchant, &10_000_000i128, &2_000_000i128);
+    assert_eq!(quote.first_charge_amount, 10_000_000);
+    assert_eq!(quote.recurring_amount, 10_000_000);
+    assert_eq!(quote.per_interval_total, 12_000_000);
+    assert_eq!(quote.required_initial_deposit, 12_000_000);
+}
+
+#[test]
+fn test_get_quote_respects_min_topup_and_reserve() {
+    let (env, client, _, _) = setup_test_env();
+    let merchant = Address::generate(&env);
+
+    // Tiny plan: min_topup (1 USDC) dominates.
+    let quote = client.get_quote(&merchant, &1000i128, &0i128);
+    assert_eq!(quote.required_initial_deposit, 1_000000);
+
+    client.set_reserve_intervals(&merchant, &3);
+    let quote = client.get_quote(&merchant, &10_000_000i128, &0i128);
+    assert_eq!(quote.required_initial_deposit, 30_000_000);
+}
+
+#[test]
+fn test_get_quote_rejects_negative_inputs() {
+    let (env, client, _, _) = setup_test_env();
+    let merchant = Address::generate(&env);
+    let res = client.try_get_quote(&merchant, &-1i128, &0i128);
+    assert_eq!(res, Err(Ok(Error::InvalidAmount)));
+    let res = client.try_get_quote(&merchant, &1i128, &-1i128);
+    assert_eq!(res, Err(Ok(Error::InvalidAmount)));
+}
+
+// =============================================================================
+// Admin Force-Charge Tests
+// =============================================================================
+
+#[test]
+fn test_force_charge_debits_and_emits_audit_event() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+
+    client.force_charge(&admin, &id0, &500i128, &Symbol::new(&env, "dispute"));
+    // The audit event plus the charge receipt.
+    assert_eq!(env.events().all().len(), 2);
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        10_000000 - 500
+    );
+
+    // Does not consume the regular billing period.
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        10_000000 - 1500
+    );
+}
+
+#[test]
+fn test_force_charge_bounded_by_ceiling_and_balance() {
+    let env = Env::default();
+    let (client, admin, id0, id1) = setup_batch_env(&env);
+    let reason = Symbol::new(&env, "dispute");
+
+    // Above the per-interval amount the subscriber authorized.
+    let res = client.try_force_charge(&admin, &id0, &1001i128, &reason);
+    assert_eq!(res, Err(Ok(Error::InvalidAmount)));
+
+    // id1 has no prepaid balance.
+    let res = client.try_force_charge(&admin, &id1, &1000i128, &reason);
+    assert_eq!(res, Err(Ok(Error::InsufficientPrepaidBalance)));
+}
+
+#[test]
+fn test_force_charge_requires_admin() {
+    let env = Env::default();
+    let (client, _, id0, _) = setup_batch_env(&env);
+    let res = client.try_force_charge(
+        &Address::generate(&env),
+        &id0,
+        &500i128,
+        &Symbol::new(&env, "dispute"),
+    );
+    assert_eq!(res, Err(Ok(Error::Unauthorized)));
+}
+
+// =============================================================================
+// Deposit Rate Limit Tests
+// =============================================================================
+
+#[test]
+fn test_deposit_rate_limit_min_interval() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    client.set_deposit_rate_limit(
+        &admin,
+        &DepositRateLimit {
+            min_interval_seconds: 60,
+            max_per_day: 0,
+        },
+    );
+
+    client.deposit_funds(&id0, &subscriber, &1_000000i128);
+    let res = client.try_deposit_funds(&id0, &subscriber, &1_000000i128);
+    assert_eq!(res, Err(Ok(Error::RateLimited)));
+
+    env.ledger().set_timestamp(T0 + INTERVAL + 60);
+    client.deposit_funds(&id0, &subscriber, &1_000000i128);
+}
+
+#[test]
+fn test_deposit_rate_limit_daily_cap() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    let limit = DepositRateLimit {
+        min_interval_seconds: 0,
+        max_per_day: 2,
+    };
+    client.set_deposit_rate_limit(&admin, &limit);
+    assert_eq!(client.get_deposit_rate_limit(), limit);
+
+    client.deposit_funds(&id0, &subscriber, &1_000000i128);
+    client.deposit_funds(&id0, &subscriber, &1_000000i128);
+    let res = client.try_deposit_funds(&id0, &subscriber, &1_000000i128);
+    assert_eq!(res, Err(Ok(Error::RateLimited)));
+
+    // Cap resets on the next day.
+    env.ledger().set_timestamp(T0 + INTERVAL + 24 * 60 * 60);
+    client.deposit_funds(&id0, &subscriber, &1_000000i128);
+}
+
+#[test]
+fn test_deposit_rate_limit_unauthorized() {
+    let (env, client, _, _) = setup_test_env();
+    let res = client.try_set_deposit_rate_limit(
+        &Address::generate(&env),
+        &DepositRateLimit {
+            min_interval_seconds: 1,
+            max_per_day: 1,
+        },
+    );
+    assert_eq!(res, Err(Ok(Error::Unauthorized)));
+}
+
+// =============================================================================
+// Settlement Token Rotation Tests
+// =============================================================================
+
+const TOKEN_ROTATION_DELAY: u64 = 2 * 24 * 60 * 60;
+
+#[test]
+fn test_token_rotation_timelock() {
+    let env = Env::default();
+    let (client, admin, _, _) = setup_batch_env(&env);
+    let new_token = env
+        .register_stellar_asset_contract_v2(admin.clone())
+        .address();
+
+    client.propose_token_rotation(&admin, &new_token);
+    let pending = client.get_pending_token_rotation().unwrap();
+    assert_eq!(pending.new_token, new_token);
+
+    let res = client.try_execute_token_rotation(&admin);
+    assert_eq!(res, Err(Ok(Error::TimelockNotElapsed)));
+
+    env.ledger()
+        .set_timestamp(T0 + INTERVAL + TOKEN_ROTATION_DELAY);
+    client.execute_token_rotation(&admin);
+    assert_eq!(client.get_token(), new_token);
+    assert_eq!(client.get_pending_token_rotation(), None);
+}
+
+#[test]
+fn test_token_rotation_cancel_and_auth() {
+    let env = Env::default();
+    let (client, admin, _, _) = setup_batch_env(&env);
+    let new_token = Address::generate(&env);
+
+    let res = client.try_propose_token_rotation(&Address::generate(&env), &new_token);
+    assert_eq!(res, Err(Ok(Error::Unauthorized)));
+
+    client.propose_token_rotation(&admin, &new_token);
+    client.cancel_token_rotation(&admin);
+    assert_eq!(client.get_pending_token_rotation(), None);
+    let res = client.try_execute_token_rotation(&admin);
+    assert_eq!(res, Err(Ok(Error::NotFound)));
+}
+
+#[test]
+fn test_existing_subscription_keeps_original_token() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    let old_token = client.get_token();
+    let new_token = env
+        .register_stellar_asset_contract_v2(admin.clone())
+        .address();
+    let sub = client.get_subscription(&id0);
+
+    client.propose_token_rotation(&admin, &new_token);
+    env.ledger()
+        .set_timestamp(T0 + INTERVAL + TOKEN_ROTATION_DELAY);
+    client.execute_token_rotation(&admin);
+
+    // Old subscription still settles in the original token.
+    assert_eq!(client.get_subscription_token(&id0), old_token);
+    let old_client = soroban_sdk::token::Client::new(&env, &old_token);
+    let before = old_client.balance(&sub.subscriber);
+    client.cancel_subscription(&id0, &sub.subscriber);
+    client.withdraw_subscriber_funds(&id0, &sub.subscriber);
+    assert_eq!(
+        old_client.balance(&sub.subscriber),
+        before + sub.prepaid_balance
+    );
+
+    // New subscriptions use the new token.
+    let id2 = client.create_subscription(
+        &sub.subscriber,
+        &sub.merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+    assert_eq!(client.get_subscription_token(&id2), new_token);
+    soroban_sdk::token::StellarAssetClient::new(&env, &new_token).mint(&sub.subscriber, &5_000000);
+    client.deposit_funds(&id2, &sub.subscriber, &5_000000i128);
+    assert_eq!(
+        soroban_sdk::token::Client::new(&env, &new_token).balance(&client.address),
+        5_000000
+    );
+}
+
+// =============================================================================
+// Merchant Settlement Report Tests
+// =============================================================================
+
+#[test]
+fn test_settlement_summary_on_withdraw() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+
+    client.charge_subscription(&id0);
+    client.force_charge(&admin, &id0, &250i128, &Symbol::new(&env, "adjust"));
+    env.ledger().set_timestamp(T0 + INTERVAL + 100);
+    client.withdraw_merchant_funds(&merchant, &1250i128);
+
+    let settlements = client.get_settlements(&merchant, &0, &100).items;
+    assert_eq!(settlements.len(), 1);
+    let s = settlements.get(0).unwrap();
+    assert_eq!(s.period_start, T0 + INTERVAL);
+    assert_eq!(s.period_end, T0 + INTERVAL + 100);
+    assert_eq!(s.charge_count, 2);
+    assert_eq!(s.gross, 1250);
+    assert_eq!(s.fees, 0);
+    assert_eq!(s.net, 1250);
+    assert_eq!(s.withdrawn, 1250);
+
+    // Next epoch starts empty at the payout time.
+    client.withdraw_merchant_funds(&merchant, &0i128);
+    let s2 = client
+        .get_settlements(&merchant, &1, &100)
+        .items
+        .get(0)
+        .unwrap();
+    assert_eq!(s2.period_start, T0 + INTERVAL + 100);
+    assert_eq!(s2.charge_count, 0);
+}
+
+#[test]
+fn test_settlement_history_is_bounded() {
+    let env = Env::default();
+    let (client, _admin, id0, _) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    client.charge_subscription(&id0);
+    for i in 0..15 {
+        client.withdraw_merchant_funds(&merchant, &(i as i128));
+    }
+    let settlements = client.get_settlements(&merchant, &0, &100).items;
+    assert_eq!(settlements.len(), 12);
+    assert_eq!(settlements.get(0).unwrap().withdrawn, 3);
+    assert_eq!(client.get_settlements(&merchant, &12, &100).items.len(), 0);
+}
+
+#[test]
+fn test_withdraw_merchant_funds_transfers_accrued_balance() {
+    let env = Env::default();
+    let (client, _admin, id0, _) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+
+    // Nothing accrued yet.
+    assert_eq!(
+        client.try_withdraw_merchant_funds(&merchant, &1i128).err(),
+        Some(Ok(Error::InsufficientBalance))
+    );
+
+    client.charge_subscription(&id0);
+    let vault_before = token.balance(&client.address);
+    client.withdraw_merchant_funds(&merchant, &600i128);
+
+    assert_eq!(token.balance(&merchant), 600);
+    assert_eq!(token.balance(&client.address), vault_before - 600);
+    assert_eq!(
+        client.get_payout_queue(&merchant, &token.address).pending,
+        400
+    );
+    assert_eq!(
+        client
+            .try_withdraw_merchant_funds(&merchant, &401i128)
+            .err(),
+        Some(Ok(Error::InsufficientBalance))
+    );
+
+    client.withdraw_merchant_funds(&merchant, &400i128);
+    assert_eq!(token.balance(&merchant), 1000);
+    assert_eq!(
+        client.get_payout_queue(&merchant, &token.address).pending,
+        0
+    );
+}
+
+// =============================================================================
+// Resumable Batch Charge Tests
+// =============================================================================
+
+#[test]
+fn test_batch_charge_resumable_pages_through_all_ids() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let id2 = client.create_subscription(
+        &client.get_subscription(&id0).subscriber,
+        &client.get_subscription(&id0).merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+    client.pause_subscription(&id2, &client.get_subscription(&id2).subscriber);
+
+    let first = client.batch_charge_resumable(&None, &2);
+    assert_eq!(first.attempted_ids.len(), 2);
+    assert_eq!(first.attempted_ids.get(0).unwrap(), id0);
+    assert!(first.results.get(0).unwrap().success);
+    assert_eq!(first.attempted_ids.get(1).unwrap(), id1);
+    assert_eq!(
+        first.results.get(1).unwrap().error_code,
+        Error::InsufficientBalance.to_code()
+    );
+    assert!(first.has_next);
+    let token = first.resume_token;
+    assert_eq!(token.last_processed_id, id1);
+    assert_eq!(token.ledger, env.ledger().sequence());
+
+    // Paused id2 is scanned but not attempted; the scan is complete.
+    let second = client.batch_charge_resumable(&Some(token), &2);
+    assert_eq!(second.attempted_ids.len(), 0);
+    assert!(!second.has_next);
+}
+
+#[test]
+fn test_batch_charge_resumable_rejects_bad_input() {
+    let env = Env::default();
+    let (client, _admin, _id0, _id1) = setup_batch_env(&env);
+    let res = client.try_batch_charge_resumable(&None, &0);
+    assert_eq!(res, Err(Ok(Error::InvalidAmount)));
+
+    let future = crate::ResumeToken {
+        last_processed_id: 0,
+        ledger: env.ledger().sequence() + 1,
+    };
+    let res = client.try_batch_charge_resumable(&Some(future), &10);
+    assert_eq!(res, Err(Ok(Error::InvalidAmount)));
+}
+
+// =============================================================================
+// Fixed-Term Renewal Tests
+// =============================================================================
+
+#[test]
+fn test_fixed_term_completes_and_renews() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    client.set_fixed_term(&id0, &sub.merchant, &2);
+
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_subscription(&id0).status,
+        SubscriptionStatus::Active
+    );
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_subscription(&id0).status,
+        SubscriptionStatus::Completed
+    );
+    assert_eq!(client.get_fixed_term(&id0).unwrap().periods_charged, 2);
+
+    // No further charges or cancellation once completed.
+    env.ledger().set_timestamp(T0 + 3 * INTERVAL);
+    assert_eq!(
+        client.try_charge_subscription(&id0),
+        Err(Ok(Error::NotActive))
+    );
+
+    let new_id = client.renew_subscription(&id0, &sub.subscriber, &Some(1500i128));
+    let successor = client.get_subscription(&new_id);
+    assert_eq!(successor.status, SubscriptionStatus::Active);
+    assert_eq!(successor.amount, 1500);
+    assert_eq!(successor.merchant, sub.merchant);
+    assert_eq!(successor.interval_seconds, INTERVAL);
+    assert_eq!(successor.prepaid_balance, 0);
+    assert_eq!(
+        client.get_fixed_term(&new_id),
+        Some(crate::FixedTerm {
+            total_periods: 2,
+            periods_charged: 0
+        })
+    );
+    assert_eq!(
+        client.get_subscription_lineage(&id0).successor,
+        Some(new_id)
+    );
+    assert_eq!(
+        client.get_subscription_lineage(&new_id).predecessor,
+        Some(id0)
+    );
+
+    // Leftover balance on the completed subscription is still withdrawable.
+    client.withdraw_subscriber_funds(&id0, &sub.subscriber);
+    assert_eq!(client.get_subscription(&id0).prepaid_balance, 0);
+}
+
+#[test]
+fn test_renew_subscription_rejects_invalid() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+
+    // Not completed yet.
+    assert_eq!(
+        client.try_renew_subscription(&id0, &sub.subscriber, &None),
+        Err(Ok(Error::InvalidStatusTransition))
+    );
+    assert_eq!(
+        client.try_set_fixed_term(&id0, &sub.merchant, &0),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_set_fixed_term(&id0, &sub.subscriber, &1),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    client.set_fixed_term(&id0, &sub.merchant, &1);
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.try_renew_subscription(&id0, &sub.merchant, &None),
+        Err(Ok(Error::Unauthorized))
+    );
+    assert_eq!(
+        client.try_renew_subscription(&id0, &sub.subscriber, &Some(0i128)),
+        Err(Ok(Error::InvalidAmount))
+    );
+    client.renew_subscription(&id0, &sub.subscriber, &None);
+    // Each subscription renews only once.
+    assert_eq!(
+        client.try_renew_subscription(&id0, &sub.subscriber, &None),
+        Err(Ok(Error::InvalidStatusTransition))
+    );
+}
+
+// =============================================================================
+// Donation Round-Up Tests
+// =============================================================================
+
+#[test]
+fn test_round_up_routes_difference_to_donation_address() {
+    let env = Env::default();
+    let (client, admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let charity = Address::generate(&env);
+    client.set_donation_address(&admin, &Some(charity.clone()));
+    client.set_round_up(&sub.subscriber, &1500i128);
+
+    client.charge_subscription(&id0);
+
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        10_000000 - 1500
+    );
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+    assert_eq!(token.balance(&charity), 500);
+    assert_eq!(client.get_round_up(&sub.subscriber).total_donated, 500);
+}
+
+#[test]
+fn test_round_up_skipped_without_address_or_exact_multiple() {
+    let env = Env::default();
+    let (client, admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+
+    // Opted in but no donation address configured.
+    client.set_round_up(&sub.subscriber, &1500i128);
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        10_000000 - 1000
+    );
+
+    // Amount is already a multiple of the unit.
+    client.set_donation_address(&admin, &Some(Address::generate(&env)));
+    client.set_round_up(&sub.subscriber, &500i128);
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        10_000000 - 2000
+    );
+    assert_eq!(client.get_round_up(&sub.subscriber).total_donated, 0);
+
+    assert_eq!(
+        client.try_set_round_up(&sub.subscriber, &-1i128),
+        Err(Ok(Error::InvalidAmount))
+    );
+}
+
+// =============================================================================
+// Grant Credit Time Tests
+// =============================================================================
+
+#[test]
+fn test_grant_credit_time_pushes_due_date() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let day = 24 * 60 * 60;
+
+    let next_due = client.grant_credit_time(&id0, &merchant, &day);
+    assert_eq!(next_due, T0 + INTERVAL + day);
+    assert_eq!(
+        client.try_charge_subscription(&id0),
+        Err(Ok(Error::IntervalNotElapsed))
+    );
+
+    env.ledger().set_timestamp(T0 + INTERVAL + day);
+    client.charge_subscription(&id0);
+}
+
+#[test]
+fn test_grant_credit_time_capped_per_period() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+
+    assert_eq!(
+        client.try_grant_credit_time(&id0, &sub.subscriber, &100),
+        Err(Ok(Error::Unauthorized))
+    );
+    assert_eq!(
+        client.try_grant_credit_time(&id0, &sub.merchant, &0),
+        Err(Ok(Error::InvalidAmount))
+    );
+    client.grant_credit_time(&id0, &sub.merchant, &(INTERVAL - 10));
+    assert_eq!(
+        client.try_grant_credit_time(&id0, &sub.merchant, &11),
+        Err(Ok(Error::LimitExceeded))
+    );
+    client.grant_credit_time(&id0, &sub.merchant, &10);
+
+    // The cap resets in the next billing period.
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.grant_credit_time(&id0, &sub.merchant, &10);
+}
+
+// =============================================================================
+// Merchant Payout Queue Tests
+// =============================================================================
+
+#[test]
+fn test_payout_queue_realizes_at_threshold() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let token_addr = client.get_token();
+    let token = soroban_sdk::token::Client::new(&env, &token_addr);
+    client.set_payout_policy(
+        &merchant,
+        &Some(crate::PayoutPolicy {
+            threshold: 2000,
+            interval_seconds: 0,
+        }),
+    );
+
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_payout_queue(&merchant, &token_addr).pending,
+        1000
+    );
+    assert_eq!(token.balance(&merchant), 0);
+
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id0);
+    let queue = client.get_payout_queue(&merchant, &token_addr);
+    assert_eq!(queue.pending, 0);
+    assert_eq!(queue.last_payout_at, T0 + 2 * INTERVAL);
+    assert_eq!(token.balance(&merchant), 2000);
+}
+
+#[test]
+fn test_payout_queue_realizes_on_schedule() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let token_addr = client.get_token();
+    let token = soroban_sdk::token::Client::new(&env, &token_addr);
+
+    // Credits queue up while no policy is set.
+    client.charge_subscription(&id0);
+    assert_eq!(client.process_payout(&merchant, &token_addr), 0);
+
+    // The schedule runs from the first credit (T0 + INTERVAL).
+    client.set_payout_policy(
+        &merchant,
+        &Some(crate::PayoutPolicy {
+            threshold: 0,
+            interval_seconds: 2 * INTERVAL,
+        }),
+    );
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_payout_queue(&merchant, &token_addr).pending,
+        2000
+    );
+    assert_eq!(client.process_payout(&merchant, &token_addr), 0);
+
+    env.ledger().set_timestamp(T0 + 3 * INTERVAL);
+    assert_eq!(client.process_payout(&merchant, &token_addr), 2000);
+    assert_eq!(token.balance(&merchant), 2000);
+}
+
+// =============================================================================
+// Admin Audit Log Tests
+// =============================================================================
+
+#[test]
+fn test_admin_log_records_mutations_in_order() {
+    let (env, client, _, admin) = setup_test_env();
+    assert_eq!(client.get_admin_log(&0, &10).total, 0);
+
+    client.set_min_topup(&admin, &2_000000i128);
+    client.set_cooling_off_period(&admin, &3600);
+    let new_admin = Address::generate(&env);
+    client.rotate_admin(&admin, &new_admin);
+
+    let log = client.get_admin_log(&0, &10).items;
+    assert_eq!(log.len(), 3);
+    let first = log.get(0).unwrap();
+    assert_eq!(first.seq, 0);
+    assert_eq!(first.admin, admin);
+    assert_eq!(first.action, Symbol::new(&env, "set_min_topup"));
+    assert_eq!(
+        log.get(1).unwrap().action,
+        Symbol::new(&env, "set_cooling_off_period")
+    );
+    assert_eq!(log.get(2).unwrap().seq, 2);
+    assert_eq!(
+        log.get(2).unwrap().action,
+        Symbol::new(&env, "rotate_admin")
+    );
+
+    // Failed (unauthorized) calls are not logged.
+    let res = client.try_set_min_topup(&admin, &1i128);
+    assert!(res.is_err());
+    assert_eq!(client.get_admin_log(&0, &10).total, 3);
+}
+
+#[test]
+fn test_admin_log_pagination() {
+    let (_env, client, _, admin) = setup_test_env();
+    for i in 0..120 {
+        client.set_cooling_off_period(&admin, &(i as u64));
+    }
+    // Limits above the page cap are clamped.
+    let page = client.get_admin_log(&0, &500);
+    assert_eq!(page.items.len(), 100);
+    assert_eq!(page.next_cursor, Some(100));
+    assert_eq!(page.total, 120);
+    let rest = client.get_admin_log(&page.next_cursor.unwrap(), &50);
+    assert_eq!(rest.items.len(), 20);
+    assert_eq!(rest.items.get(19).unwrap().seq, 119);
+    assert_eq!(rest.next_cursor, None);
+    assert_eq!(client.get_admin_log(&120, &10).items.len(), 0);
+}
+
+#[test]
+fn test_admin_log_entries_kept_in_persistent_storage() {
+    use soroban_sdk::testutils::storage::Persistent as _;
+    let (env, client, _, admin) = setup_test_env();
+    client.set_cooling_off_period(&admin, &3600);
+    env.as_contract(&client.address, || {
+        let key = (soroban_sdk::symbol_short!("alog"), 0u32);
+        assert!(env.storage().persistent().has(&key));
+        assert!(!env.storage().instance().has(&key));
+        assert!(env.storage().persistent().get_ttl(&key) >= crate::archive::ENTRY_TTL - 1);
+    });
+}
+
+// =============================================================================
+// Deferred Transfer / Claims Tests
+// =============================================================================
+
+#[test]
+fn test_failed_payout_is_deferred_to_claims() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let token_addr = client.get_token();
+    let token = soroban_sdk::token::Client::new(&env, &token_addr);
+    let sac = soroban_sdk::token::StellarAssetClient::new(&env, &token_addr);
+    client.set_payout_policy(
+        &merchant,
+        &Some(crate::PayoutPolicy {
+            threshold: 1000,
+            interval_seconds: 0,
+        }),
+    );
+
+    // Frozen trustline: the payout transfer fails but the charge still succeeds.
+    sac.set_authorized(&merchant, &false);
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        10_000000 - 1000
+    );
+    assert_eq!(client.get_claimable(&merchant, &token_addr), 1000);
+    assert_eq!(client.get_payout_queue(&merchant, &token_addr).pending, 0);
+
+    // Claiming while still frozen reverts and keeps the claim.
+    assert!(client.try_claim(&merchant, &token_addr).is_err());
+    assert_eq!(client.get_claimable(&merchant, &token_addr), 1000);
+
+    sac.set_authorized(&merchant, &true);
+    assert_eq!(client.claim(&merchant, &token_addr), 1000);
+    assert_eq!(token.balance(&merchant), 1000);
+    assert_eq!(client.get_claimable(&merchant, &token_addr), 0);
+    assert_eq!(
+        client.try_claim(&merchant, &token_addr),
+        Err(Ok(Error::NotFound))
+    );
+}
+
+#[test]
+fn test_failed_subscriber_withdrawal_is_deferred() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    let token_addr = client.get_token();
+    let sac = soroban_sdk::token::StellarAssetClient::new(&env, &token_addr);
+
+    client.cancel_subscription(&id0, &subscriber);
+    sac.set_authorized(&subscriber, &false);
+    client.withdraw_subscriber_funds(&id0, &subscriber);
+    assert_eq!(client.get_subscription(&id0).prepaid_balance, 0);
+    assert_eq!(client.get_claimable(&subscriber, &token_addr), 10_000000);
+}
+
+// =============================================================================
+// Pause Retainer Tests
+// =============================================================================
+
+#[test]
+fn test_paused_subscription_billed_retainer() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+
+    // Without a retainer, paused subscriptions are not charged.
+    client.pause_subscription(&id0, &sub.subscriber);
+    assert_eq!(
+        client.try_charge_subscription(&id0),
+        Err(Ok(Error::NotActive))
+    );
+
+    // A retainer proposed during the pause does not bill it until accepted.
+    client.set_pause_retainer(&id0, &sub.merchant, &100i128);
+    assert_eq!(client.get_proposed_pause_retainer(&id0), 100);
+    assert_eq!(
+        client.try_charge_subscription(&id0),
+        Err(Ok(Error::NotActive))
+    );
+    assert_eq!(client.get_subscription(&id0).prepaid_balance, 10_000000);
+
+    assert_eq!(
+        client.try_accept_pause_retainer(&id0, &sub.subscriber, &50i128),
+        Err(Ok(Error::InvalidAmount))
+    );
+    client.accept_pause_retainer(&id0, &sub.subscriber, &100i128);
+    assert_eq!(client.get_pause_retainer(&id0), 100);
+    client.charge_subscription(&id0);
+    let after = client.get_subscription(&id0);
+    assert_eq!(after.status, SubscriptionStatus::Paused);
+    assert_eq!(after.prepaid_balance, 10_000000 - 100);
+
+    // Normal cadence still applies.
+    assert_eq!(client.try_charge_subscription(&id0), Err(Ok(Error::Replay)));
+
+    // After resuming, the full amount is billed again.
+    client.resume_subscription(&id0, &sub.subscriber);
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        10_000000 - 100 - 1000
+    );
+}
+
+#[test]
+fn test_pause_retainer_validation_and_insufficient_balance() {
+    let env = Env::default();
+    let (client, _admin, _id0, id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id1);
+
+    assert_eq!(
+        client.try_set_pause_retainer(&id1, &sub.merchant, &1001i128),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_set_pause_retainer(&id1, &sub.merchant, &1000i128),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_set_pause_retainer(&id1, &sub.subscriber, &10i128),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    // id1 has no balance: the retainer fails without leaving Paused.
+    client.set_pause_retainer(&id1, &sub.merchant, &10i128);
+    assert_eq!(
+        client.try_accept_pause_retainer(&id1, &sub.merchant, &10i128),
+        Err(Ok(Error::Unauthorized))
+    );
+    client.accept_pause_retainer(&id1, &sub.subscriber, &10i128);
+    client.pause_subscription(&id1, &sub.subscriber);
+    assert_eq!(
+        client.try_charge_subscription(&id1),
+        Err(Ok(Error::InsufficientBalance))
+    );
+    assert_eq!(
+        client.get_subscription(&id1).status,
+        SubscriptionStatus::Paused
+    );
+}
+
+#[test]
+fn test_pause_and_resume_require_subscriber_or_merchant() {
+    let env = Env::default();
+    let (client, _, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let stranger = Address::generate(&env);
+    assert_eq!(
+        client.try_pause_subscription(&id0, &stranger),
+        Err(Ok(Error::Unauthorized))
+    );
+    client.pause_subscription(&id0, &sub.merchant);
+    assert_eq!(
+        client.try_resume_subscription(&id0, &stranger),
+        Err(Ok(Error::Unauthorized))
+    );
+    client.resume_subscription(&id0, &sub.subscriber);
+    assert_eq!(
+        client.get_subscription(&id0).status,
+        SubscriptionStatus::Active
+    );
+}
+
+// =============================================================================
+// Billing Heartbeat Tests
+// =============================================================================
+
+#[test]
+fn test_last_billing_run_recorded_by_batch_charge() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    assert_eq!(client.get_last_billing_run(), None);
+
+    let mut ids = SorobanVec::<u32>::new(&env);
+    ids.push_back(id0);
+    client.batch_charge(&ids);
+    assert_eq!(client.get_last_billing_run(), Some(T0 + INTERVAL));
+
+    env.ledger().set_timestamp(T0 + INTERVAL + 10);
+    client.batch_charge_resumable(&None, &10);
+    assert_eq!(client.get_last_billing_run(), Some(T0 + INTERVAL + 10));
+}
+
+#[test]
+fn test_billing_stalled_event_when_stale() {
+    let env = Env::default();
+    let (client, admin, id0, _id1) = setup_batch_env(&env);
+    let mut ids = SorobanVec::<u32>::new(&env);
+    ids.push_back(id0);
+    client.batch_charge(&ids);
+    client.set_billing_stale_threshold(&admin, &3600);
+
+    env.ledger().set_timestamp(T0 + INTERVAL + 3600);
+    client.get_last_billing_run();
+    assert_eq!(env.events().all().len(), 0);
+
+    env.ledger().set_timestamp(T0 + INTERVAL + 3601);
+    assert_eq!(client.get_last_billing_run(), Some(T0 + INTERVAL));
+    assert_eq!(env.events().all().len(), 1);
+}
+
+// =============================================================================
+// Pagination Tests
+// =============================================================================
+
+#[test]
+fn test_merchant_subscriptions_page_metadata() {
+    let (env, client, _, _) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    for _ in 0..3 {
+        client.create_subscription(
+            &subscriber,
+            &merchant,
+            &1000i128,
+            &INTERVAL,
+            &false,
+            &None,
+            &0i128,
+            &false,
+        );
+    }
+
+    let page = client.get_subscriptions_by_merchant(&merchant, &0, &2);
+    assert_eq!(page.items.len(), 2);
+    assert_eq!(page.next_cursor, Some(2));
+    assert_eq!(page.total, 3);
+    let last = client.get_subscriptions_by_merchant(&merchant, &2, &2);
+    assert_eq!(last.items.len(), 1);
+    assert_eq!(last.next_cursor, None);
+    assert_eq!(
+        client
+            .get_subscriptions_by_merchant(&merchant, &5, &2)
+            .items
+            .len(),
+        0
+    );
+}
+
+#[test]
+fn test_subscriber_page_total_and_cursor() {
+    let (env, client, _, _) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let other = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let a = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+    client.create_subscription(
+        &other, &merchant, &1000i128, &INTERVAL, &false, &None, &0i128, &false,
+    );
+    let c = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+
+    let page = client.list_subscriptions_by_subscriber(&subscriber, &0, &1);
+    assert_eq!(page.items.get(0).unwrap(), a);
+    assert_eq!(page.next_cursor, Some(c));
+    assert_eq!(page.total, 2);
+    let page2 = client.list_subscriptions_by_subscriber(&subscriber, &c, &1);
+    assert_eq!(page2.items.get(0).unwrap(), c);
+    assert_eq!(page2.next_cursor, None);
+    assert_eq!(page2.total, 2);
+}
+
+// =============================================================================
+// Merchant Allow-List Tests
+// =============================================================================
+
+#[test]
+fn test_allowlist_blocks_unapproved_merchants() {
+    let (env, client, _, _) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let approved = Address::generate(&env);
+    let rogue = Address::generate(&env);
+
+    // Off by default: any merchant is accepted.
+    client.create_subscription(
+        &subscriber,
+        &rogue,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+
+    client.approve_merchant(&subscriber, &approved);
+    client.set_allowlist_enforced(&subscriber, &true);
+    assert_eq!(
+        client.try_create_subscription(
+            &subscriber,
+            &rogue,
+            &1000i128,
+            &INTERVAL,
+            &false,
+            &None,
+            &0i128,
+            &false
+        ),
+        Err(Ok(Error::MerchantNotApproved))
+    );
+    client.create_subscription(
+        &subscriber,
+        &approved,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+
+    client.revoke_merchant(&subscriber, &approved);
+    assert_eq!(
+        client.try_create_subscription(
+            &subscriber,
+            &approved,
+            &1000i128,
+            &INTERVAL,
+            &false,
+            &None,
+            &0i128,
+            &false
+        ),
+        Err(Ok(Error::MerchantNotApproved))
+    );
+    assert_eq!(
+        client.try_revoke_merchant(&subscriber, &approved),
+        Err(Ok(Error::NotFound))
+    );
+
+    let allowlist = client.get_merchant_allowlist(&subscriber);
+    assert!(allowlist.enforced);
+    assert_eq!(allowlist.merchants.len(), 0);
+}
+
+#[test]
+fn test_allowlist_is_bounded_and_idempotent() {
+    let (env, client, _, _) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let first = Address::generate(&env);
+    client.approve_merchant(&subscriber, &first);
+    client.approve_merchant(&subscriber, &first);
+    assert_eq!(
+        client.get_merchant_allowlist(&subscriber).merchants.len(),
+        1
+    );
+
+    for _ in 1..crate::MAX_APPROVED_MERCHANTS {
+        client.approve_merchant(&subscriber, &Address::generate(&env));
+    }
+    assert_eq!(
+        client.try_approve_merchant(&subscriber, &Address::generate(&env)),
+        Err(Ok(Error::LimitExceeded))
+    );
+}
+
+// =============================================================================
+// Large Withdrawal Time Lock Tests
+// =============================================================================
+
+#[test]
+fn test_small_withdrawals_stay_instant_under_timelock() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    client.set_withdrawal_timelock(
+        &admin,
+        &WithdrawalTimelock {
+            threshold: 5_000000,
+            delay_seconds: 3600,
+        },
+    );
+
+    client.withdraw_excess(&id0, &subscriber, &5_000000i128);
+    assert_eq!(client.get_subscription(&id0).prepaid_balance, 5_000000);
+}
+
+#[test]
+fn test_large_withdrawal_requires_announced_request_and_delay() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    client.set_withdrawal_timelock(
+        &admin,
+        &WithdrawalTimelock {
+            threshold: 5_000000,
+            delay_seconds: 3600,
+        },
+    );
+
+    assert_eq!(
+        client.try_withdraw_excess(&id0, &subscriber, &8_000000i128),
+        Err(Ok(Error::TimelockNotElapsed))
+    );
+
+    let now = env.ledger().timestamp();
+    let executable_at = client.request_withdrawal(&id0, &subscriber, &8_000000i128);
+    assert_eq!(executable_at, now + 3600);
+    assert_eq!(
+        client.try_withdraw_excess(&id0, &subscriber, &8_000000i128),
+        Err(Ok(Error::TimelockNotElapsed))
+    );
+
+    env.ledger().with_mut(|li| li.timestamp = executable_at);
+    assert_eq!(
+        client.try_withdraw_excess(&id0, &subscriber, &9_000000i128),
+        Err(Ok(Error::InvalidAmount))
+    );
+    client.withdraw_excess(&id0, &subscriber, &8_000000i128);
+    assert_eq!(client.get_subscription(&id0).prepaid_balance, 2_000000);
+    assert_eq!(client.get_pending_withdrawal(&id0), None);
+}
+
+#[test]
+fn test_cancel_withdrawal_request_blocks_execution() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    client.set_withdrawal_timelock(
+        &admin,
+        &WithdrawalTimelock {
+            threshold: 1_000000,
+            delay_seconds: 3600,
+        },
+    );
+
+    client.request_withdrawal(&id0, &subscriber, &10_000000i128);
+    client.cancel_withdrawal_request(&id0, &subscriber);
+    assert_eq!(
+        client.try_cancel_withdrawal_request(&id0, &subscriber),
+        Err(Ok(Error::NotFound))
+    );
+
+    env.ledger().with_mut(|li| li.timestamp += 3600);
+    client.cancel_subscription(&id0, &subscriber);
+    assert_eq!(
+        client.try_withdraw_subscriber_funds(&id0, &subscriber),
+        Err(Ok(Error::TimelockNotElapsed))
+    );
+}
+
+#[test]
+fn test_request_withdrawal_validation() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    let stranger = Address::generate(&env);
+
+    assert_eq!(
+        client.try_request_withdrawal(&id0, &stranger, &1i128),
+        Err(Ok(Error::Unauthorized))
+    );
+    assert_eq!(
+        client.try_request_withdrawal(&id0, &subscriber, &0i128),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_request_withdrawal(&id0, &subscriber, &10_000001i128),
+        Err(Ok(Error::InsufficientPrepaidBalance))
+    );
+    assert_eq!(
+        client.try_set_withdrawal_timelock(
+            &stranger,
+            &WithdrawalTimelock {
+                threshold: 1,
+                delay_seconds: 1,
+            },
+        ),
+        Err(Ok(Error::Unauthorized))
+    );
+    assert_eq!(
+        client.try_set_withdrawal_timelock(
+            &admin,
+            &WithdrawalTimelock {
+                threshold: -1,
+                delay_seconds: 1,
+            },
+        ),
+        Err(Ok(Error::InvalidAmount))
+    );
+}
+
+// =============================================================================
+// Rounding Math Tests
+// =============================================================================
+
+#[test]
+fn test_mul_div_rounding_modes() {
+    assert_eq!(mul_div(10, 1, 3, Rounding::Floor), Ok(3));
+    assert_eq!(mul_div(10, 1, 3, Rounding::Ceil), Ok(4));
+    assert_eq!(mul_div(9, 1, 3, Rounding::Ceil), Ok(3));
+    assert_eq!(
+        mul_div(10, 1, 0, Rounding::Floor),
+        Err(Error::InvalidAmount)
+    );
+    assert_eq!(mul_div(-1, 1, 3, Rounding::Floor), Err(Error::Underflow));
+    assert_eq!(
+        mul_div(i128::MAX, 2, 3, Rounding::Floor),
+        Err(Error::Overflow)
+    );
+}
+
+#[test]
+fn test_bps_fee_and_round_to_multiple() {
+    assert_eq!(bps_fee(1_000000, 250), Ok(25000));
+    assert_eq!(bps_fee(3, 5000), Ok(1));
+    assert_eq!(bps_fee(100, 10_001), Err(Error::InvalidAmount));
+    assert_eq!(round_to_multiple(1234, 100, Rounding::Ceil), Ok(1300));
+    assert_eq!(round_to_multiple(1234, 100, Rounding::Floor), Ok(1200));
+    assert_eq!(round_to_multiple(1200, 100, Rounding::Ceil), Ok(1200));
+    assert_eq!(round_to_multiple(1234, 0, Rounding::Ceil), Ok(1234));
+}
+
+/// Property: across a grid of awkward amounts and rates, fee splits conserve the
+/// charged amount exactly and floored fees never exceed it.
+#[test]
+fn test_split_bps_never_leaks_dust() {
+    let amounts = [
+        0i128, 1, 2, 3, 7, 99, 101, 999, 1_000000, 1_234567, 9_999999,
+    ];
+    let rates = [0u32, 1, 3, 33, 333, 2500, 3333, 6667, 9999, 10_000];
+    for amount in amounts {
+        for bps in rates {
+            let (share, rest) = split_bps(amount, bps).unwrap();
+            assert_eq!(share + rest, amount);
+            assert!(share >= 0 && rest >= 0);
+            assert!(share <= amount);
+            // Floor: the exact share is within one unit above the rounded one.
+            assert!(share * 10_000 <= amount * bps as i128);
+            assert!((share + 1) * 10_000 > amount * bps as i128);
+        }
+    }
+}
+
+/// Property: prorated refunds round in the user's favour but never exceed the
+/// amount charged, and complementary floor/ceil halves sum to the total.
+#[test]
+fn test_prorate_refund_bounded_by_charge() {
+    let amounts = [0i128, 1, 7, 100, 999, 1_000000, 1_234567];
+    let periods = [1u64, 3, 7, 30, 86_400, INTERVAL];
+    for amount in amounts {
+        for period in periods {
+            for remaining in [0, 1, period / 3, period / 2, period - 1, period, period + 5] {
+                let refund = prorate_refund(amount, remaining, period).unwrap();
+                assert!(refund >= 0 && refund <= amount);
+                let kept = mul_div(
+                    amount,
+                    (period - remaining.min(period)) as i128,
+                    period as i128,
+                    Rounding::Floor,
+                )
+                .unwrap();
+                assert_eq!(refund + kept, amount);
+            }
+        }
+    }
+}
+
+// =============================================================================
+// Fixed-Point Decimal Tests
+// =============================================================================
+
+#[test]
+fn test_pow10_bounds() {
+    use crate::fixed_point::{pow10, MAX_DECIMALS};
+    assert_eq!(pow10(0), Ok(1));
+    assert_eq!(pow10(7), Ok(10_000_000));
+    assert_eq!(pow10(MAX_DECIMALS), Ok(10i128.pow(38)));
+    assert_eq!(pow10(MAX_DECIMALS + 1), Err(Error::InvalidAmount));
+}
+
+#[test]
+fn test_normalize_decimals_round_trips_for_all_scales() {
+    use crate::fixed_point::{normalize_decimals, MAX_DECIMALS};
+    for from in 0..=MAX_DECIMALS {
+        for to in from..=MAX_DECIMALS {
+            for amount in [0i128, 1, 7, 999] {
+                match normalize_decimals(amount, from, to, Rounding::Floor) {
+                    Ok(up) => assert_eq!(
+                        normalize_decimals(up, to, from, Rounding::Floor),
+                        Ok(amount)
+                    ),
+                    Err(e) => {
+                        assert_eq!(e, Error::Overflow);
+                        assert!(amount > 1 && to - from >= 36);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_normalize_decimals_extreme_magnitudes() {
+    use crate::fixed_point::normalize_decimals;
+    assert_eq!(
+        normalize_decimals(i128::MAX, 18, 6, Rounding::Floor),
+        Ok(i128::MAX / 10i128.pow(12))
+    );
+    assert_eq!(
+        normalize_decimals(i128::MAX, 18, 6, Rounding::Ceil),
+        Ok(i128::MAX / 10i128.pow(12) + 1)
+    );
+    assert_eq!(
+        normalize_decimals(i128::MAX, 6, 7, Rounding::Floor),
+        Err(Error::Overflow)
+    );
+    assert_eq!(normalize_decimals(i128::MAX, 38, 0, Rounding::Floor), Ok(1));
+    assert_eq!(normalize_decimals(1, 38, 0, Rounding::Floor), Ok(0));
+    assert_eq!(normalize_decimals(1, 38, 0, Rounding::Ceil), Ok(1));
+    assert_eq!(
+        normalize_decimals(-1, 7, 6, Rounding::Floor),
+        Err(Error::Underflow)
+    );
+    assert_eq!(
+        normalize_decimals(1, 39, 6, Rounding::Floor),
+        Err(Error::InvalidAmount)
+    );
+}
+
+#[test]
+fn test_mul_fixed_and_convert_with_price() {
+    use crate::fixed_point::{convert_with_price, mul_fixed};
+    // 2.5 * 1.5 = 3.75 at 6 decimals.
+    assert_eq!(
+        mul_fixed(2_500000, 1_500000, 6, Rounding::Floor),
+        Ok(3_750000)
+    );
+    assert_eq!(mul_fixed(1, 1, 6, Rounding::Floor), Ok(0));
+    assert_eq!(mul_fixed(1, 1, 6, Rounding::Ceil), Ok(1));
+    assert_eq!(
+        mul_fixed(i128::MAX, 2, 0, Rounding::Floor),
+        Err(Error::Overflow)
+    );
+
+    // 3 XLM (7 decimals) at 0.12345678 USDC/XLM (8-decimal price) -> 0.370370 USDC.
+    assert_eq!(
+        convert_with_price(30_000000, 7, 12_345678, 8, 6, Rounding::Floor),
+        Ok(370370)
+    );
+    assert_eq!(
+        convert_with_price(30_000000, 7, 12_345678, 8, 6, Rounding::Ceil),
+        Ok(370371)
+    );
+}
+
+// =============================================================================
+// Receivable Lien Tests
+// =============================================================================
+
+#[test]
+fn test_lien_redirects_share_until_target_repaid() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let token_addr = client.get_token();
+    let token = soroban_sdk::token::Client::new(&env, &token_addr);
+    let lender = Address::generate(&env);
+
+    // 25% of each 1000 charge, up to 400 in total.
+    client.grant_lien(&merchant, &lender, &token_addr, &2500, &400i128);
+
+    client.charge_subscription(&id0);
+    assert_eq!(token.balance(&lender), 250);
+    assert_eq!(client.get_payout_queue(&merchant, &token_addr).pending, 750);
+    assert_eq!(client.get_lien(&merchant).unwrap().repaid, 250);
+
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id0);
+    assert_eq!(token.balance(&lender), 400);
+    assert_eq!(
+        client.get_payout_queue(&merchant, &token_addr).pending,
+        750 + 850
+    );
+    assert_eq!(client.get_lien(&merchant), None);
+
+    env.ledger().set_timestamp(T0 + 3 * INTERVAL);
+    client.charge_subscription(&id0);
+    assert_eq!(token.balance(&lender), 400);
+}
+
+#[test]
+fn test_lien_validation_and_release() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let token_addr = client.get_token();
+    let lender = Address::generate(&env);
+    let other = Address::generate(&env);
+
+    assert_eq!(
+        client.try_grant_lien(&merchant, &lender, &token_addr, &0, &400i128),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_grant_lien(&merchant, &lender, &token_addr, &10_001, &400i128),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_grant_lien(&merchant, &lender, &token_addr, &100, &0i128),
+        Err(Ok(Error::InvalidAmount))
+    );
+
+    client.grant_lien(&merchant, &lender, &token_addr, &100, &400i128);
+    assert_eq!(
+        client.try_grant_lien(&merchant, &other, &token_addr, &100, &400i128),
+        Err(Ok(Error::LimitExceeded))
+    );
+    assert_eq!(
+        client.try_release_lien(&other, &merchant),
+        Err(Ok(Error::Unauthorized))
+    );
+    client.release_lien(&lender, &merchant);
+    assert_eq!(client.get_lien(&merchant), None);
+    assert_eq!(
+        client.try_release_lien(&lender, &merchant),
+        Err(Ok(Error::NotFound))
+    );
+}
+
+#[test]
+fn test_lien_ignores_other_tokens() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let token_addr = client.get_token();
+    let lender = Address::generate(&env);
+    let other_token = Address::generate(&env);
+
+    client.grant_lien(&merchant, &lender, &other_token, &5000, &400i128);
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_payout_queue(&merchant, &token_addr).pending,
+        1000
+    );
+    assert_eq!(client.get_lien(&merchant).unwrap().repaid, 0);
+}
+
+// =============================================================================
+// Proof-of-Service Attestation Tests
+// =============================================================================
+
+#[test]
+fn test_attest_service_for_charged_period() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let proof = BytesN::from_array(&env, &[7u8; 32]);
+
+    assert_eq!(client.get_charged_period(&id0), None);
+    assert_eq!(
+        client.try_attest_service(&id0, &merchant, &0, &proof),
+        Err(Ok(Error::NotFound))
+    );
+
+    client.charge_subscription(&id0);
+    let period = client.get_charged_period(&id0).unwrap();
+    assert_eq!(period, (T0 + INTERVAL) / INTERVAL);
+    assert_eq!(
+        client.try_attest_service(&id0, &merchant, &(period + 1), &proof),
+        Err(Ok(Error::NotFound))
+    );
+
+    client.attest_service(&id0, &merchant, &period, &proof);
+    let attestation = client.get_attestation(&id0, &period).unwrap();
+    assert_eq!(attestation.proof_hash, proof);
+    assert_eq!(attestation.attested_at, T0 + INTERVAL);
+    assert_eq!(client.get_attestation(&id0, &(period - 1)), None);
+}
+
+#[test]
+fn test_attestation_is_immutable_and_merchant_only() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let proof = BytesN::from_array(&env, &[7u8; 32]);
+    client.charge_subscription(&id0);
+    let period = client.get_charged_period(&id0).unwrap();
+
+    assert_eq!(
+        client.try_attest_service(&id0, &sub.subscriber, &period, &proof),
+        Err(Ok(Error::Unauthorized))
+    );
+    client.attest_service(&id0, &sub.merchant, &period, &proof);
+    assert_eq!(
+        client.try_attest_service(
+            &id0,
+            &sub.merchant,
+            &period,
+            &BytesN::from_array(&env, &[8u8; 32])
+        ),
+        Err(Ok(Error::Replay))
+    );
+    assert_eq!(
+        client.get_attestation(&id0, &period).unwrap().proof_hash,
+        proof
+    );
+}
+
+// =============================================================================
+// Bulk Config Tests
+// =============================================================================
+
+fn sample_config() -> Config {
+    Config {
+        min_topup: 5_000000,
+        cooling_off_seconds: 3600,
+        deposit_rate_limit: DepositRateLimit {
+            min_interval_seconds: 60,
+            max_per_day: 10,
+        },
+        withdrawal_timelock: WithdrawalTimelock {
+            threshold: 100_000000,
+            delay_seconds: 7200,
+        },
+        notify_lead_seconds: 86_400,
+        billing_stale_seconds: 43_200,
+    }
+}
+
+#[test]
+fn test_get_config_reports_defaults() {
+    let (_env, client, _token, _admin) = setup_test_env();
+    let config = client.get_config();
+    assert_eq!(config.min_topup, 1_000000);
+    assert_eq!(config.cooling_off_seconds, 0);
+    assert_eq!(config.withdrawal_timelock.threshold, 0);
+    assert_eq!(
+        config.notify_lead_seconds,
+        crate::keeper::DEFAULT_NOTIFY_LEAD_SECONDS
+    );
+    assert_eq!(
+        config.billing_stale_seconds,
+        crate::keeper::DEFAULT_BILLING_STALE_SECONDS
+    );
+}
+
+#[test]
+fn test_set_config_applies_atomically_after_timelock() {
+    let (env, client, _token, admin) = setup_test_env();
+    env.ledger().set_timestamp(T0);
+    let before = client.get_config();
+    let config = sample_config();
+
+    client.set_config(&admin, &config);
+    assert_eq!(client.get_config(), before);
+    assert_eq!(
+        client.try_apply_config(&admin),
+        Err(Ok(Error::TimelockNotElapsed))
+    );
+
+    env.ledger()
+        .set_timestamp(T0 + crate::admin::CONFIG_UPDATE_DELAY);
+    client.apply_config(&admin);
+    assert_eq!(client.get_config(), config);
+    assert_eq!(client.get_min_topup(), 5_000000);
+    assert_eq!(client.get_pending_config(), None);
+    assert_eq!(client.try_apply_config(&admin), Err(Ok(Error::NotFound)));
+}
+
+#[test]
+fn test_set_config_rejects_inconsistent_fields() {
+    let (_env, client, _token, admin) = setup_test_env();
+
+    let mut config = sample_config();
+    config.withdrawal_timelock.delay_seconds = 0;
+    assert_eq!(
+        client.try_set_config(&admin, &config),
+        Err(Ok(Error::InvalidAmount))
+    );
+
+    let mut config = sample_config();
+    config.deposit_rate_limit = DepositRateLimit {
+        min_interval_seconds: 3600,
+        max_per_day: 25,
+    };
+    assert_eq!(
+        client.try_set_config(&admin, &config),
+        Err(Ok(Error::InvalidAmount))
+    );
+
+    let mut config = sample_config();
+    config.min_topup = -1;
+    assert_eq!(
+        client.try_set_config(&admin, &config),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(client.get_pending_config(), None);
+}
+
+#[test]
+fn test_set_config_admin_only_and_cancellable() {
+    let (env, client, _token, admin) = setup_test_env();
+    let stranger = Address::generate(&env);
+    assert_eq!(
+        client.try_set_config(&stranger, &sample_config()),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    client.set_config(&admin, &sample_config());
+    assert!(client.get_pending_config().is_some());
+    assert_eq!(
+        client.try_cancel_config(&stranger),
+        Err(Ok(Error::Unauthorized))
+    );
+    client.cancel_config(&admin);
+    assert_eq!(client.get_pending_config(), None);
+    assert_eq!(client.try_cancel_config(&admin), Err(Ok(Error::NotFound)));
+}
+
+// =============================================================================
+// Scenario Builder Tests
+// =============================================================================
+
+#[test]
+fn test_scenario_builder_spreads_subscriptions_across_merchants() {
+    use crate::testutils::ScenarioBuilder;
+    let env = Env::default();
+    let scenario = ScenarioBuilder::new(&env)
+        .merchants(3)
+        .subscriptions(7, SubscriptionStatus::Active, 5000)
+        .subscription(1, SubscriptionStatus::Paused, 0)
+        .build();
+
+    assert_eq!(scenario.subscription_ids.len(), 8);
+    let totals: std::vec::Vec<u32> = scenario
+        .merchants
+        .iter()
+        .map(|m| {
+            scenario
+                .client
+                .get_subscriptions_by_merchant(m, &0, &10)
+                .total
+        })
+        .collect();
+    assert_eq!(totals, std::vec![3, 3, 2]);
+    assert_eq!(scenario.subscription(0).prepaid_balance, 5000);
+    assert_eq!(scenario.subscription(7).status, SubscriptionStatus::Paused);
+}
+
+/// Table-driven: every status × balance × elapsed-time combination yields the
+/// expected charge outcome.
+#[test]
+fn test_charge_outcomes_status_balance_time_matrix() {
+    use crate::testutils::ScenarioBuilder;
+    let statuses = [
+        SubscriptionStatus::Active,
+        SubscriptionStatus::Paused,
+        SubscriptionStatus::Cancelled,
+        SubscriptionStatus::InsufficientBalance,
+        SubscriptionStatus::Completed,
+    ];
+    let balances = [0i128, 999, 1000, 10_000];
+
+    for elapsed in [false, true] {
+        let env = Env::default();
+        let mut builder = ScenarioBuilder::new(&env).merchants(2).amount(1000);
+        let mut cases = std::vec::Vec::new();
+        for status in statuses.iter() {
+            for balance in balances {
+                builder = builder.subscription(cases.len() as u32, status.clone(), balance);
+                cases.push((status.clone(), balance));
+            }
+        }
+        let scenario = builder.build();
+        if elapsed {
+            scenario.advance(scenario.interval_seconds);
+        }
+
+        let mut ids = SorobanVec::new(&env);
+        for id in scenario.subscription_ids.iter() {
+            ids.push_back(*id);
+        }
+        let results = scenario.client.batch_charge(&ids);
+
+        for (i, (status, balance)) in cases.iter().enumerate() {
+            let expected = if *status != SubscriptionStatus::Active {
+                Some(Error::NotActive)
+            } else if !elapsed {
+                Some(Error::IntervalNotElapsed)
+            } else if *balance < 1000 {
+                Some(Error::InsufficientBalance)
+            } else {
+                None
+            };
+            let result = results.get(i as u32).unwrap();
+            assert_eq!(
+                result.error_code,
+                expected.as_ref().map(|e| e.clone().to_code()).unwrap_or(0),
+                "status {:?} balance {} elapsed {}",
+                status,
+                balance,
+                elapsed
+            );
+            let sub = scenario.subscription(i);
+            match expected {
+                None => assert_eq!(sub.prepaid_balance, balance - 1000),
+                Some(Error::InsufficientBalance) => {
+                    assert_eq!(sub.status, SubscriptionStatus::InsufficientBalance)
+                }
+                Some(_) => assert_eq!(sub.status, *status),
+            }
+        }
+    }
+}
+
+// =============================================================================
+// Reentrancy Guard Tests
+// =============================================================================
+
+mod malicious_token {
+    use crate::SubscriptionVaultClient;
+    use soroban_sdk::{contract, contractimpl, symbol_short, Address, Env};
+
+    /// Token whose `transfer` tries to call back into the vault.
+    #[contract]
+    pub struct MaliciousToken;
+
+    #[contractimpl]
+    impl MaliciousToken {
+        pub fn set_target(env: Env, vault: Address, subscription_id: u32, subscriber: Address) {
+            env.storage().instance().set(
+                &symbol_short!("target"),
+                &(vault, subscription_id, subscriber),
+            );
+        }
+
+        pub fn transfer(env: Env, _from: Address, _to: Address, amount: i128) {
+            let target: Option<(Address, u32, Address)> =
+                env.storage().instance().get(&symbol_short!("target"));
+            if let Some((vault, id, subscriber)) = target {
+                let client = SubscriptionVaultClient::new(&env, &vault);
+                let reentered = client.try_withdraw_excess(&id, &subscriber, &amount);
+                env.storage()
+                    .instance()
+                    .set(&symbol_short!("hit"), &matches!(reentered, Ok(Ok(()))));
+            }
+        }
+
+        pub fn decimals(_env: Env) -> u32 {
+            7
+        }
+
+        pub fn reentered(env: Env) -> bool {
+            env.storage()
+                .instance()
+                .get(&symbol_short!("hit"))
+                .unwrap_or(false)
+        }
+    }
+}
+
+#[test]
+fn test_guarded_entrypoints_reject_reentry() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+
+    env.as_contract(&client.address, || {
+        env.storage()
+            .instance()
+            .set(&soroban_sdk::symbol_short!("reentry"), &true);
+    });
+    assert_eq!(
+        client.try_deposit_funds(&id0, &subscriber, &1_000000i128),
+        Err(Ok(Error::Reentrancy))
+    );
+    assert_eq!(
+        client.try_charge_subscription(&id0),
+        Err(Ok(Error::Reentrancy))
+    );
+    assert_eq!(
+        client.try_withdraw_excess(&id0, &subscriber, &1i128),
+        Err(Ok(Error::Reentrancy))
+    );
+    assert_eq!(
+        client.try_batch_charge(&SorobanVec::from_array(&env, [id0])),
+        Err(Ok(Error::Reentrancy))
+    );
+
+    env.as_contract(&client.address, || {
+        env.storage()
+            .instance()
+            .remove(&soroban_sdk::symbol_short!("reentry"));
+    });
+    client.charge_subscription(&id0);
+    // The guard is released after a successful call, so the next one proceeds.
+    client.withdraw_excess(&id0, &subscriber, &1i128);
+}
+
+#[test]
+fn test_malicious_token_cannot_reenter_during_deposit() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let client = SubscriptionVaultClient::new(&env, &env.register(SubscriptionVault, ()));
+    let admin = Address::generate(&env);
+    let token_id = env.register(malicious_token::MaliciousToken, ());
+    let token = malicious_token::MaliciousTokenClient::new(&env, &token_id);
+    client.init(&token_id, &admin, &1i128);
+
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+    client.deposit_funds(&id, &subscriber, &5000i128);
+    token.set_target(&client.address, &id, &subscriber);
+
+    // The token's transfer tries to withdraw during the deposit; that must not succeed.
+    client.deposit_funds(&id, &subscriber, &5000i128);
+    assert!(!token.reentered());
+    assert_eq!(client.get_subscription(&id).prepaid_balance, 10_000);
+}
+
+// =============================================================================
+// Payout Adapter Tests
+// =============================================================================
+
+mod bridge_adapter {
+    use crate::PayoutAdapter;
+    use soroban_sdk::{contract, contractimpl, symbol_short, token, Address, Bytes, Env};
+
+    /// Adapter that pulls `amount / divisor` to a sink, or panics when divisor is 0.
+    #[contract]
+    pub struct MockBridge;
+
+    #[contractimpl]
+    impl MockBridge {
+        pub fn configure(env: Env, sink: Address, divisor: i128) {
+            env.storage()
+                .instance()
+                .set(&symbol_short!("cfg"), &(sink, divisor));
+        }
+    }
+
+    #[contractimpl]
+    impl PayoutAdapter for MockBridge {
+        fn forward_payout(
+            env: Env,
+            vault: Address,
+            token: Address,
+            _merchant: Address,
+            amount: i128,
+            _destination: Bytes,
+        ) {
+            let (sink, divisor): (Address, i128) =
+                env.storage().instance().get(&symbol_short!("cfg")).unwrap();
+            let client = token::Client::new(&env, &token);
+            if divisor == 0 {
+                client.transfer_from(&env.current_contract_address(), &vault, &sink, &amount);
+                panic!("bridge offline");
+            }
+            client.transfer_from(
+                &env.current_contract_address(),
+                &vault,
+                &sink,
+                &(amount / divisor),
+            );
+        }
+    }
+}
+
+/// Sets up a merchant with an immediate payout policy routed through a mock bridge.
+fn setup_bridged_payout(
+    env: &Env,
+    divisor: i128,
+) -> (SubscriptionVaultClient<'static>, u32, Address, Address) {
+    let (client, _admin, id0, _id1) = setup_batch_env(env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let sink = Address::generate(env);
+    let bridge_id = env.register(bridge_adapter::MockBridge, ());
+    bridge_adapter::MockBridgeClient::new(env, &bridge_id).configure(&sink, &divisor);
+    client.set_payout_policy(
+        &merchant,
+        &Some(crate::PayoutPolicy {
+            threshold: 1,
+            interval_seconds: 0,
+        }),
+    );
+    client.set_payout_route(
+        &merchant,
+        &Some(PayoutRoute {
+            adapter: bridge_id,
+            destination: soroban_sdk::Bytes::from_array(env, &[0xab; 20]),
+        }),
+    );
+    (client, id0, merchant, sink)
+}
+
+#[test]
+fn test_payout_routes_through_adapter() {
+    let env = Env::default();
+    let (client, id0, merchant, sink) = setup_bridged_payout(&env, 1);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+
+    client.charge_subscription(&id0);
+    assert_eq!(token.balance(&sink), 1000);
+    assert_eq!(token.balance(&merchant), 0);
+}
+
+#[test]
+fn test_payout_adapter_partial_pull_pays_rest_directly() {
+    let env = Env::default();
+    let (client, id0, merchant, sink) = setup_bridged_payout(&env, 4);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+
+    client.charge_subscription(&id0);
+    assert_eq!(token.balance(&sink), 250);
+    assert_eq!(token.balance(&merchant), 750);
+}
+
+#[test]
+fn test_failing_adapter_falls_back_to_direct_transfer() {
+    let env = Env::default();
+    let (client, id0, merchant, sink) = setup_bridged_payout(&env, 0);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+
+    client.charge_subscription(&id0);
+    assert_eq!(token.balance(&sink), 0);
+    assert_eq!(token.balance(&merchant), 1000);
+
+    client.set_payout_route(&merchant, &None);
+    assert_eq!(client.get_payout_route(&merchant), None);
+}
+
+// =============================================================================
+// Credit Wallet Tests
+// =============================================================================
+
+#[test]
+fn test_credit_wallet_deposit_and_withdraw() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    let token_addr = client.get_token();
+    let token = soroban_sdk::token::Client::new(&env, &token_addr);
+    let before = token.balance(&subscriber);
+
+    assert_eq!(client.deposit_credit(&subscriber, &5000i128), 5000);
+    assert_eq!(token.balance(&subscriber), before - 5000);
+    assert_eq!(client.get_credit_balance(&subscriber, &token_addr), 5000);
+
+    assert_eq!(
+        client.withdraw_credit(&subscriber, &token_addr, &2000i128),
+        3000
+    );
+    assert_eq!(token.balance(&subscriber), before - 3000);
+    assert_eq!(
+        client.try_withdraw_credit(&subscriber, &token_addr, &3001i128),
+        Err(Ok(Error::InsufficientBalance))
+    );
+    assert_eq!(
+        client.try_deposit_credit(&subscriber, &0i128),
+        Err(Ok(Error::InvalidAmount))
+    );
+}
+
+#[test]
+fn test_charge_draws_credit_before_prepaid_balance() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    let token_addr = client.get_token();
+    client.deposit_credit(&subscriber, &1500i128);
+
+    // Fully covered by credit.
+    client.charge_subscription(&id0);
+    assert_eq!(client.get_subscription(&id0).prepaid_balance, 10_000000);
+    assert_eq!(client.get_credit_balance(&subscriber, &token_addr), 500);
+
+    // id1 has no prepaid balance: 500 credit is not enough for 1000.
+    assert_eq!(
+        client.try_charge_subscription(&id1),
+        Err(Ok(Error::InsufficientBalance))
+    );
+    assert_eq!(client.get_credit_balance(&subscriber, &token_addr), 500);
+
+    // Partially covered: remaining 500 from the prepaid balance.
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        10_000000 - 500
+    );
+    assert_eq!(client.get_credit_balance(&subscriber, &token_addr), 0);
+}
+
+#[test]
+fn test_credit_wallet_frozen_while_locked() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    let token_addr = client.get_token();
+    client.deposit_credit(&subscriber, &1000i128);
+    client.lock_subscriber(&subscriber, &Address::generate(&env));
+
+    assert_eq!(
+        client.try_withdraw_credit(&subscriber, &token_addr, &1000i128),
+        Err(Ok(Error::SubscriberLocked))
+    );
+    assert_eq!(
+        client.try_deposit_credit(&subscriber, &1000i128),
+        Err(Ok(Error::SubscriberLocked))
+    );
+}
+
+// =============================================================================
+// Promo Credit Tests
+// =============================================================================
+
+#[test]
+fn test_promo_credit_applies_only_to_granting_merchant() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token_addr = client.get_token();
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_payout_queue(&sub.merchant, &token_addr).pending,
+        1000
+    );
+
+    let expires_at = T0 + 10 * INTERVAL;
+    client.grant_promo_credit(
+        &sub.merchant,
+        &sub.subscriber,
+        &token_addr,
+        &600i128,
+        &expires_at,
+    );
+    assert_eq!(
+        client.get_payout_queue(&sub.merchant, &token_addr).pending,
+        400
+    );
+
+    // A subscription with another merchant does not use the promo.
+    let other_merchant = Address::generate(&env);
+    let other = client.create_subscription(
+        &sub.subscriber,
+        &other_merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+    client.deposit_funds(&other, &sub.subscriber, &5_000000i128);
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&other);
+    assert_eq!(
+        client.get_subscription(&other).prepaid_balance,
+        5_000000 - 1000
+    );
+    assert_eq!(
+        client
+            .get_promo_credit(&sub.subscriber, &sub.merchant)
+            .unwrap()
+            .amount,
+        600
+    );
+
+    // The granting merchant's charge uses the promo first.
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        10_000000 - 1000 - 400
+    );
+    assert_eq!(
+        client.get_promo_credit(&sub.subscriber, &sub.merchant),
+        None
+    );
+    assert_eq!(
+        client.get_payout_queue(&sub.merchant, &token_addr).pending,
+        1400
+    );
+}
+
+#[test]
+fn test_expired_promo_credit_is_not_spent_and_reclaimable() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token_addr = client.get_token();
+    client.charge_subscription(&id0);
+    client.grant_promo_credit(
+        &sub.merchant,
+        &sub.subscriber,
+        &token_addr,
+        &500i128,
+        &(T0 + INTERVAL + 10),
+    );
+    assert_eq!(
+        client.try_reclaim_promo_credit(&sub.merchant, &sub.subscriber),
+        Err(Ok(Error::TimelockNotElapsed))
+    );
+
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        10_000000 - 2000
+    );
+
+    assert_eq!(
+        client.reclaim_promo_credit(&sub.merchant, &sub.subscriber),
+        500
+    );
+    assert_eq!(
+        client.get_payout_queue(&sub.merchant, &token_addr).pending,
+        2000
+    );
+    assert_eq!(
+        client.try_reclaim_promo_credit(&sub.merchant, &sub.subscriber),
+        Err(Ok(Error::NotFound))
+    );
+}
+
+#[test]
+fn test_promo_credit_grant_validation() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token_addr = client.get_token();
+    let expires_at = T0 + 10 * INTERVAL;
+
+    assert_eq!(
+        client.try_grant_promo_credit(
+            &sub.merchant,
+            &sub.subscriber,
+            &token_addr,
+            &1i128,
+            &expires_at
+        ),
+        Err(Ok(Error::InsufficientBalance))
+    );
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.try_grant_promo_credit(
+            &sub.merchant,
+            &sub.subscriber,
+            &token_addr,
+            &0i128,
+            &expires_at
+        ),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_grant_promo_credit(&sub.merchant, &sub.subscriber, &token_addr, &1i128, &T0),
+        Err(Ok(Error::InvalidAmount))
+    );
+    client.grant_promo_credit(
+        &sub.merchant,
+        &sub.subscriber,
+        &token_addr,
+        &300i128,
+        &expires_at,
+    );
+    let promo = client.grant_promo_credit(
+        &sub.merchant,
+        &sub.subscriber,
+        &token_addr,
+        &200i128,
+        &(expires_at - 1),
+    );
+    assert_eq!(promo.amount, 500);
+    assert_eq!(promo.expires_at, expires_at);
+}
+
+// =============================================================================
+// Status Bucket Query Tests
+// =============================================================================
+
+#[test]
+fn test_get_subscriptions_by_status_tracks_transitions() {
+    use crate::testutils::ScenarioBuilder;
+    let env = Env::default();
+    let scenario = ScenarioBuilder::new(&env)
+        .merchants(2)
+        .subscriptions(4, SubscriptionStatus::Active, 5000)
+        .subscription(0, SubscriptionStatus::Paused, 0)
+        .subscription(1, SubscriptionStatus::InsufficientBalance, 0)
+        .build();
+    let client = &scenario.client;
+
+    let active = client.get_subscriptions_by_status(&SubscriptionStatus::Active, &None, &0, &10);
+    assert_eq!(active.total, 4);
+    assert_eq!(
+        client
+            .get_subscriptions_by_status(&SubscriptionStatus::Paused, &None, &0, &10)
+            .total,
+        1
+    );
+
+    // Pausing moves a subscription between buckets.
+    let id = scenario.subscription_ids[0];
+    client.pause_subscription(&id, &scenario.subscribers[0]);
+    assert_eq!(
+        client
+            .get_subscriptions_by_status(&SubscriptionStatus::Active, &None, &0, &10)
+            .total,
+        3
+    );
+    let paused = client.get_subscriptions_by_status(&SubscriptionStatus::Paused, &None, &0, &10);
+    assert_eq!(paused.total, 2);
+
+    // A failed batch charge lands in the InsufficientBalance bucket.
+    scenario.advance(scenario.interval_seconds);
+    let empty = client.create_subscription(
+        &scenario.subscribers[1],
+        &scenario.merchants[1],
+        &1000i128,
+        &scenario.interval_seconds,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+    scenario.advance(scenario.interval_seconds);
+    client.batch_charge(&SorobanVec::from_array(&env, [empty]));
+    let insufficient = client.get_subscriptions_by_status(
+        &SubscriptionStatus::InsufficientBalance,
+        &None,
+        &0,
+        &10,
+    );
+    assert_eq!(insufficient.total, 2);
+}
+
+#[test]
+fn test_get_subscriptions_by_status_merchant_scope_and_paging() {
+    use crate::testutils::ScenarioBuilder;
+    let env = Env::default();
+    let scenario = ScenarioBuilder::new(&env)
+        .merchants(2)
+        .subscriptions(5, SubscriptionStatus::Active, 5000)
+        .build();
+    let client = &scenario.client;
+    let merchant0 = Some(scenario.merchants[0].clone());
+
+    let page = client.get_subscriptions_by_status(&SubscriptionStatus::Active, &merchant0, &0, &2);
+    assert_eq!(page.total, 3);
+    assert_eq!(page.items.len(), 2);
+    assert_eq!(page.next_cursor, Some(2));
+    assert!(page
+        .items
+        .iter()
+        .all(|s| s.merchant == scenario.merchants[0]));
+
+    let page = client.get_subscriptions_by_status(&SubscriptionStatus::Active, &merchant0, &2, &2);
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.next_cursor, None);
+
+    let page = client.get_subscriptions_by_status(&SubscriptionStatus::Cancelled, &None, &0, &10);
+    assert_eq!(page.total, 0);
+    assert_eq!(page.items.len(), 0);
+}
+
+// =============================================================================
+// Interval Drift Tests
+// =============================================================================
+
+#[test]
+fn test_interval_drift_reports_missed_periods() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+
+    let drift = client.get_interval_drift(&id0);
+    assert_eq!(drift.expected_charges, 1);
+    assert_eq!(drift.periods_charged, 0);
+    assert_eq!(drift.drift, 1);
+
+    client.charge_subscription(&id0);
+    assert_eq!(client.get_interval_drift(&id0).drift, 0);
+
+    // Keeper outage: three more intervals pass with a single charge.
+    env.ledger().set_timestamp(T0 + 4 * INTERVAL);
+    client.charge_subscription(&id0);
+    let drift = client.get_interval_drift(&id0);
+    assert_eq!(drift.expected_charges, 4);
+    assert_eq!(drift.periods_charged, 2);
+    assert_eq!(drift.drift, 2);
+
+    assert_eq!(
+        client.try_get_interval_drift(&999),
+        Err(Ok(Error::NotFound))
+    );
+}
+
+// =============================================================================
+// Reconciliation Tests
+// =============================================================================
+
+#[test]
+fn test_reconciliation_tracks_deposits_and_payouts() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let token_addr = client.get_token();
+
+    let report = client.emit_reconciliation(&token_addr);
+    assert_eq!(report.balance, 10_000000);
+    assert_eq!(report.liabilities, 10_000000);
+    assert_eq!(report.surplus, 0);
+    assert_eq!(report.timestamp, T0 + INTERVAL);
+
+    // A realized payout leaves the vault and reduces liabilities by the same amount.
+    client.set_payout_policy(
+        &merchant,
+        &Some(crate::PayoutPolicy {
+            threshold: 1,
+            interval_seconds: 0,
+        }),
+    );
+    client.charge_subscription(&id0);
+    assert_eq!(
+        soroban_sdk::token::Client::new(&env, &token_addr).balance(&merchant),
+        1000
+    );
+    let report = client.emit_reconciliation(&token_addr);
+    assert_eq!(report.balance, 10_000000 - 1000);
+    assert_eq!(report.liabilities, 10_000000 - 1000);
+    assert_eq!(report.surplus, 0);
+}
+
+#[test]
+fn test_reconciliation_reports_untracked_surplus() {
+    let env = Env::default();
+    let (client, _admin, _id0, _id1) = setup_batch_env(&env);
+    let token_addr = client.get_token();
+
+    // Tokens sent straight to the vault are not owed to anyone.
+    soroban_sdk::token::StellarAssetClient::new(&env, &token_addr).mint(&client.address, &500);
+    let report = client.emit_reconciliation(&token_addr);
+    assert_eq!(report.balance, 10_000000 + 500);
+    assert_eq!(report.liabilities, 10_000000);
+    assert_eq!(report.surplus, 500);
+    assert_eq!(env.events().all().len(), 1);
+    assert_eq!(client.get_tracked_liabilities(&token_addr), 10_000000);
+}
+
+// =============================================================================
+// Merchant Stake Tests
+// =============================================================================
+
+#[test]
+fn test_merchant_stake_required_for_new_subscriptions() {
+    let env = Env::default();
+    let (client, admin, id0, _id1) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    let token_addr = client.get_token();
+    let merchant = Address::generate(&env);
+    soroban_sdk::token::StellarAssetClient::new(&env, &token_addr).mint(&merchant, &5_000);
+
+    client.set_merchant_stake_policy(
+        &admin,
+        &MerchantStakePolicy {
+            amount: 2_000,
+            cooldown_seconds: 7 * 24 * 60 * 60,
+        },
+    );
+    assert_eq!(
+        client.try_create_subscription(
+            &subscriber,
+            &merchant,
+            &1000i128,
+            &INTERVAL,
+            &false,
+            &None,
+            &0i128,
+            &false
+        ),
+        Err(Ok(Error::MerchantNotStaked))
+    );
+
+    let stake = client.register_merchant(&merchant);
+    assert_eq!(stake.amount, 2_000);
+    assert_eq!(
+        soroban_sdk::token::Client::new(&env, &token_addr).balance(&merchant),
+        3_000
+    );
+    assert_eq!(
+        client.try_register_merchant(&merchant),
+        Err(Ok(Error::LimitExceeded))
+    );
+    client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+}
+
+#[test]
+fn test_merchant_stake_returned_after_cooldown() {
+    let env = Env::default();
+    let (client, admin, id0, _id1) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    let token_addr = client.get_token();
+    let token = soroban_sdk::token::Client::new(&env, &token_addr);
+    let merchant = Address::generate(&env);
+    soroban_sdk::token::StellarAssetClient::new(&env, &token_addr).mint(&merchant, &2_000);
+    let cooldown = 7 * 24 * 60 * 60;
+    client.set_merchant_stake_policy(
+        &admin,
+        &MerchantStakePolicy {
+            amount: 2_000,
+            cooldown_seconds: cooldown,
+        },
+    );
+    client.register_merchant(&merchant);
+
+    assert_eq!(
+        client.try_withdraw_merchant_stake(&merchant),
+        Err(Ok(Error::TimelockNotElapsed))
+    );
+    let now = env.ledger().timestamp();
+    assert_eq!(client.deregister_merchant(&merchant), now + cooldown);
+    assert_eq!(
+        client.try_deregister_merchant(&merchant),
+        Err(Ok(Error::Replay))
+    );
+    // De-registered merchants take no new subscriptions.
+    assert_eq!(
+        client.try_create_subscription(
+            &subscriber,
+            &merchant,
+            &1000i128,
+            &INTERVAL,
+            &false,
+            &None,
+            &0i128,
+            &false
+        ),
+        Err(Ok(Error::MerchantNotStaked))
+    );
+
+    env.ledger().set_timestamp(now + cooldown - 1);
+    assert_eq!(
+        client.try_withdraw_merchant_stake(&merchant),
+        Err(Ok(Error::TimelockNotElapsed))
+    );
+    env.ledger().set_timestamp(now + cooldown);
+    assert_eq!(client.withdraw_merchant_stake(&merchant), 2_000);
+    assert_eq!(token.balance(&merchant), 2_000);
+    assert_eq!(client.get_merchant_stake(&merchant), None);
+    assert_eq!(client.get_tracked_liabilities(&token_addr), 10_000000);
+}
+
+#[test]
+fn test_merchant_stake_policy_admin_only() {
+    let env = Env::default();
+    let (client, _admin, _id0, _id1) = setup_batch_env(&env);
+    let policy = MerchantStakePolicy {
+        amount: 1,
+        cooldown_seconds: 0,
+    };
+    assert_eq!(
+        client.try_set_merchant_stake_policy(&Address::generate(&env), &policy),
+        Err(Ok(Error::Unauthorized))
+    );
+    // Without a required stake, unregistered merchants are accepted.
+    assert_eq!(client.get_merchant_stake_policy().amount, 0);
+}
+
+#[test]
+fn test_confirmed_report_slashes_merchant_pro_rata() {
+    let env = Env::default();
+    let (client, admin, id0, _id1) = setup_batch_env(&env);
+    let alice = client.get_subscription(&id0).subscriber;
+    let token_addr = client.get_token();
+    let token_admin = soroban_sdk::token::StellarAssetClient::new(&env, &token_addr);
+    let merchant = Address::generate(&env);
+    let bob = Address::generate(&env);
+    token_admin.mint(&merchant, &2_000);
+    token_admin.mint(&bob, &1_000000);
+    client.set_merchant_stake_policy(
+        &admin,
+        &MerchantStakePolicy {
+            amount: 2_000,
+            cooldown_seconds: 0,
+        },
+    );
+    client.register_merchant(&merchant);
+
+    let a = client.create_subscription(
+        &alice, &merchant, &1000i128, &INTERVAL, &false, &None, &0i128, &false,
+    );
+    client.deposit_funds(&a, &alice, &1_000000i128);
+    let b = client.create_subscription(
+        &bob, &merchant, &3000i128, &INTERVAL, &false, &None, &0i128, &false,
+    );
+    client.deposit_funds(&b, &bob, &1_000000i128);
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&a);
+    client.charge_subscription(&b);
+    assert_eq!(
+        client.get_payout_queue(&merchant, &token_addr).pending,
+        4_000
+    );
+
+    let evidence = BytesN::from_array(&env, &[7u8; 32]);
+    client.report_merchant(&alice, &merchant, &evidence);
+    assert_eq!(
+        client.try_report_merchant(&bob, &merchant, &evidence),
+        Err(Ok(Error::LimitExceeded))
+    );
+    assert_eq!(client.get_fraud_report(&merchant).unwrap().reporter, alice);
+
+    let slashed = client.resolve_report(&admin, &merchant, &true).unwrap();
+    assert_eq!(slashed.stake_slashed, 2_000);
+    assert_eq!(slashed.pending_slashed, 4_000);
+    assert_eq!(slashed.distributed, 6_000);
+    assert_eq!(slashed.recipients, 2);
+    assert_eq!(client.get_credit_balance(&alice, &token_addr), 1_500);
+    assert_eq!(client.get_credit_balance(&bob, &token_addr), 4_500);
+    assert_eq!(client.get_payout_queue(&merchant, &token_addr).pending, 0);
+    assert_eq!(client.get_merchant_stake(&merchant), None);
+    assert_eq!(client.get_compensation_pot(&merchant, &token_addr), 0);
+    assert_eq!(client.get_fraud_report(&merchant), None);
+}
+
+#[test]
+fn test_dismissed_report_leaves_merchant_untouched() {
+    let env = Env::default();
+    let (client, admin, id0, _id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let token_addr = client.get_token();
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.try_resolve_report(&admin, &merchant, &true),
+        Err(Ok(Error::NotFound))
+    );
+
+    let reporter = Address::generate(&env);
+    client.report_merchant(&reporter, &merchant, &BytesN::from_array(&env, &[1u8; 32]));
+    assert_eq!(
+        client.try_resolve_report(&reporter, &merchant, &true),
+        Err(Ok(Error::Unauthorized))
+    );
+    assert_eq!(client.resolve_report(&admin, &merchant, &false), None);
+    assert_eq!(
+        client.get_payout_queue(&merchant, &token_addr).pending,
+        1000
+    );
+    assert_eq!(client.get_fraud_report(&merchant), None);
+}
+
+#[test]
+fn test_compensation_pot_kept_per_slashed_merchant() {
+    let env = Env::default();
+    let (client, admin, _, _) = setup_batch_env(&env);
+    let token_addr = client.get_token();
+    let token_admin = soroban_sdk::token::StellarAssetClient::new(&env, &token_addr);
+    let idle = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let bob = Address::generate(&env);
+    token_admin.mint(&idle, &2_000);
+    token_admin.mint(&merchant, &2_000);
+    token_admin.mint(&bob, &1_000000);
+    client.set_merchant_stake_policy(
+        &admin,
+        &MerchantStakePolicy {
+            amount: 2_000,
+            cooldown_seconds: 0,
+        },
+    );
+    client.register_merchant(&idle);
+    client.register_merchant(&merchant);
+    let b = client.create_subscription(
+        &bob, &merchant, &1000i128, &INTERVAL, &false, &None, &0i128, &false,
+    );
+    client.deposit_funds(&b, &bob, &1_000000i128);
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&b);
+    let evidence = BytesN::from_array(&env, &[7u8; 32]);
+
+    // No subscriber of `idle` has been charged, so its stake stays in its pot.
+    client.report_merchant(&bob, &idle, &evidence);
+    let slashed = client.resolve_report(&admin, &idle, &true).unwrap();
+    assert_eq!(slashed.distributed, 0);
+    assert_eq!(client.get_compensation_pot(&idle, &token_addr), 2_000);
+
+    // Victims of another merchant do not receive it.
+    client.report_merchant(&bob, &merchant, &evidence);
+    let slashed = client.resolve_report(&admin, &merchant, &true).unwrap();
+    assert_eq!(slashed.distributed, 3_000);
+    assert_eq!(client.get_credit_balance(&bob, &token_addr), 3_000);
+    assert_eq!(client.get_compensation_pot(&merchant, &token_addr), 0);
+    assert_eq!(client.get_compensation_pot(&idle, &token_addr), 2_000);
+}
+
+// =============================================================================
+// Seat Pricing Tests
+// =============================================================================
+
+#[test]
+fn test_seat_changes_prorate_added_seats() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.set_seat_plan(&id0, &100i128, &10u32, &5u32, &20u32),
+        1000
+    );
+    let balance = client.get_subscription(&id0).prepaid_balance;
+
+    // Three quarters of the interval remain: 4 added seats cost 3/4 × 400.
+    env.ledger().set_timestamp(T0 + INTERVAL + INTERVAL / 4);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    assert_eq!(client.update_seats(&subscriber, &id0, &14u32), 300);
+    let sub = client.get_subscription(&id0);
+    assert_eq!(sub.amount, 1400);
+    assert_eq!(sub.prepaid_balance, balance - 300);
+    assert_eq!(
+        client.try_update_seats(&subscriber, &id0, &21u32),
+        Err(Ok(Error::LimitExceeded))
+    );
+
+    // Removing seats is free and applies from the next charge.
+    assert_eq!(client.update_seats(&subscriber, &id0, &5u32), 0);
+    assert_eq!(client.get_subscription(&id0).amount, 500);
+    assert_eq!(client.get_subscription(&id0).prepaid_balance, balance - 300);
+}
+
+#[test]
+fn test_merchant_seat_proposal_needs_acceptance() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    client.charge_subscription(&id0);
+    client.set_seat_plan(&id0, &100i128, &10u32, &5u32, &20u32);
+    let sub = client.get_subscription(&id0);
+
+    assert_eq!(
+        client.try_update_seats(&Address::generate(&env), &id0, &12u32),
+        Err(Ok(Error::Unauthorized))
+    );
+    assert_eq!(client.try_accept_seats(&id0), Err(Ok(Error::NotFound)));
+    // Merchant proposals may exceed the self-serve bounds.
+    assert_eq!(client.update_seats(&sub.merchant, &id0, &30u32), 0);
+    assert_eq!(client.get_seat_plan(&id0).unwrap().proposed_seats, 30);
+    assert_eq!(client.get_subscription(&id0).amount, 1000);
+
+    // Half the interval remains: 20 added seats cost 1/2 × 2000.
+    env.ledger().set_timestamp(T0 + INTERVAL + INTERVAL / 2);
+    assert_eq!(client.accept_seats(&id0), 1000);
+    let plan = client.get_seat_plan(&id0).unwrap();
+    assert_eq!(plan.seats, 30);
+    assert_eq!(plan.proposed_seats, 0);
+    assert_eq!(client.get_subscription(&id0).amount, 3000);
+}
+
+#[test]
+fn test_seat_plan_validates_bounds() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    assert_eq!(
+        client.try_set_seat_plan(&id0, &100i128, &4u32, &5u32, &20u32),
+        Err(Ok(Error::LimitExceeded))
+    );
+    assert_eq!(
+        client.try_set_seat_plan(&id0, &0i128, &10u32, &5u32, &20u32),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(client.get_seat_plan(&id0), None);
+}
+
+// =============================================================================
+// Organization Account Tests
+// =============================================================================
+
+mod org_account {
+    use crate::OrgAdapter;
+    use soroban_sdk::{contract, contractimpl, symbol_short, Address, Env, Vec};
+
+    /// Org contract with explicit owner and member lists.
+    #[contract]
+    pub struct MockOrg;
+
+    #[contractimpl]
+    impl MockOrg {
+        pub fn configure(env: Env, owners: Vec<Address>, members: Vec<Address>) {
+            env.storage()
+                .instance()
+                .set(&symbol_short!("roles"), &(owners, members));
+        }
+    }
+
+    fn roles(env: &Env) -> (Vec<Address>, Vec<Address>) {
+        env.storage()
+            .instance()
+            .get(&symbol_short!("roles"))
+            .unwrap()
+    }
+
+    #[contractimpl]
+    impl OrgAdapter for MockOrg {
+        fn is_member(env: Env, account: Address) -> bool {
+            let (owners, members) = roles(&env);
+            owners.contains(&account) || members.contains(&account)
+        }
+
+        fn is_owner(env: Env, account: Address) -> bool {
+            roles(&env).0.contains(&account)
+        }
+    }
+}
+
+/// Returns (client, org, owner, member, subscription id) for an org-owned subscription.
+fn setup_org_subscription(
+    env: &Env,
+) -> (
+    SubscriptionVaultClient<'static>,
+    Address,
+    Address,
+    Address,
+    u32,
+) {
+    let (client, _admin, id0, _id1) = setup_batch_env(env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let token_admin = soroban_sdk::token::StellarAssetClient::new(env, &client.get_token());
+    let owner = Address::generate(env);
+    let member = Address::generate(env);
+    token_admin.mint(&owner, &10_000000);
+    token_admin.mint(&member, &10_000000);
+
+    let org = env.register(org_account::MockOrg, ());
+    org_account::MockOrgClient::new(env, &org).configure(
+        &SorobanVec::from_array(env, [owner.clone()]),
+        &SorobanVec::from_array(env, [member.clone()]),
+    );
+    client.set_org_account(&org, &true);
+    let id = client.create_subscription(
+        &org, &merchant, &1000i128, &INTERVAL, &false, &None, &0i128, &false,
+    );
+    (client, org, owner, member, id)
+}
+
+#[test]
+fn test_org_members_deposit_and_owners_cancel() {
+    let env = Env::default();
+    let (client, org, owner, member, id) = setup_org_subscription(&env);
+    assert!(client.is_org_account(&org));
+
+    client.deposit_funds(&id, &member, &1_000000i128);
+    client.deposit_funds(&id, &owner, &1_000000i128);
+    assert_eq!(client.get_subscription(&id).prepaid_balance, 2_000000);
+
+    assert_eq!(
+        client.try_cancel_subscription(&id, &member),
+        Err(Ok(Error::Unauthorized))
+    );
+    client.cancel_subscription(&id, &owner);
+    assert_eq!(
+        client.get_subscription(&id).status,
+        SubscriptionStatus::Cancelled
+    );
+}
+
+#[test]
+fn test_org_rejects_outsiders() {
+    let env = Env::default();
+    let (client, org, owner, _member, id) = setup_org_subscription(&env);
+    let outsider = Address::generate(&env);
+    soroban_sdk::token::StellarAssetClient::new(&env, &client.get_token())
+        .mint(&outsider, &10_000000);
+
+    assert_eq!(
+        client.try_deposit_funds(&id, &outsider, &1_000000i128),
+        Err(Ok(Error::Unauthorized))
+    );
+    assert_eq!(
+        client.try_cancel_subscription(&id, &outsider),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    // Once the org opts out, its owners lose delegated rights.
+    client.set_org_account(&org, &false);
+    assert_eq!(
+        client.try_cancel_subscription(&id, &owner),
+        Err(Ok(Error::Unauthorized))
+    );
+}
+
+// =============================================================================
+// Tax Tests
+// =============================================================================
+
+#[test]
+fn test_tax_held_in_merchant_sub_balance() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let token_addr = client.get_token();
+    client.set_tax_config(
+        &merchant,
+        &Some(TaxConfig {
+            rate_bps: 2_000,
+            destination: TaxDestination::SubBalance,
+        }),
+    );
+
+    client.charge_subscription(&id0);
+    use soroban_sdk::TryFromVal;
+    let event = env
+        .events()
+        .all()
+        .iter()
+        .find(|e| {
+            Symbol::try_from_val(&env, &e.1.get(0).unwrap()) == Ok(Symbol::new(&env, "charged"))
+        })
+        .map(|e| crate::SubscriptionChargedEvent::try_from_val(&env, &e.2).unwrap())
+        .unwrap();
+    assert_eq!(event.amount, 1000);
+    assert_eq!(event.tax, 200);
+    assert_eq!(client.get_payout_queue(&merchant, &token_addr).pending, 800);
+    assert_eq!(client.get_tax_balance(&merchant, &token_addr), 200);
+
+    assert_eq!(client.withdraw_tax_balance(&merchant, &token_addr), 200);
+    assert_eq!(client.get_tax_balance(&merchant, &token_addr), 0);
+    assert_eq!(
+        soroban_sdk::token::Client::new(&env, &token_addr).balance(&merchant),
+        200
+    );
+}
+
+#[test]
+fn test_tax_routed_to_recipient() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let token_addr = client.get_token();
+    let authority = Address::generate(&env);
+    assert_eq!(
+        client.try_set_tax_config(
+            &merchant,
+            &Some(TaxConfig {
+                rate_bps: 10_001,
+                destination: TaxDestination::SubBalance,
+            }),
+        ),
+        Err(Ok(Error::InvalidAmount))
+    );
+    client.set_tax_config(
+        &merchant,
+        &Some(TaxConfig {
+            rate_bps: 1_500,
+            destination: TaxDestination::Recipient(authority.clone()),
+        }),
+    );
+
+    client.charge_subscription(&id0);
+    assert_eq!(
+        soroban_sdk::token::Client::new(&env, &token_addr).balance(&authority),
+        150
+    );
+    assert_eq!(client.get_payout_queue(&merchant, &token_addr).pending, 850);
+    assert_eq!(client.get_tax_balance(&merchant, &token_addr), 0);
+}
+
+// =============================================================================
+// Receipt Chain Tests
+// =============================================================================
+
+/// Receipts emitted by the last invocation.
+fn emitted_receipts(env: &Env) -> std::vec::Vec<crate::ChargeReceipt> {
+    use soroban_sdk::TryFromVal;
+    env.events()
+        .all()
+        .iter()
+        .filter(|e| {
+            Symbol::try_from_val(env, &e.1.get(0).unwrap()) == Ok(Symbol::new(env, "receipt"))
+        })
+        .map(|e| crate::ChargeReceipt::try_from_val(env, &e.2).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_receipt_chain_verifies_against_head() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let genesis = client.get_receipt_head(&merchant);
+    assert_eq!(genesis.sequence, 0);
+
+    let mut receipts = std::vec::Vec::new();
+    client.charge_subscription(&id0);
+    receipts.extend(emitted_receipts(&env));
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id0);
+    receipts.extend(emitted_receipts(&env));
+    assert_eq!(receipts.len(), 2);
+
+    let mut hash = genesis.hash;
+    for (i, receipt) in receipts.iter().enumerate() {
+        assert_eq!(receipt.sequence, i as u64 + 1);
+        assert_eq!(receipt.prev_hash, hash);
+        hash = client.hash_receipt(receipt);
+    }
+    let head = client.get_receipt_head(&merchant);
+    assert_eq!(head.sequence, 2);
+    assert_eq!(head.hash, hash);
+
+    // Any edit to an exported receipt breaks the chain.
+    let mut tampered = receipts[0].clone();
+    tampered.amount = 1;
+    assert_ne!(client.hash_receipt(&tampered), receipts[1].prev_hash);
+}
+
+// =============================================================================
+// Fee Waiver Campaign Tests
+// =============================================================================
+
+#[test]
+fn test_fee_waiver_enrolls_new_merchant_at_first_charge() {
+    let env = Env::default();
+    let (client, admin, id0, _id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let now = T0 + INTERVAL;
+    let days_90 = 90 * 24 * 60 * 60;
+    client.create_fee_campaign(&admin, &10_000u32, &days_90, &now, &(now + INTERVAL), &true);
+    assert_eq!(client.get_merchant_fee_waiver(&merchant), None);
+
+    client.charge_subscription(&id0);
+    let waiver = client.get_merchant_fee_waiver(&merchant).unwrap();
+    assert_eq!(waiver.campaign_id, 1);
+    assert_eq!(waiver.started_at, now);
+    assert_eq!(waiver.expires_at, now + days_90);
+    assert_eq!(client.get_fee_waiver_bps(&merchant), 10_000);
+
+    env.ledger().set_timestamp(now + days_90);
+    assert_eq!(client.get_fee_waiver_bps(&merchant), 0);
+}
+
+#[test]
+fn test_fee_waiver_new_merchants_only_skips_existing_merchant() {
+    let env = Env::default();
+    let (client, admin, id0, _id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    client.charge_subscription(&id0);
+
+    let now = T0 + 2 * INTERVAL;
+    env.ledger().set_timestamp(now);
+    let id = client.create_fee_campaign(&admin, &5_000u32, &INTERVAL, &now, &(now + 10), &true);
+    client.charge_subscription(&id0);
+    assert_eq!(client.get_merchant_fee_waiver(&merchant), None);
+
+    // An open-to-all campaign picks the merchant up at its next charge.
+    client.end_fee_campaign(&admin, &id);
+    assert_eq!(client.get_fee_campaigns().len(), 0);
+    client.create_fee_campaign(
+        &admin,
+        &5_000u32,
+        &INTERVAL,
+        &now,
+        &(now + 2 * INTERVAL),
+        &false,
+    );
+    env.ledger().set_timestamp(T0 + 3 * INTERVAL);
+    client.charge_subscription(&id0);
+    assert_eq!(client.get_fee_waiver_bps(&merchant), 5_000);
+}
+
+#[test]
+fn test_fee_campaign_validation() {
+    let env = Env::default();
+    let (client, admin, _id0, _id1) = setup_batch_env(&env);
+    assert_eq!(
+        client.try_create_fee_campaign(&admin, &10_001u32, &1u64, &0u64, &1u64, &false),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_create_fee_campaign(&admin, &100u32, &1u64, &5u64, &5u64, &false),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_create_fee_campaign(
+            &Address::generate(&env),
+            &100u32,
+            &1u64,
+            &0u64,
+            &1u64,
+            &false
+        ),
+        Err(Ok(Error::Unauthorized))
+    );
+    assert_eq!(
+        client.try_end_fee_campaign(&admin, &7u32),
+        Err(Ok(Error::NotFound))
+    );
+}
+
+// =============================================================================
+// Signed Agreement Tests
+// =============================================================================
+
+/// Deterministic ed25519 key for `account`, registered with the vault.
+fn register_signing_key(
+    env: &Env,
+    client: &SubscriptionVaultClient,
+    account: &Address,
+    seed: u8,
+) -> ed25519_dalek::SigningKey {
+    let key = ed25519_dalek::SigningKey::from_bytes(&[seed; 32]);
+    client.set_signing_key(
+        account,
+        &Some(BytesN::from_array(env, &key.verifying_key().to_bytes())),
+    );
+    key
+}
+
+/// Signs the XDR encoding of `payload` with `key`.
+fn sign_xdr<T: soroban_sdk::IntoVal<Env, soroban_sdk::Val>>(
+    env: &Env,
+    key: &ed25519_dalek::SigningKey,
+    payload: T,
+) -> BytesN<64> {
+    use ed25519_dalek::Signer;
+    use soroban_sdk::xdr::ToXdr;
+    let val: soroban_sdk::Val = payload.into_val(env);
+    let message: std::vec::Vec<u8> = val.to_xdr(env).iter().collect();
+    BytesN::from_array(env, &key.sign(&message).to_bytes())
+}
+
+fn test_agreement(env: &Env, subscriber: &Address, merchant: &Address) -> SubscriptionAgreement {
+    SubscriptionAgreement {
+        subscriber: subscriber.clone(),
+        merchant: merchant.clone(),
+        amount: 2_500,
+        interval_seconds: INTERVAL,
+        usage_enabled: false,
+        expires_at: env.ledger().timestamp() + 3600,
+        nonce: 1,
+    }
+}
+
+#[test]
+fn test_import_signed_agreement_by_relayer() {
+    let env = Env::default();
+    let (client, _admin, _id0, _id1) = setup_batch_env(&env);
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let sub_key = register_signing_key(&env, &client, &subscriber, 1);
+    let merchant_key = register_signing_key(&env, &client, &merchant, 2);
+
+    let agreement = test_agreement(&env, &subscriber, &merchant);
+    let sub_sig = sign_xdr(&env, &sub_key, agreement.clone());
+    let merchant_sig = sign_xdr(&env, &merchant_key, agreement.clone());
+
+    // No Soroban auth from either party is needed.
+    env.set_auths(&[]);
+    let id = client.import_agreement(&agreement, &sub_sig, &merchant_sig);
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.subscriber, subscriber);
+    assert_eq!(sub.merchant, merchant);
+    assert_eq!(sub.amount, 2_500);
+    assert_eq!(
+        client.try_import_agreement(&agreement, &sub_sig, &merchant_sig),
+        Err(Ok(Error::Replay))
+    );
+}
+
+#[test]
+fn test_import_agreement_rejects_bad_or_expired_signatures() {
+    let env = Env::default();
+    let (client, _admin, _id0, _id1) = setup_batch_env(&env);
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let sub_key = register_signing_key(&env, &client, &subscriber, 1);
+    let agreement = test_agreement(&env, &subscriber, &merchant);
+    let sub_sig = sign_xdr(&env, &sub_key, agreement.clone());
+
+    // Merchant has no registered key.
+    assert_eq!(
+        client.try_import_agreement(&agreement, &sub_sig, &sub_sig),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    // A key that is not the merchant's registered key fails verification.
+    let merchant_key = register_signing_key(&env, &client, &merchant, 2);
+    let forged = sign_xdr(
+        &env,
+        &ed25519_dalek::SigningKey::from_bytes(&[9; 32]),
+        agreement.clone(),
+    );
+    assert!(client
+        .try_import_agreement(&agreement, &sub_sig, &forged)
+        .is_err());
+
+    // Tampered terms invalidate both signatures.
+    let merchant_sig = sign_xdr(&env, &merchant_key, agreement.clone());
+    let mut tampered = agreement.clone();
+    tampered.amount = 1;
+    assert!(client
+        .try_import_agreement(&tampered, &sub_sig, &merchant_sig)
+        .is_err());
+
+    env.ledger().set_timestamp(agreement.expires_at + 1);
+    assert_eq!(
+        client.try_import_agreement(&agreement, &sub_sig, &merchant_sig),
+        Err(Ok(Error::AgreementExpired))
+    );
+}
+
+fn signed_action(
+    env: &Env,
+    client: &SubscriptionVaultClient,
+    key: &ed25519_dalek::SigningKey,
+    signer: &Address,
+    op: SignedOp,
+) -> (SignedAction, BytesN<64>) {
+    let action = SignedAction {
+        signer: signer.clone(),
+        nonce: client.get_signer_nonce(signer),
+        expires_at: env.ledger().timestamp() + 600,
+        op,
+    };
+    let signature = sign_xdr(env, key, (client.address.clone(), action.clone()));
+    (action, signature)
+}
+
+#[test]
+fn test_execute_signed_subscriber_actions() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    let key = register_signing_key(&env, &client, &subscriber, 3);
+    let token_addr = client.get_token();
+    soroban_sdk::token::Client::new(&env, &token_addr).approve(
+        &subscriber,
+        &client.address,
+        &5_000000,
+        &1_000,
+    );
+    env.set_auths(&[]);
+
+    let (action, sig) = signed_action(
+        &env,
+        &client,
+        &key,
+        &subscriber,
+        SignedOp::Deposit(id0, 2_000000),
+    );
+    client.execute_signed(&action, &sig);
+    assert_eq!(client.get_subscription(&id0).prepaid_balance, 12_000000);
+    assert_eq!(client.get_signer_nonce(&subscriber), 1);
+    assert_eq!(
+        client.try_execute_signed(&action, &sig),
+        Err(Ok(Error::Replay))
+    );
+
+    let (action, sig) = signed_action(&env, &client, &key, &subscriber, SignedOp::Pause(id0));
+    client.execute_signed(&action, &sig);
+    assert_eq!(
+        client.get_subscription(&id0).status,
+        SubscriptionStatus::Paused
+    );
+
+    let (action, sig) = signed_action(&env, &client, &key, &subscriber, SignedOp::Cancel(id0));
+    client.execute_signed(&action, &sig);
+    assert_eq!(
+        client.get_subscription(&id0).status,
+        SubscriptionStatus::Cancelled
+    );
+}
+
+#[test]
+fn test_execute_signed_rejects_foreign_subscription_and_expiry() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let stranger = Address::generate(&env);
+    let key = register_signing_key(&env, &client, &stranger, 4);
+
+    let (action, sig) = signed_action(&env, &client, &key, &stranger, SignedOp::Cancel(id0));
+    assert_eq!(
+        client.try_execute_signed(&action, &sig),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    let (action, sig) = signed_action(&env, &client, &key, &stranger, SignedOp::Pause(id0));
+    env.ledger().set_timestamp(action.expires_at + 1);
+    assert_eq!(
+        client.try_execute_signed(&action, &sig),
+        Err(Ok(Error::AgreementExpired))
+    );
+}
+
+// =============================================================================
+// Creation Time Range Tests
+// =============================================================================
+
+#[test]
+fn test_subscriptions_created_between_cohorts() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let day = 24 * 60 * 60;
+    env.ledger().set_timestamp(10 * day + 5);
+    client.create_subscription(
+        &sub.subscriber,
+        &sub.merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+    env.ledger().set_timestamp(40 * day);
+    client.create_subscription(
+        &sub.subscriber,
+        &sub.merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+
+    // id0 and id1 were created at T0 on day 0.
+    let page = client.get_subs_created_between(&0, &day, &0, &10);
+    assert_eq!(page.total, 2);
+    assert_eq!(page.items.len(), 2);
+
+    // Bounds are exact within a day: [from, to).
+    let page = client.get_subs_created_between(&(10 * day + 5), &(10 * day + 6), &0, &10);
+    assert_eq!(page.total, 1);
+    assert_eq!(
+        page.items.get(0).unwrap().last_payment_timestamp,
+        10 * day + 5
+    );
+    assert_eq!(
+        client
+            .get_subs_created_between(&(10 * day + 6), &(11 * day), &0, &10)
+            .total,
+        0
+    );
+
+    // Paginated across several day buckets.
+    let page = client.get_subs_created_between(&0, &(41 * day), &0, &3);
+    assert_eq!(page.total, 4);
+    assert_eq!(page.items.len(), 3);
+    assert_eq!(page.next_cursor, Some(3));
+    let page = client.get_subs_created_between(&0, &(41 * day), &3, &3);
+    assert_eq!(page.items.get(0).unwrap().last_payment_timestamp, 40 * day);
+    assert_eq!(page.next_cursor, None);
+}
+
+#[test]
+fn test_subscriptions_created_between_rejects_bad_ranges() {
+    let env = Env::default();
+    let (client, _admin, _id0, _id1) = setup_batch_env(&env);
+    let day = 24 * 60 * 60;
+    assert_eq!(
+        client.try_get_subs_created_between(&5, &5, &0, &10).err(),
+        Some(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        client
+            .try_get_subs_created_between(&0, &(367 * day), &0, &10)
+            .err(),
+        Some(Ok(Error::LimitExceeded))
+    );
+    assert_eq!(
+        client
+            .get_subs_created_between(&0, &(366 * day), &0, &10)
+            .total,
+        2
+    );
+}
+
+// =============================================================================
+// Retry Queue Tests
+// =============================================================================
+
+#[test]
+fn test_batch_failures_are_retried_from_queue() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let mut ids = SorobanVec::<u32>::new(&env);
+    ids.push_back(id0);
+    ids.push_back(id1);
+    let results = client.batch_charge(&ids);
+    assert!(results.get(0).unwrap().success);
+    assert!(!results.get(1).unwrap().success);
+
+    // Only the unfunded subscription is queued, with a one-day backoff.
+    let queue = client.get_retry_queue();
+    assert_eq!(queue.len(), 1);
+    let entry = queue.get(0).unwrap();
+    assert_eq!(entry.subscription_id, id1);
+    assert_eq!(entry.attempts, 1);
+    assert_eq!(entry.next_retry_at, T0 + INTERVAL + 24 * 60 * 60);
+    assert_eq!(entry.last_error_code, Error::InsufficientBalance.to_code());
+
+    // Not due yet.
+    assert_eq!(client.process_retry_queue(&10).len(), 0);
+
+    // The subscriber tops up; the retry re-activates and charges it.
+    let subscriber = client.get_subscription(&id1).subscriber;
+    client.deposit_funds(&id1, &subscriber, &1_000000i128);
+    env.ledger().set_timestamp(T0 + INTERVAL + 24 * 60 * 60);
+    let results = client.process_retry_queue(&10);
+    assert_eq!(results.len(), 1);
+    assert!(results.get(0).unwrap().success);
+    assert_eq!(
+        client.get_subscription(&id1).status,
+        SubscriptionStatus::Active
+    );
+    assert_eq!(client.get_retry_queue().len(), 0);
+}
+
+#[test]
+fn test_retry_queue_backs_off_and_gives_up() {
+    let env = Env::default();
+    let (client, _admin, _id0, id1) = setup_batch_env(&env);
+    let mut ids = SorobanVec::<u32>::new(&env);
+    ids.push_back(id1);
+    client.batch_charge(&ids);
+    // A second failing batch does not duplicate the entry.
+    client.batch_charge(&ids);
+    assert_eq!(client.get_retry_queue().len(), 1);
+
+    let day = 24 * 60 * 60;
+    for attempts in 2..4u32 {
+        let now = client.get_retry_queue().get(0).unwrap().next_retry_at;
+        env.ledger().set_timestamp(now);
+        let result = client.process_retry_queue(&10).get(0).unwrap();
+        assert!(!result.success);
+        assert!(result.requeued);
+        let entry = client.get_retry_queue().get(0).unwrap();
+        assert_eq!(entry.attempts, attempts);
+        assert_eq!(entry.next_retry_at, now + day * attempts as u64);
+        assert_eq!(
+            client.get_subscription(&id1).status,
+            SubscriptionStatus::InsufficientBalance
+        );
+    }
+
+    env.ledger()
+        .set_timestamp(client.get_retry_queue().get(0).unwrap().next_retry_at);
+    let result = client.process_retry_queue(&10).get(0).unwrap();
+    assert!(!result.requeued);
+    assert_eq!(client.get_retry_queue().len(), 0);
+}
+
+// =============================================================================
+// Merchant Tag Tests
+// =============================================================================
+
+#[test]
+fn test_merchant_tags_index_subscriptions() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let vip = Symbol::new(&env, "VIP");
+    let at_risk = Symbol::new(&env, "AtRisk");
+    client.set_merchant_tags(
+        &merchant,
+        &SorobanVec::from_array(&env, [vip.clone(), at_risk.clone()]),
+    );
+
+    client.tag_subscription(&merchant, &id0, &Some(vip.clone()));
+    client.tag_subscription(&merchant, &id1, &Some(vip.clone()));
+    let page = client.get_subscriptions_by_tag(&merchant, &vip, &0, &10);
+    assert_eq!(page.items, SorobanVec::from_array(&env, [id0, id1]));
+
+    // Re-tagging moves the subscription between indexes; status is untouched.
+    client.tag_subscription(&merchant, &id1, &Some(at_risk.clone()));
+    assert_eq!(client.get_subscription_tag(&id1), Some(at_risk.clone()));
+    assert_eq!(
+        client
+            .get_subscriptions_by_tag(&merchant, &vip, &0, &10)
+            .total,
+        1
+    );
+    assert_eq!(
+        client
+            .get_subscriptions_by_tag(&merchant, &at_risk, &0, &10)
+            .total,
+        1
+    );
+    assert_eq!(
+        client.get_subscription(&id1).status,
+        SubscriptionStatus::Active
+    );
+
+    client.tag_subscription(&merchant, &id0, &None);
+    assert_eq!(client.get_subscription_tag(&id0), None);
+    assert_eq!(
+        client
+            .get_subscriptions_by_tag(&merchant, &vip, &0, &10)
+            .total,
+        0
+    );
+
+    // Dropping a tag from the set untags its subscriptions.
+    client.set_merchant_tags(&merchant, &SorobanVec::from_array(&env, [vip.clone()]));
+    assert_eq!(client.get_subscription_tag(&id1), None);
+    assert_eq!(
+        client
+            .get_subscriptions_by_tag(&merchant, &at_risk, &0, &10)
+            .total,
+        0
+    );
+}
+
+#[test]
+fn test_merchant_tags_validation() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let vip = Symbol::new(&env, "VIP");
+
+    assert_eq!(
+        client.try_tag_subscription(&merchant, &id0, &Some(vip.clone())),
+        Err(Ok(Error::InvalidLabel))
+    );
+    client.set_merchant_tags(&merchant, &SorobanVec::from_array(&env, [vip.clone()]));
+    assert_eq!(
+        client.try_tag_subscription(&Address::generate(&env), &id0, &Some(vip)),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    let mut too_many = SorobanVec::new(&env);
+    for i in 0..9u32 {
+        too_many.push_back(Symbol::new(&env, &std::format!("tag{i}")));
+    }
+    assert_eq!(
+        client.try_set_merchant_tags(&merchant, &too_many),
+        Err(Ok(Error::LimitExceeded))
+    );
+}
+
+#[test]
+fn test_close_account_removes_settled_subscriptions() {
+    use soroban_sdk::TryFromVal;
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let (subscriber, merchant) = (sub.subscriber.clone(), sub.merchant.clone());
+
+    // Live subscriptions block closure.
+    assert_eq!(
+        client.try_close_account(&subscriber),
+        Err(Ok(Error::AccountNotSettled))
+    );
+
+    // Cancelled but still funded also blocks closure.
+    client.cancel_subscription(&id0, &subscriber);
+    client.cancel_subscription(&id1, &subscriber);
+    assert_eq!(
+        client.try_close_account(&subscriber),
+        Err(Ok(Error::AccountNotSettled))
+    );
+
+    client.withdraw_subscriber_funds(&id0, &subscriber);
+    assert_eq!(client.close_account(&subscriber), 2);
+
+    let closed = env.events().all().iter().any(|e| {
+        Symbol::try_from_val(&env, &e.1.get(0).unwrap()) == Ok(Symbol::new(&env, "account_closed"))
+    });
+    assert!(closed);
+    assert_eq!(
+        client.try_get_subscription(&id0).err(),
+        Some(Ok(Error::NotFound))
+    );
+    assert_eq!(client.get_merchant_subscription_count(&merchant), 0);
+    assert!(client.get_tombstone(&id0).is_some());
+    assert!(client.get_tombstone(&id1).is_some());
+
+    // Nothing left to close.
+    assert_eq!(client.close_account(&subscriber), 0);
+}
+
+mod six_decimal_token {
+    use soroban_sdk::{contract, contractimpl, Env};
+
+    /// Token stub reporting USDC-style 6 decimals.
+    #[contract]
+    pub struct SixDecimalToken;
+
+    #[contractimpl]
+    impl SixDecimalToken {
+        pub fn decimals(_env: Env) -> u32 {
+            6
+        }
+    }
+}
+
+#[test]
+fn test_token_decimals_detected_at_init() {
+    let (env, client, _token, _admin) = setup_test_env();
+    assert_eq!(client.get_token_decimals(), 7);
+
+    let six = env.register(six_decimal_token::SixDecimalToken, ());
+    let other = SubscriptionVaultClient::new(&env, &env.register(SubscriptionVault, ()));
+    other.init(&six, &Address::generate(&env), &1_000000i128);
+    assert_eq!(other.get_token_decimals(), 6);
+}
+
+#[test]
+fn test_token_rotation_rejects_decimals_mismatch() {
+    let env = Env::default();
+    let (client, admin, _, _) = setup_batch_env(&env);
+    let old_token = client.get_token();
+    let six = env.register(six_decimal_token::SixDecimalToken, ());
+
+    client.propose_token_rotation(&admin, &six);
+    env.ledger()
+        .set_timestamp(T0 + INTERVAL + TOKEN_ROTATION_DELAY);
+    assert_eq!(
+        client.try_execute_token_rotation(&admin),
+        Err(Ok(Error::DecimalsMismatch))
+    );
+    assert_eq!(client.get_token(), old_token);
+    assert_eq!(client.get_token_decimals(), 7);
+}
+
+#[test]
+fn test_large_event_payload_published_as_hash() {
+    use soroban_sdk::xdr::ToXdr;
+    use soroban_sdk::TryFromVal;
+    let (env, client, _token, _admin) = setup_test_env();
+    let merchant = Address::generate(&env);
+    let adapter = Address::generate(&env);
+    let route_event = |env: &Env| {
+        env.events()
+            .all()
+            .iter()
+            .find(|e| {
+                Symbol::try_from_val(env, &e.1.get(0).unwrap())
+                    == Ok(Symbol::new(env, "payout_route_updated"))
+            })
+            .unwrap()
+            .2
+    };
+
+    let short = PayoutRoute {
+        adapter: adapter.clone(),
+        destination: soroban_sdk::Bytes::from_array(&env, &[0xab; 20]),
+    };
+    client.set_payout_route(&merchant, &Some(short.clone()));
+    let body = route_event(&env);
+    assert_eq!(
+        Option::<PayoutRoute>::try_from_val(&env, &body).unwrap(),
+        Some(short)
+    );
+
+    let long = PayoutRoute {
+        adapter,
+        destination: soroban_sdk::Bytes::from_array(&env, &[0xcd; 300]),
+    };
+    client.set_payout_route(&merchant, &Some(long.clone()));
+    let body = crate::EventPayloadRef::try_from_val(&env, &route_event(&env)).unwrap();
+    let xdr = Some(long.clone()).to_xdr(&env);
+    assert_eq!(body.size, xdr.len());
+    assert_eq!(body.hash, env.crypto().sha256(&xdr).to_bytes());
+    assert!(body.size > crate::MAX_EVENT_PAYLOAD_BYTES);
+    assert_eq!(client.get_payout_route(&merchant), Some(long));
+}
+
+#[test]
+fn test_charge_schedule_buckets_by_due_hour() {
+    let (env, client, _token, _admin) = setup_test_env();
+    env.ledger().set_timestamp(10_000);
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let create = |interval: u64| {
+        client.create_subscription(
+            &subscriber,
+            &merchant,
+            &1000,
+            &interval,
+            &false,
+            &None,
+            &0i128,
+            &false,
+        )
+    };
+    let a = create(3_600); // due 13_600
+    let b = create(5_400); // due 15_400
+    let c = create(1_800); // due 11_800
+    create(30 * 24 * 60 * 60);
+    let paused = create(3_600);
+    client.pause_subscription(&paused, &subscriber);
+
+    let schedule = client.get_charge_schedule(&merchant, &(24 * 60 * 60));
+    assert_eq!(
+        schedule,
+        SorobanVec::from_array(
+            &env,
+            [
+                crate::ChargeBucket {
+                    hour_start: 10_800,
+                    subscription_ids: SorobanVec::from_array(&env, [a, c]),
+                },
+                crate::ChargeBucket {
+                    hour_start: 14_400,
+                    subscription_ids: SorobanVec::from_array(&env, [b]),
+                },
+            ]
+        )
+    );
+
+    // Overdue subscriptions collapse into the current hour.
+    env.ledger().set_timestamp(20_000);
+    let schedule = client.get_charge_schedule(&merchant, &0);
+    assert_eq!(schedule.len(), 1);
+    let bucket = schedule.get(0).unwrap();
+    assert_eq!(bucket.hour_start, 18_000);
+    assert_eq!(
+        bucket.subscription_ids,
+        SorobanVec::from_array(&env, [a, b, c])
+    );
+
+    assert_eq!(
+        client.try_get_charge_schedule(&merchant, &(8 * 24 * 60 * 60)),
+        Err(Ok(Error::LimitExceeded))
+    );
+}
+
+#[test]
+fn test_plan_price_variants_assigned_deterministically() {
+    let (env, client, _token, _admin) = setup_test_env();
+    let merchant = Address::generate(&env);
+    let prices = SorobanVec::from_array(&env, [(1_000i128, 1u32), (2_000i128, 1u32)]);
+    let plan_id = client.create_plan(&merchant, &INTERVAL, &false, &prices);
+
+    for _ in 0..20 {
+        let subscriber = Address::generate(&env);
+        let id = client.subscribe_to_plan(&subscriber, &plan_id);
+        let assignment = client.get_plan_assignment(&id).unwrap();
+        assert_eq!(assignment.plan_id, plan_id);
+        let variant = prices.get(assignment.variant).unwrap();
+        assert_eq!(client.get_subscription(&id).amount, variant.0);
+
+        // The same subscriber always lands on the same variant.
+        let again = client.subscribe_to_plan(&subscriber, &plan_id);
+        assert_eq!(
+            client.get_plan_assignment(&again).unwrap().variant,
+            assignment.variant
+        );
+    }
+
+    let plan = client.get_plan(&plan_id);
+    let (a, b) = (
+        plan.variants.get(0).unwrap().subscribers,
+        plan.variants.get(1).unwrap().subscribers,
+    );
+    assert_eq!(a + b, 40);
+    assert!(a > 0 && b > 0);
+    assert_eq!(client.get_plan_assignment(&u32::MAX), None);
+}
+
+#[test]
+fn test_plan_validation() {
+    let (env, client, _token, _admin) = setup_test_env();
+    let merchant = Address::generate(&env);
+
+    assert_eq!(
+        client.try_create_plan(&merchant, &INTERVAL, &false, &SorobanVec::new(&env)),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_create_plan(
+            &merchant,
+            &INTERVAL,
+            &false,
+            &SorobanVec::from_array(&env, [(1_000i128, 0u32)])
+        ),
+        Err(Ok(Error::InvalidAmount))
+    );
+    let mut many = SorobanVec::new(&env);
+    for _ in 0..9 {
+        many.push_back((1_000i128, 1u32));
+    }
+    assert_eq!(
+        client.try_create_plan(&merchant, &INTERVAL, &false, &many),
+        Err(Ok(Error::LimitExceeded))
+    );
+    assert_eq!(
+        client.try_subscribe_to_plan(&Address::generate(&env), &7),
+        Err(Ok(Error::NotFound))
+    );
+}
+
+#[test]
+fn test_priority_tier_grace_keeps_subscription_active() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id1).merchant;
+    let grace = 2 * 24 * 60 * 60;
+    client.set_priority_policy(
+        &merchant,
+        &crate::PriorityTierPolicy {
+            grace_seconds: grace,
+            warning_lead_seconds: 0,
+        },
+    );
+    assert_eq!(client.get_support_tier(&id1), crate::SupportTier::Standard);
+    client.set_support_tier(&merchant, &id1, &crate::SupportTier::Priority);
+    assert_eq!(
+        client.try_set_support_tier(
+            &Address::generate(&env),
+            &id0,
+            &crate::SupportTier::Priority
+        ),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    // Underfunded within grace: the charge fails but the subscription stays Active.
+    let ids = SorobanVec::from_array(&env, [id1]);
+    let result = client.batch_charge(&ids).get(0).unwrap();
+    assert_eq!(result.error_code, Error::InsufficientBalance.to_code());
+    assert_eq!(
+        client.get_subscription(&id1).status,
+        SubscriptionStatus::Active
+    );
+
+    // After the grace period it falls back to InsufficientBalance.
+    env.ledger().set_timestamp(T0 + INTERVAL + grace);
+    let result = client.batch_charge(&ids).get(0).unwrap();
+    assert_eq!(result.error_code, Error::InsufficientBalance.to_code());
+    assert_eq!(
+        client.get_subscription(&id1).status,
+        SubscriptionStatus::InsufficientBalance
+    );
+}
+
+#[test]
+fn test_priority_tier_warned_earlier() {
+    let (env, client, _, _) = setup_test_env();
+    env.ledger().set_timestamp(T0);
+    let (standard, _, merchant) =
+        create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    let (priority, _, other_merchant) =
+        create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    client.set_priority_policy(
+        &other_merchant,
+        &crate::PriorityTierPolicy {
+            grace_seconds: 0,
+            warning_lead_seconds: 7 * 24 * 60 * 60,
+        },
+    );
+    client.set_support_tier(&other_merchant, &priority, &crate::SupportTier::Priority);
+    assert_eq!(client.get_priority_policy(&merchant), None);
+
+    // Five days out: outside the default 3-day window, inside the priority one.
+    env.ledger().set_timestamp(T0 + INTERVAL - 5 * 24 * 60 * 60);
+    let notified = client.notify_due_soon(&SorobanVec::from_array(&env, [standard, priority]));
+    assert_eq!(notified, SorobanVec::from_array(&env, [priority]));
+}
+
+#[test]
+fn test_spend_cap_blocks_charges_across_subscriptions() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    client.deposit_funds(&id1, &subscriber, &10_000000i128);
+
+    assert_eq!(
+        client.try_set_spend_cap(&subscriber, &Some(-1)),
+        Err(Ok(Error::InvalidAmount))
+    );
+    client.set_spend_cap(&subscriber, &Some(1_500));
+
+    let results = client.batch_charge(&SorobanVec::from_array(&env, [id0, id1]));
+    assert!(results.get(0).unwrap().success);
+    assert_eq!(
+        results.get(1).unwrap().error_code,
+        Error::BudgetExceeded.to_code()
+    );
+    let sub1 = client.get_subscription(&id1);
+    assert_eq!(sub1.prepaid_balance, 10_000000);
+    assert_eq!(sub1.status, SubscriptionStatus::Active);
+
+    let status = client.get_budget_status(&subscriber);
+    assert_eq!(status.cap, Some(1_500));
+    assert_eq!(status.spent, 1_000);
+    assert!(status.period_start <= T0 + INTERVAL && T0 + INTERVAL < status.period_end);
+
+    // Lifting the cap lets the charge through.
+    client.set_spend_cap(&subscriber, &None);
+    client.charge_subscription(&id1);
+    assert_eq!(client.get_budget_status(&subscriber).spent, 2_000);
+}
+
+#[test]
+fn test_simulate_set_config_reports_impacted_subscriptions() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let mut config = client.get_config();
+
+    // Unchanged config affects nobody.
+    let impact = client.simulate_set_config(&config, &0, &10);
+    assert_eq!(impact.scanned, 2);
+    assert_eq!(impact.below_min_topup, 0);
+    assert_eq!(impact.sample_ids.len(), 0);
+
+    // id1 is already below the current minimum, so only id0 is newly affected.
+    config.min_topup = 20_000000;
+    config.withdrawal_timelock = WithdrawalTimelock {
+        threshold: 5_000000,
+        delay_seconds: 60,
+    };
+    let impact = client.simulate_set_config(&config, &0, &10);
+    assert_eq!(impact.below_min_topup, 1);
+    assert_eq!(impact.newly_timelocked, 1);
+    assert_eq!(impact.sample_ids, SorobanVec::from_array(&env, [id0]));
+    assert_eq!(impact.next_cursor, None);
+
+    let page = client.simulate_set_config(&config, &0, &1);
+    assert_eq!(page.scanned, 1);
+    assert_eq!(page.next_cursor, Some(1));
+
+    config.min_topup = -1;
+    assert_eq!(
+        client.try_simulate_set_config(&config, &0, &10),
+        Err(Ok(Error::InvalidAmount))
+    );
+}
+
+#[test]
+fn test_merchant_kill_switch_halts_only_that_merchant() {
+    use soroban_sdk::TryFromVal;
+    let env = Env::default();
+    let (client, admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let other_merchant = Address::generate(&env);
+    let other = client.create_subscription(
+        &sub.subscriber,
+        &other_merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+    client.deposit_funds(&other, &sub.subscriber, &10_000000i128);
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+
+    let fraud = Symbol::new(&env, "fraud");
+    assert_eq!(
+        client.try_halt_merchant(&Address::generate(&env), &sub.merchant, &fraud),
+        Err(Ok(Error::Unauthorized))
+    );
+    client.halt_merchant(&admin, &sub.merchant, &fraud);
+    let halted_notices = env
+        .events()
+        .all()
+        .iter()
+        .filter(|e| {
+            Symbol::try_from_val(&env, &e.1.get(0).unwrap())
+                == Ok(Symbol::new(&env, "charging_halted"))
+        })
+        .count();
+    assert_eq!(halted_notices, 2);
+    assert_eq!(
+        client.get_merchant_halt(&sub.merchant).unwrap().reason,
+        fraud
+    );
+    assert_eq!(
+        client.try_halt_merchant(&admin, &sub.merchant, &fraud),
+        Err(Ok(Error::Replay))
+    );
+
+    assert_eq!(
+        client.try_charge_subscription(&id0),
+        Err(Ok(Error::MerchantHalted))
+    );
+    client.charge_subscription(&other);
+
+    client.reinstate_merchant(&admin, &sub.merchant);
+    assert_eq!(client.get_merchant_halt(&sub.merchant), None);
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.try_reinstate_merchant(&admin, &sub.merchant),
+        Err(Ok(Error::NotFound))
+    );
+}
+
+#[test]
+fn test_keeper_registry_tracks_stats_and_bans() {
+    let env = Env::default();
+    let (client, admin, id0, id1) = setup_batch_env(&env);
+    let keeper = Address::generate(&env);
+    assert_eq!(client.get_keeper_stats(&keeper), None);
+
+    // id1 is unfunded, so one success and one failure.
+    let results = client.keeper_charge(&keeper, &SorobanVec::from_array(&env, [id0, id1]));
+    assert!(results.get(0).unwrap().success);
+    assert!(!results.get(1).unwrap().success);
+    let stats = client.get_keeper_stats(&keeper).unwrap();
+    assert_eq!(stats.charges_executed, 1);
+    assert_eq!(stats.failures, 1);
+    assert_eq!(stats.last_active, T0 + INTERVAL);
+    assert!(!stats.banned);
+    assert_eq!(
+        client.list_keepers(&0, &10),
+        SorobanVec::from_array(&env, [keeper.clone()])
+    );
+
+    assert_eq!(
+        client.try_set_keeper_banned(&keeper, &keeper, &true),
+        Err(Ok(Error::Unauthorized))
+    );
+    client.set_keeper_banned(&admin, &keeper, &true);
+    assert_eq!(
+        client.try_keeper_charge(&keeper, &SorobanVec::from_array(&env, [id0])),
+        Err(Ok(Error::Unauthorized))
+    );
+    client.set_keeper_banned(&admin, &keeper, &false);
+    client.keeper_charge(&keeper, &SorobanVec::from_array(&env, [id0]));
+    assert_eq!(client.get_keeper_stats(&keeper).unwrap().failures, 2);
+    assert_eq!(client.list_keepers(&0, &10).len(), 1);
+}
+
+#[test]
+fn test_banned_keeper_not_credited_for_permissionless_charge() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    let keeper = Address::generate(&env);
+    client.keeper_charge(&keeper, &SorobanVec::from_array(&env, [id0]));
+    client.set_keeper_banned(&admin, &keeper, &true);
+
+    // The ban cannot stop a due charge, but nothing is attributed to the keeper.
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id0);
+    let stats = client.get_keeper_stats(&keeper).unwrap();
+    assert_eq!(stats.charges_executed, 1);
+    assert!(stats.banned);
+}
+
+#[test]
+fn test_referral_commission_decays_with_subscription_age() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token_addr = client.get_token();
+    let token = soroban_sdk::token::Client::new(&env, &token_addr);
+    let affiliate = Address::generate(&env);
+    let code = Symbol::new(&env, "PODCAST");
+    let day = 24 * 60 * 60;
+    let referral = crate::ReferralCode {
+        affiliate: affiliate.clone(),
+        schedule: SorobanVec::from_array(
+            &env,
+            [
+                crate::CommissionTier {
+                    until_age_seconds: 90 * day,
+                    bps: 2_000,
+                },
+                crate::CommissionTier {
+                    until_age_seconds: 365 * day,
+                    bps: 500,
+                },
+            ],
+        ),
+    };
+
+    assert_eq!(
+        client.try_apply_referral(&sub.subscriber, &id0, &code),
+        Err(Ok(Error::NotFound))
+    );
+    client.set_referral_code(&sub.merchant, &code, &Some(referral.clone()));
+    client.apply_referral(&sub.subscriber, &id0, &code);
+    assert_eq!(client.get_subscription_referral(&id0), Some(code.clone()));
+    assert_eq!(
+        client.try_apply_referral(&sub.subscriber, &id0, &code),
+        Err(Ok(Error::Replay))
+    );
+
+    // Age 30 days: 20% of the charge.
+    client.charge_subscription(&id0);
+    assert_eq!(token.balance(&affiliate), 200);
+    assert_eq!(
+        client.get_payout_queue(&sub.merchant, &token_addr).pending,
+        800
+    );
+
+    // Age 120 days: 5%.
+    env.ledger().set_timestamp(T0 + 120 * day);
+    client.charge_subscription(&id0);
+    assert_eq!(token.balance(&affiliate), 250);
+
+    // Past the schedule: nothing.
+    env.ledger().set_timestamp(T0 + 400 * day);
+    client.charge_subscription(&id0);
+    assert_eq!(token.balance(&affiliate), 250);
+
+    let mut bad = referral;
+    bad.schedule.set(
+        1,
+        crate::CommissionTier {
+            until_age_seconds: 10 * day,
+            bps: 500,
+        },
+    );
+    assert_eq!(
+        client.try_set_referral_code(&sub.merchant, &code, &Some(bad)),
+        Err(Ok(Error::InvalidAmount))
+    );
+}
+
+#[test]
+fn test_terms_acceptance_gates_price_change() {
+    let (env, client, _token, _admin) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let v1 = BytesN::from_array(&env, &[1; 32]);
+    let v2 = BytesN::from_array(&env, &[2; 32]);
+
+    client.publish_terms(&merchant, &v1, &None);
+    let id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1000,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+    assert_eq!(client.get_accepted_terms(&id), Some(v1.clone()));
+
+    // New terms raise the price, but only once the subscriber re-accepts.
+    client.publish_terms(&merchant, &v2, &Some(1500));
+    assert_eq!(client.get_subscription(&id).amount, 1000);
+    assert_eq!(client.get_accepted_terms(&id), Some(v1.clone()));
+    assert_eq!(
+        client.try_accept_terms(&subscriber, &id, &v1),
+        Err(Ok(Error::TermsMismatch))
+    );
+    assert_eq!(
+        client.try_accept_terms(&merchant, &id, &v2),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    client.accept_terms(&subscriber, &id, &v2);
+    assert_eq!(client.get_subscription(&id).amount, 1500);
+    assert_eq!(client.get_accepted_terms(&id), Some(v2.clone()));
+    assert_eq!(client.get_merchant_terms(&merchant).unwrap().terms_hash, v2);
+}
+
+#[test]
+fn test_charge_credits_merchant_and_moves_tokens_when_payout_due() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let token_addr = client.get_token();
+    let token = soroban_sdk::token::Client::new(&env, &token_addr);
+    let vault_before = token.balance(&client.address);
+
+    // Without a payout policy the charge is credited internally; tokens stay put.
+    client.charge_subscription(&id0);
+    assert_eq!(token.balance(&client.address), vault_before);
+    assert_eq!(
+        client.get_payout_queue(&merchant, &token_addr).pending,
+        1000
+    );
+
+    // Once the policy is due, the next charge transfers everything owed.
+    client.set_payout_policy(
+        &merchant,
+        &Some(crate::PayoutPolicy {
+            threshold: 1,
+            interval_seconds: 0,
+        }),
+    );
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id0);
+    assert_eq!(token.balance(&client.address), vault_before - 2000);
+    assert_eq!(token.balance(&merchant), 2000);
+    assert_eq!(client.get_payout_queue(&merchant, &token_addr).pending, 0);
+}
+
+#[test]
+fn test_batch_charge_v2_splits_successes_and_failures() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let missing = 99u32;
+
+    let summary = client.batch_charge_v2(&SorobanVec::from_array(&env, [id0, id1, missing]));
+    assert_eq!(summary.charged, SorobanVec::from_array(&env, [id0]));
+    assert_eq!(
+        summary.failed,
+        SorobanVec::from_array(
+            &env,
+            [
+                (id1, Error::InsufficientBalance.to_code()),
+                (missing, Error::NotFound.to_code()),
+            ]
+        )
+    );
+    assert_eq!(summary.count_charged, 1);
+    assert_eq!(summary.total_amount, 1000);
+}
+
+// =============================================================================
+// Subscription Merge Tests
+// =============================================================================
+
+#[test]
+fn test_merge_subscriptions_keeps_older_anchor_and_combines_balances() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+
+    // Charging id0 moves its anchor forward, so id1 now has the older one.
+    client.charge_subscription(&id0);
+    let remaining = client.get_subscription(&id0).prepaid_balance;
+
+    let survivor = client.merge_subscriptions(&subscriber, &id0, &id1);
+    assert_eq!(survivor, id1);
+    let kept = client.get_subscription(&id1);
+    assert_eq!(kept.prepaid_balance, remaining);
+    assert_eq!(kept.status, SubscriptionStatus::Active);
+    let merged = client.get_subscription(&id0);
+    assert_eq!(merged.prepaid_balance, 0);
+    assert_eq!(merged.status, SubscriptionStatus::Cancelled);
+    assert_eq!(client.get_merged_into(&id0), Some(id1));
+    assert_eq!(client.get_merged_into(&id1), None);
+
+    // The duplicate is no longer live.
+    assert_eq!(
+        client
+            .try_merge_subscriptions(&subscriber, &id1, &id0)
+            .err(),
+        Some(Ok(Error::InvalidStatusTransition))
+    );
+}
+
+#[test]
+fn test_merge_subscriptions_rejects_mismatched_terms() {
+    let env = Env::default();
+    let (client, _admin, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let other = client.create_subscription(
+        &sub.subscriber,
+        &sub.merchant,
+        &2000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+
+    assert_eq!(
+        client
+            .try_merge_subscriptions(&sub.subscriber, &id0, &other)
+            .err(),
+        Some(Ok(Error::SubscriptionsMismatch))
+    );
+    assert_eq!(
+        client
+            .try_merge_subscriptions(&Address::generate(&env), &id0, &other)
+            .err(),
+        Some(Ok(Error::Unauthorized))
+    );
+}
+
+// =============================================================================
+// Test Mode Tests
+// =============================================================================
+
+#[test]
+fn test_test_mode_charge_moves_nothing_and_skips_stats() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id1);
+    let token = client.get_token();
+
+    assert_eq!(
+        client
+            .try_set_test_mode(&Address::generate(&env), &id1, &true)
+            .err(),
+        Some(Ok(Error::Unauthorized))
+    );
+    client.set_test_mode(&sub.merchant, &id1, &true);
+    assert!(client.is_test_mode(&id1));
+
+    // id1 has no balance, yet its test-mode charge succeeds at zero.
+    client.charge_subscription(&id1);
+    let after = client.get_subscription(&id1);
+    assert_eq!(after.status, SubscriptionStatus::Active);
+    assert_eq!(after.prepaid_balance, 0);
+    assert_eq!(after.last_payment_timestamp, T0 + INTERVAL);
+    assert_eq!(client.get_payout_queue(&sub.merchant, &token).pending, 0);
+
+    // A real charge on id0 is the only thing in the settlement report.
+    client.charge_subscription(&id0);
+    env.ledger().set_timestamp(T0 + INTERVAL + 1);
+    client.withdraw_merchant_funds(&sub.merchant, &0i128);
+    let s = client
+        .get_settlements(&sub.merchant, &0, &10)
+        .items
+        .get(0)
+        .unwrap();
+    assert_eq!(s.charge_count, 1);
+    assert_eq!(s.gross, 1000);
+
+    client.set_test_mode(&sub.merchant, &id1, &false);
+    assert!(!client.is_test_mode(&id1));
+}
+
+// =============================================================================
+// Initial Deposit Tests
+// =============================================================================
+
+#[test]
+fn test_create_subscription_takes_initial_deposit() {
+    let env = Env::default();
+    let (client, _admin, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+    let wallet_before = token.balance(&sub.subscriber);
+    let vault_before = token.balance(&client.address);
+
+    let id = client.create_subscription(
+        &sub.subscriber,
+        &sub.merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &5_000000i128,
+        &false,
+    );
+    assert_eq!(client.get_subscription(&id).prepaid_balance, 5_000000);
+    assert_eq!(token.balance(&sub.subscriber), wallet_before - 5_000000);
+    assert_eq!(token.balance(&client.address), vault_before + 5_000000);
+
+    // A non-zero initial deposit is held to min_topup; the subscription is not created.
+    assert_eq!(
+        client
+            .try_create_subscription(
+                &sub.subscriber,
+                &sub.merchant,
+                &1000i128,
+                &INTERVAL,
+                &false,
+                &None,
+                &1i128,
+                &false,
+            )
+            .err(),
+        Some(Ok(Error::BelowMinimumTopup))
+    );
+    assert_eq!(client.get_subscription(&id).prepaid_balance, 5_000000);
+}
+
+// =============================================================================
+// Merchant Minimum Amount Tests
+// =============================================================================
+
+#[test]
+fn test_merchant_min_amount_enforced_on_create_and_reprice() {
+    let env = Env::default();
+    let (client, _admin, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    assert_eq!(client.get_merchant_min_amount(&sub.merchant), 0);
+
+    client.set_merchant_min_amount(&sub.merchant, &500i128);
+    assert_eq!(client.get_merchant_min_amount(&sub.merchant), 500);
+
+    assert_eq!(
+        client
+            .try_create_subscription(
+                &sub.subscriber,
+                &sub.merchant,
+                &499i128,
+                &INTERVAL,
+                &false,
+                &None,
+                &0i128,
+                &false,
+            )
+            .err(),
+        Some(Ok(Error::BelowMerchantMinimum))
+    );
+    client.create_subscription(
+        &sub.subscriber,
+        &sub.merchant,
+        &500i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+
+    // Repricing below the minimum is rejected; existing subscriptions are untouched.
+    let hash = BytesN::from_array(&env, &[7u8; 32]);
+    assert_eq!(
+        client
+            .try_publish_terms(&sub.merchant, &hash, &Some(100i128))
+            .err(),
+        Some(Ok(Error::BelowMerchantMinimum))
+    );
+    client.set_merchant_min_amount(&sub.merchant, &2000i128);
+    assert_eq!(client.get_subscription(&id0).amount, 1000);
+    client.charge_subscription(&id0);
+
+    client.set_merchant_min_amount(&sub.merchant, &0i128);
+    assert_eq!(client.get_merchant_min_amount(&sub.merchant), 0);
+}
+
+// =============================================================================
+// Init Authorization Tests
+// =============================================================================
+
+#[test]
+fn test_init_requires_admin_auth() {
+    let env = Env::default();
+    let contract_id = env.register(SubscriptionVault, ());
+    let client = SubscriptionVaultClient::new(&env, &contract_id);
+    let admin = Address::generate(&env);
+    let token = env
+        .register_stellar_asset_contract_v2(Address::generate(&env))
+        .address();
+
+    // No signature from `admin`: a front-runner cannot install it (or itself).
+    assert!(client.try_init(&token, &admin, &1_000000i128).is_err());
+
+    env.mock_all_auths();
+    client.init(&token, &admin, &1_000000i128);
+    assert_eq!(
+        env.auths().first().map(|(addr, _)| addr.clone()),
+        Some(admin.clone())
+    );
+    assert_eq!(client.get_admin(), admin);
+}
+
+#[test]
+fn test_init_cannot_be_repeated() {
+    let (env, client, token, _admin) = setup_test_env();
+    let attacker = Address::generate(&env);
+    assert_eq!(
+        client.try_init(&token, &attacker, &0i128).err(),
+        Some(Ok(Error::AlreadyInitialized))
+    );
+}
+
+#[test]
+fn test_create_subscription_charge_immediately() {
+    let env = Env::default();
+    let (client, _admin, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let now = T0 + INTERVAL;
+
+    let id = client.create_subscription(
+        &sub.subscriber,
+        &sub.merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &5_000000i128,
+        &true,
+    );
+    let created = client.get_subscription(&id);
+    assert_eq!(created.prepaid_balance, 5_000000 - 1000);
+    assert_eq!(created.last_payment_timestamp, now);
+    assert_eq!(
+        client
+            .get_payout_queue(&sub.merchant, &client.get_token())
+            .pending,
+        1000
+    );
+
+    // This period is paid; the next charge is one interval after creation.
+    assert_eq!(
+        client.try_charge_subscription(&id).err(),
+        Some(Ok(Error::Replay))
+    );
+    env.ledger().set_timestamp(now + INTERVAL);
+    client.charge_subscription(&id);
+
+    // Without funds for the first charge, nothing is created.
+    assert_eq!(
+        client
+            .try_create_subscription(
+                &sub.subscriber,
+                &sub.merchant,
+                &1000i128,
+                &INTERVAL,
+                &false,
+                &None,
+                &0i128,
+                &true,
+            )
+            .err(),
+        Some(Ok(Error::InsufficientBalance))
+    );
+    assert_eq!(
+        client.try_get_subscription(&(id + 1)).err(),
+        Some(Ok(Error::NotFound))
+    );
+}
+
+// =============================================================================
+// Refund Split Tests
+// =============================================================================
+
+#[test]
+fn test_cooling_off_refund_split_between_wallet_and_address() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    client.set_cooling_off_period(&admin, &(INTERVAL + COOLING_OFF));
+    client.set_external_refund_fee(&admin, &1000u32);
+    let sub = client.get_subscription(&id0);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+
+    // Half of refunds go out to the subscriber's address.
+    client.set_refund_split(&sub.subscriber, &5000u32);
+    assert_eq!(client.get_refund_split(&sub.subscriber), 5000);
+
+    client.charge_subscription(&id0);
+    let wallet_before = token.balance(&sub.subscriber);
+    client.cancel_subscription(&id0, &sub.subscriber);
+
+    // 500 stays in the credit wallet; 500 goes out, without the external refund
+    // fee on a cooling-off refund.
+    assert_eq!(
+        client.get_credit_balance(&sub.subscriber, &token.address),
+        500
+    );
+    assert_eq!(token.balance(&sub.subscriber), wallet_before + 500);
+    assert_eq!(
+        client
+            .get_payout_queue(&sub.merchant, &token.address)
+            .pending,
+        0
+    );
+}
+
+#[test]
+fn test_refund_split_and_fee_validation() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    assert_eq!(client.get_refund_split(&subscriber), 0);
+    assert_eq!(client.get_external_refund_fee(), 0);
+    assert_eq!(
+        client.try_set_refund_split(&subscriber, &10_001u32).err(),
+        Some(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_set_external_refund_fee(&admin, &10_001u32).err(),
+        Some(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        client
+            .try_set_external_refund_fee(&Address::generate(&env), &100u32)
+            .err(),
+        Some(Ok(Error::Unauthorized))
+    );
+}
+
+// =============================================================================
+// Subscription View Tests
+// =============================================================================
+
+#[test]
+fn test_subscription_view_reports_grace_and_attempts() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id1).merchant;
+    let grace = 2 * 24 * 60 * 60;
+    client.set_priority_policy(
+        &merchant,
+        &crate::PriorityTierPolicy {
+            grace_seconds: grace,
+            warning_lead_seconds: 0,
+        },
+    );
+    client.set_support_tier(&merchant, &id1, &crate::SupportTier::Priority);
+
+    // id1 is due now with no balance: the failed charge is queued for retry and
+    // the subscription stays Active within its grace period.
+    client.batch_charge(&SorobanVec::from_array(&env, [id1]));
+    let view = client.get_subscription_view(&id1);
+    assert_eq!(view.subscription.status, SubscriptionStatus::Active);
+    assert_eq!(view.next_due, T0 + INTERVAL);
+    assert_eq!(view.grace_deadline, T0 + INTERVAL + grace);
+    assert!(view.in_grace);
+    assert_eq!(view.failed_attempts, 1);
+    assert_eq!(
+        view.remaining_attempts,
+        crate::keeper::MAX_RETRY_ATTEMPTS - 1
+    );
+    assert_eq!(view.effective_amount, 1000);
+
+    // A funded, charged subscription is neither in grace nor retrying.
+    client.charge_subscription(&id0);
+    let view = client.get_subscription_view(&id0);
+    assert!(!view.in_grace);
+    assert_eq!(view.next_due, T0 + 2 * INTERVAL);
+    assert_eq!(view.grace_deadline, view.next_due);
+    assert_eq!(view.failed_attempts, 0);
+
+    // Test mode charges nothing.
+    client.set_test_mode(&merchant, &id0, &true);
+    assert_eq!(client.get_subscription_view(&id0).effective_amount, 0);
+}
+
+#[test]
+fn test_subscriber_index_survives_cancel_and_clears_on_close() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let other = Address::generate(&env);
+    client.create_subscription(
+        &other,
+        &sub.merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+
+    let page = client.list_subscriptions_by_subscriber(&sub.subscriber, &0, &1);
+    assert_eq!(page.total, 2);
+    assert_eq!(page.items, SorobanVec::from_array(&env, [id0]));
+    assert_eq!(page.next_cursor, Some(id1));
+
+    // Cancelled subscriptions stay discoverable.
+    client.cancel_subscription(&id1, &sub.subscriber);
+    let page = client.list_subscriptions_by_subscriber(&sub.subscriber, &id1, &10);
+    assert_eq!(page.items, SorobanVec::from_array(&env, [id1]));
+
+    client.cancel_subscription(&id0, &sub.subscriber);
+    client.withdraw_subscriber_funds(&id0, &sub.subscriber);
+    client.close_account(&sub.subscriber);
+    assert_eq!(
+        client
+            .list_subscriptions_by_subscriber(&sub.subscriber, &0, &10)
+            .total,
+        0
+    );
+    assert_eq!(
+        client
+            .list_subscriptions_by_subscriber(&other, &0, &10)
+            .total,
+        1
+    );
+}
+
+#[test]
+fn test_list_subscriptions_by_merchant_returns_summaries() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    client.create_subscription(
+        &Address::generate(&env),
+        &Address::generate(&env),
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+
+    let page = client.list_subscriptions_by_merchant(&merchant, &0, &1);
+    assert_eq!(page.total, 2);
+    assert_eq!(page.next_cursor, Some(1));
+    let first = page.items.get(0).unwrap();
+    assert_eq!(first.id, id0);
+    assert_eq!(first.merchant, merchant);
+    assert_eq!(first.amount, 1000);
+    assert_eq!(first.prepaid_balance, 10_000000);
+    assert_eq!(first.status, SubscriptionStatus::Active);
+
+    let page = client.list_subscriptions_by_merchant(&merchant, &1, &10);
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items.get(0).unwrap().id, id1);
+    assert_eq!(page.next_cursor, None);
+}
+
+#[test]
+fn test_queries_return_versioned_dtos() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    assert_eq!(client.get_dto_version(), crate::DTO_VERSION);
+
+    let dto = client.get_subscription(&id1);
+    assert_eq!(dto.id, id1);
+    assert_eq!(client.get_subscription_view(&id1).subscription, dto);
+
+    let page = client.get_subscriptions_by_merchant(&dto.merchant, &0, &10);
+    assert_eq!(page.items.get(0).unwrap().id, id0);
+    assert_eq!(page.items.get(1).unwrap(), dto);
+}
+
+#[test]
+fn test_merchant_period_summary_sums_day_aggregates() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let now = T0 + INTERVAL;
+
+    client.charge_subscription(&id0);
+    client.cancel_subscription(&id1, &sub.subscriber);
+
+    let today = client.get_merchant_period_summary(&sub.merchant, &now, &(now + 1));
+    assert_eq!(today.period_start, now - now % 86_400);
+    assert_eq!(today.period_end, today.period_start + 86_400);
+    assert_eq!(today.charge_count, 1);
+    assert_eq!(today.charged, 1000);
+    assert_eq!(today.fees, 0);
+    assert_eq!(today.refund_count, 0);
+    assert_eq!(today.new_subscriptions, 0);
+    assert_eq!(today.cancellations, 1);
+
+    let all = client.get_merchant_period_summary(&sub.merchant, &T0, &(now + 1));
+    assert_eq!(all.new_subscriptions, 2);
+    assert_eq!(all.charge_count, 1);
+
+    assert_eq!(
+        client.try_get_merchant_period_summary(&sub.merchant, &now, &now),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_get_merchant_period_summary(&sub.merchant, &0, &(367 * 86_400)),
+        Err(Ok(Error::LimitExceeded))
+    );
+}
+
+#[test]
+fn test_get_due_subscriptions_lists_elapsed_ids() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let now = T0 + INTERVAL;
+
+    assert_eq!(client.get_due_subscriptions(&(now - 1), &10).len(), 0);
+    assert_eq!(
+        client.get_due_subscriptions(&now, &10),
+        SorobanVec::from_array(&env, [id0, id1])
+    );
+    assert_eq!(
+        client.get_due_subscriptions(&now, &1),
+        SorobanVec::from_array(&env, [id0])
+    );
+
+    client.charge_subscription(&id0);
+    client.pause_subscription(&id1, &client.get_subscription(&id1).subscriber);
+    assert_eq!(client.get_due_subscriptions(&now, &10).len(), 0);
+}
+
+#[test]
+fn test_deposit_caps_limit_deposits() {
+    let env = Env::default();
+    let (client, admin, id0, id1) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    assert_eq!(client.get_deposit_capacity(&Some(id0)), i128::MAX);
+
+    let caps = DepositCaps {
+        tvl_cap: 20_000000,
+        balance_cap: 15_000000,
+    };
+    client.set_deposit_caps(&admin, &caps);
+    assert_eq!(client.get_deposit_caps(), caps);
+    assert_eq!(client.get_deposit_capacity(&Some(id0)), 5_000000);
+    assert_eq!(client.get_deposit_capacity(&Some(id1)), 10_000000);
+    assert_eq!(client.get_deposit_capacity(&None), 10_000000);
+
+    assert_eq!(
+        client.try_deposit_funds(&id0, &subscriber, &6_000000),
+        Err(Ok(Error::DepositCapExceeded))
+    );
+    client.deposit_funds(&id0, &subscriber, &5_000000);
+    assert_eq!(client.get_deposit_capacity(&Some(id1)), 5_000000);
+    assert_eq!(
+        client.try_deposit_funds(&id1, &subscriber, &6_000000),
+        Err(Ok(Error::DepositCapExceeded))
+    );
+
+    client.set_deposit_caps(
+        &admin,
+        &DepositCaps {
+            tvl_cap: 0,
+            balance_cap: 0,
+        },
+    );
+    client.deposit_funds(&id1, &subscriber, &6_000000);
+}
+
+#[test]
+fn test_subscription_expires_at_end_date() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let now = T0 + INTERVAL;
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+
+    assert_eq!(
+        client.try_set_expiry(&id0, &sub.merchant, &Some(now)),
+        Err(Ok(Error::InvalidAmount))
+    );
+    client.set_expiry(&id0, &sub.merchant, &Some(now + 100));
+    client.set_expiry(&id1, &sub.merchant, &Some(now + 100));
+    assert_eq!(client.get_expires_at(&id0), Some(now + 100));
+    assert_eq!(
+        client.try_expire_subscription(&id1),
+        Err(Ok(Error::InvalidStatusTransition))
+    );
+
+    env.ledger().set_timestamp(now + 100);
+    assert_eq!(
+        client.try_charge_subscription(&id0),
+        Err(Ok(Error::NotActive))
+    );
+    assert_eq!(client.get_due_subscriptions(&(now + 100), &10).len(), 0);
+
+    client.expire_subscription(&id1);
+    assert_eq!(
+        client.get_subscription(&id1).status,
+        SubscriptionStatus::Expired
+    );
+
+    let before = token.balance(&sub.subscriber);
+    client.withdraw_subscriber_funds(&id0, &sub.subscriber);
+    let expired = client.get_subscription(&id0);
+    assert_eq!(expired.status, SubscriptionStatus::Expired);
+    assert_eq!(expired.prepaid_balance, 0);
+    assert_eq!(token.balance(&sub.subscriber), before + 10_000000);
+}
+
+#[test]
+fn test_clock_jump_flags_charge_for_review() {
+    let env = Env::default();
+    let (client, admin, id0, _id1) = setup_batch_env(&env);
+    let due_at = T0 + INTERVAL;
+    client.set_max_charge_elapsed(&admin, &1_000);
+    assert_eq!(client.get_max_charge_elapsed(), 1_000);
+
+    let later = due_at + 2_000;
+    env.ledger().set_timestamp(later);
+    let results = client.batch_charge(&SorobanVec::from_array(&env, [id0]));
+    assert_eq!(
+        results.get(0).unwrap().error_code,
+        Error::ChargeUnderReview.to_code()
+    );
+    let review = client.get_charge_review(&id0).unwrap();
+    assert_eq!(review.due_at, due_at);
+    assert_eq!(review.attempted_at, later);
+    assert_eq!(client.get_subscription(&id0).prepaid_balance, 10_000000);
+    assert_eq!(
+        client.try_charge_subscription(&id0),
+        Err(Ok(Error::ChargeUnderReview))
+    );
+
+    client.resolve_charge_review(&admin, &id0);
+    assert_eq!(client.get_charge_review(&id0), None);
+    assert_eq!(client.get_subscription(&id0).last_payment_timestamp, later);
+    assert_eq!(
+        client.try_charge_subscription(&id0),
+        Err(Ok(Error::IntervalNotElapsed))
+    );
+}
+
+#[test]
+fn test_error_codes_are_unique_and_in_range() {
+    use crate::ErrorCategory;
+
+    let all = [
+        Error::InvalidStatusTransition,
+        Error::Unauthorized,
+        Error::NotFound,
+        Error::NotActive,
+        Error::IntervalNotElapsed,
+        Error::Replay,
+        Error::SubscriberLocked,
+        Error::Reentrancy,
+        Error::MerchantNotApproved,
+        Error::MerchantNotStaked,
+        Error::AgreementExpired,
+        Error::AccountNotSettled,
+        Error::MerchantHalted,
+        Error::TermsMismatch,
+        Error::SubscriptionsMismatch,
+        Error::AlreadyInitialized,
+        Error::ChargeUnderReview,
+        Error::TimelockNotElapsed,
+        Error::InvalidLabel,
+        Error::SubscriptionKeyTaken,
+        Error::AboveThreshold,
+        Error::Overflow,
+        Error::Underflow,
+        Error::InsufficientBalance,
+        Error::InsufficientPrepaidBalance,
+        Error::InvalidAmount,
+        Error::InvalidRecoveryAmount,
+        Error::BelowMinimumTopup,
+        Error::BelowReserve,
+        Error::BudgetExceeded,
+        Error::BelowMerchantMinimum,
+        Error::DepositCapExceeded,
+        Error::LimitExceeded,
+        Error::RateLimited,
+        Error::DecimalsMismatch,
+        Error::UsageNotEnabled,
+    ];
+    let mut codes = std::vec::Vec::new();
+    for e in all {
+        let code = e.clone().to_code();
+        assert!(
+            (400..700).contains(&code),
+            "{e:?} has out-of-range code {code}"
+        );
+        assert!(!codes.contains(&code), "{e:?} reuses code {code}");
+        codes.push(code);
+    }
+
+    assert_eq!(Error::NotFound.category(), ErrorCategory::AuthOrState);
+    assert_eq!(
+        ErrorCategory::from(Error::InsufficientBalance),
+        ErrorCategory::Accounting
+    );
+    assert_eq!(
+        ErrorCategory::from_code(Error::RateLimited.to_code()),
+        Some(ErrorCategory::Config)
+    );
+    assert_eq!(ErrorCategory::from_code(1003), None);
+    assert_eq!(ErrorCategory::from_code(700), None);
+    assert_eq!(ErrorCategory::from_code(399), None);
+}
+
+#[test]
+fn test_cancellation_quote_matches_cancel() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+
+    client.charge_subscription(&id0);
+    let quote = client.get_cancellation_quote(&id0);
+    assert!(!quote.in_cooling_off);
+    assert_eq!(quote.refund, 0);
+    assert_eq!(quote.prepaid_balance, 10_000000 - 1000);
+    assert_eq!(quote.total_returned, quote.prepaid_balance);
+
+    client.set_cooling_off_period(&admin, &(INTERVAL + COOLING_OFF));
+    client.set_external_refund_fee(&admin, &1000u32);
+    client.set_refund_split(&sub.subscriber, &5000u32);
+    let quote = client.get_cancellation_quote(&id0);
+    assert!(quote.in_cooling_off);
+    assert_eq!(quote.refund, 1000);
+    assert_eq!(quote.refund_to_wallet, 500);
+    assert_eq!(quote.refund_to_external, 500);
+    assert_eq!(quote.refund_fee, 0);
+    assert_eq!(quote.total_returned, 10_000000);
+
+    let before = token.balance(&sub.subscriber);
+    client.cancel_subscription(&id0, &sub.subscriber);
+    client.withdraw_subscriber_funds(&id0, &sub.subscriber);
+    let wallet = client.get_credit_balance(&sub.subscriber, &token.address);
+    assert_eq!(
+        token.balance(&sub.subscriber) - before + wallet,
+        quote.total_returned
+    );
+}
+
+mod webhook_mock {
+    use crate::MerchantWebhook;
+    use soroban_sdk::{contract, contractimpl, symbol_short, Address, Env};
+
+    /// Webhook that counts calls, or panics when configured to fail.
+    #[contract]
+    pub struct MockWebhook;
+
+    #[contractimpl]
+    impl MockWebhook {
+        pub fn set_failing(env: Env, failing: bool) {
+            env.storage()
+                .instance()
+                .set(&symbol_short!("fail"), &failing);
+        }
+
+        pub fn calls(env: Env) -> (u32, u32) {
+            env.storage()
+                .instance()
+                .get(&symbol_short!("calls"))
+                .unwrap_or((0, 0))
+        }
+    }
+
+    fn bump(env: &Env, charged: u32, cancelled: u32) {
+        if env
+            .storage()
+            .instance()
+            .get(&symbol_short!("fail"))
+            .unwrap_or(false)
+        {
+            panic!("webhook down");
+        }
+        let (c, x) = MockWebhook::calls(env.clone());
+        env.storage()
+            .instance()
+            .set(&symbol_short!("calls"), &(c + charged, x + cancelled));
+    }
+
+    #[contractimpl]
+    impl MerchantWebhook for MockWebhook {
+        fn on_charged(env: Env, _merchant: Address, _subscription_id: u32, _amount: i128) {
+            bump(&env, 1, 0);
+        }
+
+        fn on_cancelled(env: Env, _merchant: Address, _subscription_id: u32) {
+            bump(&env, 0, 1);
+        }
+    }
+}
+
+#[test]
+fn test_webhook_notified_and_failures_isolated() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let hook_id = env.register(webhook_mock::MockWebhook, ());
+    let hook = webhook_mock::MockWebhookClient::new(&env, &hook_id);
+
+    client.set_webhook(&sub.merchant, &Some(hook_id.clone()));
+    assert_eq!(client.get_webhook(&sub.merchant), Some(hook_id));
+    client.charge_subscription(&id0);
+    client.cancel_subscription(&id0, &sub.subscriber);
+    assert_eq!(hook.calls(), (1, 1));
+
+    // A failing hook does not block the cancellation.
+    hook.set_failing(&true);
+    client.cancel_subscription(&id1, &sub.subscriber);
+    assert_eq!(
+        client.get_subscription(&id1).status,
+        SubscriptionStatus::Cancelled
+    );
+    assert_eq!(hook.calls(), (1, 1));
+
+    client.set_webhook(&sub.merchant, &None);
+    assert_eq!(client.get_webhook(&sub.merchant), None);
+}
+
+#[test]
+fn test_mid_cycle_price_changes_are_prorated() {
+    let env = Env::default();
+    let (client, _admin, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    client.charge_subscription(&id0);
+    let after_charge = 10_000000 - 1000;
+    env.ledger().set_timestamp(T0 + INTERVAL + INTERVAL / 2);
+
+    let up = client.change_amount(&id0, &2000);
+    assert_eq!(up.previous_amount, 1000);
+    assert_eq!(up.prorated_charge, 500);
+    assert_eq!(up.prorated_credit, 0);
+    assert_eq!(client.get_subscription(&id0).amount, 2000);
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        after_charge - 500
+    );
+
+    let down = client.change_amount(&id0, &1000);
+    assert_eq!(down.prorated_credit, 500);
+    assert_eq!(client.get_subscription(&id0).prepaid_balance, after_charge);
+
+    let plan_id = client.create_plan(
+        &sub.merchant,
+        &INTERVAL,
+        &false,
+        &SorobanVec::from_array(&env, [(3000i128, 1u32)]),
+    );
+    let change = client.change_plan(&id0, &plan_id);
+    assert_eq!(change.amount, 3000);
+    assert_eq!(change.prorated_charge, 1000);
+    assert_eq!(client.get_plan_assignment(&id0).unwrap().plan_id, plan_id);
+    assert_eq!(
+        client
+            .get_plan(&plan_id)
+            .variants
+            .get(0)
+            .unwrap()
+            .subscribers,
+        1
+    );
+
+    let weekly = client.create_plan(
+        &sub.merchant,
+        &(7 * 24 * 60 * 60),
+        &false,
+        &SorobanVec::from_array(&env, [(500i128, 1u32)]),
+    );
+    assert_eq!(
+        client.try_change_plan(&id0, &weekly),
+        Err(Ok(Error::InvalidAmount))
+    );
+}
+
+#[test]
+fn test_downgrade_credit_limited_to_unrealized_payout() {
+    let env = Env::default();
+    let (client, _admin, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+    client.set_payout_policy(
+        &sub.merchant,
+        &Some(crate::PayoutPolicy {
+            threshold: 500,
+            interval_seconds: 0,
+        }),
+    );
+    client.charge_subscription(&id0);
+    env.ledger().set_timestamp(T0 + INTERVAL + INTERVAL / 2);
+
+    // The upgrade's prorated charge is paid out at once, so the downgrade has
+    // nothing to take back and credits nothing.
+    let up = client.change_amount(&id0, &2000);
+    assert_eq!(up.prorated_charge, 500);
+    assert_eq!(token.balance(&sub.merchant), 1500);
+    let balance = client.get_subscription(&id0).prepaid_balance;
+    let down = client.change_amount(&id0, &1000);
+    assert_eq!(down.prorated_credit, 0);
+    assert_eq!(client.get_subscription(&id0).prepaid_balance, balance);
+    assert!(token.balance(&client.address) >= balance);
+}
+
+#[test]
+fn test_ombudsman_cancels_and_refunds() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token = client.get_token();
+    let ombudsman = Address::generate(&env);
+    let reason = BytesN::from_array(&env, &[7u8; 32]);
+    client.charge_subscription(&id0);
+
+    assert_eq!(
+        client.try_ombudsman_cancel(&ombudsman, &id0, &0, &reason),
+        Err(Ok(Error::Unauthorized))
+    );
+    client.set_ombudsman(&admin, &ombudsman, &true);
+    assert!(client.is_ombudsman(&ombudsman));
+    assert_eq!(
+        client.try_ombudsman_cancel(&ombudsman, &id0, &1001, &reason),
+        Err(Ok(Error::InvalidAmount))
+    );
+
+    client.ombudsman_cancel(&ombudsman, &id0, &1000, &reason);
+    assert_eq!(
+        client.get_subscription(&id0).status,
+        SubscriptionStatus::Cancelled
+    );
+    assert_eq!(client.get_credit_balance(&sub.subscriber, &token), 1000);
+    assert_eq!(client.get_payout_queue(&sub.merchant, &token).pending, 0);
+    assert_eq!(
+        client.try_ombudsman_cancel(&ombudsman, &id0, &1, &reason),
+        Err(Ok(Error::InvalidAmount))
+    );
+
+    client.set_ombudsman(&admin, &ombudsman, &false);
+    assert!(!client.is_ombudsman(&ombudsman));
+}
+
+#[test]
+fn test_ombudsman_refund_limited_to_unrealized_payout() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+    let ombudsman = Address::generate(&env);
+    let reason = BytesN::from_array(&env, &[9u8; 32]);
+    client.set_ombudsman(&admin, &ombudsman, &true);
+    client.charge_subscription(&id0);
+    client.withdraw_merchant_funds(&sub.merchant, &600);
+
+    client.ombudsman_cancel(&ombudsman, &id0, &1000, &reason);
+    assert_eq!(
+        client.get_credit_balance(&sub.subscriber, &token.address),
+        400
+    );
+    assert_eq!(
+        client
+            .get_payout_queue(&sub.merchant, &token.address)
+            .pending,
+        0
+    );
+    let owed = client.get_subscription(&id0).prepaid_balance + 400;
+    assert!(token.balance(&client.address) >= owed);
+}
+
+#[test]
+fn test_subscription_update_applies_next_cycle_after_acceptance() {
+    let env = Env::default();
+    let (client, _, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let weekly = 7 * 24 * 60 * 60;
+    client.charge_subscription(&id0);
+
+    client.update_subscription(&id0, &sub.merchant, &2000, &weekly);
+    assert_eq!(
+        client.try_accept_subscription_update(&id0, &sub.subscriber, &3000, &weekly),
+        Err(Ok(Error::TermsMismatch))
+    );
+
+    // Not accepted yet: the next charge uses the current terms.
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id0);
+    assert_eq!(client.get_subscription(&id0).amount, 1000);
+
+    client.accept_subscription_update(&id0, &sub.subscriber, &2000, &weekly);
+    assert!(client.get_pending_update(&id0).unwrap().accepted);
+    let balance = client.get_subscription(&id0).prepaid_balance;
+
+    env.ledger().set_timestamp(T0 + 3 * INTERVAL);
+    client.charge_subscription(&id0);
+    let updated = client.get_subscription(&id0);
+    assert_eq!(updated.amount, 2000);
+    assert_eq!(updated.interval_seconds, weekly);
+    assert_eq!(updated.prepaid_balance, balance - 2000);
+    assert_eq!(client.get_pending_update(&id0), None);
+
+    env.ledger().set_timestamp(T0 + 3 * INTERVAL + weekly);
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        balance - 4000
+    );
+}
+
+#[test]
+fn test_batch_charge_settles_one_payout_per_merchant_and_token() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token_addr = client.get_token();
+    client.deposit_funds(&id1, &sub.subscriber, &10_000000i128);
+    client.set_payout_policy(
+        &sub.merchant,
+        &Some(crate::PayoutPolicy {
+            threshold: 1000,
+            interval_seconds: 0,
+        }),
+    );
+
+    client.batch_charge(&SorobanVec::from_array(&env, [id0, id1]));
+    use soroban_sdk::TryFromVal;
+    let payouts: std::vec::Vec<crate::PayoutRealizedEvent> = env
+        .events()
+        .all()
+        .iter()
+        .filter(|e| {
+            Symbol::try_from_val(&env, &e.1.get(0).unwrap()) == Ok(Symbol::new(&env, "payout"))
+        })
+        .map(|e| crate::PayoutRealizedEvent::try_from_val(&env, &e.2).unwrap())
+        .collect();
+    assert_eq!(payouts.len(), 1);
+    assert_eq!(payouts[0].amount, 2000);
+    assert_eq!(
+        soroban_sdk::token::Client::new(&env, &token_addr).balance(&sub.merchant),
+        2000
+    );
+    assert_eq!(
+        client.get_payout_queue(&sub.merchant, &token_addr).pending,
+        0
+    );
+}
+
+#[test]
+fn test_protocol_fee_accrues_to_collector() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let token_addr = client.get_token();
+    let collector = Address::generate(&env);
+    assert_eq!(
+        client.try_set_protocol_fee(
+            &admin,
+            &Some(crate::ProtocolFeeConfig {
+                fee_bps: 1_001,
+                collector: collector.clone(),
+            })
+        ),
+        Err(Ok(Error::InvalidAmount))
+    );
+    client.set_protocol_fee(
+        &admin,
+        &Some(crate::ProtocolFeeConfig {
+            fee_bps: 250,
+            collector: collector.clone(),
+        }),
+    );
+
+    client.charge_subscription(&id0);
+    assert_eq!(client.get_accrued_fees(&collector, &token_addr), 25);
+    assert_eq!(client.get_payout_queue(&merchant, &token_addr).pending, 975);
+
+    assert_eq!(client.withdraw_fees(&merchant, &token_addr), 0);
+    assert_eq!(client.withdraw_fees(&collector, &token_addr), 25);
+    assert_eq!(client.get_accrued_fees(&collector, &token_addr), 0);
+    assert_eq!(
+        soroban_sdk::token::Client::new(&env, &token_addr).balance(&collector),
+        25
+    );
+}
+
+#[test]
+fn test_refund_reverses_protocol_fee_and_cleared_fee_stays_withdrawable() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+    let collector = Address::generate(&env);
+    let ombudsman = Address::generate(&env);
+    let reason = BytesN::from_array(&env, &[9u8; 32]);
+    client.set_ombudsman(&admin, &ombudsman, &true);
+    client.set_protocol_fee(
+        &admin,
+        &Some(crate::ProtocolFeeConfig {
+            fee_bps: 250,
+            collector: collector.clone(),
+        }),
+    );
+    client.charge_subscription(&id0);
+    client.set_protocol_fee(&admin, &None);
+
+    // 40% of the charge is refunded: 10 of the 25 fee and 390 of the payout.
+    client.ombudsman_cancel(&ombudsman, &id0, &400, &reason);
+    assert_eq!(
+        client.get_credit_balance(&sub.subscriber, &token.address),
+        400
+    );
+    assert_eq!(client.get_accrued_fees(&collector, &token.address), 15);
+    assert_eq!(
+        client
+            .get_payout_queue(&sub.merchant, &token.address)
+            .pending,
+        585
+    );
+
+    assert_eq!(client.withdraw_fees(&collector, &token.address), 15);
+    assert_eq!(token.balance(&collector), 15);
+    let owed = client.get_subscription(&id0).prepaid_balance + 400 + 585;
+    assert!(token.balance(&client.address) >= owed);
+}
+
+#[test]
+fn test_keeper_bounty_paid_from_merchant_payout() {
+    let env = Env::default();
+    let (client, admin, id0, id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let token_addr = client.get_token();
+    let keeper = Address::generate(&env);
+    assert_eq!(
+        client.try_set_keeper_bounty(&admin, &Some(crate::KeeperBounty::Flat(-1))),
+        Err(Ok(Error::InvalidAmount))
+    );
+    client.set_keeper_bounty(&admin, &Some(crate::KeeperBounty::Bps(1_000)));
+
+    client.keeper_charge(&keeper, &SorobanVec::from_array(&env, [id0, id1]));
+    assert_eq!(
+        soroban_sdk::token::Client::new(&env, &token_addr).balance(&keeper),
+        100
+    );
+    assert_eq!(client.get_payout_queue(&merchant, &token_addr).pending, 900);
+}
+
+#[test]
+fn test_banned_keeper_gets_no_bounty_from_permissionless_charge() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+    let keeper = Address::generate(&env);
+    client.set_keeper_bounty(&admin, &Some(crate::KeeperBounty::Flat(10)));
+    client.keeper_charge(&keeper, &SorobanVec::from_array(&env, [id0]));
+    assert_eq!(token.balance(&keeper), 10);
+    client.set_keeper_banned(&admin, &keeper, &true);
+
+    // The ban cannot stop a due charge, but nothing is paid or attributed to it.
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id0);
+    assert_eq!(token.balance(&keeper), 10);
+    assert_eq!(
+        client.get_keeper_stats(&keeper).unwrap().charges_executed,
+        1
+    );
+}
+
+#[test]
+fn test_voting_weight_snapshots_spend_and_revenue() {
+    let env = Env::default();
+    env.ledger()
+        .set_min_persistent_entry_ttl(4 * crate::voting::CHECKPOINT_WINDOW);
+    let (client, _, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let window = crate::voting::CHECKPOINT_WINDOW;
+    env.ledger().set_sequence_number(window + 10);
+    client.charge_subscription(&id0);
+    env.ledger().set_sequence_number(2 * window + 10);
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id0);
+    env.ledger().set_sequence_number(3 * window + 10);
+
+    assert_eq!(client.get_voting_weight(&sub.subscriber, &(window - 1)), 0);
+    // A snapshot resolves to the end of its window.
+    assert_eq!(client.get_voting_weight(&sub.subscriber, &window), 1000);
+    assert_eq!(
+        client.get_voting_weight(&sub.subscriber, &(2 * window + 5)),
+        2000
+    );
+    assert_eq!(client.get_voting_weight(&sub.merchant, &(window + 5)), 1000);
+    assert_eq!(
+        client.try_get_voting_weight(&sub.merchant, &(3 * window)),
+        Err(Ok(Error::InvalidAmount))
+    );
+}
+
+#[test]
+fn test_voting_weight_reduced_by_refunds_and_cancellation() {
+    let env = Env::default();
+    env.ledger()
+        .set_min_persistent_entry_ttl(4 * crate::voting::CHECKPOINT_WINDOW);
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let window = crate::voting::CHECKPOINT_WINDOW;
+    let ombudsman = Address::generate(&env);
+    let reason = BytesN::from_array(&env, &[9u8; 32]);
+    client.set_ombudsman(&admin, &ombudsman, &true);
+    env.ledger().set_sequence_number(window);
+    client.charge_subscription(&id0);
+    env.ledger().set_sequence_number(2 * window);
+    client.ombudsman_cancel(&ombudsman, &id0, &400, &reason);
+    env.ledger().set_sequence_number(3 * window);
+
+    assert_eq!(client.get_voting_weight(&sub.subscriber, &window), 1000);
+    assert_eq!(client.get_voting_weight(&sub.merchant, &window), 1000);
+    // The refund comes off both sides and the rest of the cancelled charge off
+    // the subscriber.
+    assert_eq!(client.get_voting_weight(&sub.subscriber, &(2 * window)), 0);
+    assert_eq!(client.get_voting_weight(&sub.merchant, &(2 * window)), 600);
+}
+
+#[test]
+fn test_self_charges_carry_no_voting_weight() {
+    let env = Env::default();
+    env.ledger()
+        .set_min_persistent_entry_ttl(4 * crate::voting::CHECKPOINT_WINDOW);
+    let (client, _, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let id = client.create_subscription(
+        &sub.merchant,
+        &sub.merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+    let token = soroban_sdk::token::StellarAssetClient::new(&env, &client.get_token());
+    token.mint(&sub.merchant, &10_000000);
+    client.deposit_funds(&id, &sub.merchant, &10_000000);
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id);
+    env.ledger()
+        .set_sequence_number(crate::voting::CHECKPOINT_WINDOW);
+
+    assert_eq!(client.get_voting_weight(&sub.merchant, &0), 0);
+}
+
+#[test]
+fn test_create_subscription_with_salt_uses_derived_key() {
+    let env = Env::default();
+    let (client, _, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let salt = BytesN::from_array(&env, &[3u8; 32]);
+    let key = client.derive_subscription_key(&sub.merchant, &sub.subscriber, &salt);
+    assert_eq!(
+        client.try_get_subscription_id_by_key(&key),
+        Err(Ok(Error::NotFound))
+    );
+
+    let id = client.create_subscription_with_salt(
+        &sub.subscriber,
+        &sub.merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+        &salt,
+    );
+    assert_eq!(client.get_subscription_id_by_key(&key), id);
+    assert_eq!(
+        client.try_create_subscription_with_salt(
+            &sub.subscriber,
+            &sub.merchant,
+            &1000i128,
+            &INTERVAL,
+            &false,
+            &None,
+            &0i128,
+            &false,
+            &salt,
+        ),
+        Err(Ok(Error::SubscriptionKeyTaken))
+    );
+}
+
+#[test]
+fn test_charge_subscription_requires_no_authorization() {
+    let env = Env::default();
+    let (client, _, id0, _) = setup_batch_env(&env);
+    env.set_auths(&[]);
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        10_000000 - 1000
+    );
+    assert_eq!(client.try_charge_subscription(&id0), Err(Ok(Error::Replay)));
+}
+
+#[test]
+fn test_pull_subscription_charges_wallet_via_allowance() {
+    let env = Env::default();
+    let (client, _, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token_addr = client.get_token();
+    let token = soroban_sdk::token::Client::new(&env, &token_addr);
+    let id = client.create_pull_subscription(
+        &sub.subscriber,
+        &sub.merchant,
+        &1000i128,
+        &INTERVAL,
+        &None,
+    );
+    assert!(client.is_pull_billing(&id));
+    assert!(!client.is_pull_billing(&id0));
+
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    assert_eq!(
+        client.try_charge_subscription(&id),
+        Err(Ok(Error::InsufficientBalance))
+    );
+
+    client.charge_subscription(&id0);
+    let pending = client.get_payout_queue(&sub.merchant, &token_addr).pending;
+    let wallet = token.balance(&sub.subscriber);
+    token.approve(&sub.subscriber, &client.address, &5000, &1000);
+    env.ledger().set_timestamp(T0 + 3 * INTERVAL);
+    client.charge_subscription(&id);
+    assert_eq!(token.balance(&sub.subscriber), wallet - 1000);
+    assert_eq!(client.get_subscription(&id).prepaid_balance, 0);
+    assert_eq!(
+        client.get_payout_queue(&sub.merchant, &token_addr).pending,
+        pending + 1000
+    );
+    assert_eq!(token.allowance(&sub.subscriber, &client.address), 4000);
+}
+
+#[test]
+fn test_wallet_fallback_tops_up_short_prepaid_balance() {
+    let env = Env::default();
+    let (client, _, _, id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id1);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+    token.approve(&sub.subscriber, &client.address, &1000, &1000);
+    let other = Address::generate(&env);
+    assert_eq!(
+        client.try_set_wallet_fallback(&id1, &other, &true),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    client.set_wallet_fallback(&id1, &sub.subscriber, &true);
+    assert!(client.is_wallet_fallback(&id1));
+    assert!(!client.is_pull_billing(&id1));
+    let wallet = token.balance(&sub.subscriber);
+    client.charge_subscription(&id1);
+    assert_eq!(token.balance(&sub.subscriber), wallet - 1000);
+    assert_eq!(
+        client.get_subscription(&id1).status,
+        SubscriptionStatus::Active
+    );
+
+    // Allowance used up: the charge falls back to the usual shortfall handling.
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.batch_charge(&SorobanVec::from_array(&env, [id1]));
+    assert_eq!(
+        client.get_subscription(&id1).status,
+        SubscriptionStatus::InsufficientBalance
+    );
+}
+
+#[test]
+fn test_disabling_wallet_fallback_keeps_pull_billing() {
+    let env = Env::default();
+    let (client, _, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+    let id = client.create_pull_subscription(
+        &sub.subscriber,
+        &sub.merchant,
+        &1000i128,
+        &INTERVAL,
+        &None,
+    );
+    client.set_wallet_fallback(&id, &sub.subscriber, &false);
+    assert!(client.is_pull_billing(&id));
+    assert!(!client.is_wallet_fallback(&id));
+
+    token.approve(&sub.subscriber, &client.address, &1000, &1000);
+    let wallet = token.balance(&sub.subscriber);
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id);
+    assert_eq!(token.balance(&sub.subscriber), wallet - 1000);
+}
+
+#[test]
+fn test_public_stats_counts_subscriptions_volume_and_participants() {
+    let env = Env::default();
+    let (client, _, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    client.charge_subscription(&id0);
+    let other_merchant = Address::generate(&env);
+    client.create_subscription(
+        &sub.subscriber,
+        &other_merchant,
+        &500i128,
+        &INTERVAL,
+        &false,
+        &None,
+        &0i128,
+        &false,
+    );
+    client.cancel_subscription(&id0, &sub.subscriber);
+
+    let stats = client.get_public_stats();
+    assert_eq!(stats.total_subscriptions, 3);
+    assert_eq!(stats.active_subscriptions, 2);
+    assert_eq!(stats.total_volume, 1000);
+    assert_eq!(stats.unique_merchants, 2);
+    assert_eq!(stats.unique_subscribers, 1);
+}
+
+#[test]
+fn test_auto_topup_refills_below_threshold() {
+    let env = Env::default();
+    let (client, _, id0, id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id1);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+    token.approve(&sub.subscriber, &client.address, &2_000000, &1000);
+    let rule = crate::AutoTopup {
+        threshold: 1000,
+        refill: 1_000000,
+    };
+    assert_eq!(
+        client.try_execute_auto_topup(&id1),
+        Err(Ok(Error::NotFound))
+    );
+    client.set_auto_topup(&id1, &sub.subscriber, &Some(rule.clone()));
+    client.set_auto_topup(&id0, &sub.subscriber, &Some(rule));
+    assert_eq!(
+        client.try_execute_auto_topup(&id0),
+        Err(Ok(Error::AboveThreshold))
+    );
+
+    assert_eq!(client.execute_auto_topup(&id1), 1_000000);
+    assert_eq!(client.get_subscription(&id1).prepaid_balance, 1_000000);
+    assert_eq!(token.allowance(&sub.subscriber, &client.address), 1_000000);
+    assert_eq!(
+        client.try_execute_auto_topup(&id1),
+        Err(Ok(Error::AboveThreshold))
+    );
+}
+
+#[test]
+fn test_platform_cut_credited_before_merchant_share() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let token_addr = client.get_token();
+    let platform = Address::generate(&env);
+    assert_eq!(
+        client.try_join_platform(&merchant, &platform, &500),
+        Err(Ok(Error::Unauthorized))
+    );
+    client.set_platform(&admin, &platform, &Some(1_000));
+    assert_eq!(
+        client.try_join_platform(&merchant, &platform, &1_001),
+        Err(Ok(Error::InvalidAmount))
+    );
+    client.join_platform(&merchant, &platform, &500);
+    client.set_protocol_fee(
+        &admin,
+        &Some(crate::ProtocolFeeConfig {
+            fee_bps: 1_000,
+            collector: admin.clone(),
+        }),
+    );
+
+    client.charge_subscription(&id0);
+    assert_eq!(client.get_accrued_fees(&admin, &token_addr), 100);
+    assert_eq!(client.get_payout_queue(&platform, &token_addr).pending, 45);
+    assert_eq!(client.get_payout_queue(&merchant, &token_addr).pending, 855);
+
+    client.set_platform(&admin, &platform, &None);
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id0);
+    assert_eq!(client.get_payout_queue(&platform, &token_addr).pending, 45);
+}
+
+#[test]
+fn test_refund_reverses_platform_cut() {
+    let env = Env::default();
+    let (client, admin, id0, _) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+    let platform = Address::generate(&env);
+    let ombudsman = Address::generate(&env);
+    let reason = BytesN::from_array(&env, &[9u8; 32]);
+    client.set_ombudsman(&admin, &ombudsman, &true);
+    client.set_platform(&admin, &platform, &Some(1_000));
+    client.join_platform(&sub.merchant, &platform, &500);
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_payout_queue(&platform, &token.address).pending,
+        50
+    );
+
+    // Half the charge is refunded: 25 of the cut and 475 of the merchant's share.
+    client.ombudsman_cancel(&ombudsman, &id0, &500, &reason);
+    assert_eq!(
+        client.get_credit_balance(&sub.subscriber, &token.address),
+        500
+    );
+    assert_eq!(
+        client.get_payout_queue(&platform, &token.address).pending,
+        25
+    );
+    assert_eq!(
+        client
+            .get_payout_queue(&sub.merchant, &token.address)
+            .pending,
+        475
+    );
+    let owed = client.get_subscription(&id0).prepaid_balance + 500 + 25 + 475;
+    assert!(token.balance(&client.address) >= owed);
 }
@@ -0,0 +1,90 @@
+//! Per-subscription support tiers.
+//!
+//! **PRs that only change support tiers should edit this file only.**
+//!
+//! A merchant marks subscriptions `Standard` (the default) or `Priority` and sets
+//! one [`PriorityTierPolicy`] for its priority customers. A priority subscription
+//! whose charge fails for lack of funds stays `Active` for the policy's grace period
+//! (see `charge_core`), and `notify_due_soon` warns it earlier when the policy's
+//! warning lead exceeds the global one.
+
+use crate::queries::get_subscription;
+use crate::types::{Error, PriorityTierPolicy, SupportTier};
+use soroban_sdk::{symbol_short, Address, Env, Symbol};
+
+const KEY_TIER: Symbol = symbol_short!("tier");
+const KEY_TIER_POLICY: Symbol = symbol_short!("tierpol");
+
+pub fn get_support_tier(env: &Env, subscription_id: u32) -> SupportTier {
+    env.storage()
+        .instance()
+        .get(&(KEY_TIER, subscription_id))
+        .unwrap_or(SupportTier::Standard)
+}
+
+pub fn get_priority_policy(env: &Env, merchant: &Address) -> Option<PriorityTierPolicy> {
+    env.storage()
+        .instance()
+        .get(&(KEY_TIER_POLICY, merchant.clone()))
+}
+
+/// Drops a subscription's tier (used when its record is deleted).
+pub fn remove_support_tier(env: &Env, subscription_id: u32) {
+    env.storage()
+        .instance()
+        .remove(&(KEY_TIER, subscription_id));
+}
+
+/// The merchant policy applying to a subscription, if it is `Priority`.
+pub fn priority_policy_for(
+    env: &Env,
+    subscription_id: u32,
+    merchant: &Address,
+) -> Option<PriorityTierPolicy> {
+    match get_support_tier(env, subscription_id) {
+        SupportTier::Priority => get_priority_policy(env, merchant),
+        SupportTier::Standard => None,
+    }
+}
+
+/// Set the grace and warning terms for the merchant's priority subscriptions.
+/// Auth: merchant.
+pub fn do_set_priority_policy(
+    env: &Env,
+    merchant: Address,
+    policy: PriorityTierPolicy,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    env.storage()
+        .instance()
+        .set(&(KEY_TIER_POLICY, merchant.clone()), &policy);
+    env.events()
+        .publish((Symbol::new(env, "priority_policy_set"), merchant), policy);
+    Ok(())
+}
+
+/// Assign a subscription's support tier. Auth: the subscription's merchant.
+pub fn do_set_support_tier(
+    env: &Env,
+    merchant: Address,
+    subscription_id: u32,
+    tier: SupportTier,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.merchant != merchant {
+        return Err(Error::Unauthorized);
+    }
+    match tier {
+        SupportTier::Standard => remove_support_tier(env, subscription_id),
+        SupportTier::Priority => env
+            .storage()
+            .instance()
+            .set(&(KEY_TIER, subscription_id), &tier),
+    }
+    env.events().publish(
+        (Symbol::new(env, "support_tier_set"), subscription_id),
+        tier,
+    );
+    Ok(())
+}
@@ -1,9 +1,11 @@
-//! Contract types: errors and subscription data structures.
+//! Contract types: subscription data structures. Errors live in [`crate::errors`]
+//! and are re-exported here.
 //!
 //! Kept in a separate module to reduce merge conflicts when editing state machine
 //! or contract entrypoints.
 
-use soroban_sdk::{contracterror, contracttype, Address};
+pub use crate::errors::Error;
+use soroban_sdk::{contracttype, Address, Bytes, BytesN, String, Symbol, Vec};
 
 /// Storage keys for secondary indices.
 #[contracttype]
@@ -11,63 +13,90 @@ use soroban_sdk::{contracterror, contracttype, Address};
 pub enum DataKey {
     /// Maps a merchant address to its list of subscription IDs.
     MerchantSubs(Address),
-}
-
-#[contracterror]
-#[derive(Clone, Debug, Eq, PartialEq)]
-#[repr(u32)]
-pub enum Error {
-    NotFound = 404,
-    Unauthorized = 401,
-    /// Charge attempted before `last_payment_timestamp + interval_seconds`.
-    IntervalNotElapsed = 1001,
-    /// Subscription is not Active (e.g. Paused, Cancelled).
-    NotActive = 1002,
-    InvalidStatusTransition = 400,
-    BelowMinimumTopup = 402,
-    /// Arithmetic overflow in computation (e.g. amount * intervals).
-    Overflow = 403,
-    /// Arithmetic underflow (e.g. negative amount or balance would go negative).
-    Underflow = 1004,
-    /// Charge failed due to insufficient prepaid balance.
-    InsufficientBalance = 1003,
-    /// Usage-based charge attempted on a subscription with `usage_enabled = false`.
-    UsageNotEnabled = 1009,
-    /// Usage-based charge amount exceeds the available prepaid balance.
-    InsufficientPrepaidBalance = 1010,
-    /// The provided amount is zero or negative.
-    InvalidAmount = 1006,
-    /// Charge already processed for this billing period.
-    Replay = 1007,
-    /// Recovery amount is zero or negative.
-    InvalidRecoveryAmount = 1008,
-}
-
-impl Error {
-    /// Returns the numeric code for this error (for batch result reporting).
-    pub const fn to_code(self) -> u32 {
-        match self {
-            Error::NotFound => 404,
-            Error::Unauthorized => 401,
-            Error::IntervalNotElapsed => 1001,
-            Error::NotActive => 1002,
-            Error::InvalidStatusTransition => 400,
-            Error::BelowMinimumTopup => 402,
-            Error::Overflow => 403,
-            Error::Underflow => 1004,
-            Error::InsufficientBalance => 1003,
-            Error::UsageNotEnabled => 1009,
-            Error::InsufficientPrepaidBalance => 1010,
-            Error::InvalidAmount => 1006,
-            Error::Replay => 1007,
-            Error::InvalidRecoveryAmount => 1008,
-        }
-    }
+    /// Maps a merchant address to its registered API keys.
+    MerchantApiKeys(Address),
+    /// Ledger timestamp at which a subscription was created.
+    SubCreatedAt(u32),
+    /// Cumulative amount charged (interval + usage) against a subscription.
+    SubTotalCharged(u32),
+    /// Recovery address for a subscriber that engaged the emergency lock.
+    SubscriberLock(Address),
+    /// Merchant-anchored terms/metadata hash for a subscription.
+    SubTermsHash(u32),
+    /// Ledger-sequence schedule for a subscription (absent = timestamp basis).
+    SubLedgerSchedule(u32),
+    /// Number of intervals a merchant requires subscribers to keep prepaid.
+    MerchantReserve(Address),
+    /// Settlement token a subscription's balance is held in.
+    SubToken(u32),
+    /// Charges accrued by a merchant since its last settlement.
+    MerchantEpoch(Address),
+    /// The merchant's most recent settlement summaries (oldest first).
+    MerchantSettlements(Address),
+    /// Fixed number of billing periods for a fixed-term subscription.
+    SubFixedTerm(u32),
+    /// Predecessor/successor links for renewed fixed-term subscriptions.
+    SubLineage(u32),
+    /// A subscriber's charge round-up (donation) setting and running total.
+    SubscriberRoundUp(Address),
+    /// When a merchant's queued payout credits are realized as a transfer.
+    MerchantPayoutPolicy(Address),
+    /// Queued payout credits per (merchant, settlement token).
+    MerchantPayoutQueue(Address, Address),
+    /// Undeliverable outbound transfers per (recipient, token), pulled via `claim`.
+    Claim(Address, Address),
+    /// Reduced amount billed per interval while a subscription is paused.
+    SubPauseRetainer(u32),
+    /// Pause retainer a merchant proposed, awaiting the subscriber's acceptance.
+    SubPauseRetainerOffer(u32),
+    /// Merchants a subscriber pre-approved for new subscriptions.
+    SubscriberAllowlist(Address),
+    /// Active lien on a merchant's future receivables.
+    MerchantLien(Address),
+    /// Bridge adapter a merchant's payouts are routed through.
+    MerchantPayoutRoute(Address),
+    /// Subscriber credit wallet balance per (subscriber, token).
+    SubscriberCredit(Address, Address),
+    /// Merchant-funded promo credit per (subscriber, merchant).
+    PromoCredit(Address, Address),
+    /// Ids of subscriptions currently in a given status (persistent storage).
+    StatusBucket(SubscriptionStatus),
+    /// Tracked amount of a token the vault owes (for reconciliation).
+    TokenLiabilities(Address),
+    /// Maps a merchant address to its registration stake.
+    MerchantStake(Address),
+    /// Open fraud report against a merchant, awaiting admin resolution.
+    FraudReport(Address),
+    /// Seat-based pricing for a subscription.
+    SubSeatPlan(u32),
+    /// Subscriber address registered as an organization contract.
+    OrgAccount(Address),
+    /// A merchant's charge-time tax configuration.
+    MerchantTaxConfig(Address),
+    /// Tax collected and held for a merchant per (merchant, token).
+    MerchantTaxBalance(Address, Address),
+    /// Head of a merchant's hash-chained charge receipts.
+    MerchantReceiptHead(Address),
+    /// A merchant's fee waiver enrollment.
+    MerchantFeeWaiver(Address),
+    /// Registered ed25519 public key that signs off-chain for an account.
+    SigningKey(Address),
+    /// Next meta-transaction nonce for a signer.
+    SignerNonce(Address),
+    /// Ids of subscriptions created on a given day (`timestamp / 86_400`), in
+    /// persistent storage.
+    CreatedDayBucket(u64),
+    /// Tags a merchant has defined for its CRM workflows.
+    MerchantTags(Address),
+    /// The merchant tag attached to a subscription.
+    SubMerchantTag(u32),
+    /// Ids of a merchant's subscriptions carrying a tag, per (merchant, tag).
+    MerchantTagIndex(Address, Symbol),
 }
 
 /// Result of charging one subscription in a batch. Used by [`crate::SubscriptionVault::batch_charge`].
 #[contracttype]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct BatchChargeResult {
     /// True if the charge succeeded.
     pub success: bool,
@@ -75,6 +104,56 @@ pub struct BatchChargeResult {
     pub error_code: u32,
 }
 
+/// Aggregate result of [`crate::SubscriptionVault::batch_charge_v2`].
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BatchChargeSummary {
+    /// Ids charged successfully, in input order.
+    pub charged: Vec<u32>,
+    /// `(id, error_code)` for every failed charge, in input order.
+    pub failed: Vec<(u32, u32)>,
+    /// Number of successful charges (`charged.len()`).
+    pub count_charged: u32,
+    /// Sum of the amounts charged.
+    pub total_amount: i128,
+}
+
+/// Opaque continuation for `batch_charge_resumable`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ResumeToken {
+    /// Last subscription id scanned by the previous call.
+    pub last_processed_id: u32,
+    /// Ledger sequence at which the token was issued.
+    pub ledger: u32,
+}
+
+/// Result of one `batch_charge_resumable` call.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ResumableBatchResult {
+    /// Ids a charge was attempted for (billable subscriptions in the scanned range).
+    pub attempted_ids: Vec<u32>,
+    /// Per-attempt results, index-aligned with `attempted_ids`.
+    pub results: Vec<BatchChargeResult>,
+    /// Token to pass to the next call; only meaningful when `has_next` is true.
+    pub resume_token: ResumeToken,
+    /// Whether ids remain to be scanned.
+    pub has_next: bool,
+}
+
+/// Result of one `cancel_all_subscriptions` page.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct BulkCancelResult {
+    /// Subscription ids cancelled by this call.
+    pub cancelled_ids: Vec<u32>,
+    /// Cursor to pass to the next call.
+    pub next_cursor: u32,
+    /// True if ids remain beyond `next_cursor`.
+    pub has_next: bool,
+}
+
 /// Represents the lifecycle state of a subscription.
 ///
 /// # State Machine
@@ -93,6 +172,12 @@ pub struct BatchChargeResult {
 /// - **InsufficientBalance**: Subscription failed due to insufficient funds.
 ///   - Can transition to: `Active` (after deposit), `Cancelled`
 ///
+/// - **Completed**: A fixed-term subscription charged its final period.
+///   - No outgoing transitions (terminal state); may be renewed into a successor
+///
+/// - **Expired**: The subscription reached its `expires_at` end date.
+///   - No outgoing transitions (terminal state)
+///
 /// Invalid transitions (e.g., `Cancelled` -> `Active`) are rejected with
 /// [`Error::InvalidStatusTransition`].
 #[contracttype]
@@ -106,6 +191,10 @@ pub enum SubscriptionStatus {
     Cancelled = 2,
     /// Subscription failed due to insufficient balance for charging.
     InsufficientBalance = 3,
+    /// Fixed-term subscription that charged all of its periods (terminal state).
+    Completed = 4,
+    /// Subscription reached its end date (terminal state).
+    Expired = 5,
 }
 
 /// Stores subscription details and current state.
@@ -124,6 +213,36 @@ pub struct Subscription {
     pub status: SubscriptionStatus,
     pub prepaid_balance: i128,
     pub usage_enabled: bool,
+    /// Optional human-readable label for wallets (e.g. "Netflix family plan").
+    /// At most [`MAX_LABEL_LEN`] bytes; set by the subscriber.
+    pub label: Option<String>,
+}
+
+/// Maximum length, in bytes, of a subscription label.
+pub const MAX_LABEL_LEN: u32 = 64;
+
+// Event payload convention
+//
+// Events whose body holds caller-supplied variable-size data (bridge destinations,
+// metadata blobs) are published through `events::publish_bounded`. A body whose XDR
+// encoding fits in [`MAX_EVENT_PAYLOAD_BYTES`] is emitted as-is; a larger one is
+// replaced by an [`EventPayloadRef`] naming the storage key that holds the full value
+// and its SHA-256, so indexers read it from state and can verify what they read.
+// Fixed-size bodies are published directly.
+
+/// Largest event body, in XDR bytes, emitted inline.
+pub const MAX_EVENT_PAYLOAD_BYTES: u32 = 256;
+
+/// Stand-in body for an event whose payload exceeds [`MAX_EVENT_PAYLOAD_BYTES`].
+#[contracttype]
+#[derive(Clone)]
+pub struct EventPayloadRef {
+    /// SHA-256 of the payload's XDR encoding.
+    pub hash: BytesN<32>,
+    /// Size of the payload's XDR encoding, in bytes.
+    pub size: u32,
+    /// Storage key the full payload can be read from.
+    pub key: DataKey,
 }
 
 // Event types
@@ -151,80 +270,1267 @@ pub struct SubscriptionChargedEvent {
     pub subscription_id: u32,
     pub merchant: Address,
     pub amount: i128,
+    /// Tax line item included in `amount` (0 without a tax configuration).
+    pub tax: i128,
+}
+
+/// Fixed-term billing: the subscription completes after `total_periods` interval charges.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FixedTerm {
+    pub total_periods: u32,
+    pub periods_charged: u32,
+}
+
+/// Subscriber opt-in for rounding interval charges up to a multiple of `unit`.
+///
+/// The difference is donated to the admin-configured donation address.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RoundUp {
+    /// Rounding unit in token base units; `0` means opted out.
+    pub unit: i128,
+    /// Total donated so far across all of the subscriber's subscriptions.
+    pub total_donated: i128,
 }
 
+/// Where a refund went: the subscriber's credit wallet, their own address (minus
+/// the external refund fee, which the merchant keeps), or both.
 #[contracttype]
 #[derive(Clone, Debug)]
-pub struct SubscriptionCancelledEvent {
-    pub subscription_id: u32,
-    pub authorizer: Address,
-    pub refund_amount: i128,
+pub struct RefundRoutedEvent {
+    pub subscriber: Address,
+    pub token: Address,
+    pub to_wallet: i128,
+    pub to_external: i128,
+    pub fee: i128,
 }
 
 #[contracttype]
 #[derive(Clone, Debug)]
-pub struct SubscriptionPausedEvent {
+pub struct DonationEvent {
     pub subscription_id: u32,
-    pub authorizer: Address,
+    pub subscriber: Address,
+    pub donation_address: Address,
+    pub amount: i128,
 }
 
 #[contracttype]
 #[derive(Clone, Debug)]
-pub struct SubscriptionResumedEvent {
+pub struct CreditTimeGrantedEvent {
     pub subscription_id: u32,
-    pub authorizer: Address,
+    pub merchant: Address,
+    pub seconds: u64,
+    /// Timestamp the next interval charge is now due at.
+    pub next_due: u64,
+}
+
+/// Merchant payout batching policy.
+///
+/// Queued credits are transferred once `pending >= threshold` or `interval_seconds`
+/// have passed since the last payout. A zero field disables that trigger.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PayoutPolicy {
+    pub threshold: i128,
+    pub interval_seconds: u64,
+}
+
+/// Accumulated, not yet transferred merchant credits in one token.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PayoutQueue {
+    pub pending: i128,
+    pub last_payout_at: u64,
 }
 
 #[contracttype]
 #[derive(Clone, Debug)]
-pub struct MerchantWithdrawalEvent {
+pub struct PayoutRealizedEvent {
     pub merchant: Address,
+    pub token: Address,
     pub amount: i128,
 }
 
-/// Emitted when a merchant-initiated one-off charge is applied to a subscription.
+/// Routes a merchant's realized payouts through a [`PayoutAdapter`] contract
+/// (e.g. a bridge) instead of a direct transfer.
+///
+/// [`PayoutAdapter`]: crate::PayoutAdapter
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PayoutRoute {
+    pub adapter: Address,
+    /// Adapter-specific destination (e.g. an address on another chain).
+    pub destination: Bytes,
+}
+
 #[contracttype]
 #[derive(Clone, Debug)]
-pub struct OneOffChargedEvent {
-    pub subscription_id: u32,
+pub struct PayoutBridgedEvent {
     pub merchant: Address,
+    pub adapter: Address,
+    pub token: Address,
     pub amount: i128,
 }
 
-/// Represents the reason for stranded funds that can be recovered by admin.
+/// Promotional credit a merchant granted to a subscriber, usable only on that
+/// merchant's charges in `token` until `expires_at`.
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub enum RecoveryReason {
-    /// Funds sent to contract address by mistake (no associated subscription).
-    AccidentalTransfer = 0,
-    /// Funds from deprecated contract flows or logic errors.
-    DeprecatedFlow = 1,
-    /// Funds from cancelled subscriptions with unreachable addresses.
-    UnreachableSubscriber = 2,
+pub struct PromoCredit {
+    pub token: Address,
+    pub amount: i128,
+    pub expires_at: u64,
+}
+
+/// A lien on a merchant's future receivables in one token.
+///
+/// `share_bps` of every charge credited to the merchant in `token` is paid to the
+/// lienholder until `repaid` reaches `target`, at which point the lien is discharged.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Lien {
+    pub lienholder: Address,
+    pub token: Address,
+    pub share_bps: u32,
+    pub target: i128,
+    pub repaid: i128,
 }
 
-/// Event emitted when admin recovers stranded funds.
 #[contracttype]
 #[derive(Clone, Debug)]
-pub struct RecoveryEvent {
-    /// The admin who authorized the recovery
-    pub admin: Address,
-    /// The destination address receiving the recovered funds
-    pub recipient: Address,
-    /// The amount of funds recovered
+pub struct LienPaymentEvent {
+    pub merchant: Address,
+    pub lienholder: Address,
     pub amount: i128,
-    /// The documented reason for recovery
-    pub reason: RecoveryReason,
-    /// Timestamp when recovery was executed
+    pub repaid: i128,
+    pub target: i128,
+}
+
+/// Admin-set onboarding stake required of merchants.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MerchantStakePolicy {
+    /// Stake in the settlement token; 0 disables the registration requirement.
+    pub amount: i128,
+    /// Delay between de-registration and the stake becoming withdrawable.
+    pub cooldown_seconds: u64,
+}
+
+/// A registered merchant's locked stake.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MerchantStake {
+    pub token: Address,
+    pub amount: i128,
+    pub staked_at: u64,
+    /// When the merchant de-registered; 0 while registered.
+    pub unstake_requested_at: u64,
+}
+
+/// An open fraud report against a merchant.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FraudReport {
+    pub reporter: Address,
+    /// Hash of the off-chain evidence bundle.
+    pub evidence: BytesN<32>,
+    pub reported_at: u64,
+}
+
+/// Emitted when a confirmed fraud report slashes a merchant.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MerchantSlashedEvent {
+    pub merchant: Address,
+    pub token: Address,
+    pub stake_slashed: i128,
+    /// Queued, not yet paid out merchant credits that were confiscated.
+    pub pending_slashed: i128,
+    /// Amount credited to affected subscribers' credit wallets.
+    pub distributed: i128,
+    /// Number of subscribers compensated.
+    pub recipients: u32,
+}
+
+/// Seat-based pricing for a subscription (`amount = unit_price × seats`).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SeatPlan {
+    pub unit_price: i128,
+    pub seats: u32,
+    /// Bounds within which the subscriber may change seats without the merchant.
+    pub min_seats: u32,
+    pub max_seats: u32,
+    /// Seat count proposed by the merchant, awaiting acceptance; 0 if none.
+    pub proposed_seats: u32,
+}
+
+/// Emitted when a subscription's seat count changes.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SeatsUpdatedEvent {
+    pub subscription_id: u32,
+    pub previous_seats: u32,
+    pub seats: u32,
+    /// New per-interval amount.
+    pub amount: i128,
+    /// Prorated amount charged now for seats added mid-cycle.
+    pub prorated_charge: i128,
+}
+
+/// One price arm of a plan's pricing experiment.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PriceVariant {
+    /// Per-interval amount charged to subscribers assigned this variant.
+    pub amount: i128,
+    /// Relative share of new subscribers assigned this variant.
+    pub weight: u32,
+    /// Subscribers assigned so far.
+    pub subscribers: u32,
+}
+
+/// Merchant-defined plan that `subscribe_to_plan` creates subscriptions from.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Plan {
+    pub merchant: Address,
+    pub interval_seconds: u64,
+    pub usage_enabled: bool,
+    /// At most [`MAX_PLAN_VARIANTS`]; a single variant is a fixed price.
+    pub variants: Vec<PriceVariant>,
+}
+
+/// Maximum number of price variants per plan.
+pub const MAX_PLAN_VARIANTS: u32 = 8;
+
+/// Plan and price variant a subscription was created from or last changed to.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PlanAssignment {
+    pub plan_id: u32,
+    /// Index into the plan's `variants`.
+    pub variant: u32,
+}
+
+/// Result of a mid-cycle price change (`change_plan`, `change_amount`), also
+/// emitted as `price_changed`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PriceChange {
+    pub subscription_id: u32,
+    pub previous_amount: i128,
+    /// New per-interval amount.
+    pub amount: i128,
+    /// Prorated difference charged now on an upgrade.
+    pub prorated_charge: i128,
+    /// Prorated difference credited to the prepaid balance on a downgrade.
+    pub prorated_credit: i128,
+}
+
+/// Commission rate applying while a referred subscription is younger than
+/// `until_age_seconds`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CommissionTier {
+    pub until_age_seconds: u64,
+    pub bps: u32,
+}
+
+/// A merchant's referral code: who earns commission and on what decaying schedule.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReferralCode {
+    pub affiliate: Address,
+    /// Tiers in increasing `until_age_seconds` order, at most
+    /// [`MAX_COMMISSION_TIERS`]. No commission is paid past the last tier.
+    pub schedule: Vec<CommissionTier>,
+}
+
+/// Maximum number of tiers in a commission schedule.
+pub const MAX_COMMISSION_TIERS: u32 = 8;
+
+/// Emitted when a charge pays an affiliate commission.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CommissionPaidEvent {
+    pub subscription_id: u32,
+    pub affiliate: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub bps: u32,
+}
+
+/// Performance record of a third-party keeper (`get_keeper_stats`).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KeeperStats {
+    /// Charges that succeeded through `keeper_charge`.
+    pub charges_executed: u32,
+    /// Charges that failed (not due, underfunded, ...).
+    pub failures: u32,
+    /// Timestamp of the keeper's latest `keeper_charge` call.
+    pub last_active: u64,
+    /// Set by the admin; a banned keeper cannot call `keeper_charge`.
+    pub banned: bool,
+}
+
+/// Admin kill switch on a merchant's charging (`halt_merchant`).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MerchantHalt {
+    /// Short reason code, e.g. `fraud`.
+    pub reason: Symbol,
+    pub halted_at: u64,
+}
+
+/// A subscriber's spend against their monthly cap (`get_budget_status`).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BudgetStatus {
+    /// Cap per budget month; `None` if the subscriber has not set one.
+    pub cap: Option<i128>,
+    /// Charged so far in the current budget month.
+    pub spent: i128,
+    pub period_start: u64,
+    pub period_end: u64,
+}
+
+/// Support tier a merchant assigns to a subscription.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SupportTier {
+    Standard,
+    Priority,
+}
+
+/// A merchant's terms for its `Priority` subscriptions.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PriorityTierPolicy {
+    /// How long after the due time a charge may fail for lack of funds before the
+    /// subscription moves to `InsufficientBalance`.
+    pub grace_seconds: u64,
+    /// Lead window for `due_soon` warnings; the global window applies if it is longer.
+    pub warning_lead_seconds: u64,
+}
+
+/// An address's voting weight at the end of the checkpoint window starting at `ledger`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VotingCheckpoint {
+    pub ledger: u32,
+    pub weight: i128,
+}
+
+/// Admin-configured reward paid to a keeper for each charge it triggers.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum KeeperBounty {
+    /// Fixed amount per charge, in the subscription's token.
+    Flat(i128),
+    /// Basis points of the charged amount.
+    Bps(u32),
+}
+
+/// Emitted when a keeper is paid a bounty for a charge.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KeeperBountyPaidEvent {
+    pub keeper: Address,
+    pub subscription_id: u32,
+    pub merchant: Address,
+    pub token: Address,
+    pub amount: i128,
+}
+
+/// Where a merchant's charge-time tax is routed.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TaxDestination {
+    /// Transferred to a tax authority or escrow address at charge time.
+    Recipient(Address),
+    /// Held in the merchant's tax sub-balance until withdrawn.
+    SubBalance,
+}
+
+/// A merchant's tax configuration. Prices are tax-inclusive.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TaxConfig {
+    /// Share of each charge that is tax, in basis points.
+    pub rate_bps: u32,
+    pub destination: TaxDestination,
+}
+
+/// Tax line item emitted for every taxed charge.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TaxChargedEvent {
+    pub subscription_id: u32,
+    pub merchant: Address,
+    pub token: Address,
+    /// Charged amount, tax included.
+    pub gross: i128,
+    pub tax: i128,
+    pub rate_bps: u32,
+    pub destination: TaxDestination,
+}
+
+/// A charge receipt, chained to the merchant's previous receipt by `prev_hash`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChargeReceipt {
+    pub merchant: Address,
+    /// 1-based position in the merchant's chain.
+    pub sequence: u64,
+    pub subscription_id: u32,
+    pub token: Address,
+    /// Charged amount, tax included.
+    pub amount: i128,
+    pub tax: i128,
     pub timestamp: u64,
+    pub prev_hash: BytesN<32>,
 }
 
-/// Result of computing next charge information for a subscription.
+/// Latest hash and length of a merchant's receipt chain.
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct NextChargeInfo {
-    /// Estimated timestamp for the next charge attempt.
-    pub next_charge_timestamp: u64,
-    /// Whether a charge is actually expected based on the subscription status.
-    pub is_charge_expected: bool,
+pub struct ReceiptHead {
+    pub hash: BytesN<32>,
+    pub sequence: u64,
+}
+
+/// Admin-defined, time-boxed fee waiver campaign.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeeWaiverCampaign {
+    pub id: u32,
+    /// Share of the fee waived, in basis points (10 000 = no fee).
+    pub waiver_bps: u32,
+    /// How long each enrolled merchant's waiver lasts from its enrollment.
+    pub duration_seconds: u64,
+    /// Enrollment window `[enroll_from, enroll_until)`.
+    pub enroll_from: u64,
+    pub enroll_until: u64,
+    /// Only merchants charging for the first time are enrolled.
+    pub new_merchants_only: bool,
+}
+
+/// A merchant's enrollment in a fee waiver campaign.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MerchantFeeWaiver {
+    pub campaign_id: u32,
+    pub waiver_bps: u32,
+    pub started_at: u64,
+    pub expires_at: u64,
+}
+
+/// Admin-configured protocol fee taken from every charge.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProtocolFeeConfig {
+    pub fee_bps: u32,
+    /// Account allowed to withdraw accrued fees.
+    pub collector: Address,
+}
+
+/// Emitted when a charge pays the protocol fee.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProtocolFeeChargedEvent {
+    pub subscription_id: u32,
+    pub merchant: Address,
+    pub token: Address,
+    /// Amount the fee was computed on (the charge net of tax).
+    pub gross: i128,
+    pub fee: i128,
+    /// Part of the fee waived by the merchant's fee waiver.
+    pub waived: i128,
+}
+
+/// Subscription terms signed off-chain by both subscriber and merchant.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SubscriptionAgreement {
+    pub subscriber: Address,
+    pub merchant: Address,
+    pub amount: i128,
+    pub interval_seconds: u64,
+    pub usage_enabled: bool,
+    /// Last timestamp at which the agreement can be imported.
+    pub expires_at: u64,
+    /// Distinguishes otherwise identical agreements.
+    pub nonce: u64,
+}
+
+/// Subscriber operation carried by a [`SignedAction`].
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SignedOp {
+    /// Deposit `amount` into the subscription, pulled via the vault's token allowance.
+    Deposit(u32, i128),
+    Pause(u32),
+    Resume(u32),
+    Cancel(u32),
+}
+
+/// A subscriber action signed off-chain for submission by a relayer.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SignedAction {
+    pub signer: Address,
+    /// Must equal the signer's next nonce (`get_signer_nonce`).
+    pub nonce: u64,
+    /// Last timestamp at which the action can be executed.
+    pub expires_at: u64,
+    pub op: SignedOp,
+}
+
+/// A failed batch charge waiting in the retry queue.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RetryEntry {
+    pub subscription_id: u32,
+    /// Failed attempts so far, including the original batch charge.
+    pub attempts: u32,
+    /// Earliest timestamp at which `process_retry_queue` re-attempts the charge.
+    pub next_retry_at: u64,
+    pub last_error_code: u32,
+}
+
+/// Outcome of one retry in `process_retry_queue`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RetryResult {
+    pub subscription_id: u32,
+    pub success: bool,
+    /// Error code on failure, 0 on success.
+    pub error_code: u32,
+    /// Whether the entry was rescheduled for another retry.
+    pub requeued: bool,
+}
+
+/// Emitted when a subscriber closes their account.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AccountClosedEvent {
+    pub subscriber: Address,
+    /// Number of subscription records removed.
+    pub subscriptions_removed: u32,
+    /// Rolling SHA-256 over the removed records' tombstone hashes, in id order.
+    pub history_hash: BytesN<32>,
+}
+
+/// Solvency heartbeat emitted by `emit_reconciliation`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReconciliationEvent {
+    pub token: Address,
+    /// The vault's actual token balance.
+    pub balance: i128,
+    /// Tracked amount owed to subscribers, merchants and claimants.
+    pub liabilities: i128,
+    /// `balance - liabilities`; negative means the vault is under-collateralized.
+    pub surplus: i128,
+    pub timestamp: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct BillingStalledEvent {
+    /// Timestamp of the last successful billing run.
+    pub last_run: u64,
+    pub seconds_since: u64,
+    pub threshold: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct TransferDeferredEvent {
+    pub recipient: Address,
+    pub token: Address,
+    pub amount: i128,
+}
+
+/// One append-only admin audit log record.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AdminLogEntry {
+    /// Sequence number, starting at 0.
+    pub seq: u32,
+    pub admin: Address,
+    /// Name of the admin entrypoint that mutated state (e.g. `set_min_topup`).
+    pub action: Symbol,
+    pub timestamp: u64,
+    pub ledger: u32,
+}
+
+/// Maximum number of merchants on a subscriber's allow-list.
+pub const MAX_APPROVED_MERCHANTS: u32 = 50;
+
+/// Merchants a subscriber has pre-approved.
+///
+/// When `enforced` is true, new subscriptions (including renewals) may only be
+/// created for merchants in `merchants` (deny-by-default).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MerchantAllowlist {
+    pub enforced: bool,
+    pub merchants: Vec<Address>,
+}
+
+/// Renewal history links for a subscription.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SubscriptionLineage {
+    /// Completed subscription this one renewed, if any.
+    pub predecessor: Option<u32>,
+    /// Subscription created by renewing this one, if any.
+    pub successor: Option<u32>,
+}
+
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionsMergedEvent {
+    pub survivor_id: u32,
+    pub merged_id: u32,
+    /// Prepaid balance moved from the merged subscription into the survivor.
+    pub balance_moved: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionRenewedEvent {
+    pub predecessor_id: u32,
+    pub successor_id: u32,
+    pub amount: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionCancelledEvent {
+    pub subscription_id: u32,
+    pub authorizer: Address,
+    pub refund_amount: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionPausedEvent {
+    pub subscription_id: u32,
+    pub authorizer: Address,
+}
+
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionResumedEvent {
+    pub subscription_id: u32,
+    pub authorizer: Address,
+}
+
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct MerchantWithdrawalEvent {
+    pub merchant: Address,
+    pub amount: i128,
+    /// Merchant's accrued balance left in the vault after the withdrawal.
+    pub remaining_balance: i128,
+}
+
+/// Emitted when a merchant-initiated one-off charge is applied to a subscription.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct OneOffChargedEvent {
+    pub subscription_id: u32,
+    pub merchant: Address,
+    pub amount: i128,
+}
+
+/// Represents the reason for stranded funds that can be recovered by admin.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RecoveryReason {
+    /// Funds sent to contract address by mistake (no associated subscription).
+    AccidentalTransfer = 0,
+    /// Funds from deprecated contract flows or logic errors.
+    DeprecatedFlow = 1,
+    /// Funds from cancelled subscriptions with unreachable addresses.
+    UnreachableSubscriber = 2,
+}
+
+/// Event emitted when admin recovers stranded funds.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct RecoveryEvent {
+    /// The admin who authorized the recovery
+    pub admin: Address,
+    /// The destination address receiving the recovered funds
+    pub recipient: Address,
+    /// The amount of funds recovered
+    pub amount: i128,
+    /// The documented reason for recovery
+    pub reason: RecoveryReason,
+    /// Timestamp when recovery was executed
+    pub timestamp: u64,
+}
+
+/// Result of computing next charge information for a subscription.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NextChargeInfo {
+    /// Estimated timestamp for the next charge attempt.
+    pub next_charge_timestamp: u64,
+    /// Whether a charge is actually expected based on the subscription status.
+    pub is_charge_expected: bool,
+}
+
+// ── Query DTOs ──────────────────────────────────────────────────────────────
+//
+// Queries return view structs, never storage structs, so the stored layout (e.g.
+// splitting `Subscription` into terms and state) can change without touching the
+// client ABI. A DTO's fields are only ever appended; a breaking change ships as a
+// new struct and entrypoint alongside the old one, and [`DTO_VERSION`] is bumped.
+
+/// Version of the query DTO set, returned by `get_dto_version`.
+pub const DTO_VERSION: u32 = 1;
+
+/// Client-facing view of one subscription, independent of how it is stored.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SubscriptionDto {
+    pub id: u32,
+    pub subscriber: Address,
+    pub merchant: Address,
+    pub amount: i128,
+    pub interval_seconds: u64,
+    pub last_payment_timestamp: u64,
+    pub status: SubscriptionStatus,
+    pub prepaid_balance: i128,
+    pub usage_enabled: bool,
+    pub label: Option<String>,
+}
+
+/// A subscription with the derived state a frontend needs to render it, from one read.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionView {
+    pub subscription: SubscriptionDto,
+    /// Timestamp the next interval charge is due (estimated for ledger schedules).
+    pub next_due: u64,
+    /// Time until which an underfunded charge leaves the subscription `Active`
+    /// (`next_due` unless the subscription has a grace period).
+    pub grace_deadline: u64,
+    /// Overdue but still `Active` within its grace period.
+    pub in_grace: bool,
+    /// Failed charge attempts waiting in the retry queue (0 if not queued).
+    pub failed_attempts: u32,
+    /// Retries left before the retry queue gives up on the subscription.
+    pub remaining_attempts: u32,
+    /// Amount the next interval charge takes after test mode, pause retainers and
+    /// the merchant's promo credit.
+    pub effective_amount: i128,
+}
+
+/// Emitted by the keeper when a subscription's next charge is within the lead window.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct DueSoonEvent {
+    pub subscription_id: u32,
+    pub subscriber: Address,
+    pub merchant: Address,
+    pub amount: i128,
+    /// Timestamp at which the next charge becomes due.
+    pub due_at: u64,
+    /// Seconds remaining until `due_at` when the event was emitted.
+    pub seconds_until_due: u64,
+}
+
+/// Permission granted to a merchant API key.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ApiKeyScope {
+    /// May submit metered usage charges (`charge_usage`).
+    Metering = 0,
+    /// May perform refund-releasing actions on the merchant's behalf (e.g. cancellation).
+    Refunds = 1,
+}
+
+/// A named address authorized to act for a merchant within a single scope.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MerchantApiKey {
+    pub key: Address,
+    pub name: Symbol,
+    pub scope: ApiKeyScope,
+}
+
+/// Merchant proof-of-service for one charged billing period.
+///
+/// Immutable once posted; dispute resolution can consult it as a defensive record.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ServiceAttestation {
+    pub subscription_id: u32,
+    /// Billing period index the attestation covers (same basis as charge replay protection).
+    pub period_index: u64,
+    /// Hash of the off-chain delivery proof.
+    pub proof_hash: BytesN<32>,
+    pub attested_at: u64,
+}
+
+/// A merchant's current terms version (`publish_terms`).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MerchantTerms {
+    pub terms_hash: BytesN<32>,
+    /// Per-interval price under these terms, applied to a subscription when its
+    /// subscriber accepts them; `None` leaves prices unchanged.
+    pub amount: Option<i128>,
+    pub published_at: u64,
+}
+
+/// Emitted when a merchant anchors (or changes) the terms hash of a subscription.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct TermsAnchoredEvent {
+    pub subscription_id: u32,
+    pub merchant: Address,
+    /// Hash that was anchored before this change, if any.
+    pub previous_hash: Option<BytesN<32>>,
+    pub terms_hash: BytesN<32>,
+    pub timestamp: u64,
+}
+
+/// Compact subscription record used by `export_subscriptions` for indexer bootstrap
+/// and by `list_subscriptions_by_merchant` for merchant dashboards.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionRecord {
+    pub id: u32,
+    pub subscriber: Address,
+    pub merchant: Address,
+    pub amount: i128,
+    pub interval_seconds: u64,
+    pub status: SubscriptionStatus,
+    pub prepaid_balance: i128,
+}
+
+// ── Pagination ──────────────────────────────────────────────────────────────
+//
+// Every list-returning query returns a `Page<T>`-shaped struct:
+// `items`, `next_cursor` (`None` on the last page) and `total` (items matching
+// the query across all pages). `#[contracttype]` cannot be generic, so each item
+// type has its own concrete page struct with exactly these fields.
+
+/// Maximum number of items returned by one paginated query; larger limits are capped.
+pub const MAX_PAGE_LIMIT: u32 = 100;
+
+/// Page of subscription ids.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SubscriptionIdPage {
+    pub items: Vec<u32>,
+    pub next_cursor: Option<u32>,
+    pub total: u32,
+}
+
+/// Page of subscriptions as [`SubscriptionDto`]s.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionPage {
+    pub items: Vec<SubscriptionDto>,
+    pub next_cursor: Option<u32>,
+    pub total: u32,
+}
+
+/// Page of compact subscription records (`export_subscriptions`,
+/// `list_subscriptions_by_merchant`).
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionExportPage {
+    pub items: Vec<SubscriptionRecord>,
+    pub next_cursor: Option<u32>,
+    pub total: u32,
+}
+
+/// Page of admin audit log entries.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AdminLogPage {
+    pub items: Vec<AdminLogEntry>,
+    pub next_cursor: Option<u32>,
+    pub total: u32,
+}
+
+/// Page of merchant settlement summaries.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SettlementPage {
+    pub items: Vec<SettlementSummary>,
+    pub next_cursor: Option<u32>,
+    pub total: u32,
+}
+
+/// Page of merchant API keys.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ApiKeyPage {
+    pub items: Vec<MerchantApiKey>,
+    pub next_cursor: Option<u32>,
+    pub total: u32,
+}
+
+/// Average ledger close time used to convert between ledger and timestamp schedules.
+pub const AVG_LEDGER_CLOSE_SECONDS: u64 = 5;
+
+/// Which clock determines when a subscription is due.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ScheduleBasis {
+    /// Due at `last_payment_timestamp + interval_seconds` (default).
+    Timestamp = 0,
+    /// Due at `last_payment_ledger + interval_ledgers`.
+    LedgerSequence = 1,
+}
+
+/// Ledger-sequence schedule state for subscriptions that opted out of timestamps.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LedgerSchedule {
+    pub interval_ledgers: u32,
+    pub last_payment_ledger: u32,
+}
+
+/// Next due point expressed in both bases; the non-native one is an estimate
+/// based on [`AVG_LEDGER_CLOSE_SECONDS`].
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScheduleInfo {
+    pub basis: ScheduleBasis,
+    pub next_charge_timestamp: u64,
+    pub next_charge_ledger: u32,
+}
+
+/// Subscriptions falling due within one hour (`get_charge_schedule`).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChargeBucket {
+    /// Start of the hour, as a ledger timestamp aligned to 3600 seconds.
+    pub hour_start: u64,
+    pub subscription_ids: Vec<u32>,
+}
+
+/// Expected vs actual interval charges since creation (`get_interval_drift`).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IntervalDrift {
+    /// Whole intervals elapsed since creation.
+    pub expected_charges: u32,
+    /// Interval charges actually made.
+    pub periods_charged: u32,
+    /// `expected_charges - periods_charged`; positive means missed billing periods.
+    pub drift: i64,
+}
+
+/// Pricing quote for a prospective subscription, returned by `get_quote`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Quote {
+    /// Amount debited by the first interval charge.
+    pub first_charge_amount: i128,
+    /// Amount debited by each subsequent interval charge.
+    pub recurring_amount: i128,
+    /// Expected per-interval total including estimated metered usage.
+    pub per_interval_total: i128,
+    /// Minimum deposit that satisfies `min_topup` and the merchant's reserve policy.
+    pub required_initial_deposit: i128,
+}
+
+/// Audit event emitted for every admin force-charge (settlement correction).
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct ForceChargeEvent {
+    pub admin: Address,
+    pub subscription_id: u32,
+    pub merchant: Address,
+    pub amount: i128,
+    /// Short documented reason code (e.g. `dispute`).
+    pub reason: Symbol,
+    pub timestamp: u64,
+}
+
+/// Admin-configured deposit rate limit. A zero field disables that check.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DepositRateLimit {
+    /// Minimum seconds between two deposits into the same subscription.
+    pub min_interval_seconds: u64,
+    /// Maximum deposits into the same subscription per UTC day.
+    pub max_per_day: u32,
+}
+
+/// Recorded when a charge was attempted implausibly far past its due time.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChargeReview {
+    pub subscription_id: u32,
+    /// When the charge was due.
+    pub due_at: u64,
+    /// Ledger time of the flagged charge attempt.
+    pub attempted_at: u64,
+}
+
+/// What cancelling a subscription now would return to the subscriber, from
+/// `get_cancellation_quote`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CancellationQuote {
+    pub subscription_id: u32,
+    /// Returned in full by `withdraw_subscriber_funds` after cancelling.
+    pub prepaid_balance: i128,
+    /// Whether the cooling-off refund applies.
+    pub in_cooling_off: bool,
+    /// Cooling-off refund of everything charged so far (0 outside the window).
+    pub refund: i128,
+    /// Part of `refund` credited to the subscriber's credit wallet.
+    pub refund_to_wallet: i128,
+    /// Part of `refund` transferred to the subscriber, net of `refund_fee`.
+    pub refund_to_external: i128,
+    /// External refund fee kept by the merchant.
+    pub refund_fee: i128,
+    /// `prepaid_balance + refund_to_wallet + refund_to_external`.
+    pub total_returned: i128,
+}
+
+/// Emitted for every ombudsman cancellation, with the hash of the documented case.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OmbudsmanActionEvent {
+    pub ombudsman: Address,
+    pub subscription_id: u32,
+    pub subscriber: Address,
+    pub merchant: Address,
+    /// Amount refunded on top of any cooling-off refund.
+    pub refund: i128,
+    pub reason_hash: BytesN<32>,
+}
+
+/// Merchant-proposed change of price and interval, applied from the next cycle once
+/// the subscriber has accepted it.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SubscriptionUpdate {
+    pub amount: i128,
+    pub interval_seconds: u64,
+    /// Whether the subscriber has accepted it.
+    pub accepted: bool,
+}
+
+/// Admin-configured deposit caps for the beta period. A zero field disables that cap.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DepositCaps {
+    /// Maximum tracked liabilities of a token (total value locked in the vault).
+    pub tvl_cap: i128,
+    /// Maximum prepaid balance of a single subscription.
+    pub balance_cap: i128,
+}
+
+/// Emitted when a deposit is rejected by a deposit cap.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DepositCapExceededEvent {
+    /// `None` for credit-wallet deposits.
+    pub subscription_id: Option<u32>,
+    pub amount: i128,
+    /// Capacity left at the time of the attempt.
+    pub remaining: i128,
+}
+
+/// Per-subscription deposit activity tracked for rate limiting.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct DepositActivity {
+    pub last_deposit_timestamp: u64,
+    /// `timestamp / 86400` of the day `count` refers to.
+    pub day_index: u64,
+    pub count: u32,
+}
+
+/// Admin-configured time lock for large subscriber withdrawals.
+///
+/// Withdrawals above `threshold` must be announced with `request_withdrawal` and can
+/// only execute `delay_seconds` later. A zero `threshold` disables the time lock.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WithdrawalTimelock {
+    pub threshold: i128,
+    pub delay_seconds: u64,
+}
+
+/// An announced large withdrawal waiting for its time lock.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PendingWithdrawal {
+    pub amount: i128,
+    /// Earliest timestamp at which the withdrawal may be executed.
+    pub executable_at: u64,
+}
+
+/// A proposed settlement-token rotation waiting for its timelock.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PendingTokenRotation {
+    pub new_token: Address,
+    /// Earliest timestamp at which the rotation may be executed.
+    pub executable_at: u64,
+}
+
+/// Snapshot of the admin-tunable parameters, read and written as one unit by
+/// deployment tooling via `get_config` / `set_config`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Config {
+    pub min_topup: i128,
+    pub cooling_off_seconds: u64,
+    pub deposit_rate_limit: DepositRateLimit,
+    pub withdrawal_timelock: WithdrawalTimelock,
+    pub notify_lead_seconds: u64,
+    pub billing_stale_seconds: u64,
+}
+
+/// Active subscriptions a proposed [`Config`] would newly affect, for one page of
+/// the active set (`simulate_set_config`).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConfigImpact {
+    /// Active subscriptions examined on this page.
+    pub scanned: u32,
+    /// Balances that meet the current `min_topup` but not the proposed one.
+    pub below_min_topup: u32,
+    /// Balances whose withdrawal is free today but would need the proposed time lock.
+    pub newly_timelocked: u32,
+    /// Up to [`MAX_IMPACT_SAMPLES`] affected ids, in bucket order.
+    pub sample_ids: Vec<u32>,
+    pub next_cursor: Option<u32>,
+}
+
+/// Maximum number of sample ids in a [`ConfigImpact`].
+pub const MAX_IMPACT_SAMPLES: u32 = 10;
+
+/// A staged [`Config`] waiting for its timelock.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PendingConfig {
+    pub config: Config,
+    /// Earliest timestamp at which the config may be applied.
+    pub executable_at: u64,
+}
+
+/// Charges accrued by a merchant since its last payout.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SettlementEpoch {
+    pub period_start: u64,
+    pub charge_count: u32,
+    pub gross: i128,
+    pub fees: i128,
+}
+
+/// One UTC day of a merchant's bookkeeping aggregates (see `reports`).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MerchantDayStats {
+    pub charge_count: u32,
+    /// Gross amount charged.
+    pub charged: i128,
+    /// Tax and affiliate commission withheld from `charged`.
+    pub fees: i128,
+    pub refund_count: u32,
+    /// Amount refunded out of the merchant's revenue.
+    pub refunded: i128,
+    pub new_subscriptions: u32,
+    pub cancellations: u32,
+}
+
+/// Merchant aggregates summed over a day-aligned period, returned by
+/// `get_merchant_period_summary`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MerchantPeriodSummary {
+    pub merchant: Address,
+    /// Start of the first day covered (inclusive).
+    pub period_start: u64,
+    /// End of the last day covered (exclusive).
+    pub period_end: u64,
+    pub charge_count: u32,
+    pub charged: i128,
+    pub fees: i128,
+    pub refund_count: u32,
+    pub refunded: i128,
+    pub new_subscriptions: u32,
+    pub cancellations: u32,
+}
+
+/// Platform a merchant sells through and the cut it takes of every charge.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MerchantPlatform {
+    pub platform: Address,
+    pub cut_bps: u32,
+}
+
+/// Emitted when a charge credits a platform's cut.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PlatformCutEvent {
+    pub subscription_id: u32,
+    pub merchant: Address,
+    pub platform: Address,
+    pub token: Address,
+    /// Amount the cut was computed on (the charge net of tax and protocol fee).
+    pub gross: i128,
+    pub cut: i128,
+}
+
+/// Subscriber rule refilling the prepaid balance from their wallet.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AutoTopup {
+    /// A top-up may run while the prepaid balance is below this.
+    pub threshold: i128,
+    /// Amount deposited per top-up.
+    pub refill: i128,
+}
+
+/// Vault-wide counters for explorers, returned by `get_public_stats`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PublicStats {
+    /// Subscriptions ever created, including pruned ones.
+    pub total_subscriptions: u32,
+    pub active_subscriptions: u32,
+    /// Gross amount ever charged, summed over settlement tokens in their own units.
+    pub total_volume: i128,
+    pub unique_merchants: u32,
+    pub unique_subscribers: u32,
+}
+
+/// Summary emitted and stored at each merchant payout.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SettlementSummary {
+    pub merchant: Address,
+    pub period_start: u64,
+    pub period_end: u64,
+    pub charge_count: u32,
+    pub gross: i128,
+    pub fees: i128,
+    /// `gross - fees`.
+    pub net: i128,
+    /// Amount paid out by the withdrawal that closed this epoch.
+    pub withdrawn: i128,
 }
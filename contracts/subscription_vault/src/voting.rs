@@ -0,0 +1,94 @@
+//! Governance voting weight from protocol usage.
+//!
+//! **PRs that only change voting weight should edit this file only.**
+//!
+//! An address's weight is what it has been charged as a subscriber plus the net
+//! revenue it has earned as a merchant, in token units, less refunds. Cancelling a
+//! subscription also takes what is left of its charges off the subscriber's weight,
+//! so weight follows live spend rather than lifetime spend. Charges of a merchant
+//! to itself carry no weight. Changes are checkpointed per [`CHECKPOINT_WINDOW`]
+//! ledgers as a [`VotingCheckpoint`], so `get_voting_weight` can answer for any past
+//! window. Only the latest [`MAX_CHECKPOINTS`] checkpoints per address are kept; a
+//! snapshot older than the retained history fails with `LimitExceeded`.
+
+use crate::safe_math::safe_add;
+use crate::types::{Error, VotingCheckpoint};
+use soroban_sdk::{symbol_short, Address, Env, Symbol, Vec};
+
+/// Checkpoints kept per address.
+pub const MAX_CHECKPOINTS: u32 = 64;
+
+/// Ledgers per checkpoint window (about a day at 5 s per ledger).
+pub const CHECKPOINT_WINDOW: u32 = 17_280;
+
+const KEY_CHECKPOINTS: Symbol = symbol_short!("votes");
+
+fn get_checkpoints(env: &Env, account: &Address) -> Vec<VotingCheckpoint> {
+    env.storage()
+        .instance()
+        .get(&(KEY_CHECKPOINTS, account.clone()))
+        .unwrap_or(Vec::new(env))
+}
+
+fn window_start(ledger: u32) -> u32 {
+    ledger - ledger % CHECKPOINT_WINDOW
+}
+
+/// Adds `delta` to the account's weight from the current window on. Weight does
+/// not go below zero.
+pub fn record_weight(env: &Env, account: &Address, delta: i128) -> Result<(), Error> {
+    if delta == 0 {
+        return Ok(());
+    }
+    let ledger = window_start(env.ledger().sequence());
+    let mut checkpoints = get_checkpoints(env, account);
+    let last = checkpoints.last().map(|c| c.weight).unwrap_or(0);
+    let weight = safe_add(last, delta)?.max(0);
+    if weight == last {
+        return Ok(());
+    }
+    match checkpoints.last() {
+        Some(last) if last.ledger == ledger => {
+            checkpoints.set(checkpoints.len() - 1, VotingCheckpoint { ledger, weight });
+        }
+        _ => {
+            if checkpoints.len() >= MAX_CHECKPOINTS {
+                checkpoints.remove(0);
+            }
+            checkpoints.push_back(VotingCheckpoint { ledger, weight });
+        }
+    }
+    env.storage()
+        .instance()
+        .set(&(KEY_CHECKPOINTS, account.clone()), &checkpoints);
+    Ok(())
+}
+
+/// Drops the account's weight history.
+pub fn remove_weight(env: &Env, account: &Address) {
+    env.storage()
+        .instance()
+        .remove(&(KEY_CHECKPOINTS, account.clone()));
+}
+
+/// Weight of `account` at the end of the window containing `snapshot_ledger`.
+///
+/// The snapshot must lie in a past window (`InvalidAmount`), since the current one
+/// can still change.
+pub fn get_voting_weight(env: &Env, account: Address, snapshot_ledger: u32) -> Result<i128, Error> {
+    if window_start(snapshot_ledger) >= window_start(env.ledger().sequence()) {
+        return Err(Error::InvalidAmount);
+    }
+    let snapshot_ledger = window_start(snapshot_ledger);
+    let checkpoints = get_checkpoints(env, &account);
+    for i in (0..checkpoints.len()).rev() {
+        let checkpoint = checkpoints.get(i).unwrap();
+        if checkpoint.ledger <= snapshot_ledger {
+            return Ok(checkpoint.weight);
+        }
+    }
+    if checkpoints.len() >= MAX_CHECKPOINTS {
+        return Err(Error::LimitExceeded);
+    }
+    Ok(0)
+}
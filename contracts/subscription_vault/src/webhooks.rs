@@ -0,0 +1,82 @@
+//! Merchant webhook contracts notified after charges and cancellations.
+//!
+//! **PRs that only change merchant webhooks should edit this file only.**
+//!
+//! A merchant may register one [`MerchantWebhook`] contract. After a charge or a
+//! cancellation of one of its subscriptions the vault calls it with a fixed-size
+//! payload. Calls are best-effort: a hook that fails, panics or re-enters the vault
+//! has its own effects rolled back, a `webhook_failed` event is published, and the
+//! charge or cancellation goes ahead.
+//!
+//! Soroban has no separate budget per sub-call, so the hook runs on the calling
+//! transaction's budget. Costs are bounded only by each hook doing little; a hook
+//! that exhausts the budget fails the transaction, so keepers should exclude that
+//! merchant's subscriptions until it is fixed or unregistered.
+
+use crate::types::Error;
+use soroban_sdk::{contractclient, symbol_short, Address, Env, Symbol};
+
+const KEY_WEBHOOK: Symbol = symbol_short!("webhook");
+
+/// Interface a merchant webhook contract implements.
+#[contractclient(name = "MerchantWebhookClient")]
+pub trait MerchantWebhook {
+    /// A subscription of `merchant` was charged `amount`.
+    fn on_charged(env: Env, merchant: Address, subscription_id: u32, amount: i128);
+    /// A subscription of `merchant` was cancelled.
+    fn on_cancelled(env: Env, merchant: Address, subscription_id: u32);
+}
+
+pub fn get_webhook(env: &Env, merchant: &Address) -> Option<Address> {
+    env.storage()
+        .instance()
+        .get(&(KEY_WEBHOOK, merchant.clone()))
+}
+
+/// Register or clear (`None`) the merchant's webhook contract. Auth: merchant.
+pub fn do_set_webhook(env: &Env, merchant: Address, webhook: Option<Address>) -> Result<(), Error> {
+    merchant.require_auth();
+    let key = (KEY_WEBHOOK, merchant.clone());
+    match &webhook {
+        Some(hook) => env.storage().instance().set(&key, hook),
+        None => env.storage().instance().remove(&key),
+    }
+    env.events()
+        .publish((Symbol::new(env, "webhook_set"), merchant), webhook);
+    Ok(())
+}
+
+pub fn notify_charged(env: &Env, merchant: &Address, subscription_id: u32, amount: i128) {
+    if let Some(hook) = get_webhook(env, merchant) {
+        let ok = matches!(
+            MerchantWebhookClient::new(env, &hook).try_on_charged(
+                merchant,
+                &subscription_id,
+                &amount
+            ),
+            Ok(Ok(()))
+        );
+        if !ok {
+            publish_failure(env, merchant, subscription_id, "on_charged");
+        }
+    }
+}
+
+pub fn notify_cancelled(env: &Env, merchant: &Address, subscription_id: u32) {
+    if let Some(hook) = get_webhook(env, merchant) {
+        let ok = matches!(
+            MerchantWebhookClient::new(env, &hook).try_on_cancelled(merchant, &subscription_id),
+            Ok(Ok(()))
+        );
+        if !ok {
+            publish_failure(env, merchant, subscription_id, "on_cancelled");
+        }
+    }
+}
+
+fn publish_failure(env: &Env, merchant: &Address, subscription_id: u32, callback: &str) {
+    env.events().publish(
+        (Symbol::new(env, "webhook_failed"), merchant.clone()),
+        (subscription_id, Symbol::new(env, callback)),
+    );
+}